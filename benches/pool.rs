@@ -0,0 +1,121 @@
+//! Compare collecting the same input 1000 times into freshly allocated buffers with collecting it into buffers taken from a `BufferPool`.
+//!
+//! A bench cannot link to a binary crate, so the pool is included from `src/buffers/pool.rs` directly, and buffers are filled the way the `buffered` strategy fills them (`io::copy()` into the buffer's writer.) Allocations are counted by a global allocator that wraps the system one. Each way is ran several times, and the fastest run of each is reported (with the most allocations any of its runs made):
+//! ``` shell
+//! $ cargo bench --bench pool
+//! $ COLLECT_BENCH_KIB=4096 COLLECT_BENCH_RUNS=10 cargo bench --bench pool
+//! ```
+use std::{
+    alloc::{
+	GlobalAlloc,
+	Layout,
+	System,
+    },
+    env,
+    hint,
+    io,
+    num::NonZeroUsize,
+    sync::atomic::{
+	AtomicUsize,
+	Ordering,
+    },
+    time::{
+	Duration,
+	Instant,
+    },
+};
+
+#[path = "../src/buffers/pool.rs"]
+mod pool;
+use pool::{
+    BufferPool,
+    Reusable,
+};
+
+/// The number of times the input is collected, per run.
+const ITERATIONS: usize = 1000;
+
+/// The system allocator, counting every allocation (including reallocations, which may move the buffer.)
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8
+    {
+	ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+	System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout)
+    {
+	System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8
+    {
+	ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+	System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: Counting = Counting;
+
+/// Read the number in the environment variable `name`, or `default` if it is not set.
+fn env_or(name: &str, default: usize) -> usize
+{
+    env::var(name).ok()
+	.map(|value| value.parse().unwrap_or_else(|_| panic!("{name} is not a number: {value:?}")))
+	.unwrap_or(default)
+}
+
+/// Collect `input` into `buffer`, then write it out; as the `buffered` strategy does.
+fn collect_into(buffer: &mut Vec<u8>, input: &[u8]) -> io::Result<()>
+{
+    io::copy(&mut &input[..], buffer)?;
+    // `black_box()`, so the copy into a buffer that is about to be freed is not optimised out.
+    io::copy(&mut hint::black_box(&buffer[..]), &mut io::sink())?;
+    Ok(())
+}
+
+/// Collect `input` `ITERATIONS` times with `collect`, `runs` times; and count the allocations made while doing so.
+///
+/// # Returns
+/// The time taken by the fastest run, and the most allocations any run made (the pool only allocates in the first one.)
+fn run(runs: usize, input: &[u8], mut collect: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<(Duration, usize)>
+{
+    let (mut fastest, mut most) = (Duration::MAX, 0);
+    for _ in 0..runs {
+	let before = ALLOCATIONS.load(Ordering::Relaxed);
+	let start = Instant::now();
+	for _ in 0..ITERATIONS {
+	    collect(input)?;
+	}
+	fastest = fastest.min(start.elapsed());
+	most = most.max(ALLOCATIONS.load(Ordering::Relaxed) - before);
+    }
+    Ok((fastest, most))
+}
+
+fn main() -> io::Result<()>
+{
+    let kib = env_or("COLLECT_BENCH_KIB", 1024);
+    let runs = env_or("COLLECT_BENCH_RUNS", 5);
+    let input = (0..kib << 10).map(|i: usize| (i.wrapping_mul(31) ^ (i >> 8)) as u8).collect::<Vec<_>>();
+    let cap = NonZeroUsize::new(input.len());
+
+    let fresh = run(runs, &input, |input| collect_into(&mut Vec::allocate(cap), input))?;
+    let mut pool = BufferPool::<Vec<u8>>::new();
+    let pooled = run(runs, &input, |input| {
+	let mut buffer = pool.acquire(cap);
+	let result = collect_into(&mut buffer, input);
+	pool.release(buffer);
+	result
+    })?;
+    assert_eq!((pool.allocated(), pool.free()), (1, 1), "The pool did not reuse its buffer");
+
+    for (name, (fastest, allocations)) in [("fresh", fresh), ("pooled", pooled)] {
+	println!("{name:>8}: {fastest:?}, at most {allocations} allocations ({ITERATIONS} collections of {kib} KiB; fastest of {runs} runs)");
+    }
+    Ok(())
+}
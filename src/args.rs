@@ -8,13 +8,23 @@ use std::{
     iter,
     fmt, error,
     borrow::Cow,
+    path::{
+	Path,
+	PathBuf,
+    },
+    time::Duration,
 };
+#[cfg(feature="logging")]
 use std::any::type_name;
 //TODO: When added, the `args` comptime feature will need to enable `lazy_static`.
 use ::lazy_static::lazy_static;
 
 /// The string used for positional argument replacements in `-exec{}`.
-pub const POSITIONAL_ARG_STRING: &'static str = "{}";
+pub const POSITIONAL_ARG_STRING: &str = "{}";
+
+/// The string replaced with the number of bytes collected in `-exec{}`'s arguments (anywhere in an argument, unlike `POSITIONAL_ARG_STRING`.)
+#[cfg_attr(not(feature="exec"), allow(dead_code))]
+pub const SIZE_ARG_STRING: &str = "{size}";
 
 /// The token that terminates adding arguments for `-exec` / `-exec{}`.
 ///
@@ -22,7 +32,18 @@ pub const POSITIONAL_ARG_STRING: &'static str = "{}";
 /// If the user wants multiple `-exec/{}` parameters, they must be seperated with this token. e.g. `sh$ collect -exec c a b c \; -exec{} c2 d {} e f {} g`
 ///
 /// It is not required for the user to provide the terminator when the `-exec/{}` is the final argument passed, but they can if they wish. e.g. `sh$ collect -exec command a b c` is valid, and `sh$ collect -exec command a b c \;` is *also* valid. 
-pub const EXEC_MODE_STRING_TERMINATOR: &'static str = ";";
+pub const EXEC_MODE_STRING_TERMINATOR: &str = ";";
+
+/// The exit code for `--fail-on-empty`, if one is not given.
+pub const DEFAULT_FAIL_ON_EMPTY: i32 = 1;
+
+/// The percentage of available memory the in-memory buffer can grow to before a warning is logged, if `--mem-warn` is not passed.
+#[cfg_attr(not(feature="memfile"), allow(dead_code))]
+pub const DEFAULT_MEM_WARN: u8 = 70;
+
+/// The percentage of available memory the in-memory buffer can grow to before collecting fails, if `--mem-max` is not passed.
+#[cfg_attr(not(feature="memfile"), allow(dead_code))]
+pub const DEFAULT_MEM_MAX: u8 = 90;
 
 /// Mode for `-exec` / `-exec{}`
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -36,50 +57,40 @@ impl fmt::Display for ExecMode
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
     {
+	/// Write `string`, in `QUOTE`s if it is empty or contains whitespace or a `QUOTE`, with every `QUOTE` and `\` escaped.
 	#[inline] 
-	fn quote_into<'a, const QUOTE: u8>(string: &'a [u8], f: &mut (impl fmt::Write + ?Sized)) -> fmt::Result
+	fn quote_into<const QUOTE: u8>(string: &[u8], f: &mut (impl fmt::Write + ?Sized)) -> fmt::Result
 	{
-	    let data = if let Some(mut location) = memchr::memchr(QUOTE, string) {
-		let mut data = Vec::with_capacity(string.len() * 2);
-		Cow::Owned(loop {
-		    data.extend_from_slice(&string[..location]);
-		    data.extend([b'\\', QUOTE]);
-		    location += match memchr::memchr(QUOTE, &string[location..]) {
-			Some(x) if !&string[(location + x)..].is_empty() => x,
-			_ => break data,
-		    };
-		})
-	    } else {
-		Cow::Borrowed(string)
-	    };
-	    let string = String::from_utf8_lossy(data.as_ref());
-	    
-	    if string.split_whitespace().take(2).count() == 1
-	    {
-		f.write_char(QUOTE as char)?;
-		f.write_str(string.as_ref())?;
-		f.write_char(QUOTE as char)
-	    } else {
-		f.write_str(string.as_ref())
+	    let string = String::from_utf8_lossy(string);
+	    if !string.is_empty() && !string.contains(|c: char| c.is_whitespace() || c == QUOTE as char || c == '\\') {
+		return f.write_str(string.as_ref());
+	    }
+	    f.write_char(QUOTE as char)?;
+	    for c in string.chars() {
+		if c == QUOTE as char || c == '\\' {
+		    f.write_char('\\')?;
+		}
+		f.write_char(c)?;
 	    }
+	    f.write_char(QUOTE as char)
 	}
 	match self {
 	    Self::Stdin { command, args } => {
 		quote_into::<b'\''>(command.as_bytes(), f)?;
-		args.iter().map(move |arg| {
+		args.iter().try_for_each(move |arg| {
 		    use fmt::Write;
 		    f.write_char(' ').and_then(|_| quote_into::<b'"'>(arg.as_bytes(), f))
-		}).collect()
+		})
 	    },
 	    Self::Positional { command, args } => {	
 		quote_into::<b'\''>(command.as_bytes(), f)?;
-		args.iter().map(move |arg| {
+		args.iter().try_for_each(move |arg| {
 		    use fmt::Write;
 		    f.write_char(' ').and_then(|_| match arg.as_ref() {
 			Some(arg) => quote_into::<b'"'>(arg.as_bytes(), f),
 			None => f.write_str(POSITIONAL_ARG_STRING),
 		    })
-		}).collect()
+		})
 	    },
 	}
     }
@@ -87,22 +98,35 @@ impl fmt::Display for ExecMode
 
 
 impl ExecMode {
+    /// Create an `-exec` (stdin) mode from a NUL-seperated argument vector (`command\0arg1\0arg2\0...`.)
+    ///
+    /// A trailing NUL is optional.
+    ///
+    /// # Returns
+    /// `None` if there is no command, or the command is empty.
+    #[cfg_attr(not(feature="exec"), allow(dead_code))]
+    pub fn from_nul_separated(argv: &[u8]) -> Option<Self>
+    {
+	let argv = argv.strip_suffix(b"\0").unwrap_or(argv);
+	let mut argv = argv.split(|&b| b == 0).map(|arg| OsStr::from_bytes(arg).to_owned());
+	let command = argv.next().filter(|command| !command.is_empty())?;
+	Some(Self::Stdin { command, args: argv.collect() })
+    }
+
     #[inline(always)] 
     pub fn is_positional(&self) -> bool
     {
-	if let Self::Positional { .. } = &self {
-	    true
-	} else {
-	    false
-	}
+	matches!(self, Self::Positional { .. })
     }
     #[inline(always)] 
+    #[cfg_attr(not(feature="exec"), allow(dead_code))]
     pub fn is_stdin(&self) -> bool
     {
 	!self.is_positional()
     }
     
     #[inline(always)] 
+    #[cfg_attr(not(feature="exec"), allow(dead_code))]
     pub fn command(&self) -> &OsStr
     {
 	match self {
@@ -116,6 +140,7 @@ impl ExecMode {
     ///
     /// Its output type is `Option<&OsStr>`, because the variant may be `Positional`. If it is instead `Stdin`, all values yielded will be `Some()`.
     #[inline] 
+    #[cfg_attr(not(feature="exec"), allow(dead_code))]
     pub fn arguments(&self) -> impl Iterator<Item = Option<&'_ OsStr>>
     {
 	#[derive(Debug, Clone)]
@@ -159,7 +184,8 @@ impl ExecMode {
     /// * When invariant is `Positional`, `positional` is iterated on for every instance a positional argument should appear.
     ///   If the iterator completes and there are positional arguments left, they are removed from the iterator's output, and the next argument is shifted along. `iter::repeat(arg)` can be used to insert the same argument into each instance where a positional argument is expected.
     #[inline] 
-    pub fn into_process_info<T, I>(self, positional: I) -> (OsString, ExecModeArgIterator<I>)
+    #[allow(dead_code)]
+    pub fn into_process_info<I>(self, positional: I) -> (OsString, ExecModeArgIterator<I>)
     where I: IntoIterator<Item=OsString>,
     {
 	
@@ -174,6 +200,7 @@ impl ExecMode {
     /// # Panics
     /// If the invariant of the enum was `Positional`.
     #[inline] 
+    #[allow(dead_code)]
     pub fn into_process_info_stdin(self) -> (OsString, ExecModeArgIterator<NoPositionalArgs>)
     {
 	#[cold]
@@ -189,11 +216,355 @@ impl ExecMode {
     }
 }
 
+/// How `$VAR` / `${VAR}` references in `-exec/{}` arguments are expanded (see `--exec-expand-env`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EnvExpansion
+{
+    /// References to undefined variables are left in the argument as they were written.
+    Lenient,
+    /// References to undefined variables are an error, and the child is not spawned.
+    Strict,
+}
+
+impl Default for EnvExpansion
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Lenient
+    }
+}
+
+/// What to do with a read error once the data collected before it has been written (see `--best-effort`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BestEffort
+{
+    /// The read error is returned, and `collect` exits with a failure.
+    Error,
+    /// A warning is issued, and `collect` exits successfully.
+    Warn,
+}
+
+impl Default for BestEffort
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Error
+    }
+}
+
+/// When to show the progress of reading the input (see `--progress`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Progress
+{
+    /// Only if `stderr` is a terminal.
+    Auto,
+    /// Even if `stderr` is not a terminal.
+    Force,
+}
+
+impl Default for Progress
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Auto
+    }
+}
+
+/// How the output file is synced to its storage once it has been written (see `--fsync`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Fsync
+{
+    /// `fdatasync()`: only the data, and the metadata needed to read it back (e.g. its size.)
+    Data,
+    /// `fsync()`: the data and all of the file's metadata.
+    All,
+}
+
+impl Default for Fsync
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::All
+    }
+}
+
+/// Which digest of the collected input is printed to `stderr` (see `--sha256`/`--crc32`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DigestKind
+{
+    /// SHA-256, printed the same way as `sha256sum`.
+    Sha256,
+    /// CRC-32 (IEEE), printed as 8 hex digits.
+    Crc32,
+}
+
+impl fmt::Display for DigestKind
+{
+    #[inline] 
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	f.write_str(match self {
+	    Self::Sha256 => "sha256",
+	    Self::Crc32 => "crc32",
+	})
+    }
+}
+
+/// Which huge pages back the in-memory file (see `--hugetlb`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HugePageSize
+{
+    /// The largest huge page size the system supports.
+    Largest,
+    /// Huge pages of exactly this many bytes.
+    Bytes(u64),
+}
+
+impl HugePageSize
+{
+    /// The size of the huge pages, if a specific one was requested.
+    #[inline] 
+    #[cfg_attr(not(feature="hugetlb"), allow(dead_code))]
+    pub fn bytes(&self) -> Option<u64>
+    {
+	match self {
+	    Self::Largest => None,
+	    Self::Bytes(size) => Some(*size),
+	}
+    }
+}
+
+/// Which seals are applied to the buffer given to `-exec/{}` children (see `--exec-input-seal-level`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct SealLevel
+{
+    /// `F_SEAL_SHRINK`
+    pub shrink: bool,
+    /// `F_SEAL_GROW`
+    pub grow: bool,
+    /// `F_SEAL_WRITE`
+    pub write: bool,
+}
+
+impl SealLevel
+{
+    /// All seals.
+    pub const ALL: Self = Self { shrink: true, grow: true, write: true };
+    /// No seals.
+    pub const NONE: Self = Self { shrink: false, grow: false, write: false };
+}
+
+/// Where `-exec/{}` children get their input from (see `--exec-stdin-from`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ExecStdinFrom
+{
+    /// The collected buffer, after it has been written to `stdout`.
+    Buffer,
+    /// `collect`'s own `stdin`, which is not collected at all. Only a single child can be given it.
+    Input,
+}
+
+impl Default for ExecStdinFrom
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Buffer
+    }
+}
+
+/// What to do when `stdin` and `stdout`, or the input and the `--output` file, are the same regular file (see `--on-same-file`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SameFile
+{
+    /// The whole input is collected before anything is written, and `stdout` is moved back to where it was before the read, so the file is rewritten in place.
+    Buffer,
+    /// Exit with an error before reading anything.
+    Error,
+}
+
+impl Default for SameFile
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Buffer
+    }
+}
+
+/// How the path `{}` is replaced with in `-exec{}` refers to the buffer's fd (see `--fd-style`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FdPathStyle
+{
+    /// `/proc/<pid>/fd/<fd>`, the link in `collect`'s own procfs directory.
+    Proc,
+    /// `/dev/fd/<fd>`, which the child resolves itself: so the fd is inherited by it.
+    DevFd,
+}
+
+impl Default for FdPathStyle
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Proc
+    }
+}
+
+/// Where the collected input is written (see `--output`.)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OutputTarget
+{
+    /// `stdout`, which is closed once everything has been written.
+    Stdout,
+    /// A file, created if it does not exist and truncated if it does.
+    File(PathBuf),
+    /// An already open file descriptor inherited from the parent (`--output-fd`), which is written to from its current position and not truncated.
+    Fd(RawFd),
+    /// Nowhere: the output is discarded (`--null`.)
+    Null,
+}
+
+impl Default for OutputTarget
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Stdout
+    }
+}
+
+impl fmt::Display for OutputTarget
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match self {
+	    Self::Stdout => f.write_str("stdout"),
+	    Self::File(path) => write!(f, "{}", path.display()),
+	    Self::Fd(fd) => write!(f, "fd {fd}"),
+	    Self::Null => f.write_str("/dev/null"),
+	}
+    }
+}
+
+/// Where the input is collected from (see `InputPath`.)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InputSource
+{
+    /// `stdin`.
+    Stdin,
+    /// A file given as a bare argument, which is opened and read instead of `stdin`.
+    Path(PathBuf),
+}
+
+impl Default for InputSource
+{
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	Self::Stdin
+    }
+}
+
+/// The part of the collected input to write, instead of all of it (see `--output-range`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OutputRange
+{
+    /// The first byte to write.
+    pub start: u64,
+    /// The byte to stop writing before, or `None` to write up to the end of the input.
+    pub end: Option<u64>,
+}
+
+impl OutputRange
+{
+    /// This range within an input of `len` bytes, if it fits inside it.
+    #[inline] 
+    pub fn within(&self, len: u64) -> Option<std::ops::Range<u64>>
+    {
+	let end = self.end.unwrap_or(len);
+	(self.start <= end && end <= len).then_some(self.start..end)
+    }
+
+    /// This range within an input of `len` bytes, with any part outside of it cut off.
+    #[inline] 
+    pub fn clamped(&self, len: u64) -> std::ops::Range<u64>
+    {
+	let end = std::cmp::min(self.end.unwrap_or(len), len);
+	std::cmp::min(self.start, end)..end
+    }
+}
+
+impl fmt::Display for OutputRange
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	write!(f, "{}:", self.start)?;
+	match self.end {
+	    Some(end) => write!(f, "{end}"),
+	    None => Ok(()),
+	}
+    }
+}
+
+/// The strategy used to collect the input (see `--strategy`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Strategy
+{
+    /// Choose `Buffered` or `Memfd` at runtime, based on the input and available memory (see `choose_strategy()` in `main`.)
+    #[cfg(feature="memfile")]
+    Auto,
+    /// Collect into an allocated buffer.
+    Buffered,
+    /// Collect into an in-memory file.
+    #[cfg(feature="memfile")]
+    Memfd,
+}
+
+impl fmt::Display for Strategy
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	f.write_str(match self {
+	    #[cfg(feature="memfile")]
+	    Self::Auto => "auto",
+	    Self::Buffered => "buffered",
+	    #[cfg(feature="memfile")]
+	    Self::Memfd => "memfd",
+	})
+    }
+}
+
+impl Default for Strategy
+{
+    #[inline] 
+    fn default() -> Self
+    {
+	cfg_if! {
+	    if #[cfg(feature="memfile")] {
+		Self::Auto
+	    } else {
+		Self::Buffered
+	    }
+	}
+    }
+}
+
+#[allow(dead_code)]
 pub struct ArgZippingIter<T>(std::vec::IntoIter<Option<OsString>>, iter::Fuse<T::IntoIter>)
 where T: IntoIterator<Item = OsString>;
 
 /// Private trait used to mark an instantiation of `ExecModeArgIterator<T>` as not ever being the `Positional` invariant.
+///
+/// # Safety
+/// Implementors must be uninhabited (or otherwise never yield a positional argument.)
+#[allow(dead_code)]
 unsafe trait NoPositional{}
+#[allow(dead_code)]
 pub enum NoPositionalArgs{}
 impl Iterator for NoPositionalArgs
 {
@@ -224,6 +595,7 @@ impl From<std::convert::Infallible> for NoPositionalArgs
     }
 }
 
+#[allow(dead_code)]
 pub enum ExecModeArgIterator<T: IntoIterator<Item = OsString>> {
     Stdin(std::vec::IntoIter<OsString>),
     Positional(ArgZippingIter<T>),
@@ -253,11 +625,17 @@ where I: IntoIterator<Item = OsString>
 	    }
 	}
     }
-    #[inline(always)] 
+    #[inline] 
     fn size_hint(&self) -> (usize, Option<usize>) {
 	match self {
 	    Self::Stdin(vec, ..) => vec.size_hint(),
-	    Self::Positional(ArgZippingIter(vec, ..)) => vec.size_hint(),
+	    // Each positional slot is replaced by at most one argument (or removed once `pos` runs out), so only the fixed arguments are certain to be yielded.
+	    Self::Positional(ArgZippingIter(vec, pos)) => {
+		let slots = vec.as_slice().iter().filter(|arg| arg.is_none()).count();
+		let fixed = vec.len() - slots;
+		let (low, high) = pos.size_hint();
+		(fixed + std::cmp::min(low, slots), Some(fixed + high.map_or(slots, |high| std::cmp::min(high, slots))))
+	    },
 	}
     }
 }
@@ -266,22 +644,152 @@ where I: IntoIterator<Item = OsString>{}
 // ExecModeArgIterator can never be FixedSizeIterator if it is *ever* `Positional`
 impl<I: NoPositional> ExactSizeIterator for ExecModeArgIterator<I>
 where I: IntoIterator<Item = OsString>{}
+// Nor can it be iterated from the back: which positional argument fills a slot depends on how many slots came before it.
+impl<I: NoPositional> DoubleEndedIterator for ExecModeArgIterator<I>
+where I: IntoIterator<Item = OsString>
+{
+    #[inline] 
+    fn next_back(&mut self) -> Option<Self::Item>
+    {
+	match self {
+	    Self::Stdin(vec) => vec.next_back(),
+	    Self::Positional(..) => unreachable!("`ExecModeArgIterator` cannot be `Positional` for a `NoPositional` type"),
+	}
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub struct Options {
     /// For `-exec` (stdin exec) and `-ecec{}` (positional exec)
     exec: Vec<ExecMode>,
+    /// For `--exec-command-from-fd <fd>`: an extra `-exec` whose command line is read from this fd at startup.
+    exec_command_from_fd: Option<RawFd>,
+    /// For `--exec-on-signal <command>`: run `command` with `/bin/sh -c` if `SIGINT`, `SIGTERM` or `SIGHUP` is caught.
+    exec_on_signal: Option<OsString>,
+    /// For `--exec-stdin-from=input|buffer`
+    exec_stdin_from: Option<ExecStdinFrom>,
+    /// For `--exec-stdin-tee`: the collected buffer is written to `stdout` *and* fed (from offset 0) to every `-exec` child's `stdin`.
+    exec_stdin_tee: bool,
+    /// For `--exec-input-nonblock`: `O_NONBLOCK` is set on the buffer fd given to `-exec` children as `stdin`.
+    exec_input_nonblock: bool,
+    /// For `--exec-stdin-keep-open`: `-exec` children are fed the buffer through a pipe that is not closed after it is written.
+    exec_stdin_keep_open: bool,
+    /// For `--exec-stdin-pipe`: `-exec` children are fed the buffer through a pipe, instead of being given the buffer itself.
+    exec_stdin_pipe: bool,
+    /// For `--exec-chdir-to-fd-dir`: `-exec{}` children are started in `/proc/<pid>/fd`, and `{}` is the buffer's bare fd number.
+    exec_chdir_to_fd_dir: bool,
+    /// For `--fd-style <proc|dev>`: the form of the path `{}` is replaced with in `-exec{}`.
+    fd_path_style: FdPathStyle,
+    /// For `--exec-input-readonly-reopen`: `-exec/{}` children are given a new read-only open of the buffer, instead of a `dup()` of it.
+    exec_input_readonly_reopen: bool,
+    /// For `--exec-stdin-memfd-refresh`: an `-exec` child is not spawned if it cannot be given its own open of the buffer.
+    exec_stdin_memfd_refresh: bool,
+    /// For `--exec-parallel`: all `-exec/{}` children are spawned before any of them is waited on.
+    exec_parallel: bool,
+    /// For `--exec-output-to-memfd`: `-exec/{}` children are chained, each one's output is captured into a new memfd that is the input of the next.
+    exec_output_to_memfd: bool,
+    /// For `--exec-pid-file <PATH>`: the PIDs of spawned children are written to this file, one per line.
+    exec_pid_file: Option<PathBuf>,
+    /// For `--exec-nice <N>`: the nice value (`-20..=19`) of `-exec/{}` children.
+    exec_nice: Option<libc::c_int>,
+    /// For `--exec-max-output <BYTES>`: the most output of each child captured by `--exec-output-to-memfd`.
+    exec_max_output: Option<u64>,
+    /// For `--exec-timeout <DURATION>`: a child still running this long after it was spawned is terminated.
+    exec_timeout: Option<Duration>,
+    /// For `--exec-stdout <PATH>`: where the `stdout` of `-exec/{}` children goes, instead of `collect`'s own.
+    exec_stdout: Option<OutputTarget>,
+    /// For `--exec-working-fd`: an `O_PATH` fd of `collect`'s working directory is inherited by `-exec/{}` children, and its number passed in `COLLECT_CWD_FD`.
+    exec_working_fd: bool,
+    /// For `--exec-fail-fast`: stop running `-exec/{}` children after the first one that fails.
+    exec_fail_fast: bool,
+    /// For `--exec-input-seal-level <write,grow,shrink|all|none>`
+    exec_input_seal_level: Option<SealLevel>,
+    /// For `--seal`: shorthand for `--exec-input-seal-level all`, unless that is passed too.
+    seal: bool,
+    /// For `--no-memfd-seal-check`: seals from `--exec-input-seal-level` that are not present after applying them are only a warning.
+    no_memfd_seal_check: bool,
+    /// For `--exec-env NAME=VALUE`
+    exec_env: Vec<(OsString, OsString)>,
+    /// For `--exec-prefix '<command> [args...]'`: a wrapper command (split into words) that every `-exec/{}` command is ran through.
+    exec_prefix: Vec<OsString>,
+    /// For `--exec-expand-env[=strict|lenient]`
+    exec_expand_env: Option<EnvExpansion>,
+    /// For `--strategy <auto|buffered|memfd>`
+    strategy: Option<Strategy>,
+    /// For `--buffer-backend=vec|bytes`
+    buffer_backend: Option<buffers::BufferBackend>,
+    /// For `--best-effort[=error|warn]`
+    best_effort: Option<BestEffort>,
+    /// For `--on-same-file <buffer|error>`
+    on_same_file: Option<SameFile>,
+    /// For `--head <BYTES>`: only the first `BYTES` bytes of the input are read, then the input is closed.
+    head: Option<u64>,
+    /// For `--limit <BYTES>`: only the first `BYTES` bytes of the input are read, the rest is left unread.
+    limit: Option<u64>,
+    /// For `--tail <BYTES>`: only the last `BYTES` bytes of the input are kept and written.
+    tail: Option<usize>,
+    /// For `--pipe-size <BYTES>`: the capacity to enlarge `stdin` to, if it is a pipe.
+    pipe_size: Option<usize>,
+    /// For `-b/--buffer-size <BYTES>`: the size to preallocate the buffer to, instead of the size of `stdin`.
+    buffer_size: Option<NonZeroUsize>,
+    /// For `--mem-warn <PERCENT>`: a warning is logged once the in-memory buffer holds this much of the available memory.
+    mem_warn: Option<u8>,
+    /// For `--mem-max <PERCENT>`: collecting fails once the in-memory buffer would hold more than this much of the available memory.
+    mem_max: Option<u8>,
+    /// For `--output-range <START:END>`: only this part of the collected input is written.
+    output_range: Option<OutputRange>,
+    /// For `--output-range-clamp`: an `--output-range` past the end of the input is cut off, instead of being an error.
+    output_range_clamp: bool,
+    /// For `-o/--output <PATH>`, `--output-fd <FD>` and `--null`: write here instead of `stdout` (the last one passed wins.)
+    output: Option<OutputTarget>,
+    /// For `--append`: the `--output` file is appended to instead of truncated.
+    append: bool,
+    /// For `--fsync[=data|all]`: the `--output` file is synced before it is closed.
+    fsync: Option<Fsync>,
+    /// For `--no-close-stdout`: `stdout` is not closed at the end of `main()`, only when the process exits.
+    no_close_stdout: bool,
+    /// For `--sha256`/`--crc32`: a digest of the bytes written is printed to `stderr` (the last one passed wins.)
+    digest: Option<DigestKind>,
+    /// For `-t/--tee <PATH>`: also write the collected input to each of these.
+    tee: Vec<OutputTarget>,
+    /// For `--tee-strict`: failing to write to a `--tee` target is an error, instead of a warning.
+    tee_strict: bool,
+    /// For `[FILE]`: read from this file instead of `stdin`.
+    input: InputSource,
+    /// For `--fail-on-empty[=CODE]`: exit with this code if no input was collected.
+    fail_on_empty: Option<i32>,
+    /// For `--completions <bash|zsh|fish>`: print a completion script for the shell and exit.
+    completions: Option<completions::Shell>,
+    /// For `--check`: validate the `-exec/{}` commands and exit, without reading `stdin` or spawning anything.
+    check: bool,
+    /// For `--dry-run`: report the input, strategy, output and `-exec/{}` commands and exit, without reading the input or spawning anything.
+    dry_run: bool,
+    /// For `--probe`: report what `stdin` is and exit, without reading from it.
+    probe: bool,
+    /// For `-q/--quiet`: all logging is turned off once the arguments are parsed, whatever `RUST_LOG` is.
+    quiet: bool,
+    /// For `--progress[=force]`: show how much of the input has been read on `stderr` while reading it.
+    progress: Option<Progress>,
+    /// For `--stats`: print the size and duration of each phase to `stderr` once everything is done.
+    stats: bool,
+    /// For `--name <name>`: the name of the in-memory file (`memfile` strategy only.)
+    memfile_name: Option<String>,
+    /// For `--name-truncate`: truncate a `--name` that is too long for `memfd_create()` instead of failing.
+    memfile_name_truncate: bool,
+    /// For `--hugetlb[=SIZE]`: back the in-memory file with huge pages (`memfile` strategy only.)
+    hugetlb: Option<HugePageSize>,
 }
 
 impl Options
 {
     #[inline(always)] 
+    #[allow(dead_code)]
     fn count_exec(&self) -> (usize, usize)
     {
-	self.exec.is_empty().then(|| (0, 0))
+	self.exec.is_empty().then_some((0, 0))
 	    .or_else(move ||
 		     self.exec.iter().map(|x| {
-			 x.is_positional().then(|| (0, 1)).unwrap_or((1, 0))
+			 if x.is_positional() { (0, 1) } else { (1, 0) }
 		     })
 		     .reduce(|(s, p), (s1, p1)| (s + s1, p + p1)))
 	    .unwrap_or((0,0))
@@ -290,9 +798,10 @@ impl Options
     ///
     /// Tuple element 1 is for `-exec`; element 2 is for `-exec{}`.
     #[inline(always)] 
+    #[allow(dead_code)]
     pub fn has_exec(&self) -> (bool, bool)
     {
-	self.exec.is_empty().then(|| (false, false))
+	self.exec.is_empty().then_some((false, false))
 	    .or_else(move || 
 		     self.exec.iter().map(|x| {
 			 let x = x.is_positional();
@@ -302,286 +811,2349 @@ impl Options
 	    .unwrap_or((false, false))
     }
     #[inline] 
+    #[allow(dead_code)]
     pub fn has_positional_exec(&self) -> bool
     {
 	self.has_exec().1
     }
     #[inline]
+    #[allow(dead_code)]
     pub fn has_stdin_exec(&self) -> bool
     {
 	self.has_exec().0
     }
 
     #[inline] 
-    pub fn opt_exec(&self) -> impl Iterator<Item= &'_ ExecMode> + ExactSizeIterator + iter::FusedIterator + DoubleEndedIterator
+    pub fn opt_exec(&self) -> impl ExactSizeIterator<Item= &'_ ExecMode> + iter::FusedIterator + DoubleEndedIterator
     {
 	self.exec.iter()
     }
     #[inline] 
-    pub fn into_opt_exec(self) -> impl Iterator<Item=ExecMode> + ExactSizeIterator + iter::FusedIterator
+    #[allow(dead_code)]
+    pub fn into_opt_exec(self) -> impl ExactSizeIterator<Item=ExecMode> + iter::FusedIterator
     {
 	self.exec.into_iter()
     }
-}
 
-/// The executable name of this program.
-///
-/// # Returns
-/// * If the program's executable name is a valid UTF8 string, that string.
-/// * If it is not, then that string is lossily-converted to a UTF8 string, with invalid characters replaced accordingly. This can be checked by checking if the return value is `Cow::Owned`, if it is, then this is not a reliable indication of the exetuable path's basename.
-/// * If there is no program name provided, i.e. if `argc == 0`, then an empty string is returned.
-#[inline(always)] 
-pub fn program_name() -> Cow<'static, str>
-{
-    lazy_static! {
-	static ref NAME: OsString = std::env::args_os().next().unwrap_or(OsString::from_vec(Vec::new()));
+    /// Add an `-exec/{}` mode after the ones passed as arguments.
+    #[inline] 
+    #[cfg_attr(not(feature="exec"), allow(dead_code))]
+    pub fn push_exec(&mut self, mode: ExecMode)
+    {
+	self.exec.push(mode);
     }
-    String::from_utf8_lossy(NAME.as_bytes())
-}
 
-/// Parse the program's arguments into an `Options` array.
-/// If parsing fails, an `ArgParseError` is returned detailing why it failed.
-#[inline]
-#[cfg_attr(feature="logging", instrument(err(Debug)))]
-pub fn parse_args() -> Result<Options, ArgParseError>
-{
-    let iter = std::env::args_os();
-    if_trace!(trace!("argc == {}, argv == {iter:?}", iter.len()));
-    
-    parse_from(iter.skip(1))
-}
+    /// The fd to read an extra `-exec` command line from, if `--exec-command-from-fd` was passed.
+    #[inline(always)] 
+    pub fn exec_command_from_fd(&self) -> Option<RawFd>
+    {
+	self.exec_command_from_fd
+    }
 
-#[inline(always)] 
-pub fn type_name_short<T: ?Sized>() -> &'static str
-{
-    let mut s = std::any::type_name::<T>();
-    if let Some(idx) = memchr::memrchr(b':', s.as_bytes()) {
-	s = &s[idx.saturating_sub(1)..];
-	if s.len() >= 2 && &s[..2] == "::" {
-	    s = &s[2..];
-	}
+    /// Should `-exec/{}` children inherit a fd of `collect`'s working directory?
+    #[inline(always)] 
+    pub fn exec_working_fd(&self) -> bool
+    {
+	self.exec_working_fd
     }
-    if s.len() > 0 && (s.as_bytes()[s.len()-1] == b'>' && s.as_bytes()[0] != b'<') {
-	s = &s[..(s.len()-1)];
+
+    /// Should no more `-exec/{}` children be ran after one fails?
+    ///
+    /// If not, every child is ran and all the failures are reported together (see `exec::wait_all()`.)
+    #[inline(always)] 
+    pub fn exec_fail_fast(&self) -> bool
+    {
+	self.exec_fail_fast
     }
-    s
-}
 
-#[cfg_attr(feature="logging", instrument(level="debug", skip_all, fields(args = ?type_name_short::<I>())))]
-fn parse_from<I, T>(args: I) -> Result<Options, ArgParseError>
-where I: IntoIterator<Item = T>,
-      T: Into<OsString>
-{   
-    let mut args = args.into_iter().map(Into::into);
-    let mut output = Options::default();
-    let mut idx = 0;
-    //XXX: When `-exec{}` is provided, but no `{}` arguments are found, maybe issue a warning with `if_trace!(warning!())`? There are valid situations to do this in, but they are rare...
-    let mut parser = || -> Result<_, ArgParseError> {
-	while let Some(mut arg) = args.next() {
-	    idx += 1;
-	    macro_rules! try_parse_for {
-		(@ assert_parser_okay $parser:path) => {
-		    const _:() = {
-			const fn _assert_is_parser<P: TryParse + ?Sized>() {}
-			const fn _assert_is_result<P: TryParse + ?Sized>(res: P::Output) -> P::Output { res }
-			
-			_assert_is_parser::<$parser>();
-		    };
-		};
-		($parser:path => $then:expr) => {
-		    {
-			try_parse_for!(@ assert_parser_okay $parser);
-			//_assert_is_closure(&$then); //XXX: There isn't a good way to tell without having to specify some bound on return type...
-			if let Some(result) = parsers::try_parse_with::<$parser>(&mut arg, &mut args) {
-			    
-			    // Result succeeded on visitation, use this parser for this argument and then continue to the next one.
-			    $then(result?);
-			    continue;
-			}
-			// Result failed on visitation, so continue the control flow to the next `try_parse_for!()` visitation attempt.
-		    }
-		};
-		/*($parser:path => $then:expr) => {
-		    $then(try_parse_for!(try $parser => std::convert::identity)?)
-		}*/
-	    }	    
-	    //TODO: Add `impl TryParse` struct for `--help` and add it at the *top* of the visitation stack (it will most likely appear there.)
-	    // This may require a re-work of the `Options` struct, or an enum wrapper around it should be returned instead of options directly, for special modes (like `--help` is, etc.) Perhaps `pub enum Mode { Normal(Options), Help, }` or something should be returned, and `impl From<Options>` for it, with the caller of this closure (below) 
-	    try_parse_for!(parsers::ExecMode => |result| output.exec.push(result));
-	    
-	    //Note: try_parse_for!(parsers::SomeOtherOption => |result| output.some_other_option.set(result.something)), etc, for any newly added arguments.
-	    
-	    if_trace!(debug!("reached end of parser visitation for argument #{idx} {arg:?}! Failing now with `UnknownOption`"));
-	    return Err(ArgParseError::UnknownOption(arg));
-	}
-	Ok(())
-    };
-    parser()
-	.with_index(idx)
-	.map(move |_| output.into()) //XXX: This is `output.into()`, because when successful result return type is changed from directly `Options` to `enum Mode` (which will `impl From<Options>`), it will allow any `impl Into<Mode>` to be returned. (Boxed dynamic dispatch with a trait `impl FromMode<T: ?Sized> (for Mode) { fn from(val: Box<T>) -> Self { IntoMode::into(val) } }, auto impl trait IntoMode { fn into(self: Box<Self>) -> Mode }` may be required if different types are returned from the closure, this is okay, as argument parsed struct can get rather large.)
-}
+    /// The file to write the PIDs of spawned children to, if `--exec-pid-file` was passed.
+    #[inline(always)] 
+    pub fn exec_pid_file(&self) -> Option<&Path>
+    {
+	self.exec_pid_file.as_deref()
+    }
 
-#[derive(Debug)]
-pub enum ArgParseError
-{
-    /// With an added argument index.
-    WithIndex(usize, Box<ArgParseError>),
-    /// Returned when an invalid or unknown argument is found
-    UnknownOption(OsString),
-    /// Returned when the argument, `argument`, is passed in an invalid context by the user.
-    InvalidUsage { argument: String, message: String, inner: Option<Box<dyn error::Error + Send + Sync + 'static>> },
-    //VisitationFailed,
-    
-}
+    /// The nice value to run `-exec/{}` children with, if `--exec-nice` was passed.
+    #[inline(always)] 
+    pub fn exec_nice(&self) -> Option<libc::c_int>
+    {
+	self.exec_nice
+    }
 
-trait ArgParseErrorExt<T>: Sized
-{
-    fn with_index(self, idx: usize) -> Result<T, ArgParseError>;
-}
-impl ArgParseError
-{
+    /// The number of bytes of each child's output to capture with `--exec-output-to-memfd`, if `--exec-max-output` was passed.
+    ///
+    /// A child that writes more than this is killed, and its output truncated to it.
+    #[inline(always)] 
+    pub fn exec_max_output(&self) -> Option<u64>
+    {
+	self.exec_max_output
+    }
+
+    /// How long each `-exec/{}` child may run for, if `--exec-timeout` was passed.
+    ///
+    /// A child still running after this is sent `SIGTERM`, then `SIGKILL` if that does not stop it.
+    #[inline(always)] 
+    pub fn exec_timeout(&self) -> Option<Duration>
+    {
+	self.exec_timeout
+    }
+
+    /// Where the `stdout` of `-exec/{}` children goes, if `--exec-stdout` was passed.
+    ///
+    /// Without it (or with `--exec-stdout -`), children inherit `collect`'s own `stdout`.
+    #[inline(always)]
+    pub fn exec_stdout(&self) -> Option<&OutputTarget>
+    {
+	self.exec_stdout.as_ref()
+    }
+
+    /// Were any `-exec/{}` commands, or options that only affect them, passed?
+    ///
+    /// A build without the `exec` feature rejects these, instead of silently ignoring them.
+    pub fn uses_exec(&self) -> bool
+    {
+	self.opt_exec().len() > 0
+	    || self.exec_command_from_fd().is_some()
+	    || self.exec_on_signal().is_some()
+	    || self.exec_working_fd()
+	    || self.exec_pid_file().is_some()
+	    || self.exec_fail_fast()
+	    || self.exec_stdin_keep_open()
+	    || self.exec_stdin_pipe()
+	    || self.exec_chdir_to_fd_dir()
+	    || self.exec_input_readonly_reopen()
+	    || self.exec_parallel()
+	    || self.exec_stdin_memfd_refresh()
+	    || self.fd_path_style() != FdPathStyle::Proc
+	    || self.exec_max_output().is_some()
+	    || self.exec_timeout().is_some()
+	    || self.exec_stdout().is_some()
+	    || !self.exec_prefix().is_empty()
+	    || self.exec_stdin_tee()
+	    || self.exec_input_nonblock()
+	    || self.exec_output_to_memfd()
+	    || self.exec_nice().is_some()
+	    || !self.exec_env().is_empty()
+	    || self.exec_expand_env().is_some()
+	    // Also set by `--seal`.
+	    || self.exec_input_seal_level().is_some()
+	    || self.exec_stdin_from.is_some()
+    }
+
+    /// The command to run when a terminating signal is caught, if `--exec-on-signal` was passed.
+    #[inline(always)] 
+    pub fn exec_on_signal(&self) -> Option<&OsStr>
+    {
+	self.exec_on_signal.as_deref()
+    }
+
+    /// Remove all `-exec/{}` modes from the options, leaving the rest of the options intact.
     #[inline] 
-    pub fn wrap_index(self, idx: usize) -> Self {
-	Self::WithIndex(idx, Box::new(self))
+    #[cfg_attr(not(feature="exec"), allow(dead_code))]
+    pub fn take_exec(&mut self) -> Vec<ExecMode>
+    {
+	std::mem::take(&mut self.exec)
     }
-}
-impl<T, E: Into<ArgParseError>> ArgParseErrorExt<T> for Result<T, E>
-{
+
+    /// Where `-exec/{}` children get their input from.
+    #[inline] 
+    pub fn exec_stdin_from(&self) -> ExecStdinFrom
+    {
+	self.exec_stdin_from.unwrap_or_default()
+    }
+
+    /// Is `--exec-stdin-tee` set?
+    ///
+    /// If it is, children spawned with `-exec` *must* see the full buffer from offset 0 (the same data that was written to `stdout`.)
     #[inline(always)] 
-    fn with_index(self, idx: usize) -> Result<T, ArgParseError> {
-	self.map_err(Into::into)
-	    .map_err(move |e| e.wrap_index(idx))
+    pub fn exec_stdin_tee(&self) -> bool
+    {
+	self.exec_stdin_tee
     }
-}
 
-impl error::Error for ArgParseError
-{
+    /// The wrapper command and its arguments that every `-exec/{}` command is ran through, if `--exec-prefix` was passed (empty if not.)
+    #[inline(always)] 
+    pub fn exec_prefix(&self) -> &[OsString]
+    {
+	&self.exec_prefix[..]
+    }
+
+    /// Is `--exec-stdin-keep-open` set?
+    ///
+    /// If it is, `-exec` children do not see EOF on `stdin` after the buffer (see `exec::feed_pipe()`.)
+    #[inline(always)] 
+    pub fn exec_stdin_keep_open(&self) -> bool
+    {
+	self.exec_stdin_keep_open
+    }
+
+    /// Is `--exec-stdin-pipe` set?
+    ///
+    /// If it is, `-exec` children are fed the buffer through a pipe that is closed after it is written (see `exec::feed_pipe()`.)
+    #[inline(always)] 
+    pub fn exec_stdin_pipe(&self) -> bool
+    {
+	self.exec_stdin_pipe
+    }
+
+    /// Is `--exec-chdir-to-fd-dir` set?
+    ///
+    /// If it is, `-exec{}` children are started in the directory holding the link to their input, which `{}` is then relative to.
+    #[inline(always)] 
+    pub fn exec_chdir_to_fd_dir(&self) -> bool
+    {
+	self.exec_chdir_to_fd_dir
+    }
+
+    /// The form of the path to its input that `{}` is replaced with in `-exec{}` (see `--fd-style`.)
+    #[inline(always)] 
+    pub fn fd_path_style(&self) -> FdPathStyle
+    {
+	self.fd_path_style
+    }
+
+    /// Is `--exec-input-readonly-reopen` set?
+    ///
+    /// If it is, each `-exec/{}` child's input is the buffer re-opened read-only (through `/proc/self/fd`), with its own offset.
+    #[inline(always)] 
+    pub fn exec_input_readonly_reopen(&self) -> bool
+    {
+	self.exec_input_readonly_reopen
+    }
+
+    /// Is `--exec-stdin-memfd-refresh` set?
+    ///
+    /// Every `-exec` child is given its own open of the buffer (through `/proc/self/fd`), starting at offset 0, when possible. If this is set, a child for which it is not possible is not spawned, instead of being given a `dup()` of the buffer that shares its offset.
+    #[inline(always)] 
+    pub fn exec_stdin_memfd_refresh(&self) -> bool
+    {
+	self.exec_stdin_memfd_refresh
+    }
+
+    /// Is `--exec-parallel` set?
+    ///
+    /// If it is, every `-exec/{}` child is spawned up front and they run concurrently, instead of each one being waited on before the next is spawned.
+    #[inline(always)] 
+    pub fn exec_parallel(&self) -> bool
+    {
+	self.exec_parallel
+    }
+
+    /// Is `--exec-input-nonblock` set?
+    #[inline(always)] 
+    pub fn exec_input_nonblock(&self) -> bool
+    {
+	self.exec_input_nonblock
+    }
+
+    /// Is `--exec-output-to-memfd` set?
+    ///
+    /// If it is, the `-exec/{}` children are ran in order as a chain (see `exec::spawn_chain_sync()`.)
+    #[inline(always)] 
+    pub fn exec_output_to_memfd(&self) -> bool
+    {
+	self.exec_output_to_memfd
+    }
+
+    /// Is it an error if the seals from `--exec-input-seal-level` are not all present after applying them?
+    ///
+    /// This is `false` if `--no-memfd-seal-check` was passed, in which case it is only a warning.
+    #[inline(always)] 
+    #[cfg_attr(not(any(feature="memfile", feature="exec")), allow(dead_code))]
+    pub fn memfd_seal_check(&self) -> bool
+    {
+	!self.no_memfd_seal_check
+    }
+
+    /// The seals to apply to the buffer before it is given to `-exec/{}` children, if `--exec-input-seal-level` (or `--seal`, which is `all`) was passed.
+    ///
+    /// If neither was, only the size is sealed, and failing to do so is not an error.
+    #[inline(always)] 
+    pub fn exec_input_seal_level(&self) -> Option<SealLevel>
+    {
+	self.exec_input_seal_level.or(self.seal.then_some(SealLevel::ALL))
+    }
+
+    /// Extra environment variables to set for `-exec/{}` children.
+    ///
+    /// Any `{}` in a value is replaced with the path to the buffer when the child is spawned.
     #[inline] 
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-	match self {
-	    Self::InvalidUsage { inner, .. } => inner.as_ref().map(|x| -> &(dyn error::Error + 'static) {  x.as_ref() }),
-	    Self::WithIndex(_, inner) => inner.source(),
-	    _ => None,
+    pub fn exec_env(&self) -> &[(OsString, OsString)]
+    {
+	&self.exec_env[..]
+    }
+
+    /// Should `$VAR` / `${VAR}` references in `-exec/{}` arguments be expanded, and if so, how?
+    #[inline(always)] 
+    pub fn exec_expand_env(&self) -> Option<EnvExpansion>
+    {
+	self.exec_expand_env
+    }
+
+    /// The strategy to collect the input with.
+    ///
+    /// If `--strategy` was not passed, this is `auto` when the `memfile` strategy is compiled in, and `buffered` otherwise.
+    #[inline] 
+    pub fn strategy(&self) -> Strategy
+    {
+	self.strategy.unwrap_or_default()
+    }
+
+    /// The backend to allocate the buffer with for the `buffered` strategy.
+    ///
+    /// If `--buffer-backend` was not passed, this is the backend of `buffers::DefaultMut`.
+    #[inline] 
+    pub fn buffer_backend(&self) -> buffers::BufferBackend
+    {
+	self.buffer_backend.unwrap_or_default()
+    }
+
+    /// If reading from `stdin` fails partway, should the data collected before the failure still be written, and if so, what happens to the error afterwards?
+    #[inline(always)] 
+    pub fn best_effort(&self) -> Option<BestEffort>
+    {
+	self.best_effort
+    }
+
+    /// The shell to print a completion script for, if `--completions` was passed.
+    #[inline(always)] 
+    pub fn completions(&self) -> Option<completions::Shell>
+    {
+	self.completions
+    }
+
+    /// Is `--check` set? If it is, the `-exec/{}` commands are only validated, nothing is collected or run.
+    #[inline(always)] 
+    pub fn check(&self) -> bool
+    {
+	self.check
+    }
+
+    /// Is `--dry-run` set? If it is, what would be done is only reported, nothing is collected or run.
+    #[inline(always)] 
+    pub fn dry_run(&self) -> bool
+    {
+	self.dry_run
+    }
+
+    /// The number of bytes at the start of the input to read before closing it, if `--head` was passed.
+    #[inline(always)] 
+    pub fn head(&self) -> Option<u64>
+    {
+	self.head
+    }
+
+    /// The most bytes to read from the input: the smaller of `--head` and `--limit`, if either was passed.
+    #[inline] 
+    pub fn read_limit(&self) -> Option<u64>
+    {
+	match (self.head, self.limit) {
+	    (Some(head), Some(limit)) => Some(std::cmp::min(head, limit)),
+	    (head, limit) => head.or(limit),
 	}
     }
-}
-impl fmt::Display for ArgParseError
-{
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+
+    /// The number of bytes at the end of the input to keep, if `--tail` was passed.
+    #[inline(always)] 
+    pub fn tail(&self) -> Option<usize>
     {
-	match self {
-	    Self::WithIndex(index, inner) => write!(f, "Argument #{index}: {inner}"),
-	    Self::UnknownOption(opt) => {
-		f.write_str("Invalid/unknown argument: `")?;
-		f.write_str(String::from_utf8_lossy(opt.as_bytes()).as_ref())?;
-		f.write_str("`")
-	    },
-	    Self::InvalidUsage { argument, message, .. } => write!(f, "Invalid usage for argument `{argument}`: {message}")
+	self.tail
+    }
+
+    /// The capacity to set on `stdin` if it is a pipe, if `--pipe-size` was passed.
+    #[inline(always)] 
+    pub fn pipe_size(&self) -> Option<usize>
+    {
+	self.pipe_size
+    }
+
+    /// The extra targets (from `-t/--tee`) that the collected input is also written to.
+    #[inline(always)] 
+    pub fn tee(&self) -> &[OutputTarget]
+    {
+	&self.tee[..]
+    }
+
+    /// Is `--tee-strict` set?
+    ///
+    /// If it is, the first `--tee` target that cannot be written to fails the whole transfer, instead of only being skipped (with a warning.)
+    #[inline(always)] 
+    pub fn tee_strict(&self) -> bool
+    {
+	self.tee_strict
+    }
+
+    /// The code to exit with if no input was collected, if `--fail-on-empty` was passed.
+    #[inline(always)] 
+    pub fn fail_on_empty(&self) -> Option<i32>
+    {
+	self.fail_on_empty
+    }
+
+    /// The size to preallocate the buffer to, if `--buffer-size` was passed.
+    ///
+    /// This takes the place of the size of `stdin` (if it has one), but more than this can still be collected.
+    #[inline(always)] 
+    pub fn buffer_size(&self) -> Option<NonZeroUsize>
+    {
+	self.buffer_size
+    }
+
+    /// The percentage of available memory the in-memory buffer can hold before a warning is logged (`--mem-warn`, or `DEFAULT_MEM_WARN`.)
+    #[inline(always)] 
+    #[cfg_attr(not(feature="memfile"), allow(dead_code))]
+    pub fn mem_warn(&self) -> u8
+    {
+	self.mem_warn.unwrap_or(DEFAULT_MEM_WARN)
+    }
+
+    /// The percentage of available memory the in-memory buffer can hold before collecting fails (`--mem-max`, or `DEFAULT_MEM_MAX`.)
+    #[inline(always)] 
+    #[cfg_attr(not(feature="memfile"), allow(dead_code))]
+    pub fn mem_max(&self) -> u8
+    {
+	self.mem_max.unwrap_or(DEFAULT_MEM_MAX)
+    }
+
+    /// The part of the collected input to write, if `--output-range` was passed.
+    #[inline(always)] 
+    pub fn output_range(&self) -> Option<OutputRange>
+    {
+	self.output_range
+    }
+
+    /// Is `--output-range-clamp` set?
+    ///
+    /// If it is, the part of `--output-range` that is past the end of the input is ignored; otherwise, it is an error.
+    #[inline(always)] 
+    pub fn output_range_clamp(&self) -> bool
+    {
+	self.output_range_clamp
+    }
+
+    /// Where the input is collected from (`stdin`, unless a file was given.)
+    #[inline(always)] 
+    pub fn input(&self) -> &InputSource
+    {
+	&self.input
+    }
+
+    /// Where the collected input is written to (`stdout`, unless `--output`, `--output-fd` or `--null` was passed.)
+    #[inline] 
+    pub fn output(&self) -> &OutputTarget
+    {
+	const STDOUT: &OutputTarget = &OutputTarget::Stdout;
+	self.output.as_ref().unwrap_or(STDOUT)
+    }
+
+    /// Is `--append` set?
+    ///
+    /// If it is, the `--output` file is appended to, instead of being truncated and set to the length written. It is an error without `--output <PATH>`.
+    #[inline(always)] 
+    pub fn append(&self) -> bool
+    {
+	self.append
+    }
+
+    /// How the `--output` file is synced once it has been written, if `--fsync` was passed.
+    #[inline(always)] 
+    pub fn fsync(&self) -> Option<Fsync>
+    {
+	self.fsync
+    }
+
+    /// Which digest of the bytes written is printed to `stderr`, if `--sha256` or `--crc32` was passed.
+    #[inline(always)] 
+    pub fn digest(&self) -> Option<DigestKind>
+    {
+	self.digest
+    }
+
+    /// Is `stdout` closed once everything has been written to it (and any `-exec/{}` children have exited)?
+    ///
+    /// This is `false` if `--no-close-stdout` was passed, in which case it is only closed when the process exits.
+    #[inline(always)] 
+    pub fn close_stdout(&self) -> bool
+    {
+	!self.no_close_stdout
+    }
+
+    /// What to do if `stdin` and `stdout` are the same regular file.
+    #[inline] 
+    pub fn on_same_file(&self) -> SameFile
+    {
+	self.on_same_file.unwrap_or_default()
+    }
+
+    /// Is `--probe` set?
+    ///
+    /// If it is, `stdin` is inspected (see `sys::Probe`) and nothing is transferred.
+    #[inline(always)] 
+    pub fn probe(&self) -> bool
+    {
+	self.probe
+    }
+
+    /// Is `-q/--quiet` set?
+    ///
+    /// If it is, nothing is logged after the arguments are parsed, even if `RUST_LOG` asks for it (see `silence_tracing()`.)
+    #[inline(always)] 
+    #[cfg_attr(not(feature="logging"), allow(dead_code))]
+    pub fn quiet(&self) -> bool
+    {
+	self.quiet
+    }
+
+    /// When to show the progress of reading the input, if `--progress` was passed (see `progress::ProgressReader`.)
+    #[inline(always)] 
+    pub fn progress(&self) -> Option<Progress>
+    {
+	self.progress
+    }
+
+    /// Is `--stats` set?
+    ///
+    /// If it is, how much was read and written, and how long reading, writing and running `-exec/{}` children took (see `TransferReport`), is printed to `stderr`.
+    #[inline(always)] 
+    pub fn stats(&self) -> bool
+    {
+	self.stats
+    }
+
+    /// The name to give the in-memory file, if `--name` was passed.
+    #[inline] 
+    #[cfg_attr(not(feature="memfile"), allow(dead_code))]
+    pub fn memfile_name(&self) -> Option<&str>
+    {
+	self.memfile_name.as_deref()
+    }
+
+    /// Is `--name-truncate` set?
+    ///
+    /// If it is, a `--name` longer than `memfd_create()` allows is truncated (with a warning) instead of being an error.
+    #[inline(always)] 
+    #[cfg_attr(not(feature="memfile"), allow(dead_code))]
+    pub fn memfile_name_truncate(&self) -> bool
+    {
+	self.memfile_name_truncate
+    }
+
+    /// The huge pages to back the in-memory file with, if `--hugetlb` was passed.
+    #[inline(always)] 
+    pub fn hugetlb(&self) -> Option<HugePageSize>
+    {
+	self.hugetlb
+    }
+
+    /// Set the input to `input` (from `FILE`, or an argument after `--`), which can only be given once.
+    fn set_input(&mut self, input: InputSource) -> Result<(), ArgParseError>
+    {
+	match &self.input {
+	    InputSource::Path(first) => Err(ArgParseError::InvalidUsage { argument: parsers::InputPath::ARGUMENT.to_owned(), message: format!("only one input file can be given ({first:?} was already)"), inner: None }),
+	    InputSource::Stdin => {
+		self.input = input;
+		Ok(())
+	    },
+	}
+    }
+
+    /// Apply an argument-less switch to the options.
+    #[inline] 
+    fn apply_switch(&mut self, switch: parsers::Switch)
+    {
+	use parsers::Switch::*;
+	match switch {
+	    ExecStdinTee => self.exec_stdin_tee = true,
+	    ExecInputNonblock => self.exec_input_nonblock = true,
+	    ExecOutputToMemfd => self.exec_output_to_memfd = true,
+	    NameTruncate => self.memfile_name_truncate = true,
+	    Probe => self.probe = true,
+	    Stats => self.stats = true,
+	    Quiet => self.quiet = true,
+	    ExecWorkingFd => self.exec_working_fd = true,
+	    ExecFailFast => self.exec_fail_fast = true,
+	    NoMemfdSealCheck => self.no_memfd_seal_check = true,
+	    NoCloseStdout => self.no_close_stdout = true,
+	    Seal => self.seal = true,
+	    OutputRangeClamp => self.output_range_clamp = true,
+	    Append => self.append = true,
+	    Null => self.output = Some(OutputTarget::Null),
+	    TeeStrict => self.tee_strict = true,
+	    ExecStdinKeepOpen => self.exec_stdin_keep_open = true,
+	    ExecStdinPipe => self.exec_stdin_pipe = true,
+	    ExecChdirToFdDir => self.exec_chdir_to_fd_dir = true,
+	    ExecInputReadonlyReopen => self.exec_input_readonly_reopen = true,
+	    ExecParallel => self.exec_parallel = true,
+	    ExecStdinMemfdRefresh => self.exec_stdin_memfd_refresh = true,
+	    Check => self.check = true,
+	    DryRun => self.dry_run = true,
+	    Sha256 => self.digest = Some(DigestKind::Sha256),
+	    Crc32 => self.digest = Some(DigestKind::Crc32),
+	}
+    }
+}
+
+/// How the value of a known option is given, for generating shell completions (see `known_options()`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptionValue
+{
+    /// A switch, which takes no value.
+    None,
+    /// A required value (`--opt value` or `--opt=value`), which is one of these (or anything, if empty.)
+    Required(&'static [&'static str]),
+    /// A required value that is a file path.
+    File,
+    /// An optional value, which can only be given as `--opt=value` and is one of these.
+    Optional(&'static [&'static str]),
+}
+
+/// Every option recognised by `parse_args()` (except the `-exec/{}` modes), and how its value is given.
+pub fn known_options() -> impl Iterator<Item = (&'static str, OptionValue)>
+{
+    use parsers::*;
+    use OptionValue::*;
+    let values: [(&'static str, OptionValue); 34] = [
+	(Completions::ARGUMENT, Required(&["bash", "zsh", "fish"])),
+	(ExecCommandFromFd::ARGUMENT, Required(&[])),
+	(ExecStdinFrom::ARGUMENT, Required(&["input", "buffer"])),
+	(ExecPidFile::ARGUMENT, File),
+	(ExecNice::ARGUMENT, Required(&[])),
+	(ExecMaxOutput::ARGUMENT, Required(&[])),
+	(ExecTimeout::ARGUMENT, Required(&[])),
+	(ExecStdout::ARGUMENT, File),
+	(ExecOnSignal::ARGUMENT, Required(&[])),
+	(ExpandEnv::ARGUMENT, Optional(&["lenient", "strict"])),
+	(ExecEnv::ARGUMENT, Required(&[])),
+	(SealLevel::ARGUMENT, Required(&["write", "grow", "shrink", "all", "none"])),
+	(BufferBackend::ARGUMENT, Required(&["vec", #[cfg(feature="bytes")] "bytes"])),
+	(Strategy::ARGUMENT, Required(&[#[cfg(feature="memfile")] "auto", "buffered", #[cfg(feature="memfile")] "memfd"])),
+	(Name::ARGUMENT, Required(&[])),
+	(Hugetlb::ARGUMENT, Optional(&[])),
+	(BestEffort::ARGUMENT, Optional(&["error", "warn"])),
+	(Progress::ARGUMENT, Optional(&["force"])),
+	(FailOnEmpty::ARGUMENT, Optional(&[])),
+	(OnSameFile::ARGUMENT, Required(&["buffer", "error"])),
+	(Head::ARGUMENT, Required(&[])),
+	(Limit::ARGUMENT, Required(&[])),
+	(Tail::ARGUMENT, Required(&[])),
+	(PipeSize::ARGUMENT, Required(&[])),
+	(BufferSize::ARGUMENT, Required(&[])),
+	(MemWarn::ARGUMENT, Required(&[])),
+	(MemMax::ARGUMENT, Required(&[])),
+	(OutputRange::ARGUMENT, Required(&[])),
+	(Output::ARGUMENT, File),
+	(OutputFd::ARGUMENT, Required(&[])),
+	(Fsync::ARGUMENT, Optional(&["data", "all"])),
+	(Tee::ARGUMENT, File),
+	(ExecPrefix::ARGUMENT, Required(&[])),
+	(FdStyle::ARGUMENT, Required(&["proc", "dev"])),
+    ];
+    Switch::ALL.iter().map(|&(string, _)| (string, None))
+	.chain(values)
+}
+
+/// The executable name of this program.
+///
+/// # Returns
+/// * If the program's executable name is a valid UTF8 string, that string.
+/// * If it is not, then that string is lossily-converted to a UTF8 string, with invalid characters replaced accordingly. This can be checked by checking if the return value is `Cow::Owned`, if it is, then this is not a reliable indication of the exetuable path's basename.
+/// * If there is no program name provided, i.e. if `argc == 0`, then an empty string is returned.
+#[inline(always)] 
+pub fn program_name() -> Cow<'static, str>
+{
+    lazy_static! {
+	static ref NAME: OsString = std::env::args_os().next().unwrap_or(OsString::from_vec(Vec::new()));
+    }
+    String::from_utf8_lossy(NAME.as_bytes())
+}
+
+/// Parse the program's arguments into an `Options` array.
+/// If parsing fails, an `ArgParseError` is returned detailing why it failed.
+#[inline]
+#[cfg_attr(feature="logging", instrument(err(Debug)))]
+pub fn parse_args() -> Result<Options, ArgParseError>
+{
+    let iter = std::env::args_os();
+    if_trace!(trace!("argc == {}, argv == {iter:?}", iter.len()));
+    
+    parse_from(iter.skip(1))
+}
+
+#[inline(always)] 
+#[cfg_attr(not(feature="logging"), allow(dead_code))]
+pub fn type_name_short<T: ?Sized>() -> &'static str
+{
+    let mut s = std::any::type_name::<T>();
+    if let Some(idx) = memchr::memrchr(b':', s.as_bytes()) {
+	s = &s[idx.saturating_sub(1)..];
+	if s.len() >= 2 && &s[..2] == "::" {
+	    s = &s[2..];
+	}
+    }
+    if !s.is_empty() && (s.as_bytes()[s.len()-1] == b'>' && s.as_bytes()[0] != b'<') {
+	s = &s[..(s.len()-1)];
+    }
+    s
+}
+
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, fields(args = ?type_name_short::<I>())))]
+#[allow(clippy::useless_conversion)] // See the `XXX` on `output.into()` below.
+pub(crate) fn parse_from<I, T>(args: I) -> Result<Options, ArgParseError>
+where I: IntoIterator<Item = T>,
+      T: Into<OsString>
+{   
+    let mut args = args.into_iter().map(Into::into);
+    let mut output = Options::default();
+    let mut idx = 0;
+    //XXX: When `-exec{}` is provided, but no `{}` arguments are found, maybe issue a warning with `if_trace!(warning!())`? There are valid situations to do this in, but they are rare...
+    let mut parser = || -> Result<_, ArgParseError> {
+	while let Some(mut arg) = args.next() {
+	    idx += 1;
+	    macro_rules! try_parse_for {
+		(@ assert_parser_okay $parser:path) => {
+		    const _:() = {
+			const fn _assert_is_parser<P: TryParse + ?Sized>() {}
+			const fn _assert_is_result<P: TryParse + ?Sized>(res: P::Output) -> P::Output { res }
+			
+			_assert_is_parser::<$parser>();
+		    };
+		};
+		($parser:path => try $then:expr) => {
+		    {
+			try_parse_for!(@ assert_parser_okay $parser);
+			if let Some(result) = parsers::try_parse_with::<$parser>(&mut arg, &mut args) {
+			    // The argument was parsed, but it may still be invalid along with the ones before it.
+			    $then(result?)?;
+			    continue;
+			}
+		    }
+		};
+		($parser:path => $then:expr) => {
+		    {
+			try_parse_for!(@ assert_parser_okay $parser);
+			//_assert_is_closure(&$then); //XXX: There isn't a good way to tell without having to specify some bound on return type...
+			if let Some(result) = parsers::try_parse_with::<$parser>(&mut arg, &mut args) {
+			    
+			    // Result succeeded on visitation, use this parser for this argument and then continue to the next one.
+			    $then(result?);
+			    continue;
+			}
+			// Result failed on visitation, so continue the control flow to the next `try_parse_for!()` visitation attempt.
+		    }
+		};
+		/*($parser:path => $then:expr) => {
+		    $then(try_parse_for!(try $parser => std::convert::identity)?)
+		}*/
+	    }	    
+	    //TODO: Add `impl TryParse` struct for `--help` and add it at the *top* of the visitation stack (it will most likely appear there.)
+	    // This may require a re-work of the `Options` struct, or an enum wrapper around it should be returned instead of options directly, for special modes (like `--help` is, etc.) Perhaps `pub enum Mode { Normal(Options), Help, }` or something should be returned, and `impl From<Options>` for it, with the caller of this closure (below) 
+	    try_parse_for!(parsers::ExecMode => |result| output.exec.push(result));
+	    try_parse_for!(parsers::Switch => |result| output.apply_switch(result));
+	    try_parse_for!(parsers::Completions => |result| output.completions = Some(result));
+	    try_parse_for!(parsers::ExecCommandFromFd => |result| output.exec_command_from_fd = Some(result));
+	    try_parse_for!(parsers::ExecStdinFrom => |result| output.exec_stdin_from = Some(result));
+	    try_parse_for!(parsers::ExecPidFile => |result| output.exec_pid_file = Some(result));
+	    try_parse_for!(parsers::ExecNice => |result| output.exec_nice = Some(result));
+	    try_parse_for!(parsers::ExecMaxOutput => |result| output.exec_max_output = Some(result));
+	    try_parse_for!(parsers::ExecTimeout => |result| output.exec_timeout = Some(result));
+	    try_parse_for!(parsers::ExecStdout => |result| output.exec_stdout = Some(result));
+	    try_parse_for!(parsers::ExecOnSignal => |result| output.exec_on_signal = Some(result));
+	    try_parse_for!(parsers::ExpandEnv => |result| output.exec_expand_env = Some(result));
+	    try_parse_for!(parsers::ExecEnv => |result| output.exec_env.push(result));
+	    try_parse_for!(parsers::SealLevel => |result| output.exec_input_seal_level = Some(result));
+	    try_parse_for!(parsers::BufferBackend => |result| output.buffer_backend = Some(result));
+	    try_parse_for!(parsers::Strategy => |result| output.strategy = Some(result));
+	    try_parse_for!(parsers::Name => |result| output.memfile_name = Some(result));
+	    try_parse_for!(parsers::Hugetlb => |result| output.hugetlb = Some(result));
+	    try_parse_for!(parsers::BestEffort => |result| output.best_effort = Some(result));
+	    try_parse_for!(parsers::Progress => |result| output.progress = Some(result));
+	    try_parse_for!(parsers::Fsync => |result| output.fsync = Some(result));
+	    try_parse_for!(parsers::FailOnEmpty => |result| output.fail_on_empty = Some(result));
+	    try_parse_for!(parsers::OnSameFile => |result| output.on_same_file = Some(result));
+	    try_parse_for!(parsers::FdStyle => |result| output.fd_path_style = result);
+	    try_parse_for!(parsers::Head => |result| output.head = Some(result));
+	    try_parse_for!(parsers::Limit => |result| output.limit = Some(result));
+	    try_parse_for!(parsers::Tail => |result| output.tail = Some(result));
+	    try_parse_for!(parsers::PipeSize => |result| output.pipe_size = Some(result));
+	    try_parse_for!(parsers::BufferSize => |result| output.buffer_size = Some(result));
+	    try_parse_for!(parsers::MemWarn => |result| output.mem_warn = Some(result));
+	    try_parse_for!(parsers::MemMax => |result| output.mem_max = Some(result));
+	    try_parse_for!(parsers::OutputRange => |result| output.output_range = Some(result));
+	    try_parse_for!(parsers::Output => |result| output.output = Some(result));
+	    try_parse_for!(parsers::OutputFd => |result| output.output = Some(OutputTarget::Fd(result)));
+	    try_parse_for!(parsers::Tee => |result| output.tee.push(result));
+	    try_parse_for!(parsers::ExecPrefix => |result| output.exec_prefix = result);
+	    try_parse_for!(parsers::InputPath => try |result| output.set_input(result));
+	    // Everything after `--` is the input, even if it starts with `-`.
+	    try_parse_for!(parsers::EndOfOptions => try |rest: Vec<OsString>| rest.into_iter().try_for_each(|path| output.set_input(parsers::InputPath::source(path))));
+	    
+	    //Note: try_parse_for!(parsers::SomeOtherOption => |result| output.some_other_option.set(result.something)), etc, for any newly added arguments.
+	    
+	    if_trace!(debug!("reached end of parser visitation for argument #{idx} {arg:?}! Failing now with `UnknownOption`"));
+	    return Err(ArgParseError::UnknownOption(arg));
+	}
+	Ok(())
+    };
+    parser()
+	.with_index(idx)
+	.map(move |_| output.into()) //XXX: This is `output.into()`, because when successful result return type is changed from directly `Options` to `enum Mode` (which will `impl From<Options>`), it will allow any `impl Into<Mode>` to be returned. (Boxed dynamic dispatch with a trait `impl FromMode<T: ?Sized> (for Mode) { fn from(val: Box<T>) -> Self { IntoMode::into(val) } }, auto impl trait IntoMode { fn into(self: Box<Self>) -> Mode }` may be required if different types are returned from the closure, this is okay, as argument parsed struct can get rather large.)
+}
+
+#[derive(Debug)]
+pub enum ArgParseError
+{
+    /// With an added argument index.
+    WithIndex(usize, Box<ArgParseError>),
+    /// Returned when an invalid or unknown argument is found
+    UnknownOption(OsString),
+    /// Returned when the argument, `argument`, is passed in an invalid context by the user.
+    InvalidUsage { argument: String, message: String, inner: Option<Box<dyn error::Error + Send + Sync + 'static>> },
+    //VisitationFailed,
+    
+}
+
+trait ArgParseErrorExt<T>: Sized
+{
+    fn with_index(self, idx: usize) -> Result<T, ArgParseError>;
+}
+impl ArgParseError
+{
+    #[inline] 
+    pub fn wrap_index(self, idx: usize) -> Self {
+	Self::WithIndex(idx, Box::new(self))
+    }
+}
+impl<T, E: Into<ArgParseError>> ArgParseErrorExt<T> for Result<T, E>
+{
+    #[inline(always)] 
+    fn with_index(self, idx: usize) -> Result<T, ArgParseError> {
+	self.map_err(Into::into)
+	    .map_err(move |e| e.wrap_index(idx))
+    }
+}
+
+impl error::Error for ArgParseError
+{
+    #[inline] 
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	match self {
+	    Self::InvalidUsage { inner, .. } => inner.as_ref().map(|x| -> &(dyn error::Error + 'static) {  x.as_ref() }),
+	    Self::WithIndex(_, inner) => inner.source(),
+	    _ => None,
+	}
+    }
+}
+impl fmt::Display for ArgParseError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match self {
+	    Self::WithIndex(index, inner) => write!(f, "Argument #{index}: {inner}"),
+	    Self::UnknownOption(opt) => {
+		f.write_str("Invalid/unknown argument: `")?;
+		f.write_str(String::from_utf8_lossy(opt.as_bytes()).as_ref())?;
+		f.write_str("`")
+	    },
+	    Self::InvalidUsage { argument, message, .. } => write!(f, "Invalid usage for argument `{argument}`: {message}")
+	}
+    }
+}
+
+trait ArgError: error::Error + Send + Sync + 'static
+{
+    fn into_invalid_usage(self) -> (String, String, Box<dyn error::Error + Send + Sync + 'static>)
+    where Self: Sized;
+}
+
+impl ArgError for std::convert::Infallible
+{
+    #[inline(always)] 
+    fn into_invalid_usage(self) -> (String, String, Box<dyn error::Error + Send + Sync + 'static>)
+    where Self: Sized {
+	match self {}
+    }
+}
+
+/// Error returned when the value of an option is missing or invalid.
+#[derive(Debug)]
+pub struct ValueParseError
+{
+    argument: &'static str,
+    value: Option<OsString>,
+    expected: Cow<'static, str>,
+}
+
+impl ValueParseError
+{
+    #[inline] 
+    pub fn new(argument: &'static str, value: Option<OsString>, expected: impl Into<Cow<'static, str>>) -> Self
+    {
+	Self { argument, value, expected: expected.into() }
+    }
+}
+
+impl error::Error for ValueParseError{}
+impl fmt::Display for ValueParseError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match &self.value {
+	    Some(value) => write!(f, "invalid value {value:?} for {}: expected {}", self.argument, self.expected),
+	    None => write!(f, "{} needs a value: expected {}", self.argument, self.expected),
+	}
+    }
+}
+
+impl ArgError for ValueParseError
+{
+    fn into_invalid_usage(self) -> (String, String, Box<dyn error::Error + Send + Sync + 'static>)
+    where Self: Sized {
+	(self.argument.to_owned(), format!("Expected {}.", self.expected), Box::new(self))
+    }
+}
+
+/// Take the value of an option that requires one.
+///
+/// The value is either given in the same argument (`--option=value`), or is the next argument (`--option value`.)
+#[inline] 
+fn take_value<I>(argument: &OsStr, rest: &mut I) -> Option<OsString>
+where I: ?Sized + Iterator<Item = OsString>
+{
+    match split_value(argument) {
+	(_, Some(value)) => Some(value.to_owned()),
+	(_, None) => rest.next(),
+    }
+}
+
+/// Split an argument of the form `--option=value` into `(--option, Some(value))`.
+///
+/// If there is no `=` in the argument, `(argument, None)` is returned.
+#[inline] 
+fn split_value(argument: &OsStr) -> (&OsStr, Option<&OsStr>)
+{
+    let bytes = argument.as_bytes();
+    match memchr::memchr(b'=', bytes) {
+	Some(idx) => (OsStr::from_bytes(&bytes[..idx]), Some(OsStr::from_bytes(&bytes[(idx+1)..]))),
+	None => (argument, None),
+    }
+}
+
+trait TryParse: Sized
+{
+    type Error: ArgError;
+    type Output;
+    
+    #[inline(always)] 
+    fn visit(argument: &OsStr) -> Option<Self> { let _ = argument;  None }
+    fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+    where I: ?Sized + Iterator<Item = OsString>;
+}
+
+impl<E: error::Error + Send + Sync + 'static> From<(String, String, E)> for ArgParseError
+{
+    #[inline] 
+    fn from((argument, message, inner): (String, String, E)) -> Self
+    {
+	Self::InvalidUsage { argument, message, inner: Some(Box::new(inner)) }
+    }
+}
+
+impl<E: ArgError> From<E> for ArgParseError
+{
+    #[inline(always)] 
+    fn from(from: E) -> Self
+    {
+	let (argument, message, inner) = from.into_invalid_usage();
+	Self::InvalidUsage { argument, message, inner: Some(inner) }
+    }
+}
+
+#[inline(always)] 
+#[cfg_attr(not(feature="logging"), allow(dead_code))]
+fn extract_last_pathspec(s: &str) -> &str
+{
+    //#[cfg_attr(feature="logging", feature(instrument(ret)))]
+    #[allow(dead_code)]
+    fn string_diff<'a>(a: &'a str, b: &'a str) -> usize
+    {
+	#[cold]
+	#[inline(never)]
+	fn _panic_non_inclusive(swap: bool) -> !
+	{
+	    let a = if swap { "b" } else { "a" };
+	    let b = if swap { "a" } else { "b" };
+	    panic!("String {a} was not inside string {b}")
+	}
+	let a_addr = a.as_ptr() as usize;
+	let b_addr = b.as_ptr() as usize;
+	let (a_addr, b_addr, sw) = 
+	    if !(a_addr + a.len() > b_addr + b.len() && b_addr + b.len() < a_addr + a.len()) {
+		(b_addr, a_addr, true)
+	    } else {
+		(a_addr, a_addr, false)
+	    };
+	
+	if b_addr < a_addr /*XXX || (b_addr + b.len()) > (a_addr + a.len())*/ {
+	    _panic_non_inclusive(sw)
+	}
+	a_addr.abs_diff(b_addr)
+    }
+    s.rsplit_once("::")
+	.map(|(_a, b)| /*XXX: This doesn't work...match _a.rsplit_once("::") {
+	     Some((_, last)) => &s[string_diff(s, last)..],
+	     _ => b
+	}*/ b)
+	.unwrap_or(s)
+}
+
+mod parsers {
+    use super::*;
+
+    #[inline(always)]
+    #[cfg_attr(feature="logging", instrument(level="debug", skip(rest), fields(parser = %extract_last_pathspec(type_name::<P>()))))]
+    pub(super) fn try_parse_with<P>(arg: &mut OsString, rest: &mut impl Iterator<Item = OsString>) -> Option<Result<P::Output, ArgParseError>>
+    where P: TryParse
+    {
+	#[cfg(feature="logging")] 
+	let _span = tracing::warn_span!("parse", parser= %extract_last_pathspec(type_name::<P>()), ?arg);
+	P::visit(arg.as_os_str()).map(move |parser| {
+	    #[cfg(feature="logging")]
+	    let _in = _span.enter();
+	    parser.parse(/*if_trace!{true arg.clone(); */std::mem::take(arg) /*}*/, rest).map_err(Into::into) //This clone is not needed, the argument is captured by `try_parse_with` (in debug) and `parse` (in warning) already.
+	}).inspect(|_res| {
+	    #[cfg(feature="logging")]
+	    if let Err(err) = _res.as_ref() {
+		::tracing::event!(::tracing::Level::ERROR, ?err, "Attempted parse failed with error")
+	    }
+	}).or_else(|| {
+	    #[cfg(feature="logging")]
+	    ::tracing::event!(::tracing::Level::TRACE, "no match for this parser with this arg, continuing visitation.");
+	    None
+	})
+    }
+
+    /// Parser for argument-less switches (e.g. `--exec-stdin-tee`.)
+    ///
+    /// Parsing a switch cannot fail, it is either matched or it is not.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Switch {
+	/// `--exec-stdin-tee`
+	ExecStdinTee,
+	/// `--exec-input-nonblock`
+	ExecInputNonblock,
+	/// `--exec-output-to-memfd`
+	ExecOutputToMemfd,
+	/// `--name-truncate`
+	NameTruncate,
+	/// `--probe`
+	Probe,
+	/// `--stats`
+	Stats,
+	/// `-q/--quiet`
+	Quiet,
+	/// `--exec-working-fd`
+	ExecWorkingFd,
+	/// `--exec-fail-fast`
+	ExecFailFast,
+	/// `--no-memfd-seal-check`
+	NoMemfdSealCheck,
+	/// `--no-close-stdout`
+	NoCloseStdout,
+	/// `--seal`
+	Seal,
+	/// `--output-range-clamp`
+	OutputRangeClamp,
+	/// `--append`
+	Append,
+	/// `--null`
+	Null,
+	/// `--tee-strict`
+	TeeStrict,
+	/// `--exec-stdin-keep-open`
+	ExecStdinKeepOpen,
+	/// `--exec-stdin-pipe`
+	ExecStdinPipe,
+	/// `--exec-chdir-to-fd-dir`
+	ExecChdirToFdDir,
+	/// `--exec-input-readonly-reopen`
+	ExecInputReadonlyReopen,
+	/// `--exec-parallel`
+	ExecParallel,
+	/// `--exec-stdin-memfd-refresh`
+	ExecStdinMemfdRefresh,
+	/// `--check`
+	Check,
+	/// `--dry-run`
+	DryRun,
+	/// `--sha256`
+	Sha256,
+	/// `--crc32`
+	Crc32,
+    }
+
+    impl Switch {
+	/// All switches, and the argument string that selects them.
+	pub(super) const ALL: &'static [(&'static str, Self)] = &[
+	    ("--exec-stdin-tee", Self::ExecStdinTee),
+	    ("--exec-input-nonblock", Self::ExecInputNonblock),
+	    ("--exec-output-to-memfd", Self::ExecOutputToMemfd),
+	    ("--name-truncate", Self::NameTruncate),
+	    ("--probe", Self::Probe),
+	    ("--stats", Self::Stats),
+	    ("--quiet", Self::Quiet),
+	    ("--exec-working-fd", Self::ExecWorkingFd),
+	    ("--exec-fail-fast", Self::ExecFailFast),
+	    ("--no-memfd-seal-check", Self::NoMemfdSealCheck),
+	    ("--no-close-stdout", Self::NoCloseStdout),
+	    ("--seal", Self::Seal),
+	    ("--output-range-clamp", Self::OutputRangeClamp),
+	    ("--append", Self::Append),
+	    ("--null", Self::Null),
+	    ("--tee-strict", Self::TeeStrict),
+	    ("--exec-stdin-keep-open", Self::ExecStdinKeepOpen),
+	    ("--exec-stdin-pipe", Self::ExecStdinPipe),
+	    ("--exec-chdir-to-fd-dir", Self::ExecChdirToFdDir),
+	    ("--exec-input-readonly-reopen", Self::ExecInputReadonlyReopen),
+	    ("--exec-parallel", Self::ExecParallel),
+	    ("--exec-stdin-memfd-refresh", Self::ExecStdinMemfdRefresh),
+	    ("--check", Self::Check),
+	    ("--dry-run", Self::DryRun),
+	    ("--sha256", Self::Sha256),
+	    ("--crc32", Self::Crc32),
+	];
+
+	/// Switches that also have a short form, and that form (these are not in `ALL`, so they are not completed.)
+	const SHORT: &'static [(&'static str, Self)] = &[
+	    ("-q", Self::Quiet),
+	];
+    }
+
+    impl TryParse for Switch
+    {
+	type Error = std::convert::Infallible;
+	type Output = Self;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    Self::ALL.iter().chain(Self::SHORT)
+		.find_map(|&(string, switch)| (argument.as_bytes() == string.as_bytes()).then_some(switch))
+	}
+
+	#[inline(always)] 
+	fn parse<I>(self, _argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    Ok(self)
+	}
+    }
+
+    /// Parser for `--completions <bash|zsh|fish>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Completions;
+
+    impl Completions {
+	pub const ARGUMENT: &'static str = "--completions";
+	const EXPECTED: &'static str = "`bash`, `zsh` or `fish`";
+    }
+
+    impl TryParse for Completions
+    {
+	type Error = ValueParseError;
+	type Output = completions::Shell;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    Ok(match value.as_bytes() {
+		b"bash" => completions::Shell::Bash,
+		b"zsh" => completions::Shell::Zsh,
+		b"fish" => completions::Shell::Fish,
+		_ => return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    })
+	}
+    }
+
+    /// Parser for `--exec-command-from-fd <fd>`.
+    ///
+    /// The standard streams (0, 1 and 2) are not accepted.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecCommandFromFd;
+
+    impl ExecCommandFromFd {
+	pub const ARGUMENT: &'static str = "--exec-command-from-fd";
+	const EXPECTED: &'static str = "a file descriptor number greater than 2";
+    }
+
+    impl TryParse for ExecCommandFromFd
+    {
+	type Error = ValueParseError;
+	type Output = RawFd;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    match value.to_str().and_then(|value| value.parse::<RawFd>().ok()) {
+		Some(fd) if fd > 2 => Ok(fd),
+		_ => Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--head <BYTES>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Head;
+
+    impl Head {
+	pub const ARGUMENT: &'static str = "--head";
+	const EXPECTED: &'static str = "a number of bytes";
+    }
+
+    impl TryParse for Head
+    {
+	type Error = ValueParseError;
+	type Output = u64;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    value.to_str().and_then(|value| value.parse::<u64>().ok())
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `--exec-max-output <BYTES>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecMaxOutput;
+
+    impl ExecMaxOutput {
+	pub const ARGUMENT: &'static str = "--exec-max-output";
+	const EXPECTED: &'static str = "a number of bytes, optionally followed by `K`, `M`, or `G`";
+    }
+
+    impl TryParse for ExecMaxOutput
+    {
+	type Error = ValueParseError;
+	type Output = u64;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    parse_size(&value)
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `--exec-timeout <DURATION>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecTimeout;
+
+    impl ExecTimeout {
+	pub const ARGUMENT: &'static str = "--exec-timeout";
+	const EXPECTED: &'static str = "a non-zero duration, in seconds or followed by `ms`, `s`, or `m`";
+    }
+
+    impl TryParse for ExecTimeout
+    {
+	type Error = ValueParseError;
+	type Output = std::time::Duration;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    parse_duration(&value)
+		.filter(|duration| !duration.is_zero())
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `--limit <BYTES>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Limit;
+
+    impl Limit {
+	pub const ARGUMENT: &'static str = "--limit";
+	const EXPECTED: &'static str = "a number of bytes, optionally followed by `K`, `M`, or `G`";
+    }
+
+    impl TryParse for Limit
+    {
+	type Error = ValueParseError;
+	type Output = u64;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    parse_size(&value)
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `--tail <BYTES>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Tail;
+
+    impl Tail {
+	pub const ARGUMENT: &'static str = "--tail";
+	const EXPECTED: &'static str = "a number of bytes";
+    }
+
+    impl TryParse for Tail
+    {
+	type Error = ValueParseError;
+	type Output = usize;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    value.to_str().and_then(|value| value.parse::<usize>().ok())
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `--output-range <START:END>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct OutputRange;
+
+    impl OutputRange {
+	pub const ARGUMENT: &'static str = "--output-range";
+	const EXPECTED: &'static str = "`START:END`, `START:` or `:END` in bytes, where `START` is not after `END`";
+    }
+
+    impl TryParse for OutputRange
+    {
+	type Error = ValueParseError;
+	type Output = super::OutputRange;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    let parse = |value: &str| -> Option<super::OutputRange> {
+		let (start, end) = value.split_once(':')?;
+		let start = match start {
+		    "" => 0,
+		    start => start.parse().ok()?,
+		};
+		let end = match end {
+		    "" => None,
+		    end => match end.parse().ok()? {
+			end if start <= end => Some(end),
+			_ => return None,
+		    },
+		};
+		Some(super::OutputRange { start, end })
+	    };
+	    value.to_str().and_then(parse)
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `--pipe-size <BYTES>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct PipeSize;
+
+    impl PipeSize {
+	pub const ARGUMENT: &'static str = "--pipe-size";
+	const EXPECTED: &'static str = "a non-zero number of bytes";
+    }
+
+    impl TryParse for PipeSize
+    {
+	type Error = ValueParseError;
+	type Output = usize;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    value.to_str().and_then(|value| value.parse::<usize>().ok())
+		.filter(|&size| size > 0)
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parse a number of bytes, which may end with a binary multiplier suffix: `K` (1024), `M`, `G`, or `T` (either case.)
+    fn parse_size(value: &OsStr) -> Option<u64>
+    {
+	let value = value.to_str()?;
+	let (number, shift) = match value.as_bytes().last()? {
+	    b'k' | b'K' => (&value[..value.len() - 1], 10),
+	    b'm' | b'M' => (&value[..value.len() - 1], 20),
+	    b'g' | b'G' => (&value[..value.len() - 1], 30),
+	    b't' | b'T' => (&value[..value.len() - 1], 40),
+	    _ => (value, 0),
+	};
+	let number = number.parse::<u64>().ok()?;
+	number.checked_mul(1 << shift)
+    }
+
+    /// Parse a whole number of seconds, or of the unit given by a suffix: `ms`, `s`, or `m` (minutes.)
+    fn parse_duration(value: &OsStr) -> Option<std::time::Duration>
+    {
+	use std::time::Duration;
+	let value = value.to_str()?;
+	let (number, millis) = if let Some(number) = value.strip_suffix("ms") {
+	    (number, 1)
+	} else if let Some(number) = value.strip_suffix('s') {
+	    (number, 1000)
+	} else if let Some(number) = value.strip_suffix('m') {
+	    (number, 60 * 1000)
+	} else {
+	    (value, 1000)
+	};
+	let number = number.parse::<u64>().ok()?;
+	number.checked_mul(millis).map(Duration::from_millis)
+    }
+
+    /// Parser for `-b/--buffer-size <BYTES>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct BufferSize;
+
+    impl BufferSize {
+	pub const ARGUMENT: &'static str = "--buffer-size";
+	pub const SHORT: &'static str = "-b";
+	const EXPECTED: &'static str = "a non-zero number of bytes, optionally followed by `K`, `M`, or `G`";
+    }
+
+    impl TryParse for BufferSize
+    {
+	type Error = ValueParseError;
+	type Output = NonZeroUsize;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    let name = split_value(argument).0.as_bytes();
+	    (name == Self::ARGUMENT.as_bytes() || name == Self::SHORT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    parse_size(&value)
+		.and_then(|size| usize::try_from(size).ok())
+		.and_then(NonZeroUsize::new)
+		.ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parse a percentage from 1 to 100, with or without a trailing `%`.
+    fn parse_percent(value: &OsStr) -> Option<u8>
+    {
+	let value = value.to_str()?;
+	value.strip_suffix('%').unwrap_or(value).parse::<u8>().ok()
+	    .filter(|percent| (1..=100).contains(percent))
+    }
+
+    /// Parser for `--mem-warn <PERCENT>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct MemWarn;
+
+    impl MemWarn {
+	pub const ARGUMENT: &'static str = "--mem-warn";
+	const EXPECTED: &'static str = "a percentage of available memory from 1 to 100";
+    }
+
+    impl TryParse for MemWarn
+    {
+	type Error = ValueParseError;
+	type Output = u8;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    parse_percent(&value).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `--mem-max <PERCENT>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct MemMax;
+
+    impl MemMax {
+	pub const ARGUMENT: &'static str = "--mem-max";
+	const EXPECTED: &'static str = "a percentage of available memory from 1 to 100";
+    }
+
+    impl TryParse for MemMax
+    {
+	type Error = ValueParseError;
+	type Output = u8;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    parse_percent(&value).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
+	}
+    }
+
+    /// Parser for `-o/--output <PATH>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Output;
+
+    impl Output {
+	pub const ARGUMENT: &'static str = "--output";
+	pub const SHORT: &'static str = "-o";
+	const EXPECTED: &'static str = "a file path, or `-` for stdout";
+    }
+
+    impl TryParse for Output
+    {
+	type Error = ValueParseError;
+	type Output = super::OutputTarget;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    let name = split_value(argument).0.as_bytes();
+	    (name == Self::ARGUMENT.as_bytes() || name == Self::SHORT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    match take_value(&argument, rest) {
+		Some(value) if value.as_bytes() == b"-" => Ok(super::OutputTarget::Stdout),
+		Some(value) if !value.is_empty() => Ok(super::OutputTarget::File(value.into())),
+		value => Err(ValueParseError::new(Self::ARGUMENT, value, Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--output-fd <FD>`, an inherited file descriptor to write the output to instead of `stdout`.
+    ///
+    /// The standard streams (0, 1 and 2) are not accepted: `stdout` is `-o -`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct OutputFd;
+
+    impl OutputFd {
+	pub const ARGUMENT: &'static str = "--output-fd";
+	const EXPECTED: &'static str = "a file descriptor number greater than 2";
+    }
+
+    impl TryParse for OutputFd
+    {
+	type Error = ValueParseError;
+	type Output = RawFd;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    match value.to_str().and_then(|value| value.parse::<RawFd>().ok()) {
+		Some(fd) if fd > 2 => Ok(fd),
+		_ => Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for a bare `FILE` argument, the input to read instead of `stdin`.
+    ///
+    /// Any argument that does not start with `-` is taken as the input. `-` on its own is `stdin`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct InputPath;
+
+    impl InputPath {
+	pub const ARGUMENT: &'static str = "FILE";
+
+	/// The input named by `argument`: `-` is `stdin`, anything else is a path.
+	#[inline] 
+	pub fn source(argument: OsString) -> super::InputSource
+	{
+	    match argument.as_bytes() {
+		b"-" => super::InputSource::Stdin,
+		_ => super::InputSource::Path(argument.into()),
+	    }
+	}
+    }
+
+    impl TryParse for InputPath
+    {
+	type Error = ValueParseError;
+	type Output = super::InputSource;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    match argument.as_bytes() {
+		b"-" => Some(Self),
+		[b'-', ..] | [] => None,
+		_ => Some(Self),
+	    }
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    Ok(Self::source(argument))
+	}
+    }
+
+    /// Parser for the `--` end-of-options marker.
+    ///
+    /// Every argument after it is taken as the input (see `InputPath`), so a file whose name starts with `-` can be given. (`--` inside an `-exec/{}` command is part of the command, which ends at its own `;`.)
+    #[derive(Debug, Clone, Copy)]
+    pub struct EndOfOptions;
+
+    impl EndOfOptions {
+	pub const ARGUMENT: &'static str = "--";
+    }
+
+    impl TryParse for EndOfOptions
+    {
+	type Error = std::convert::Infallible;
+	type Output = Vec<OsString>;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (argument.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, _argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    Ok(rest.collect())
+	}
+    }
+
+    /// Parser for `-t/--tee <PATH>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Tee;
+
+    impl Tee {
+	pub const ARGUMENT: &'static str = "--tee";
+	pub const SHORT: &'static str = "-t";
+	const EXPECTED: &'static str = "a file path, or `-` for stdout";
+    }
+
+    impl TryParse for Tee
+    {
+	type Error = ValueParseError;
+	type Output = super::OutputTarget;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    let name = split_value(argument).0.as_bytes();
+	    (name == Self::ARGUMENT.as_bytes() || name == Self::SHORT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    match take_value(&argument, rest) {
+		Some(value) if value.as_bytes() == b"-" => Ok(super::OutputTarget::Stdout),
+		Some(value) if !value.is_empty() => Ok(super::OutputTarget::File(value.into())),
+		value => Err(ValueParseError::new(Self::ARGUMENT, value, Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-stdout <PATH>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecStdout;
+
+    impl ExecStdout {
+	pub const ARGUMENT: &'static str = "--exec-stdout";
+	const EXPECTED: &'static str = "a file path, or `-` for stdout";
+    }
+
+    impl TryParse for ExecStdout
+    {
+	type Error = ValueParseError;
+	type Output = super::OutputTarget;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    match take_value(&argument, rest) {
+		Some(value) if value.as_bytes() == b"-" => Ok(super::OutputTarget::Stdout),
+		Some(value) if !value.is_empty() => Ok(super::OutputTarget::File(value.into())),
+		value => Err(ValueParseError::new(Self::ARGUMENT, value, Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-stdin-from=input|buffer`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecStdinFrom;
+
+    impl ExecStdinFrom {
+	pub const ARGUMENT: &'static str = "--exec-stdin-from";
+	const EXPECTED: &'static str = "`input` or `buffer`";
+    }
+
+    impl TryParse for ExecStdinFrom
+    {
+	type Error = ValueParseError;
+	type Output = super::ExecStdinFrom;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    Ok(match value.as_bytes() {
+		b"input" => super::ExecStdinFrom::Input,
+		b"buffer" => super::ExecStdinFrom::Buffer,
+		_ => return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    })
+	}
+    }
+
+    /// Parser for `--exec-pid-file <PATH>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecPidFile;
+
+    impl ExecPidFile {
+	pub const ARGUMENT: &'static str = "--exec-pid-file";
+	const EXPECTED: &'static str = "a file path";
+    }
+
+    impl TryParse for ExecPidFile
+    {
+	type Error = ValueParseError;
+	type Output = PathBuf;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    match take_value(&argument, rest) {
+		Some(value) if !value.is_empty() => Ok(value.into()),
+		value => Err(ValueParseError::new(Self::ARGUMENT, value, Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-nice <N>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecNice;
+
+    impl ExecNice {
+	pub const ARGUMENT: &'static str = "--exec-nice";
+	const EXPECTED: &'static str = "a nice value from -20 to 19";
+    }
+
+    impl TryParse for ExecNice
+    {
+	type Error = ValueParseError;
+	type Output = libc::c_int;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    match value.to_str().and_then(|value| value.parse::<libc::c_int>().ok()) {
+		Some(nice) if (-20..=19).contains(&nice) => Ok(nice),
+		_ => Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-on-signal <command>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecOnSignal;
+
+    impl ExecOnSignal {
+	pub const ARGUMENT: &'static str = "--exec-on-signal";
+	const EXPECTED: &'static str = "a non-empty shell command";
+    }
+
+    impl TryParse for ExecOnSignal
+    {
+	type Error = ValueParseError;
+	type Output = OsString;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    match take_value(&argument, rest) {
+		Some(value) if !value.is_empty() => Ok(value),
+		value => Err(ValueParseError::new(Self::ARGUMENT, value, Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-env NAME=VALUE`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecEnv;
+
+    impl ExecEnv {
+	pub const ARGUMENT: &'static str = "--exec-env";
+	const EXPECTED: &'static str = "`NAME=VALUE`";
+    }
+
+    impl TryParse for ExecEnv
+    {
+	type Error = ValueParseError;
+	type Output = (OsString, OsString);
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let variable = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    match split_value(&variable) {
+		(name, Some(value)) if !name.is_empty() && memchr::memchr(0, variable.as_bytes()).is_none() => Ok((name.to_owned(), value.to_owned())),
+		_ => Err(ValueParseError::new(Self::ARGUMENT, Some(variable), Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-prefix '<command> [args...]'`.
+    ///
+    /// The value is split into words like a shell would: on whitespace, except inside `'single'` or `"double"` quotes, with `\` escaping the next character (outside single quotes.)
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExecPrefix;
+
+    impl ExecPrefix {
+	pub const ARGUMENT: &'static str = "--exec-prefix";
+	const EXPECTED: &'static str = "a command and its arguments";
+
+	/// Split `value` into words, or `None` if a quote is not closed or there is a trailing `\`.
+	fn split_words(value: &[u8]) -> Option<Vec<OsString>>
+	{
+	    let mut words = Vec::new();
+	    let mut word: Option<Vec<u8>> = None;
+	    let mut quote = None;
+	    let mut bytes = value.iter().copied();
+	    while let Some(byte) = bytes.next() {
+		match (quote, byte) {
+		    (Some(q), b) if b == q => quote = None,
+		    (Some(b'\''), b) => word.get_or_insert_with(Vec::new).push(b),
+		    (_, b'\\') => {
+			let escaped = bytes.next()?;
+			word.get_or_insert_with(Vec::new).push(escaped);
+		    },
+		    (Some(_), b) => word.get_or_insert_with(Vec::new).push(b),
+		    (None, b'\'' | b'"') => {
+			quote = Some(byte);
+			word.get_or_insert_with(Vec::new);
+		    },
+		    (None, b) if b.is_ascii_whitespace() => words.extend(word.take().map(OsString::from_vec)),
+		    (None, b) => word.get_or_insert_with(Vec::new).push(b),
+		}
+	    }
+	    if quote.is_some() {
+		return None;
+	    }
+	    words.extend(word.map(OsString::from_vec));
+	    Some(words)
+	}
+    }
+
+    impl TryParse for ExecPrefix
+    {
+	type Error = ValueParseError;
+	type Output = Vec<OsString>;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    match Self::split_words(value.as_bytes()) {
+		Some(words) if !words.is_empty() => Ok(words),
+		_ => Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-expand-env[=strict|lenient]`.
+    ///
+    /// When no value is given, `lenient` is used.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ExpandEnv;
+
+    impl ExpandEnv {
+	pub const ARGUMENT: &'static str = "--exec-expand-env";
+    }
+
+    impl TryParse for ExpandEnv
+    {
+	type Error = ValueParseError;
+	type Output = super::EnvExpansion;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    Ok(match split_value(&argument).1.map(OsStr::as_bytes) {
+		None | Some(b"lenient") => super::EnvExpansion::Lenient,
+		Some(b"strict") => super::EnvExpansion::Strict,
+		Some(value) => return Err(ValueParseError::new(Self::ARGUMENT, Some(OsStr::from_bytes(value).to_owned()), "`strict` or `lenient`")),
+	    })
+	}
+    }
+
+    /// Parser for `--best-effort[=error|warn]`.
+    ///
+    /// When no value is given, `error` is used.
+    #[derive(Debug, Clone, Copy)]
+    pub struct BestEffort;
+
+    impl BestEffort {
+	pub const ARGUMENT: &'static str = "--best-effort";
+    }
+
+    impl TryParse for BestEffort
+    {
+	type Error = ValueParseError;
+	type Output = super::BestEffort;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    Ok(match split_value(&argument).1.map(OsStr::as_bytes) {
+		None | Some(b"error") => super::BestEffort::Error,
+		Some(b"warn") => super::BestEffort::Warn,
+		Some(value) => return Err(ValueParseError::new(Self::ARGUMENT, Some(OsStr::from_bytes(value).to_owned()), "`error` or `warn`")),
+	    })
+	}
+    }
+
+    /// Parser for `--progress[=force]`.
+    ///
+    /// When no value is given, progress is only shown if `stderr` is a terminal.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Progress;
+
+    impl Progress {
+	pub const ARGUMENT: &'static str = "--progress";
+    }
+
+    impl TryParse for Progress
+    {
+	type Error = ValueParseError;
+	type Output = super::Progress;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    Ok(match split_value(&argument).1.map(OsStr::as_bytes) {
+		None => super::Progress::Auto,
+		Some(b"force") => super::Progress::Force,
+		Some(value) => return Err(ValueParseError::new(Self::ARGUMENT, Some(OsStr::from_bytes(value).to_owned()), "`force`")),
+	    })
+	}
+    }
+
+    /// Parser for `--fsync[=data|all]`.
+    ///
+    /// When no value is given, the whole file is synced (`all`.)
+    #[derive(Debug, Clone, Copy)]
+    pub struct Fsync;
+
+    impl Fsync {
+	pub const ARGUMENT: &'static str = "--fsync";
+    }
+
+    impl TryParse for Fsync
+    {
+	type Error = ValueParseError;
+	type Output = super::Fsync;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    Ok(match split_value(&argument).1.map(OsStr::as_bytes) {
+		None | Some(b"all") => super::Fsync::All,
+		Some(b"data") => super::Fsync::Data,
+		Some(value) => return Err(ValueParseError::new(Self::ARGUMENT, Some(OsStr::from_bytes(value).to_owned()), "`data` or `all`")),
+	    })
+	}
+    }
+
+    /// Parser for `--fail-on-empty[=CODE]`.
+    ///
+    /// When no code is given, `DEFAULT_FAIL_ON_EMPTY` is used.
+    #[derive(Debug, Clone, Copy)]
+    pub struct FailOnEmpty;
+
+    impl FailOnEmpty {
+	pub const ARGUMENT: &'static str = "--fail-on-empty";
+	const EXPECTED: &'static str = "an exit code from 1 to 255";
+    }
+
+    impl TryParse for FailOnEmpty
+    {
+	type Error = ValueParseError;
+	type Output = i32;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    match split_value(&argument).1 {
+		None => Ok(super::DEFAULT_FAIL_ON_EMPTY),
+		Some(value) => value.to_str().and_then(|value| value.parse::<i32>().ok())
+		    .filter(|code| (1..=255).contains(code))
+		    .ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value.to_owned()), Self::EXPECTED)),
+	    }
+	}
+    }
+
+    /// Parser for `--exec-input-seal-level <level>`.
+    ///
+    /// The level is a comma-seperated list of `write`, `grow`, `shrink`, `all` or `none`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct SealLevel;
+
+    impl SealLevel {
+	pub const ARGUMENT: &'static str = "--exec-input-seal-level";
+	const EXPECTED: &'static str = "a comma-seperated list of `write`, `grow`, `shrink`, `all` or `none`";
+    }
+
+    impl TryParse for SealLevel
+    {
+	type Error = ValueParseError;
+	type Output = super::SealLevel;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    let mut level = super::SealLevel::NONE;
+	    for seal in value.as_bytes().split(|&b| b == b',') {
+		match seal {
+		    b"write" => level.write = true,
+		    b"grow" => level.grow = true,
+		    b"shrink" => level.shrink = true,
+		    b"all" => level = super::SealLevel::ALL,
+		    b"none" => (),
+		    _ => return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+		}
+	    }
+	    Ok(level)
+	}
+    }
+
+    /// Parser for `--on-same-file <buffer|error>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct OnSameFile;
+
+    impl OnSameFile {
+	pub const ARGUMENT: &'static str = "--on-same-file";
+	const EXPECTED: &'static str = "`buffer` or `error`";
+    }
+
+    impl TryParse for OnSameFile
+    {
+	type Error = ValueParseError;
+	type Output = super::SameFile;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    Ok(match value.as_bytes() {
+		b"buffer" => super::SameFile::Buffer,
+		b"error" => super::SameFile::Error,
+		_ => return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    })
+	}
+    }
+
+    /// Parser for `--fd-style <proc|dev>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct FdStyle;
+
+    impl FdStyle {
+	pub const ARGUMENT: &'static str = "--fd-style";
+	const EXPECTED: &'static str = "`proc` or `dev`";
+    }
+
+    impl TryParse for FdStyle
+    {
+	type Error = ValueParseError;
+	type Output = super::FdPathStyle;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    Ok(match value.as_bytes() {
+		b"proc" => super::FdPathStyle::Proc,
+		b"dev" => super::FdPathStyle::DevFd,
+		_ => return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    })
+	}
+    }
+
+    /// Parser for `--strategy <auto|buffered|memfd>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Strategy;
+
+    impl Strategy {
+	pub const ARGUMENT: &'static str = "--strategy";
+	const EXPECTED: &'static str = if cfg!(feature="memfile") {
+	    "`auto`, `buffered` or `memfd`"
+	} else {
+	    "`buffered` (`auto` and `memfd` were not compiled in)"
+	};
+    }
+
+    impl TryParse for Strategy
+    {
+	type Error = ValueParseError;
+	type Output = super::Strategy;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    Ok(match value.as_bytes() {
+		#[cfg(feature="memfile")]
+		b"auto" => super::Strategy::Auto,
+		b"buffered" => super::Strategy::Buffered,
+		#[cfg(feature="memfile")]
+		b"memfd" => super::Strategy::Memfd,
+		_ => return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    })
+	}
+    }
+
+    /// Parser for `--buffer-backend <vec|bytes>`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct BufferBackend;
+
+    impl BufferBackend {
+	pub const ARGUMENT: &'static str = "--buffer-backend";
+	const EXPECTED: &'static str = if cfg!(feature="bytes") {
+	    "`vec` or `bytes`"
+	} else {
+	    "`vec` (`bytes` was not compiled in)"
+	};
+    }
+
+    impl TryParse for BufferBackend
+    {
+	type Error = ValueParseError;
+	type Output = buffers::BufferBackend;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    Ok(match value.as_bytes() {
+		b"vec" => buffers::BufferBackend::Vec,
+		#[cfg(feature="bytes")]
+		b"bytes" => buffers::BufferBackend::Bytes,
+		_ => return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED)),
+	    })
 	}
     }
-}
-
-trait ArgError: error::Error + Send + Sync + 'static
-{
-    fn into_invalid_usage(self) -> (String, String, Box<dyn error::Error + Send + Sync + 'static>)
-    where Self: Sized;
-}
 
-trait TryParse: Sized
-{
-    type Error: ArgError;
-    type Output;
-    
-    #[inline(always)] 
-    fn visit(argument: &OsStr) -> Option<Self> { let _ = argument;  None }
-    fn parse<I: ?Sized>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
-    where I: Iterator<Item = OsString>;
-}
+    /// Parser for `--name <name>`.
+    ///
+    /// The name must be valid UTF-8 and not contain any nul bytes. Its length is checked when the file is created, see `memfile::MEMFD_NAME_MAX`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Name;
 
-impl<E: error::Error + Send + Sync + 'static> From<(String, String, E)> for ArgParseError
-{
-    #[inline] 
-    fn from((argument, message, inner): (String, String, E)) -> Self
-    {
-	Self::InvalidUsage { argument, message, inner: Some(Box::new(inner)) }
+    impl Name {
+	pub const ARGUMENT: &'static str = "--name";
+	const EXPECTED: &'static str = "a UTF-8 name without nul bytes";
     }
-}
 
-impl<E: ArgError> From<E> for ArgParseError
-{
-    #[inline(always)] 
-    fn from(from: E) -> Self
+    impl TryParse for Name
     {
-	let (argument, message, inner) = from.into_invalid_usage();
-	Self::InvalidUsage { argument, message, inner: Some(inner) }
-    }
-}
+	type Error = ValueParseError;
+	type Output = String;
 
-#[inline(always)] 
-fn extract_last_pathspec<'a>(s: &'a str) -> &'a str
-{
-    //#[cfg_attr(feature="logging", feature(instrument(ret)))]
-    #[allow(dead_code)]
-    fn string_diff<'a>(a: &'a str, b: &'a str) -> usize
-    {
-	#[cold]
-	#[inline(never)]
-	fn _panic_non_inclusive(swap: bool) -> !
-	{
-	    let a = swap.then(|| "b").unwrap_or("a");
-	    let b = swap.then(|| "a").unwrap_or("b");
-	    panic!("String {a} was not inside string {b}")
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
 	}
-	let a_addr = a.as_ptr() as usize;
-	let b_addr = b.as_ptr() as usize;
-	let (a_addr, b_addr, sw) = 
-	    if !(a_addr + a.len() > b_addr + b.len() && b_addr + b.len() < a_addr + a.len()) {
-		(b_addr, a_addr, true)
-	    } else {
-		(a_addr, a_addr, false)
-	    };
-	
-	if b_addr < a_addr /*XXX || (b_addr + b.len()) > (a_addr + a.len())*/ {
-	    _panic_non_inclusive(sw)
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    let value = take_value(&argument, rest).ok_or_else(|| ValueParseError::new(Self::ARGUMENT, None, Self::EXPECTED))?;
+	    if memchr::memchr(0, value.as_bytes()).is_some() {
+		return Err(ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED));
+	    }
+	    value.into_string()
+		.map_err(|value| ValueParseError::new(Self::ARGUMENT, Some(value), Self::EXPECTED))
 	}
-	return a_addr.abs_diff(b_addr);
     }
-    s.rsplit_once("::")
-	.map(|(_a, b)| /*XXX: This doesn't work...match _a.rsplit_once("::") {
-	     Some((_, last)) => &s[string_diff(s, last)..],
-	     _ => b
-	}*/ b)
-	.unwrap_or(s)
-}
 
-mod parsers {
-    use super::*;
+    /// Parser for `--hugetlb[=SIZE]`.
+    ///
+    /// When no size is given, the largest one the system supports is used. Whether a size is supported is checked when the file is created.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Hugetlb;
 
-    #[inline(always)]
-    #[cfg_attr(feature="logging", instrument(level="debug", skip(rest), fields(parser = %extract_last_pathspec(type_name::<P>()))))]
-    pub(super) fn try_parse_with<P>(arg: &mut OsString, rest: &mut impl Iterator<Item = OsString>) -> Option<Result<P::Output, ArgParseError>>
-    where P: TryParse
+    impl Hugetlb {
+	pub const ARGUMENT: &'static str = "--hugetlb";
+	const EXPECTED: &'static str = "a huge page size that is a power of two, e.g. `2M` or `1G`";
+    }
+
+    impl TryParse for Hugetlb
     {
-	#[cfg(feature="logging")] 
-	let _span = tracing::warn_span!("parse", parser= %extract_last_pathspec(type_name::<P>()), ?arg);
-	P::visit(arg.as_os_str()).map(move |parser| {
-	    #[cfg(feature="logging")]
-	    let _in = _span.enter();
-	    parser.parse(/*if_trace!{true arg.clone(); */std::mem::replace(arg, OsString::default()) /*}*/, rest).map_err(Into::into) //This clone is not needed, the argument is captured by `try_parse_with` (in debug) and `parse` (in warning) already.
-	}).map(|res| {
-	    #[cfg(feature="logging")]
-	    match res.as_ref() {
-		Err(err) => {
-		    ::tracing::event!(::tracing::Level::ERROR, ?err, "Attempted parse failed with error")
-		},
-		_ => ()
+	type Error = ValueParseError;
+	type Output = super::HugePageSize;
+
+	#[inline] 
+	fn visit(argument: &OsStr) -> Option<Self> {
+	    (split_value(argument).0.as_bytes() == Self::ARGUMENT.as_bytes()).then_some(Self)
+	}
+
+	#[inline] 
+	fn parse<I>(self, argument: OsString, _rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
+	    match split_value(&argument).1 {
+		None => Ok(super::HugePageSize::Largest),
+		Some(value) => parse_size(value)
+		    .filter(|size| size.is_power_of_two())
+		    .map(super::HugePageSize::Bytes)
+		    .ok_or_else(|| ValueParseError::new(Self::ARGUMENT, Some(value.to_owned()), Self::EXPECTED)),
 	    }
-	    res
-	}).or_else(|| {
-	    #[cfg(feature="logging")]
-	    ::tracing::event!(::tracing::Level::TRACE, "no match for this parser with this arg, continuing visitation.");
-	    None
-	})
+	}
     }
 
     /// Parser for `ExecMode`
@@ -596,10 +3168,7 @@ mod parsers {
 	#[inline(always)] 
 	fn is_positional(&self) -> bool
 	{
-	    match self {
-		Self::Postional => true,
-		_ => false
-	    }
+	    matches!(self, Self::Postional)
 	}
 	#[inline(always)] 
 	fn command_string(&self) -> &'static str
@@ -650,8 +3219,8 @@ mod parsers {
 	}
 
 	#[inline] 
-	fn parse<I: ?Sized>(self, _argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
-	where I: Iterator<Item = OsString> {
+	fn parse<I>(self, _argument: OsString, rest: &mut I) -> Result<Self::Output, Self::Error>
+	where I: ?Sized + Iterator<Item = OsString> {
 	    mod warnings {
 		use super::*;
 		/// Issue a warning when `-exec{}` is provided as an argument, but no positional arguments (`{}`) are specified in the argument list to the command.
@@ -666,6 +3235,7 @@ mod parsers {
 		#[cold]
 		#[cfg_attr(feature="logging", inline(never))]
 		#[cfg_attr(not(feature="logging"), inline(always))]
+		#[cfg_attr(not(feature="logging"), allow(unused_variables))]
 		pub fn exec_apparent_missing_terminator(first_is_positional: bool, second_is_positional: bool, command: &OsStr, argument_number: usize)
 		{
 		    if_trace! {
@@ -689,6 +3259,7 @@ mod parsers {
 		#[cold]
 		#[cfg_attr(feature="logging", inline(never))]
 		#[cfg_attr(not(feature="logging"), inline(always))]
+		#[cfg_attr(not(feature="logging"), allow(unused_variables))]
 		pub fn exec_terminator_as_command(exec_arg_str: &str)
 		{
 		    if_trace! {
@@ -697,7 +3268,7 @@ mod parsers {
 		}
 	    }
 	    
-	    let command = rest.next().ok_or_else(|| ExecModeParseError(self))?;
+	    let command = rest.next().ok_or(ExecModeParseError(self))?;
 	    if command == EXEC_MODE_STRING_TERMINATOR {
 		warnings::exec_terminator_as_command(self.command_string());
 	    }
@@ -742,3 +3313,473 @@ mod parsers {
 	}
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    #[test]
+    fn exec_stdin_tee() -> eyre::Result<()>
+    {
+	let opt = parse_from(["--exec-stdin-tee", "-exec", "cat"])?;
+	assert!(opt.exec_stdin_tee(), "`--exec-stdin-tee` was not set");
+	assert!(opt.has_stdin_exec(), "`-exec` was not parsed after a switch");
+	
+	let opt = parse_from(["-exec", "cat", ";"])?;
+	assert!(!opt.exec_stdin_tee(), "`--exec-stdin-tee` was set without being passed");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_mode_arg_iterator_len()
+    {
+	let stdin = ExecMode::Stdin { command: "cat".into(), args: vec!["-n".into(), "-".into(), "-v".into()] };
+	let (_, mut args) = stdin.clone().into_process_info_stdin();
+	assert_eq!(args.len(), 3);
+	assert_eq!(args.next_back().as_deref(), Some(OsStr::new("-v")));
+	assert_eq!(args.len(), 2);
+	assert_eq!(args.next().as_deref(), Some(OsStr::new("-n")));
+	assert_eq!(args.len(), args.count(), "`len()` did not match the number of arguments yielded");
+	let (_, args) = stdin.into_process_info_stdin();
+	assert_eq!(args.rev().collect::<Vec<_>>(), ["-v", "-", "-n"]);
+
+	let positional = ExecMode::Positional { command: "cp".into(), args: vec![None, Some("-t".into()), None, None] };
+	for given in 0..=4 {
+	    let (_, args) = positional.clone().into_process_info(iter::repeat_n(OsString::from("{}"), given));
+	    let (low, high) = args.size_hint();
+	    let yielded = args.count();
+	    assert!(low <= yielded && high.is_none_or(|high| yielded <= high), "Hint ({low}, {high:?}) does not cover the {yielded} arguments yielded with {given} positional");
+	    assert_eq!(yielded, 1 + std::cmp::min(given, 3));
+	}
+	let (_, args) = positional.into_process_info(iter::repeat(OsString::from("{}")));
+	assert_eq!(args.size_hint(), (4, Some(4)), "Hint is not exact when every slot is filled");
+    }
+
+    #[test]
+    fn dry_run() -> eyre::Result<()>
+    {
+	let opt = parse_from(["--dry-run", "-exec", "cat"])?;
+	assert!(opt.dry_run(), "`--dry-run` was not set");
+	assert!(opt.has_stdin_exec(), "`-exec` was not parsed after `--dry-run`");
+	assert!(!parse_from(["-exec", "cat"])?.dry_run(), "`--dry-run` was set without being passed");
+	assert_eq!(Strategy::Buffered.to_string(), "buffered");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_stdin_pipe() -> eyre::Result<()>
+    {
+	let opt = parse_from(["--exec-stdin-pipe", "-exec", "cat"])?;
+	assert!(opt.exec_stdin_pipe(), "`--exec-stdin-pipe` was not set");
+	assert!(!opt.exec_stdin_keep_open(), "`--exec-stdin-keep-open` was set without being passed");
+	assert!(!parse_from(["-exec", "cat"])?.exec_stdin_pipe(), "`--exec-stdin-pipe` was set without being passed");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_expand_env() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--exec-expand-env"])?.exec_expand_env(), Some(EnvExpansion::Lenient));
+	assert_eq!(parse_from(["--exec-expand-env=strict"])?.exec_expand_env(), Some(EnvExpansion::Strict));
+	assert_eq!(parse_from(["-exec", "cat"])?.exec_expand_env(), None);
+	assert!(parse_from(["--exec-expand-env=sometimes"]).is_err(), "Invalid value was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn buffer_backend() -> eyre::Result<()>
+    {
+	use buffers::BufferBackend as B;
+	assert_eq!(parse_from(["--buffer-backend=vec"])?.buffer_backend(), B::Vec);
+	assert_eq!(parse_from(["--buffer-backend", "vec"])?.buffer_backend(), B::Vec);
+	#[cfg(feature="bytes")]
+	assert_eq!(parse_from(["--buffer-backend=bytes"])?.buffer_backend(), B::Bytes);
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.buffer_backend(), B::default());
+	assert!(parse_from(["--buffer-backend"]).is_err(), "Missing value was accepted");
+	assert!(parse_from(["--buffer-backend=btree"]).is_err(), "Invalid value was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn memfile_name() -> eyre::Result<()>
+    {
+	let opt = parse_from(["--name", "buffer"])?;
+	assert_eq!(opt.memfile_name(), Some("buffer"));
+	assert!(!opt.memfile_name_truncate(), "`--name-truncate` was set without being passed");
+	
+	let opt = parse_from(["--name=buffer", "--name-truncate"])?;
+	assert_eq!(opt.memfile_name(), Some("buffer"));
+	assert!(opt.memfile_name_truncate(), "`--name-truncate` was not set");
+	
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.memfile_name(), None);
+	assert!(parse_from(["--name"]).is_err(), "Missing value was accepted");
+	assert!(parse_from(["--name=a\0b"]).is_err(), "Name with a nul byte was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn hugetlb() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.hugetlb(), None);
+	assert_eq!(parse_from(["--hugetlb"])?.hugetlb(), Some(HugePageSize::Largest));
+	assert_eq!(parse_from(["--hugetlb=2M"])?.hugetlb(), Some(HugePageSize::Bytes(2 << 20)));
+	assert_eq!(parse_from(["--hugetlb=1G"])?.hugetlb().and_then(|size| size.bytes()), Some(1 << 30));
+	// The size can only be given with `=`, so the next argument is not taken as it.
+	let opt = parse_from(["--hugetlb", "-exec", "cat"])?;
+	assert_eq!(opt.hugetlb(), Some(HugePageSize::Largest));
+	assert_eq!(opt.opt_exec().len(), 1);
+
+	assert!(parse_from(["--hugetlb=3M"]).is_err(), "Size that is not a power of two was accepted");
+	assert!(parse_from(["--hugetlb=0"]).is_err(), "Zero size was accepted");
+	assert!(parse_from(["--hugetlb=huge"]).is_err(), "Invalid size was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn best_effort() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--best-effort"])?.best_effort(), Some(BestEffort::Error));
+	assert_eq!(parse_from(["--best-effort=warn"])?.best_effort(), Some(BestEffort::Warn));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.best_effort(), None);
+	assert!(parse_from(["--best-effort=ignore"]).is_err(), "Invalid value was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn fsync() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--fsync"])?.fsync(), Some(Fsync::All));
+	assert_eq!(parse_from(["--fsync=all"])?.fsync(), Some(Fsync::All));
+	assert_eq!(parse_from(["--fsync=data", "-o", "out"])?.fsync(), Some(Fsync::Data));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.fsync(), None);
+	assert!(parse_from(["--fsync=never"]).is_err(), "Invalid value was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn digest() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--sha256"])?.digest(), Some(DigestKind::Sha256));
+	assert_eq!(parse_from(["--crc32"])?.digest(), Some(DigestKind::Crc32));
+	assert_eq!(parse_from(["--crc32", "--sha256"])?.digest(), Some(DigestKind::Sha256), "The last digest passed was not used");
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.digest(), None);
+	assert_eq!(DigestKind::Crc32.to_string(), "crc32");
+	Ok(())
+    }
+
+    #[test]
+    fn close_stdout() -> eyre::Result<()>
+    {
+	assert!(parse_from(std::iter::empty::<OsString>())?.close_stdout(), "`stdout` is not closed by default");
+	assert!(!parse_from(["--no-close-stdout"])?.close_stdout(), "`--no-close-stdout` was not set");
+	Ok(())
+    }
+
+    #[test]
+    fn progress() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--progress"])?.progress(), Some(Progress::Auto));
+	assert_eq!(parse_from(["--progress=force"])?.progress(), Some(Progress::Force));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.progress(), None);
+	assert!(parse_from(["--progress=always"]).is_err(), "Invalid value was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_input_seal_level() -> eyre::Result<()>
+    {
+	let level = |value: &str| parse_from(["--exec-input-seal-level", value]).map(|opt| opt.exec_input_seal_level());
+	assert_eq!(level("write")?, Some(SealLevel { write: true, ..SealLevel::NONE }));
+	assert_eq!(level("grow,shrink")?, Some(SealLevel { grow: true, shrink: true, ..SealLevel::NONE }));
+	assert_eq!(level("all")?, Some(SealLevel::ALL));
+	assert_eq!(level("none")?, Some(SealLevel::NONE));
+	assert_eq!(parse_from(["--exec-input-seal-level=write,grow"])?.exec_input_seal_level(), Some(SealLevel { write: true, grow: true, ..SealLevel::NONE }));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.exec_input_seal_level(), None);
+	assert!(parse_from(std::iter::empty::<OsString>())?.memfd_seal_check());
+	assert!(!parse_from(["--no-memfd-seal-check"])?.memfd_seal_check());
+	assert!(level("write,").is_err(), "Empty seal was accepted");
+	assert!(level("seal").is_err(), "Invalid seal was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn seal() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--seal"])?.exec_input_seal_level(), Some(SealLevel::ALL));
+	assert_eq!(parse_from(["--seal", "--exec-input-seal-level", "write"])?.exec_input_seal_level(), Some(SealLevel { write: true, ..SealLevel::NONE }), "`--exec-input-seal-level` did not take precedence over `--seal`");
+	Ok(())
+    }
+
+    #[test]
+    fn quiet() -> eyre::Result<()>
+    {
+	assert!(parse_from(["-q"])?.quiet());
+	assert!(parse_from(["--quiet"])?.quiet());
+	assert!(!parse_from(std::iter::empty::<OsString>())?.quiet());
+	// Only the long form is completed.
+	assert!(known_options().any(|(option, _)| option == "--quiet"));
+	assert!(!known_options().any(|(option, _)| option == "-q"));
+	Ok(())
+    }
+
+    #[test]
+    fn exec_nice() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--exec-nice", "10"])?.exec_nice(), Some(10));
+	assert_eq!(parse_from(["--exec-nice=-20"])?.exec_nice(), Some(-20));
+	assert!(parse_from(["--exec-nice", "20"]).is_err(), "Out of range nice value was accepted");
+	assert!(parse_from(["--exec-nice", "-21"]).is_err(), "Out of range nice value was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_timeout() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--exec-timeout", "30"])?.exec_timeout(), Some(Duration::from_secs(30)));
+	assert_eq!(parse_from(["--exec-timeout=30s"])?.exec_timeout(), Some(Duration::from_secs(30)));
+	assert_eq!(parse_from(["--exec-timeout", "250ms"])?.exec_timeout(), Some(Duration::from_millis(250)));
+	assert_eq!(parse_from(["--exec-timeout", "2m"])?.exec_timeout(), Some(Duration::from_secs(120)));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.exec_timeout(), None);
+	for invalid in ["0", "0ms", "-1s", "1h", "s", "1.5s"] {
+	    assert!(parse_from(["--exec-timeout", invalid]).is_err(), "Invalid duration {invalid:?} was accepted");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn uses_exec() -> eyre::Result<()>
+    {
+	assert!(!parse_from(std::iter::empty::<OsString>())?.uses_exec(), "No exec options were passed");
+	assert!(!parse_from(["--strategy", "buffered", "-o", "out"])?.uses_exec());
+	for args in [&["-exec", "true"][..], &["--exec-timeout", "1s"], &["--exec-stdout", "log"], &["--fd-style", "dev"], &["--exec-parallel"],
+		     &["--exec-stdin-tee"], &["--exec-input-nonblock"], &["--exec-output-to-memfd"], &["--exec-nice", "5"], &["--exec-env", "KEY=value"],
+		     &["--exec-expand-env"], &["--exec-input-seal-level", "write"], &["--seal"], &["--exec-stdin-from", "input"]] {
+	    assert!(parse_from(args.iter().copied())?.uses_exec(), "{args:?} does not use -exec/{{}}");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn exec_stdout() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--exec-stdout", "/dev/null"])?.exec_stdout(), Some(&OutputTarget::File("/dev/null".into())));
+	assert_eq!(parse_from(["--exec-stdout=children.log"])?.exec_stdout(), Some(&OutputTarget::File("children.log".into())));
+	assert_eq!(parse_from(["--exec-stdout", "-"])?.exec_stdout(), Some(&OutputTarget::Stdout));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.exec_stdout(), None);
+	assert!(parse_from(["--exec-stdout"]).is_err(), "Missing path was accepted");
+	assert!(parse_from(["--exec-stdout="]).is_err(), "Empty path was accepted");
+	// `--exec-stdout` does not change where `collect` itself writes.
+	assert_eq!(parse_from(["--exec-stdout", "/dev/null"])?.output(), &OutputTarget::Stdout);
+	Ok(())
+    }
+
+    #[test]
+    fn head_and_tail() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--head", "4096"])?.head(), Some(4096));
+	assert_eq!(parse_from(["--tail=0"])?.tail(), Some(0));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.head(), None);
+	assert!(parse_from(["--head", "-1"]).is_err(), "Negative size was accepted");
+	assert!(parse_from(["--tail"]).is_err(), "Missing size was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn limit() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--limit", "10M"])?.read_limit(), Some(10 << 20));
+	assert_eq!(parse_from(["--limit=512"])?.read_limit(), Some(512));
+	assert_eq!(parse_from(["--limit", "1K", "--head", "100"])?.read_limit(), Some(100));
+	assert_eq!(parse_from(["--head", "4096"])?.read_limit(), Some(4096));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.read_limit(), None);
+	assert!(parse_from(["--limit", "lots"]).is_err(), "Invalid size was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_prefix() -> eyre::Result<()>
+    {
+	let prefix = |value: &str| parse_from(["--exec-prefix", value]).map(|opt| opt.exec_prefix().iter().map(|word| word.to_str().unwrap().to_owned()).collect::<Vec<_>>());
+	assert_eq!(prefix("strace -f")?, ["strace", "-f"]);
+	assert_eq!(prefix("  env  'A=1 2' \"B=\\\"3\\\"\"  C=4\\ 5 ''")?, ["env", "A=1 2", "B=\"3\"", "C=4 5", ""]);
+	assert!(parse_from(["--exec-prefix", "sh -c 'oops"]).is_err(), "Unclosed quote was accepted");
+	assert!(parse_from(["--exec-prefix", "  "]).is_err(), "Empty prefix was accepted");
+	assert!(parse_from(std::iter::empty::<OsString>())?.exec_prefix().is_empty());
+	Ok(())
+    }
+
+    #[test]
+    fn fail_on_empty() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--fail-on-empty"])?.fail_on_empty(), Some(DEFAULT_FAIL_ON_EMPTY));
+	assert_eq!(parse_from(["--fail-on-empty=3"])?.fail_on_empty(), Some(3));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.fail_on_empty(), None);
+	for bad in ["0", "256", "-1", "empty", ""] {
+	    assert!(parse_from([format!("--fail-on-empty={bad}")]).is_err(), "`--fail-on-empty={bad}` was accepted");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn buffer_size() -> eyre::Result<()>
+    {
+	let size = |value: &str| -> eyre::Result<Option<usize>> { Ok(parse_from(["--buffer-size", value])?.buffer_size().map(NonZeroUsize::get)) };
+	assert_eq!(size("4096")?, Some(4096));
+	assert_eq!(size("4K")?, Some(4 << 10));
+	assert_eq!(size("2m")?, Some(2 << 20));
+	assert_eq!(size("1G")?, Some(1 << 30));
+	assert_eq!(parse_from(["-b", "16k"])?.buffer_size().map(NonZeroUsize::get), Some(16 << 10));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.buffer_size(), None);
+	for bad in ["0", "0K", "K", "", "1.5M", "-1", "4KB", "99999999999T"] {
+	    assert!(parse_from(["--buffer-size", bad]).is_err(), "`--buffer-size {bad}` was accepted");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn mem_thresholds() -> eyre::Result<()>
+    {
+	let opt = parse_from(std::iter::empty::<OsString>())?;
+	assert_eq!((opt.mem_warn(), opt.mem_max()), (DEFAULT_MEM_WARN, DEFAULT_MEM_MAX));
+	let opt = parse_from(["--mem-warn", "50", "--mem-max=75%"])?;
+	assert_eq!((opt.mem_warn(), opt.mem_max()), (50, 75));
+	for bad in ["0", "101", "-5", "half", "%"] {
+	    assert!(parse_from(["--mem-max", bad]).is_err(), "`--mem-max {bad}` was accepted");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn pipe_size() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--pipe-size", "1048576"])?.pipe_size(), Some(1048576));
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.pipe_size(), None);
+	assert!(parse_from(["--pipe-size=0"]).is_err(), "Zero size was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn output() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["-o", "result.bin"])?.output(), &OutputTarget::File("result.bin".into()));
+	assert_eq!(parse_from(["--output=result.bin"])?.output(), &OutputTarget::File("result.bin".into()));
+	assert_eq!(parse_from(["--output", "-"])?.output(), &OutputTarget::Stdout);
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.output(), &OutputTarget::Stdout);
+	assert!(parse_from(["-o"]).is_err(), "Missing path was accepted");
+	assert!(parse_from(["--output="]).is_err(), "Empty path was accepted");
+	assert!(parse_from(["-o", "log.bin", "--append"])?.append());
+	assert!(!parse_from(["-o", "log.bin"])?.append());
+
+	assert_eq!(parse_from(["--output-fd", "5"])?.output(), &OutputTarget::Fd(5));
+	assert_eq!(parse_from(["--null"])?.output(), &OutputTarget::Null);
+	// The last one passed wins.
+	assert_eq!(parse_from(["--null", "--output-fd=7"])?.output(), &OutputTarget::Fd(7));
+	assert_eq!(parse_from(["--output-fd=7", "-o", "result.bin"])?.output(), &OutputTarget::File("result.bin".into()));
+	for bad in ["1", "-1", "stdout", ""] {
+	    assert!(parse_from(["--output-fd", bad]).is_err(), "`--output-fd {bad}` was accepted");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn input() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["input.bin"])?.input(), &InputSource::Path("input.bin".into()));
+	assert_eq!(parse_from(["--head", "10", "input.bin", "-o", "output.bin"])?.input(), &InputSource::Path("input.bin".into()));
+	assert_eq!(parse_from(["-"])?.input(), &InputSource::Stdin);
+	assert_eq!(parse_from(std::iter::empty::<OsString>())?.input(), &InputSource::Stdin);
+	assert!(parse_from(["a.bin", "b.bin"]).is_err(), "Two input files were accepted");
+	// `-exec` takes every argument up to `;` as part of the command.
+	assert_eq!(parse_from(["-exec", "cat", "file"])?.input(), &InputSource::Stdin);
+	Ok(())
+    }
+
+    #[test]
+    fn end_of_options() -> eyre::Result<()>
+    {
+	assert!(parse_from(["-weirdname"]).is_err(), "Unknown option was accepted as the input");
+	assert_eq!(parse_from(["--", "-weirdname"])?.input(), &InputSource::Path("-weirdname".into()));
+	assert_eq!(parse_from(["--head", "10", "--", "--head"])?.input(), &InputSource::Path("--head".into()));
+	assert_eq!(parse_from(["--"])?.input(), &InputSource::Stdin);
+	assert!(parse_from(["--", "a.bin", "b.bin"]).is_err(), "Two input files were accepted after `--`");
+	assert!(parse_from(["a.bin", "--", "b.bin"]).is_err(), "Two input files were accepted around `--`");
+
+	// `--` inside an `-exec` command is passed to it, and options still follow its `;`.
+	let opt = parse_from(["-exec", "grep", "--", "-x", ";", "--", "-input"])?;
+	assert_eq!(opt.opt_exec().next().map(ToString::to_string).as_deref(), Some("grep -- -x"));
+	assert_eq!(opt.input(), &InputSource::Path("-input".into()));
+	// After `--`, `-exec` is a file name.
+	let opt = parse_from(["--", "-exec"])?;
+	assert_eq!(opt.opt_exec().len(), 0, "`-exec` was parsed after `--`");
+	assert_eq!(opt.input(), &InputSource::Path("-exec".into()));
+	Ok(())
+    }
+
+    #[test]
+    fn tee() -> eyre::Result<()>
+    {
+	let opt = parse_from(["-t", "a.bin", "--tee=b.bin", "--tee", "-"])?;
+	assert_eq!(opt.tee(), &[OutputTarget::File("a.bin".into()), OutputTarget::File("b.bin".into()), OutputTarget::Stdout]);
+	assert!(!opt.tee_strict());
+	assert!(parse_from(["--tee-strict"])?.tee_strict());
+	assert!(parse_from(std::iter::empty::<OsString>())?.tee().is_empty());
+	assert!(parse_from(["-t"]).is_err(), "Missing path was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn output_range() -> eyre::Result<()>
+    {
+	let range = parse_from(["--output-range", "1024:2048"])?.output_range().expect("No range");
+	assert_eq!(range, OutputRange { start: 1024, end: Some(2048) });
+	assert_eq!(range.to_string(), "1024:2048");
+	assert_eq!(parse_from(["--output-range=1024:"])?.output_range(), Some(OutputRange { start: 1024, end: None }));
+	assert_eq!(parse_from(["--output-range=:10"])?.output_range(), Some(OutputRange { start: 0, end: Some(10) }));
+	for invalid in ["10:5", "1024", "a:b", "-1:", ""] {
+	    assert!(parse_from(["--output-range", invalid]).is_err(), "Invalid range {invalid:?} was accepted");
+	}
+
+	assert_eq!(range.within(4096), Some(1024..2048));
+	assert_eq!(range.within(2048), Some(1024..2048));
+	assert_eq!(range.within(1500), None, "Range past the end fits");
+	assert_eq!(range.clamped(1500), 1024..1500);
+	assert_eq!(range.clamped(100), 100..100);
+	assert_eq!(OutputRange { start: 1024, end: None }.within(4096), Some(1024..4096));
+	Ok(())
+    }
+
+    #[test]
+    fn exec_command_from_fd() -> eyre::Result<()>
+    {
+	assert_eq!(parse_from(["--exec-command-from-fd", "3"])?.exec_command_from_fd(), Some(3));
+	assert_eq!(parse_from(["--exec-command-from-fd=10"])?.exec_command_from_fd(), Some(10));
+	assert!(parse_from(["--exec-command-from-fd", "1"]).is_err(), "Standard stream was accepted");
+	assert!(parse_from(["--exec-command-from-fd", "three"]).is_err(), "Invalid fd was accepted");
+	
+	assert_eq!(ExecMode::from_nul_separated(b"printf\0%s\0hello\0"), Some(ExecMode::Stdin { command: "printf".into(), args: vec!["%s".into(), "hello".into()] }));
+	assert_eq!(ExecMode::from_nul_separated(b"true"), Some(ExecMode::Stdin { command: "true".into(), args: vec![] }));
+	assert_eq!(ExecMode::from_nul_separated(b""), None);
+	assert_eq!(ExecMode::from_nul_separated(b"\0arg\0"), None);
+	Ok(())
+    }
+
+    #[test]
+    fn exec_mode_display()
+    {
+	let exec = ExecMode::Stdin { command: "sh".into(), args: vec!["-c".into(), r#"echo "$X"; wc -c"#.into(), "".into()] };
+	assert_eq!(exec.to_string(), r#"sh -c "echo \"$X\"; wc -c" """#);
+	let exec = ExecMode::Positional { command: "my command".into(), args: vec![Some(r"a\b".into()), None] };
+	assert_eq!(exec.to_string(), r#"'my command' "a\\b" {}"#);
+    }
+
+    #[test]
+    fn exec_env() -> eyre::Result<()>
+    {
+	let opt = parse_from(["--exec-env", "INPUT={}", "--exec-env=EMPTY=", "--exec-env", "EQ=a=b"])?;
+	assert_eq!(opt.exec_env(), &[("INPUT".into(), "{}".into()), ("EMPTY".into(), "".into()), ("EQ".into(), "a=b".into())][..]);
+	assert!(parse_from(["--exec-env", "NOVALUE"]).is_err(), "Variable without a value was accepted");
+	assert!(parse_from(["--exec-env", "=value"]).is_err(), "Variable without a name was accepted");
+	assert!(parse_from(["--exec-env"]).is_err(), "Missing variable was accepted");
+	Ok(())
+    }
+}
@@ -12,11 +12,191 @@ pub type DefaultMut = bytes::BytesMut;
 #[allow(dead_code)]
 pub type DefaultMut = Vec<u8>;
 
+// Only the tests collect more than one input per process; `benches/pool.rs` includes it by itself.
+#[cfg(test)]
+mod pool;
+
 /// Default immutable buffer
 #[allow(dead_code)]
 pub type Default = <DefaultMut as MutBuffer>::Frozen;
 
 
+/// The backend used for allocating a buffer at runtime (see `--buffer-backend`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BufferBackend
+{
+    /// `Vec<u8>`
+    Vec,
+    /// `bytes::BytesMut` (requires the `bytes` feature.)
+    #[cfg(feature="bytes")]
+    Bytes,
+}
+
+impl std::default::Default for BufferBackend
+{
+    /// The backend of `DefaultMut`.
+    #[inline(always)] 
+    fn default() -> Self
+    {
+	cfg_if! {
+	    if #[cfg(feature="bytes")] {
+		Self::Bytes
+	    } else {
+		Self::Vec
+	    }
+	}
+    }
+}
+
+impl BufferBackend
+{
+    /// Create a new buffer for this backend with an optional capacity.
+    #[inline]
+    #[cfg_attr(feature="logging", instrument(level="debug"))]
+    pub fn create_buffer(self, cap: Option<NonZeroUsize>) -> AnyMutBuffer
+    {
+	match self {
+	    Self::Vec => AnyMutBuffer::from(Vec::<u8>::maybe_with_capacity(cap)),
+	    #[cfg(feature="bytes")]
+	    Self::Bytes => AnyMutBuffer::from(bytes::BytesMut::maybe_with_capacity(cap)),
+	}
+    }
+}
+
+/// A mutable buffer whose backend is selected at runtime.
+#[derive(Debug)]
+pub enum AnyMutBuffer
+{
+    Vec(Vec<u8>),
+    #[cfg(feature="bytes")]
+    Bytes(bytes::BytesMut),
+}
+
+/// An immutable (frozen) `AnyMutBuffer`.
+#[derive(Debug)]
+pub enum AnyBuffer
+{
+    Vec(Box<[u8]>),
+    #[cfg(feature="bytes")]
+    Bytes(bytes::Bytes),
+}
+
+impl From<Vec<u8>> for AnyMutBuffer
+{
+    #[inline(always)] 
+    fn from(from: Vec<u8>) -> Self
+    {
+	Self::Vec(from)
+    }
+}
+
+#[cfg(feature="bytes")]
+impl From<bytes::BytesMut> for AnyMutBuffer
+{
+    #[inline(always)] 
+    fn from(from: bytes::BytesMut) -> Self
+    {
+	Self::Bytes(from)
+    }
+}
+
+impl AnyMutBuffer
+{
+    /// The backend this buffer was allocated with.
+    #[inline] 
+    #[cfg_attr(not(feature="logging"), allow(dead_code))]
+    pub fn backend(&self) -> BufferBackend
+    {
+	match self {
+	    Self::Vec(_) => BufferBackend::Vec,
+	    #[cfg(feature="bytes")]
+	    Self::Bytes(_) => BufferBackend::Bytes,
+	}
+    }
+
+    #[inline] 
+    pub fn capacity(&self) -> usize
+    {
+	match self {
+	    Self::Vec(v) => v.capacity(),
+	    #[cfg(feature="bytes")]
+	    Self::Bytes(b) => b.capacity(),
+	}
+    }
+}
+
+impl AsRef<[u8]> for AnyMutBuffer
+{
+    #[inline] 
+    fn as_ref(&self) -> &[u8]
+    {
+	match self {
+	    Self::Vec(v) => &v[..],
+	    #[cfg(feature="bytes")]
+	    Self::Bytes(b) => &b[..],
+	}
+    }
+}
+
+impl AsMut<[u8]> for AnyMutBuffer
+{
+    #[inline] 
+    fn as_mut(&mut self) -> &mut [u8]
+    {
+	match self {
+	    Self::Vec(v) => &mut v[..],
+	    #[cfg(feature="bytes")]
+	    Self::Bytes(b) => &mut b[..],
+	}
+    }
+}
+
+impl AsRef<[u8]> for AnyBuffer
+{
+    #[inline] 
+    fn as_ref(&self) -> &[u8]
+    {
+	match self {
+	    Self::Vec(v) => &v[..],
+	    #[cfg(feature="bytes")]
+	    Self::Bytes(b) => &b[..],
+	}
+    }
+}
+
+impl std::ops::Deref for AnyBuffer
+{
+    type Target = [u8];
+    #[inline(always)] 
+    fn deref(&self) -> &Self::Target {
+	self.as_ref()
+    }
+}
+
+impl MutBuffer for AnyMutBuffer
+{
+    type Frozen = AnyBuffer;
+
+    #[inline] 
+    fn freeze(self) -> Self::Frozen {
+	match self {
+	    Self::Vec(v) => AnyBuffer::Vec(v.freeze()),
+	    #[cfg(feature="bytes")]
+	    Self::Bytes(b) => AnyBuffer::Bytes(b.freeze()),
+	}
+    }
+
+    #[inline] 
+    fn copy_from_slice(&mut self, st: usize, buf: &[u8]) -> usize
+    {
+	match self {
+	    Self::Vec(v) => v.copy_from_slice(st, buf),
+	    #[cfg(feature="bytes")]
+	    Self::Bytes(b) => MutBuffer::copy_from_slice(b, st, buf),
+	}
+    }
+}
+
 /// Reader from a mutable reference of a `Buffer`.
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct BufferReader<'a, B: ?Sized>(&'a mut B, usize);
@@ -32,23 +212,31 @@ const _: () = {
 	#[inline(always)] 
 	pub fn get(&self) -> &B
 	{
-	    &self.0
+	    self.0
 	}
 	#[inline(always)] 
 	pub fn get_mut(&mut self) -> &B
 	{
-	    &mut self.0
+	    self.0
 	}
 	#[inline(always)] 
 	pub fn amount_read(&self) -> usize
 	{
 	    self.1
 	}
+	/// Move the reader to `st` in the buffer, so `amount_read()` is then `st`.
+	///
+	/// Other readers of the same buffer are not affected. If `st` is past the end of the buffer, nothing more is read.
+	#[inline(always)] 
+	pub fn seek(&mut self, st: usize)
+	{
+	    self.1 = st;
+	}
     }
     impl<'a, 'b: 'a, B: Buffer + 'b> BufferReader<'a, B>
     {
 	#[inline] 
-	pub fn unsize(self) -> BufferReader<'a, (dyn Buffer + 'b)>
+	pub fn unsize(self) -> BufferReader<'a, dyn Buffer + 'b>
 	{
 	    BufferReader(self.0, self.1)
 	}
@@ -59,12 +247,12 @@ const _: () = {
 	#[inline(always)] 
 	pub fn get(&self) -> &B
 	{
-	    &self.0
+	    self.0
 	}
 	#[inline(always)] 
 	pub fn get_mut(&mut self) -> &B
 	{
-	    &mut self.0
+	    self.0
 	}
 	#[inline(always)] 
 	pub fn amount_written(&self) -> usize
@@ -75,7 +263,7 @@ const _: () = {
     impl<'a, 'b: 'a, B: Buffer + 'b> BufferWriter<'a, B>
     {
 	#[inline] 
-	pub fn unsize(self) -> BufferWriter<'a, (dyn Buffer + 'b)>
+	pub fn unsize(self) -> BufferWriter<'a, dyn Buffer + 'b>
 	{
 	    BufferWriter(self.0, self.1)
 	}
@@ -87,7 +275,8 @@ impl<'a, B: ?Sized + Buffer> io::Read for BufferReader<'a, B>
     #[inline] 
     #[cfg_attr(feature="logging", instrument(level="trace", skip_all, fields(buf = ?buf.len())))]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-	let adv = self.0.copy_to_slice(self.1, buf);
+	// A reader started (or moved) past the end reads nothing, like a file.
+	let adv = self.0.try_copy_to_slice(self.1, buf).unwrap_or(0);
 	self.1 += adv;
 	if_trace!(? trace!(" -> reading one buffer +{adv}"));
 	Ok(adv)
@@ -120,12 +309,8 @@ pub trait Buffer: AsRef<[u8]>
     #[cfg_attr(feature="logging", instrument(level="trace", skip_all, fields(buf = ?slice.len())))]
     fn copy_to_slice(&self, st: usize, slice: &mut [u8]) -> usize
     {
-	let by = self.as_ref();
-	if st >= by.len() {
-	    return 0;
-	}
-	
-	let by = &by[st..];
+	debug_assert!(st <= self.as_ref().len(), "Start {st} is past the end of the buffer ({} bytes)", self.as_ref().len());
+	let by = self.slice_from(st);
 	let len = std::cmp::min(by.len(), slice.len());
 	// SAFETY: We know `self`'s AsRef impl cannot overlap with `slice`, since `slice` is a mutable reference.
 	if len > 0 {
@@ -136,6 +321,23 @@ pub trait Buffer: AsRef<[u8]>
 	len
     }
 
+    /// Like `copy_to_slice()`, but a `st` past the end of the buffer is an error instead of a bug.
+    #[inline]
+    fn try_copy_to_slice(&self, st: usize, slice: &mut [u8]) -> io::Result<usize>
+    {
+	let len = self.as_ref().len();
+	if st > len {
+	    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("start {st} is past the end of the buffer ({len} bytes)")));
+	}
+	Ok(self.copy_to_slice(st, slice))
+    }
+
+    /// The content of the buffer from `st`, which is empty if `st` is at or past its end.
+    #[inline]
+    fn slice_from(&self, st: usize) -> &[u8]
+    {
+	self.as_ref().get(st..).unwrap_or(&[])
+    }
 }
 pub trait BufferExt: Buffer
 {
@@ -165,16 +367,14 @@ pub trait MutBuffer: AsMut<[u8]>
     fn freeze(self) -> Self::Frozen;
 
     #[inline]
-    #[cfg_attr(feature="logging", instrument(level="debug", skip_all, fields(st, buflen = ?slice.len())))]
+    #[cfg_attr(feature="logging", instrument(level="trace", skip_all, fields(st, buflen = ?slice.len())))]
     fn copy_from_slice(&mut self, st: usize, slice: &[u8]) -> usize
     {
 	let by = self.as_mut();
-	dbg!(&by);
-
 	if st >= by.len() {
 	    return 0;
 	}
-	dbg!(st);
+	if_trace!(? trace!("copying up to {} bytes into buffer of {} at {st}", slice.len(), by.len()));
 
 	let by = &mut by[st..];
 	let len = std::cmp::min(by.len(), slice.len());
@@ -217,25 +417,26 @@ impl MutBuffer for bytes::BytesMut
     fn freeze(self) -> Self::Frozen {
 	bytes::BytesMut::freeze(self)
     }
-    //TODO: XXX: Impl copy_from_slice() as is done in impl for Vec<u8>? Or change how `.writer()` works for us to return the BytesMut writer which seems more efficient.
-    /*#[instrument]
+    /// Unlike `BytesMut`'s own (slice) `copy_from_slice()`, this grows the buffer past its length (and capacity) as needed, as the `Vec<u8>` impl does.
+    #[cfg_attr(feature="logging", instrument(level="trace", skip(buf, self), fields(st = ?st, self = ?self.len(), alloc= ?self.capacity())))]
     fn copy_from_slice(&mut self, st: usize, buf: &[u8]) -> usize
     {
-    //TODO: Special case for `st == 0` maybe? No slicing of the BytesMut might increase perf? Idk.
-    if  (st + buf.len()) <= self.len() {
-    // We can put `buf` in st..buf.len()
-    self[st..].copy_from_slice(buf); 
-} else if  st < self.len() {
-    // The start is lower but the end is not
-    let rem = self.len() - st;
-    self[st..].copy_from_slice(&buf[..rem]);
-    self.extend_from_slice(&buf[rem..]);
-} else {
-    // it is past the end, extend.
-    self.extend_from_slice(buf);
-}
-    buf.len()
-}*/
+	if  (st + buf.len()) <= self.len() {
+	    // We can put `buf` in st..buf.len()
+	    self[st..(st + buf.len())].copy_from_slice(buf);
+	} else if  st < self.len() {
+	    // The start is lower but the end is not
+	    let rem = self.len() - st;
+	    self[st..].copy_from_slice(&buf[..rem]);
+	    if_trace!(trace!("extending buffer (partial, +{})", buf[rem..].len()));
+	    self.extend_from_slice(&buf[rem..]);
+	} else {
+	    // it is past the end, extend.
+	    if_trace!(trace!("extending buffer (whole, +{})", buf.len()));
+	    self.extend_from_slice(buf);
+	}
+	buf.len()
+    }
 }
 
 #[cfg(feature="recolored")] 
@@ -415,6 +616,7 @@ pub trait WithCapExt: WithCapacity
 {
     fn maybe_with_capacity(maybe: Option<NonZeroUsize>) -> Self;
     #[inline(always)]
+    #[allow(dead_code)]
     fn try_with_capacity(cap: usize) -> Self
     {
 	Self::maybe_with_capacity(NonZeroUsize::new(cap))
@@ -422,6 +624,7 @@ pub trait WithCapExt: WithCapacity
 }
 
 /// A type that can be used as a size for creating a `WithCapacity` buffer
+#[allow(dead_code)]
 pub trait TryCreateBuffer
 {
     fn create_buffer<T: WithCapacity>(&self) -> T;
@@ -478,6 +681,102 @@ macro_rules! cap_buffer  {
     };
 }
 
+/// A freelist of `DefaultMut` buffers, for collecting many inputs one after another (see `work::buffered_into()`.)
+#[cfg(test)]
+pub type BufferPool = pool::BufferPool<DefaultMut>;
+
+/// A fixed-capacity buffer that only keeps the last `capacity()` bytes written to it (see `--tail`.)
+///
+/// Its content wraps around, so it is not contiguous and cannot be a `Buffer` itself (which requires `AsRef<[u8]>`.) Use `as_slices()` to get its content in order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RingBuffer
+{
+    data: Box<[u8]>,
+    /// Index of the oldest byte in `data`.
+    start: usize,
+    len: usize,
+}
+
+impl RingBuffer
+{
+    #[inline] 
+    pub fn new(capacity: usize) -> Self
+    {
+	Self {
+	    data: vec![0; capacity].into_boxed_slice(),
+	    start: 0,
+	    len: 0,
+	}
+    }
+
+    #[inline(always)] 
+    pub fn capacity(&self) -> usize
+    {
+	self.data.len()
+    }
+
+    /// The number of bytes currently held (at most `capacity()`.)
+    #[inline(always)] 
+    pub fn len(&self) -> usize
+    {
+	self.len
+    }
+
+    /// The content of the buffer in order, as two slices (the second of which is empty if the content does not wrap around.)
+    #[inline] 
+    pub fn as_slices(&self) -> (&[u8], &[u8])
+    {
+	let end = self.start + self.len;
+	if end <= self.capacity() {
+	    (&self.data[self.start..end], &[])
+	} else {
+	    (&self.data[self.start..], &self.data[..(end - self.capacity())])
+	}
+    }
+
+    /// Append `buf`, discarding the oldest bytes if there is not enough room.
+    #[cfg_attr(feature="logging", instrument(level="trace", skip_all, fields(buf = ?buf.len())))]
+    pub fn push_slice(&mut self, mut buf: &[u8])
+    {
+	let cap = self.capacity();
+	if buf.len() >= cap {
+	    // Only the end of `buf` fits.
+	    self.data.copy_from_slice(&buf[(buf.len() - cap)..]);
+	    self.start = 0;
+	    self.len = cap;
+	    return;
+	}
+	let pushed = buf.len();
+	let mut pos = (self.start + self.len) % cap;
+	while !buf.is_empty() {
+	    let len = std::cmp::min(buf.len(), cap - pos);
+	    self.data[pos..(pos + len)].copy_from_slice(&buf[..len]);
+	    buf = &buf[len..];
+	    pos = (pos + len) % cap;
+	}
+	let len = self.len + pushed;
+	if len > cap {
+	    self.start = (self.start + (len - cap)) % cap;
+	    self.len = cap;
+	} else {
+	    self.len = len;
+	}
+    }
+}
+
+impl io::Write for RingBuffer
+{
+    #[inline] 
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+	self.push_slice(buf);
+	Ok(buf.len())
+    }
+    #[inline(always)] 
+    fn flush(&mut self) -> io::Result<()> {
+	Ok(())
+    }
+}
+
 pub mod prelude
 {
     /// Export these items anonymously.
@@ -506,6 +805,8 @@ pub mod prelude
 	WithCapExt,
     }
     
+    // Which of these are used depends on the enabled features.
+    #[allow(unused_imports)]
     pub use super::{
 	WithCapacity,
 	TryCreateBuffer,
@@ -514,9 +815,228 @@ pub mod prelude
     };
 }
 
-pub(crate) use cap_buffer;
-
 // cap_buffer impls
 
-#[cfg(feature="bytes")] buffers::cap_buffer!(bytes::BytesMut);
+#[cfg(feature="bytes")] cap_buffer!(bytes::BytesMut);
 cap_buffer!(Vec<u8>);
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::io::Read;
+
+    /// Collect `input` through a buffer allocated by `backend`, the same way the `buffered` strategy does.
+    fn collect_through(backend: BufferBackend, cap: Option<NonZeroUsize>, input: &[u8]) -> io::Result<AnyBuffer>
+    {
+	let mut buffer = backend.create_buffer(cap);
+	assert_eq!(buffer.backend(), backend, "Created buffer has the wrong backend");
+	let read = io::copy(&mut &input[..], &mut MutBufferExt::writer(&mut buffer))?;
+	assert_eq!(read as usize, input.len(), "Invalid read size");
+	Ok(buffer.freeze())
+    }
+    
+    /// A fixed-size buffer that only has the provided `MutBuffer::copy_from_slice()`.
+    struct FixedBuffer([u8; 64]);
+
+    impl AsMut<[u8]> for FixedBuffer
+    {
+	fn as_mut(&mut self) -> &mut [u8]
+	{
+	    &mut self.0[..]
+	}
+    }
+
+    impl MutBuffer for FixedBuffer
+    {
+	type Frozen = Vec<u8>;
+	fn freeze(self) -> Self::Frozen
+	{
+	    self.0.to_vec()
+	}
+    }
+
+    #[test]
+    fn default_copy_from_slice_is_silent() -> io::Result<()>
+    {
+	use std::process::Command;
+	/// Set in the copy of the test binary that does the writes, whose `stderr` is not captured by the test harness.
+	const CHILD: &str = "COLLECT_TEST_COPY_FROM_SLICE_CHILD";
+	if std::env::var_os(CHILD).is_some() {
+	    let mut buffer = FixedBuffer([0; 64]);
+	    for chunk in b"Hello world!".chunks(3) {
+		io::Write::write_all(&mut buffer.writer_from(0), chunk)?;
+	    }
+	    io::Write::write_all(&mut buffer.writer_from(60), b"tail")?;
+	    assert_eq!(&buffer.freeze()[60..], b"tail");
+	    return Ok(());
+	}
+	let output = Command::new(std::env::current_exe()?)
+	    .args(["buffers::tests::default_copy_from_slice_is_silent", "--exact", "--nocapture", "--test-threads=1"])
+	    .env(CHILD, "1")
+	    .env("RUST_LOG", "off")
+	    .output()?;
+	assert!(output.status.success(), "Writing into the buffer failed: {}", String::from_utf8_lossy(&output.stdout[..]));
+	assert!(output.stderr.is_empty(), "Writing into the buffer printed to stderr: {}", String::from_utf8_lossy(&output.stderr[..]));
+	Ok(())
+    }
+
+    #[test]
+    fn collect_each_backend() -> io::Result<()>
+    {
+	const STRING: &[u8] = b"Hello world!";
+	let backends = [
+	    BufferBackend::Vec,
+	    #[cfg(feature="bytes")]
+	    BufferBackend::Bytes,
+	];
+	for backend in backends {
+	    for cap in [None, NonZeroUsize::new(4), NonZeroUsize::new(4096)] {
+		let frozen = collect_through(backend, cap, STRING)?;
+		assert_eq!(&frozen[..], STRING, "Invalid buffer data for {backend:?} (cap {cap:?})");
+
+		let mut output = Vec::new();
+		BufferExt::reader(&mut &frozen[..]).read_to_end(&mut output)?;
+		assert_eq!(&output[..], STRING, "Invalid read data for {backend:?} (cap {cap:?})");
+	    }
+	}
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="bytes")]
+    fn bytes_mut_grows() -> io::Result<()>
+    {
+	const STRING: &[u8] = b"Hello world! This is longer than the initial capacity.";
+	let mut buffer = bytes::BytesMut::with_capacity(4);
+	let read = io::copy(&mut &STRING[..], &mut MutBufferExt::writer(&mut buffer))?;
+	assert_eq!(read as usize, STRING.len(), "Invalid read size");
+	assert_eq!(&MutBuffer::freeze(buffer)[..], STRING, "Invalid frozen data");
+
+	// Overwriting within, and then past, the current length.
+	let mut buffer = bytes::BytesMut::with_capacity(6);
+	buffer.extend_from_slice(b"xxxxxx");
+	assert_eq!(MutBuffer::copy_from_slice(&mut buffer, 0, b"Hi"), 2);
+	assert_eq!(MutBuffer::copy_from_slice(&mut buffer, 4, b"there"), 5);
+	assert_eq!(&MutBuffer::freeze(buffer)[..], b"Hixxthere");
+	Ok(())
+    }
+
+    #[test]
+    fn readers_at_offsets() -> io::Result<()>
+    {
+	const STRING: &[u8] = b"Hello world!";
+	let mut buffer = DefaultMut::wc_new();
+	io::copy(&mut &STRING[..], &mut MutBufferExt::writer(&mut buffer))?;
+	let buffer: Default = MutBuffer::freeze(buffer);
+	assert_eq!(buffer.slice_from(6), b"world!");
+	assert!(buffer.slice_from(STRING.len() + 1).is_empty(), "Slice past the end is not empty");
+	assert!(buffer.try_copy_to_slice(STRING.len() + 1, &mut [0; 4]).is_err(), "Copy from past the end succeeded");
+
+	// Both readers borrow the same buffer, neither copies it.
+	let (mut first, mut second) = (&buffer, &buffer);
+	let mut first = BufferExt::reader_from(&mut first, 0);
+	let mut second = BufferExt::reader_from(&mut second, 6);
+	let mut chunk = [0u8; 5];
+	first.read_exact(&mut chunk)?;
+	assert_eq!(&chunk, b"Hello");
+	second.read_exact(&mut chunk)?;
+	assert_eq!(&chunk, b"world");
+	assert_eq!((first.amount_read(), second.amount_read()), (5, 11), "Readers did not progress independently");
+
+	let mut output = Vec::new();
+	first.read_to_end(&mut output)?;
+	assert_eq!(&output[..], b" world!");
+	second.seek(0);
+	output.clear();
+	second.read_to_end(&mut output)?;
+	assert_eq!(&output[..], STRING, "Seeking back did not read the whole buffer again");
+
+	second.seek(STRING.len() + 1);
+	assert_eq!(second.read(&mut chunk)?, 0, "Reader past the end read something");
+	Ok(())
+    }
+
+    /// Push `input` into a `RingBuffer` of `cap` in `chunk` sized writes, and check it holds the last `cap` bytes of it.
+    fn assert_ring_tail(cap: usize, chunk: usize, input: &[u8]) -> io::Result<()>
+    {
+	let mut ring = RingBuffer::new(cap);
+	for chunk in input.chunks(chunk) {
+	    io::Write::write_all(&mut ring, chunk)?;
+	}
+	let expected = &input[input.len().saturating_sub(cap)..];
+	assert_eq!(ring.len(), expected.len(), "Invalid ring length (cap {cap}, chunk {chunk})");
+
+	let (first, second) = ring.as_slices();
+	assert_eq!(&[first, second].concat()[..], expected, "Invalid data (cap {cap}, chunk {chunk})");
+	Ok(())
+    }
+
+    #[test]
+    fn ring_longer_than_window() -> io::Result<()>
+    {
+	let input: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+	for chunk in [1, 3, 7, 16, 17, 1000] {
+	    assert_ring_tail(16, chunk, &input[..])?;
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn ring_shorter_than_window() -> io::Result<()>
+    {
+	const STRING: &[u8] = b"Hello world!";
+	for chunk in [1, 5, STRING.len()] {
+	    assert_ring_tail(4096, chunk, STRING)?;
+	    assert_ring_tail(STRING.len(), chunk, STRING)?;
+	}
+	assert_ring_tail(0, 1, STRING)
+    }
+
+    /// Acquire, fill and release a buffer of `pool` `iterations` times.
+    fn cycle_pool(pool: &mut BufferPool, iterations: usize, input: &[u8]) -> io::Result<()>
+    {
+	for _ in 0..iterations {
+	    let mut buffer = pool.acquire(NonZeroUsize::new(input.len()));
+	    io::Write::write_all(&mut MutBufferExt::writer(&mut buffer), input)?;
+	    assert_eq!(&buffer[..], input, "Pooled buffer has the wrong content");
+	    pool.release(buffer);
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn pool_reuses_buffers() -> io::Result<()>
+    {
+	let input = vec![0xa5u8; 4096];
+	let mut pool = BufferPool::new();
+	cycle_pool(&mut pool, 1000, &input[..])?;
+	assert_eq!(pool.allocated(), 1, "Pool allocated more than one buffer for one at a time");
+	assert_eq!(pool.free(), 1);
+
+	let buffer = pool.acquire(NonZeroUsize::new(input.len()));
+	assert!(buffer.is_empty(), "Released buffer was not cleared");
+	assert!(buffer.capacity() >= input.len(), "Released buffer lost its capacity");
+	let ptr = buffer.as_ptr();
+	pool.release(buffer);
+	let buffer = pool.acquire(None);
+	assert_eq!(buffer.as_ptr(), ptr, "Pool did not give back the same allocation");
+	assert_eq!(pool.allocated(), 1);
+	Ok(())
+    }
+
+    #[test]
+    fn pool_allocates_when_empty()
+    {
+	let mut pool = BufferPool::new();
+	let first = pool.acquire(None);
+	let second = pool.acquire(NonZeroUsize::new(100));
+	assert!(second.capacity() >= 100);
+	assert_eq!((pool.allocated(), pool.free()), (2, 0), "Acquired buffers were not allocated");
+	pool.release(first);
+	pool.release(second);
+	// The largest free buffer is given out first.
+	assert!(pool.acquire(None).capacity() >= 100, "Pool did not give out its largest buffer");
+	assert_eq!((pool.allocated(), pool.free()), (2, 1));
+    }
+}
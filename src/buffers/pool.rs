@@ -0,0 +1,140 @@
+//! A freelist of buffers, for reusing their allocations
+//!
+//! `collect` itself only ever collects one input, so this is for when its buffered core is called in a loop (see `work::buffered_into()`.) In this crate, that is the tests and `benches/pool.rs`, which includes this file directly (a bench cannot link to a binary crate); so it only uses `std` (and `bytes`, with that feature.)
+use std::num::NonZeroUsize;
+
+/// A growable buffer whose allocation a `BufferPool` can reuse.
+pub trait Reusable: Sized
+{
+    /// Allocate an empty buffer that can hold at least `cap` bytes (if given) without growing.
+    fn allocate(cap: Option<NonZeroUsize>) -> Self;
+    /// The number of bytes it can hold without growing.
+    fn capacity(&self) -> usize;
+    /// Make room for at least `additional` more bytes than it holds.
+    fn reserve(&mut self, additional: usize);
+    /// Empty it, keeping its allocation.
+    fn clear(&mut self);
+}
+
+impl Reusable for Vec<u8>
+{
+    #[inline]
+    fn allocate(cap: Option<NonZeroUsize>) -> Self
+    {
+	cap.map_or_else(Vec::new, |cap| Vec::with_capacity(cap.get()))
+    }
+    #[inline(always)]
+    fn capacity(&self) -> usize
+    {
+	Vec::capacity(self)
+    }
+    #[inline(always)]
+    fn reserve(&mut self, additional: usize)
+    {
+	Vec::reserve(self, additional)
+    }
+    #[inline(always)]
+    fn clear(&mut self)
+    {
+	Vec::clear(self)
+    }
+}
+
+#[cfg(feature="bytes")]
+impl Reusable for bytes::BytesMut
+{
+    #[inline]
+    fn allocate(cap: Option<NonZeroUsize>) -> Self
+    {
+	cap.map_or_else(bytes::BytesMut::new, |cap| bytes::BytesMut::with_capacity(cap.get()))
+    }
+    #[inline(always)]
+    fn capacity(&self) -> usize
+    {
+	bytes::BytesMut::capacity(self)
+    }
+    #[inline(always)]
+    fn reserve(&mut self, additional: usize)
+    {
+	bytes::BytesMut::reserve(self, additional)
+    }
+    #[inline(always)]
+    fn clear(&mut self)
+    {
+	bytes::BytesMut::clear(self)
+    }
+}
+
+/// A freelist of `B` buffers, so that collecting many inputs one after another reuses the allocations of the earlier ones instead of making a new buffer each time.
+#[derive(Debug)]
+pub struct BufferPool<B>
+{
+    free: Vec<B>,
+    /// The number of buffers `acquire()` had to allocate because there were none free.
+    allocated: usize,
+}
+
+impl<B> Default for BufferPool<B>
+{
+    #[inline]
+    fn default() -> Self
+    {
+	Self { free: Vec::new(), allocated: 0 }
+    }
+}
+
+impl<B: Reusable> BufferPool<B>
+{
+    /// An empty pool.
+    #[inline]
+    pub fn new() -> Self
+    {
+	Self::default()
+    }
+
+    /// Take a free buffer from the pool (the one with the most capacity), or allocate a new one if there are none.
+    ///
+    /// The buffer is empty, and can hold at least `cap` bytes (if given) without growing.
+    pub fn acquire(&mut self, cap: Option<NonZeroUsize>) -> B
+    {
+	let largest = self.free.iter().enumerate()
+	    .max_by_key(|(_, buffer)| buffer.capacity())
+	    .map(|(index, _)| index);
+	match largest {
+	    Some(index) => {
+		let mut buffer = self.free.swap_remove(index);
+		if let Some(cap) = cap {
+		    // It is empty, so this makes room for `cap` bytes in total.
+		    buffer.reserve(cap.get());
+		}
+		buffer
+	    },
+	    None => {
+		self.allocated += 1;
+		B::allocate(cap)
+	    },
+	}
+    }
+
+    /// Put `buffer` back into the pool, emptied but keeping its capacity for the next `acquire()`.
+    #[inline]
+    pub fn release(&mut self, mut buffer: B)
+    {
+	buffer.clear();
+	self.free.push(buffer);
+    }
+
+    /// The number of buffers in the pool that are not acquired.
+    #[inline]
+    pub fn free(&self) -> usize
+    {
+	self.free.len()
+    }
+
+    /// The number of buffers the pool has allocated (because there were none free when one was acquired.)
+    #[inline]
+    pub fn allocated(&self) -> usize
+    {
+	self.allocated
+    }
+}
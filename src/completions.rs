@@ -0,0 +1,138 @@
+//! Shell completion script generation (`--completions`)
+use super::*;
+use args::OptionValue;
+use std::fmt::{
+    self,
+    Write,
+};
+
+/// A shell that a completion script can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Shell
+{
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// The `-exec/{}` mode arguments, which take a command and its arguments.
+const EXEC_MODES: [&str; 2] = ["-exec", "-exec{}"];
+
+/// Options that can be given more than once.
+const REPEATABLE: [&str; 1] = ["--exec-env"];
+
+/// Generate a completion script for `shell`, completing the options of the program `name`.
+///
+/// This completes every option from `args::known_options()` (with the possible values of those that have a fixed set), the `-exec/{}` modes (with command names), and their terminator `;`.
+pub fn generate(shell: Shell, name: &str) -> String
+{
+    let mut script = String::new();
+    match shell {
+	Shell::Bash => bash(&mut script, name),
+	Shell::Zsh => zsh(&mut script, name),
+	Shell::Fish => fish(&mut script, name),
+    }.expect("Writing to a `String` cannot fail");
+    script
+}
+
+fn bash(script: &mut String, name: &str) -> fmt::Result
+{
+    let function = format!("_{}", name.replace(|c: char| !c.is_ascii_alphanumeric(), "_"));
+    writeln!(script, "{function}() {{")?;
+    writeln!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"")?;
+    writeln!(script, "    case \"$prev\" in")?;
+    writeln!(script, "\t'{}') COMPREPLY=($(compgen -c -- \"$cur\")); return;;", EXEC_MODES.join("'|'"))?;
+    for (option, value) in args::known_options() {
+	match value {
+	    OptionValue::Required(&[]) => writeln!(script, "\t{option}) return;;")?,
+	    OptionValue::Required(values) => writeln!(script, "\t{option}) COMPREPLY=($(compgen -W '{}' -- \"$cur\")); return;;", values.join(" "))?,
+	    OptionValue::File => writeln!(script, "\t{option}) COMPREPLY=($(compgen -f -- \"$cur\")); return;;")?,
+	    OptionValue::None | OptionValue::Optional(_) => (),
+	}
+    }
+    writeln!(script, "    esac")?;
+
+    let mut words: Vec<String> = EXEC_MODES.iter().map(|&mode| mode.to_owned()).collect();
+    // `compgen -W` removes one level of quoting, leaving `\;`.
+    words.push(format!("\\\\{}", args::EXEC_MODE_STRING_TERMINATOR));
+    for (option, value) in args::known_options() {
+	words.push(option.to_owned());
+	if let OptionValue::Optional(values) = value {
+	    words.extend(values.iter().map(|value| format!("{option}={value}")));
+	}
+    }
+    writeln!(script, "    COMPREPLY=($(compgen -W '{}' -- \"$cur\"))", words.join(" "))?;
+    writeln!(script, "}}")?;
+    writeln!(script, "complete -F {function} {name}")
+}
+
+fn zsh(script: &mut String, name: &str) -> fmt::Result
+{
+    writeln!(script, "#compdef {name}")?;
+    writeln!(script, "_arguments \\")?;
+    for (option, value) in args::known_options() {
+	let repeat = if REPEATABLE.contains(&option) { "*" } else { "" };
+	match value {
+	    OptionValue::None => writeln!(script, "    '{option}' \\")?,
+	    OptionValue::Required(&[]) => writeln!(script, "    '{repeat}{option}:value:' \\")?,
+	    OptionValue::Required(values) => writeln!(script, "    '{repeat}{option}:value:({})' \\", values.join(" "))?,
+	    OptionValue::File => writeln!(script, "    '{repeat}{option}:file:_files' \\")?,
+	    OptionValue::Optional(values) => writeln!(script, "    '{repeat}{option}=-::value:({})' \\", values.join(" "))?,
+	}
+    }
+    for mode in EXEC_MODES {
+	writeln!(script, "    '*{mode}:command:_command_names' \\")?;
+    }
+    writeln!(script, "    '*:: :_normal'")
+}
+
+fn fish(script: &mut String, name: &str) -> fmt::Result
+{
+    for (option, value) in args::known_options() {
+	let long = option.trim_start_matches('-');
+	match value {
+	    OptionValue::None => writeln!(script, "complete -c {name} -l {long}")?,
+	    OptionValue::Required(&[]) => writeln!(script, "complete -c {name} -l {long} -x")?,
+	    OptionValue::Required(values) => writeln!(script, "complete -c {name} -l {long} -x -a '{}'", values.join(" "))?,
+	    OptionValue::File => writeln!(script, "complete -c {name} -l {long} -r -F")?,
+	    OptionValue::Optional(values) => writeln!(script, "complete -c {name} -l {long} -f -a '{}'", values.join(" "))?,
+	}
+    }
+    for mode in EXEC_MODES {
+	writeln!(script, "complete -c {name} -o '{}' -x -a '(__fish_complete_command)'", mode.trim_start_matches('-'))?;
+    }
+    writeln!(script, "complete -c {name} -f -a '\\{}'", args::EXEC_MODE_STRING_TERMINATOR)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn bash_has_every_option()
+    {
+	let script = generate(Shell::Bash, "collect");
+	for (option, _) in args::known_options() {
+	    assert!(script.contains(option), "Bash completion is missing {option}");
+	}
+	for expected in ["--strategy", "--probe", "--exec-env", "-exec{}", "complete -F _collect collect"] {
+	    assert!(script.contains(expected), "Bash completion is missing {expected:?}");
+	}
+	// Check the syntax if `bash` is available.
+	if let Ok(output) = std::process::Command::new("bash").args(["-n", "-c", &script[..]]).output() {
+	    assert!(output.status.success(), "Generated bash script is invalid: {}", String::from_utf8_lossy(&output.stderr[..]));
+	}
+    }
+
+    #[test]
+    fn zsh_and_fish_have_every_option()
+    {
+	for shell in [Shell::Zsh, Shell::Fish] {
+	    let script = generate(shell, "collect");
+	    for (option, _) in args::known_options() {
+		assert!(script.contains(option.trim_start_matches('-')), "{shell:?} completion is missing {option}");
+	    }
+	}
+    }
+}
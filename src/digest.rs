@@ -0,0 +1,128 @@
+//! Digests of the collected input (`--sha256`/`--crc32`)
+use super::*;
+use sha2::Digest as _;
+
+/// A running digest of the bytes fed to it with `update()`.
+#[derive(Debug, Clone)]
+pub enum Hasher
+{
+    Sha256(sha2::Sha256),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher
+{
+    /// Start a digest of `kind`.
+    #[inline]
+    pub fn new(kind: args::DigestKind) -> Self
+    {
+	match kind {
+	    args::DigestKind::Sha256 => Self::Sha256(sha2::Sha256::new()),
+	    args::DigestKind::Crc32 => Self::Crc32(crc32fast::Hasher::new()),
+	}
+    }
+
+    /// Feed `bytes` into the digest.
+    #[inline]
+    pub fn update(&mut self, bytes: &[u8])
+    {
+	match self {
+	    Self::Sha256(hasher) => hasher.update(bytes),
+	    Self::Crc32(hasher) => hasher.update(bytes),
+	}
+    }
+
+    /// The digest of everything fed into it, as lowercase hex.
+    pub fn finish(self) -> String
+    {
+	use std::fmt::Write;
+	match self {
+	    Self::Sha256(hasher) => hasher.finalize().iter().fold(String::with_capacity(64), |mut hex, byte| {
+		let _ = write!(hex, "{byte:02x}");
+		hex
+	    }),
+	    Self::Crc32(hasher) => format!("{:08x}", hasher.finalize()),
+	}
+    }
+}
+
+/// The digest of `kind` of the `bufs`, in order.
+#[inline]
+pub fn digest_slices(kind: args::DigestKind, bufs: &[&[u8]]) -> String
+{
+    let mut hasher = Hasher::new(kind);
+    for buf in bufs {
+	hasher.update(buf);
+    }
+    hasher.finish()
+}
+
+/// The digest of `kind` of the `len` bytes of `file` from `offset`.
+///
+/// The file is read with `pread()` a chunk at a time, so its position is not moved.
+#[cfg(feature="memfile")]
+pub fn digest_file(kind: args::DigestKind, file: &std::fs::File, offset: u64, len: u64) -> io::Result<String>
+{
+    use std::os::unix::fs::FileExt;
+    let mut hasher = Hasher::new(kind);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut done = 0u64;
+    while done < len {
+	let want = std::cmp::min(len - done, buffer.len() as u64) as usize;
+	match file.read_at(&mut buffer[..want], offset + done) {
+	    Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("file ended after {done} of the {len} bytes to digest"))),
+	    Ok(read) => {
+		hasher.update(&buffer[..read]);
+		done += read as u64;
+	    },
+	    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+	    Err(err) => return Err(err),
+	}
+    }
+    Ok(hasher.finish())
+}
+
+/// Print the `hex` digest of `kind` to `stderr`, the way `sha256sum` prints the digest of `stdin`: `<hex>  -`.
+pub fn print(kind: args::DigestKind, hex: &str) -> eyre::Result<()>
+{
+    use io::Write;
+    if_trace!(debug!("--{kind}: digest of the output is {hex}"));
+    writeln!(io::stderr().lock(), "{hex}  -")
+	.wrap_err("Failed to print the digest of the output")
+	.with_section(move || kind.header("Digest was"))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use args::DigestKind;
+
+    #[test]
+    fn known_digests()
+    {
+	assert_eq!(digest_slices(DigestKind::Sha256, &[b"abc"]), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+	assert_eq!(digest_slices(DigestKind::Sha256, &[]), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+	assert_eq!(digest_slices(DigestKind::Crc32, &[b"123456789"]), "cbf43926");
+	assert_eq!(digest_slices(DigestKind::Crc32, &[]), "00000000");
+	assert_eq!(digest_slices(DigestKind::Crc32, &[b"1234", b"", b"56789"]), "cbf43926", "Digest of pieces differs from the digest of the whole");
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn file_digest_keeps_position() -> eyre::Result<()>
+    {
+	use std::io::{Seek, Write};
+	let mut file = std::fs::File::from(memfile::RawFile::open_mem(None, 0)?);
+	let input: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+	file.write_all(&input[..])?;
+	let position = file.stream_position()?;
+	for kind in [DigestKind::Sha256, DigestKind::Crc32] {
+	    assert_eq!(digest_file(kind, &file, 0, input.len() as u64)?, digest_slices(kind, &[&input[..]]));
+	    assert_eq!(digest_file(kind, &file, 1000, 70_000)?, digest_slices(kind, &[&input[1000..71_000]]));
+	}
+	assert_eq!(file.stream_position()?, position, "Digesting the file moved its position");
+	assert!(digest_file(DigestKind::Crc32, &file, 1, input.len() as u64).is_err(), "Digesting past the end of the file succeeded");
+	Ok(())
+    }
+}
@@ -5,13 +5,12 @@ use std::{
     fmt,
     error,
 };
-use std::os::unix::prelude::*;
 
 pub const DEFAULT_USE_ENV: bool = std::option_env!("NO_RT_ERROR_CTL").is_none();
 
 pub type DispersedResult<T, const USE_ENV: bool = DEFAULT_USE_ENV> = Result<T, Dispersed<USE_ENV>>;
 
-pub const ENV_NAME: &'static str = "RUST_VERBOSE";
+pub const ENV_NAME: &str = "RUST_VERBOSE";
 lazy_static!{
     static ref DEFAULT_ENV_VERBOSE: DispersedVerbosity = match std::option_env!("DEFAULT_ERROR") {
 	Some("1") |
@@ -19,7 +18,7 @@ lazy_static!{
 	Some("verbose") |
 	Some("VERBOSE") |
 	Some("v") => DispersedVerbosity::Verbose,
-	Some("0") |
+	// Including `0`.
 	_ => DispersedVerbosity::static_default(),
     };
 }
@@ -69,8 +68,15 @@ impl DispersedVerbosity
 
 fn get_env_value() -> DispersedVerbosity
 {
-    match std::env::var_os(ENV_NAME) {
-	Some(mut value) => {
+    parse_env_value(std::env::var_os(ENV_NAME).as_deref())
+}
+
+/// The verbosity that the value of `ENV_NAME` (if it is set) asks for.
+fn parse_env_value(value: Option<&std::ffi::OsStr>) -> DispersedVerbosity
+{
+    match value {
+	Some(value) => {
+	    let mut value = value.to_owned();
 	    value.make_ascii_lowercase();
 	    match value.as_bytes() {
 		b"1" |
@@ -110,15 +116,30 @@ impl<const E: bool> From<eyre::Report> for Dispersed<E>
 impl<const E: bool> Dispersed<E>
 {
     #[inline] 
+    #[allow(dead_code)]
     pub fn into_inner(self) -> eyre::Report
     {
 	self.0
     }
 }
 
+impl<const E: bool> Dispersed<E>
+where Self: fmt::Debug
+{
+    /// Print this error the same way returning it from `main()` does, then exit with `code` instead of `1`.
+    #[inline] 
+    #[cfg(feature="exec")]
+    pub fn exit_with(self, code: i32) -> !
+    {
+	eprintln!("Error: {:?}", self);
+	std::process::exit(code)
+    }
+}
+
 impl Dispersed<false>
 {
     #[inline(always)] 
+    #[allow(dead_code)]
     pub fn obey_env(self) -> Dispersed<true>
     {
 	Dispersed(self.0)
@@ -128,6 +149,7 @@ impl Dispersed<false>
 impl Dispersed<true>
 {
     #[inline(always)]
+    #[allow(dead_code)]
     pub fn ignore_env(self) -> Dispersed<false>
     {
 	Dispersed(self.0)
@@ -137,7 +159,8 @@ impl Dispersed<true>
 impl<const E: bool> Dispersed<E>
 {
     #[inline(always)] 
-    pub fn set_env<const To: bool>(self) -> Dispersed<To>
+    #[allow(dead_code)]
+    pub fn set_env<const TO: bool>(self) -> Dispersed<TO>
     {
 	Dispersed(self.0)
     }
@@ -205,3 +228,44 @@ impl fmt::Display for Dispersed<true>
     }
 }
 
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn env_round_trip()
+    {
+	let report = eyre::Report::new(io::Error::other("inner")).wrap_err("outer");
+	let ignored: Dispersed<false> = report.into();
+	let obeyed: Dispersed<true> = ignored.obey_env();
+	let ignored: Dispersed<false> = obeyed.ignore_env();
+	let obeyed: Dispersed<true> = ignored.set_env::<true>();
+	let same: Dispersed<true> = obeyed.set_env();
+	assert_eq!(same.source().map(ToString::to_string).as_deref(), Some("inner"), "Source was lost switching verbosity");
+
+	let report = same.set_env::<false>().into_inner();
+	assert_eq!(report.to_string(), "outer", "Report was changed switching verbosity");
+    }
+
+    #[test]
+    fn env_value()
+    {
+	use std::ffi::OsStr;
+	assert_eq!(parse_env_value(Some(OsStr::new("VERBOSE"))), DispersedVerbosity::Verbose);
+	assert_eq!(parse_env_value(Some(OsStr::new("Simple"))), DispersedVerbosity::Simple);
+	// Not UTF-8, so not any of the values.
+	assert_eq!(parse_env_value(Some(OsStr::from_bytes(b"v\xff"))), DispersedVerbosity::default());
+	assert_eq!(parse_env_value(None), DispersedVerbosity::default());
+    }
+
+    #[test]
+    fn ignored_env_is_simple()
+    {
+	let dispersed: Dispersed<false> = eyre!("message").wrap_err("outer").into();
+	assert_eq!(format!("{dispersed:?}"), "outer");
+	assert!(Dispersed::<false>::from(eyre!("message")).source().is_none());
+    }
+}
@@ -3,7 +3,14 @@ use super::*;
 use args::Options;
 use std::{
     fs,
+    fmt,
+    error,
     process,
+    sync::atomic::{
+	AtomicI32,
+	AtomicBool,
+	Ordering,
+    },
     path::{
 	Path,
 	PathBuf,
@@ -11,42 +18,393 @@ use std::{
     ffi::{
 	OsStr,
 	OsString,
-    }
+    },
+    time::{
+	Duration,
+	Instant,
+    },
 };
 
-/// Get a path to the file-descriptor refered to by `file`.
-
+/// Get a path to the file-descriptor refered to by `file`, in the form of `style` (see `--fd-style`.)
+///
+/// A `/dev/fd/<fd>` path is resolved by whoever opens it, so it only refers to `file` in a child that inherits the fd.
     #[cfg_attr(feature="logging", instrument(skip_all, fields(fd = ?file.as_raw_fd())))]
-fn proc_file<F: ?Sized + AsRawFd>(file: &F) -> PathBuf
+fn proc_file<F: ?Sized + AsRawFd>(file: &F, style: args::FdPathStyle) -> PathBuf
 {
     let fd = file.as_raw_fd();
-    let pid = process::id();
-    //process::Command::new("/bin/ls").arg("-l").arg(format!("/proc/{pid}/fd/")).spawn().unwrap().wait().unwrap();
-    format!("/proc/{pid}/fd/{fd}").into()
-    //format!("/dev/fd/{fd}").into()
+    match style {
+	args::FdPathStyle::Proc => {
+	    let pid = process::id();
+	    format!("/proc/{pid}/fd/{fd}").into()
+	},
+	args::FdPathStyle::DevFd => format!("/dev/fd/{fd}").into(),
+    }
 }
 
-/// Attempt to `dup()` a file descriptor into a `RawFile`.
+/// The name of the environment variable injected into every child containing the number of bytes collected.
+pub const ENV_COLLECT_BYTES: &str = "COLLECT_BYTES";
+
+/// The name of the environment variable containing the fd of `collect`'s working directory (see `--exec-working-fd`.)
+pub const ENV_COLLECT_CWD_FD: &str = "COLLECT_CWD_FD";
+
+/// The fd of `collect`'s working directory opened by `open_working_dir()`, if it has been.
+static WORKING_DIR_FD: std::sync::OnceLock<RawFd> = std::sync::OnceLock::new();
+
+/// Open `collect`'s current working directory as an `O_PATH` fd that is inherited by every `-exec/{}` child, which is told its number in `COLLECT_CWD_FD`.
+///
+/// Children can then resolve paths relative to where `collect` was run (e.g. with `openat()`, or through `/proc/self/fd/$COLLECT_CWD_FD`) even after they change their own working directory. This should be called once, at startup; later calls return the same fd.
+#[cfg_attr(feature="logging", instrument(ret, err))]
+pub fn open_working_dir() -> io::Result<RawFd>
+{
+    if let Some(&fd) = WORKING_DIR_FD.get() {
+	return Ok(fd);
+    }
+    // No `O_CLOEXEC`: this fd is meant to be inherited.
+    let fd = match unsafe { libc::open(c".".as_ptr(), libc::O_DIRECTORY | libc::O_PATH) } {
+	-1 => return Err(io::Error::last_os_error()),
+	fd => fd,
+    };
+    Ok(*WORKING_DIR_FD.get_or_init(move || fd))
+}
+
+/// The file `-exec/{}` children's `stdout` is set to, opened by `open_exec_stdout()` (for `--exec-stdout <PATH>`.)
+static EXEC_STDOUT: std::sync::OnceLock<fs::File> = std::sync::OnceLock::new();
+
+/// Open the `--exec-stdout` target that every `-exec/{}` child's `stdout` is set to: a file is created if needed, and truncated.
+///
+/// This should be called once, at startup. `collect` closes its own `stdout` once it has been written, so a path that refers to it (e.g. `/dev/stdout`) has to be opened before then; every child is then given a duplicate of this one open file, so their output is written one after another instead of over each other. `-` is `collect`'s own `stdout`, which children inherit anyway, so nothing is opened for it.
+#[cfg_attr(feature="logging", instrument(err))]
+pub fn open_exec_stdout(target: &args::OutputTarget) -> io::Result<()>
+{
+    match target {
+	args::OutputTarget::Stdout => Ok(()),
+	args::OutputTarget::File(path) => {
+	    let file = fs::OpenOptions::new()
+		.write(true)
+		.create(true)
+		.truncate(true)
+		.open(path)?;
+	    let _ = EXEC_STDOUT.set(file);
+	    Ok(())
+	},
+	args::OutputTarget::Fd(fd) => {
+	    let file = unsafe { std::os::fd::BorrowedFd::borrow_raw(*fd) }.try_clone_to_owned()?;
+	    let _ = EXEC_STDOUT.set(file.into());
+	    Ok(())
+	},
+	args::OutputTarget::Null => {
+	    let _ = EXEC_STDOUT.set(fs::OpenOptions::new().write(true).open("/dev/null")?);
+	    Ok(())
+	},
+    }
+}
+
+/// The `stdout` of an `-exec/{}` child: the file opened by `open_exec_stdout()` if there is one, or `collect`'s own `stdout` otherwise.
 #[inline]
+fn child_stdout() -> io::Result<process::Stdio>
+{
+    match EXEC_STDOUT.get() {
+	Some(file) => file.try_clone().map(process::Stdio::from),
+	None => Ok(process::Stdio::inherit()),
+    }
+}
+
+/// Environment variables injected into every `-exec/{}` child (these are also available to `--exec-expand-env`.)
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, fields(fd = ?file.as_raw_fd())))]
+fn injected_env<F: ?Sized + AsRawFd>(file: &F) -> Vec<(OsString, OsString)>
+{
+    let mut env = Vec::with_capacity(2);
+    if let Some(fd) = WORKING_DIR_FD.get() {
+	env.push((ENV_COLLECT_CWD_FD.into(), fd.to_string().into()));
+    }
+    match memfile::stream_len(file) {
+	Ok(len) => env.push((ENV_COLLECT_BYTES.into(), len.to_string().into())),
+	Err(err) => {
+	    if_trace!(warn!("Failed to get length of buffer for `{ENV_COLLECT_BYTES}`: {err}"));
+	    let _ = err;
+	},
+    }
+    env
+}
+
+/// Replace every `{}` in `value` with `path`.
+///
+/// This is the substring version of the positional argument replacement for `-exec{}`, used for `--exec-env` values.
+#[inline] 
+fn substitute_positional(value: &OsStr, path: &OsStr) -> OsString
+{
+    substitute(value, args::POSITIONAL_ARG_STRING, path)
+}
+
+/// Replace every occurrence of `placeholder` in `value` with `with`.
+fn substitute(value: &OsStr, placeholder: &str, with: &OsStr) -> OsString
+{
+    let placeholder = placeholder.as_bytes();
+    let mut value = value.as_bytes();
+    let mut output = Vec::with_capacity(value.len());
+    while let Some(idx) = memchr::memmem::find(value, placeholder) {
+	output.extend_from_slice(&value[..idx]);
+	output.extend_from_slice(with.as_bytes());
+	value = &value[(idx + placeholder.len())..];
+    }
+    output.extend_from_slice(value);
+    OsString::from_vec(output)
+}
+
+/// Expand `$VAR` and `${VAR}` references in `arg`.
+///
+/// `lookup` is queried for the value of each variable. `\$` is an escaped literal `$`, and a `$` that does not begin a valid reference is left as it is.
+///
+/// # Returns
+/// The expanded argument. If a variable is undefined, it is left as it was written if `mode` is `Lenient`; if `mode` is `Strict`, an `InvalidInput` error is returned instead.
+fn expand_env<L>(arg: &OsStr, mode: args::EnvExpansion, lookup: L) -> io::Result<OsString>
+where L: Fn(&OsStr) -> Option<OsString>
+{
+    #[inline(always)] 
+    fn is_name(chr: u8, first: bool) -> bool
+    {
+	chr == b'_' || chr.is_ascii_alphabetic() || (!first && chr.is_ascii_digit())
+    }
+    let bytes = arg.as_bytes();
+    if memchr::memchr(b'$', bytes).is_none() {
+	return Ok(arg.to_owned());
+    }
+    
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+	match bytes[i] {
+	    b'\\' if bytes.get(i+1) == Some(&b'$') => {
+		output.push(b'$');
+		i += 2;
+		continue;
+	    },
+	    b'$' => (),
+	    chr => {
+		output.push(chr);
+		i += 1;
+		continue;
+	    },
+	}
+	// `(name, length of the whole reference)`
+	let reference = match bytes.get(i+1) {
+	    Some(b'{') => memchr::memchr(b'}', &bytes[(i+2)..])
+		.map(|end| &bytes[(i+2)..(i+2+end)])
+		.filter(|name| !name.is_empty() && is_name(name[0], true) && name.iter().all(|&c| is_name(c, false)))
+		.map(|name| (name, name.len() + 3)),
+	    Some(&c) if is_name(c, true) => {
+		let name = &bytes[(i+1)..];
+		let len = name.iter().position(|&c| !is_name(c, false)).unwrap_or(name.len());
+		Some((&name[..len], len + 1))
+	    },
+	    _ => None,
+	};
+	match reference {
+	    Some((name, len)) => {
+		let name = OsStr::from_bytes(name);
+		match lookup(name) {
+		    Some(value) => output.extend_from_slice(value.as_bytes()),
+		    None if mode == args::EnvExpansion::Strict => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("undefined environment variable {name:?} in -exec argument {arg:?}"))),
+		    None => {
+			if_trace!(debug!("Environment variable {name:?} is undefined, leaving it unexpanded"));
+			output.extend_from_slice(&bytes[i..(i+len)]);
+		    },
+		}
+		i += len;
+	    },
+	    None => {
+		output.push(b'$');
+		i += 1;
+	    },
+	}
+    }
+    Ok(OsString::from_vec(output))
+}
+
+/// Read a NUL-seperated command line from `fd` (until EOF) and create an `-exec` from it, for `--exec-command-from-fd`.
+///
+/// `fd` is closed afterwards.
+#[cfg_attr(feature="logging", instrument(level="debug", err))]
+pub fn command_from_fd(fd: RawFd) -> io::Result<args::ExecMode>
+{
+    use std::io::Read;
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } < 0 {
+	return Err(io::Error::last_os_error());
+    }
+    let mut argv = Vec::new();
+    unsafe { fs::File::from_raw_fd(fd) }.read_to_end(&mut argv)?;
+    let mode = args::ExecMode::from_nul_separated(&argv[..])
+	.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("no command was read from fd {fd}")))?;
+    if_trace!(debug!("read command from fd {fd}: {mode}"));
+    Ok(mode)
+}
+
+/// Set `O_NONBLOCK` on the open file description of `file`.
+///
+/// # Note
+/// The flag is shared by all `dup()`s of this fd. For a memfd this changes nothing for us, since reads from a regular file never return `EAGAIN`.
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, err, fields(fd = ?file.as_raw_fd())))]
+fn set_nonblocking<F: ?Sized + AsRawFd>(file: &F) -> io::Result<()>
+{
+    use libc::{
+	fcntl,
+	F_GETFL, F_SETFL,
+	O_NONBLOCK,
+    };
+    let fd = file.as_raw_fd();
+    unsafe {
+	match fcntl(fd, F_GETFL) {
+	    -1 => Err(io::Error::last_os_error()),
+	    flags if flags & O_NONBLOCK != 0 => Ok(()),
+	    flags => match fcntl(fd, F_SETFL, flags | O_NONBLOCK) {
+		-1 => Err(io::Error::last_os_error()),
+		_ => Ok(()),
+	    },
+	}
+    }
+}
+
+/// The nice value of this process.
+#[inline] 
+fn current_nice() -> libc::c_int
+{
+    // `getpriority()` can legitimately return `-1`, but only fails for invalid arguments, which these are not.
+    unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) }
+}
+
+/// Set the nice value of the child spawned by `command` to `nice` (`setpriority()` in the child, before `exec()`.)
+///
+/// If it fails (e.g. `EACCES` for a value lower than ours, without privilege), spawning the child fails with that error.
+#[inline] 
+fn set_nice(command: &mut process::Command, nice: libc::c_int)
+{
+    use std::os::unix::process::CommandExt;
+    // SAFETY: `setpriority()` is a plain system call, so it is async-signal-safe.
+    unsafe {
+	command.pre_exec(move || match libc::setpriority(libc::PRIO_PROCESS, 0, nice) {
+	    -1 => Err(io::Error::last_os_error()),
+	    _ => Ok(()),
+	});
+    }
+}
+
+/// Make the child spawned by `command` inherit only its standard streams (and the fds in `keep` that are given), by marking every other fd close-on-exec in it before `exec()`.
+///
+/// Not every fd held by `collect` is `O_CLOEXEC`: `dup()` clears the flag on the buffer's duplicates, and fds inherited from `collect`'s own parent may not have it either. They are marked instead of closed because `process::Command` reports `exec()` failures through a close-on-exec pipe of its own, which must stay open until then.
+/// The fds in `keep` have the flag cleared instead, since a re-opened buffer has it set.
+#[inline] 
+fn inherit_only(command: &mut process::Command, keep: [Option<RawFd>; 2])
+{
+    use std::os::unix::process::CommandExt;
+    // Upper bound for the fallback loop, found before `fork()`.
+    let open_max = match unsafe { libc::sysconf(libc::_SC_OPEN_MAX) } {
+	n if n > 0 => std::cmp::min(n, libc::c_int::MAX as libc::c_long) as libc::c_uint,
+	_ => 1024,
+    };
+    let mark_cloexec = move |first: libc::c_uint, last: libc::c_uint| {
+	if first > last {
+	    return Ok(());
+	}
+	// SAFETY: `close_range()` and `fcntl()` are plain system calls, so they are async-signal-safe.
+	if unsafe { libc::syscall(libc::SYS_close_range, first, last, libc::CLOSE_RANGE_CLOEXEC) } == 0 {
+	    return Ok(());
+	}
+	match io::Error::last_os_error().raw_os_error() {
+	    // `close_range()` (or `CLOSE_RANGE_CLOEXEC`) is not supported before Linux 5.11.
+	    Some(libc::ENOSYS | libc::EINVAL) => (),
+	    _ => return Err(io::Error::last_os_error()),
+	}
+	for fd in first..=std::cmp::min(last, open_max) {
+	    let fd = fd as RawFd;
+	    match unsafe { libc::fcntl(fd, libc::F_GETFD) } {
+		-1 => continue,
+		flags if flags & libc::FD_CLOEXEC == 0 => unsafe {
+		    libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+		},
+		_ => (),
+	    }
+	}
+	Ok(())
+    };
+    // Sorted before `fork()`, with the fds that are not kept (and the standard streams, which always are) moved to the end.
+    let mut keep = keep.map(|fd| fd.filter(|&fd| fd > 2).map_or(libc::c_uint::MAX, |fd| fd as libc::c_uint));
+    keep.sort_unstable();
+    // SAFETY: See `mark_cloexec`; nothing here allocates.
+    unsafe {
+	command.pre_exec(move || {
+	    let mut first = 3;
+	    for fd in keep.into_iter().take_while(|&fd| fd != libc::c_uint::MAX) {
+		mark_cloexec(first, fd.saturating_sub(1))?;
+		match libc::fcntl(fd as RawFd, libc::F_GETFD) {
+		    -1 => return Err(io::Error::last_os_error()),
+		    flags if flags & libc::FD_CLOEXEC != 0 && libc::fcntl(fd as RawFd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) == -1 => return Err(io::Error::last_os_error()),
+		    _ => (),
+		}
+		first = fd + 1;
+	    }
+	    mark_cloexec(first, libc::c_uint::MAX)
+	});
+    }
+}
 
+/// Attempt to `dup()` a file descriptor into a `RawFile`.
+///
+/// The duplicate is `O_CLOEXEC`, so it is not inherited by children (or their children) unless it is given to them explicitly: as `stdin`, or with `inherit_only()`.
+#[inline]
     #[cfg_attr(feature="logging", instrument(skip_all, err, fields(fd = ?file.as_raw_fd())))]
 fn dup_file<F: ?Sized + AsRawFd>(file: &F) -> io::Result<memfile::RawFile>
 {
     let fd = file.as_raw_fd();
     debug_assert!(fd >= 0, "Bad input file descriptor from {} (value was {fd})", std::any::type_name::<F>());
-    let fd = unsafe {
-	let res = libc::dup(fd);
-	if res < 0 {
-	    return Err(io::Error::last_os_error());
-	} else {
-	    res
-	}
-    };
-    Ok(memfile::RawFile::take_ownership_of_unchecked(fd))
+    memfile::fd::RawFileDescriptor::try_new(fd)
+	.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?
+	.dup_cloexec()
+}
+
+/// Re-open the file behind `file` through `/proc/self/fd/<fd>`: read-only for `--exec-input-readonly-reopen`, or (if `write` is `true`) read-write.
+///
+/// Unlike `dup()`, the result is a new open file description: it has its own offset (starting at 0), so reading from it does not move the offset of any other descriptor of the file. If it is read-only, it cannot be written to or resized through.
+#[cfg_attr(feature="logging", instrument(skip_all, err, fields(fd = ?file.as_raw_fd())))]
+fn reopen<F: ?Sized + AsRawFd>(file: &F, write: bool) -> io::Result<memfile::RawFile>
+{
+    let path = format!("/proc/self/fd/{}", file.as_raw_fd());
+    fs::OpenOptions::new()
+	.read(true)
+	.write(write)
+	.open(&path)
+	.map(memfile::RawFile::from_file)
+	.map_err(|err| io::Error::new(err.kind(), format!("failed to re-open {path}{}: {err}", if write { "" } else { " read-only" })))
+}
+
+/// Spawn `filename` with `args` and `env`, applying the options that affect every child (`--exec-nice`, and only inheriting the standard streams, along with `inherit` if given.)
+#[allow(clippy::too_many_arguments)]
+fn spawn_command<I>(filename: impl AsRef<OsStr>, args: I, env: &[(OsString, OsString)], cwd: Option<&Path>, inherit: Option<RawFd>, stdin: process::Stdio, stdout: process::Stdio, opt: &Options) -> io::Result<process::Child>
+where I: IntoIterator<Item = OsString>,
+{
+    let mut command = process::Command::new(filename);
+    command
+        .args(args)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(process::Stdio::inherit());
+    if let Some(cwd) = cwd {
+	command.current_dir(cwd);
+    }
+    if let Some(nice) = opt.exec_nice() {
+	set_nice(&mut command, nice);
+    }
+    inherit_only(&mut command, [WORKING_DIR_FD.get().copied(), inherit]);
+    command.spawn()
+	.map_err(|err| match (err.raw_os_error(), opt.exec_nice()) {
+	    (Some(libc::EACCES | libc::EPERM), Some(nice)) if nice < current_nice() => io::Error::new(err.kind(), format!("{err} (raising the priority of children to nice value {nice} with `--exec-nice` requires privilege, e.g. `CAP_SYS_NICE`)")),
+	    _ => err,
+	})
 }
 
     #[cfg_attr(feature="logging", instrument(skip_all, fields(has_stdin = ?file.is_some(), filename = ?filename.as_ref())))]
-fn run_stdin<I>(file: Option<impl Into<fs::File>>, filename: impl AsRef<OsStr>, args: I) -> io::Result<(process::Child, Option<fs::File>)>
+#[allow(clippy::too_many_arguments)]
+fn run_stdin<I>(file: Option<impl Into<fs::File>>, filename: impl AsRef<OsStr>, args: I, env: &[(OsString, OsString)], cwd: Option<&Path>, inherit: Option<RawFd>, stdout: process::Stdio, opt: &Options) -> io::Result<(process::Child, Option<fs::File>)>
 where I: IntoIterator<Item = OsString>,
 {
     let file = {
@@ -57,26 +415,43 @@ where I: IntoIterator<Item = OsString>,
 	    Some(mut file) => {
 		use std::io::Seek;
 		if let Err(err) = file.seek(io::SeekFrom::Start(0)) {
+		    if opt.exec_stdin_tee() {
+			// `--exec-stdin-tee`: The child must see the same full buffer that was written to `stdout`, so we cannot continue from an unknown offset.
+			if_trace!(error!("Failed to seek to start: {err}"));
+			return Err(err);
+		    }
 		    if_trace!(warn!("Failed to seed to start: {err}"));
 		}
-		let _ = try_seal_size(&file);
+		if opt.exec_input_seal_level().is_none() {
+		    let _ = try_seal_size(&file);
+		}
+		// (A child fed through a pipe is given the pipe as `stdin` instead, which is made non-blocking below.)
+		if opt.exec_input_nonblock() && !(opt.exec_stdin_pipe() || opt.exec_stdin_keep_open()) {
+		    set_nonblocking(&file)?;
+		}
 		Some(file)
 	    },
 	}
     };
     
-    let child = process::Command::new(filename)
-        .args(args)
-        .stdin(file.as_ref().map(|file| process::Stdio::from(fs::File::from(dup_file(file).unwrap()))).unwrap_or_else(|| process::Stdio::null())) //XXX: Maybe change to `piped()` and `io::copy()` from begining (using pread()/send_file()/copy_file_range()?)
-        .stdout(process::Stdio::inherit())
-        .stderr(process::Stdio::inherit())
-        .spawn()?;
-    //TODO: XXX: Why does `/proc/{pid}/fd/{fd}` **and** `/dev/fd/{fd}` not work for -exec{}, and why foes `Stdio::from(file)` not work for stdin even *afer* re-seeking the file???
-    /*
-    if let Some((mut input, mut output)) = file.as_mut().zip(child.stdin.take()) {
-	io::copy(&mut input, &mut output)
-	    /*.wrap_err("Failed to pipe file into stdin for child")*/?;
-    }*/
+    // By default, the child is given a `dup()` of the buffer itself (not a pipe), so it can `stat()`, seek, or (unless sealed) write to its input.
+    // Since the duplicate shares the file's offset, the file must be re-seeked to the start above for the child to see all of it.
+    let (stdin, piped) = match &file {
+	// `--exec-stdin-pipe` / `--exec-stdin-keep-open`: The buffer is fed through a pipe instead, see `feed_pipe()`.
+	Some(file) if opt.exec_stdin_pipe() || opt.exec_stdin_keep_open() => {
+	    let (read, write) = sys::pipe_cloexec()?;
+	    if opt.exec_input_nonblock() {
+		set_nonblocking(&read)?;
+	    }
+	    (process::Stdio::from(fs::File::from(read)), Some((fs::File::from(dup_file(file)?), write)))
+	},
+	Some(file) => (process::Stdio::from(fs::File::from(dup_file(file)?)), None),
+	None => (process::Stdio::null(), None),
+    };
+    let child = spawn_command(filename, args, env, cwd, inherit, stdin, stdout, opt)?;
+    if let Some((input, pipe)) = piped {
+	feed_pipe(input, pipe, opt.exec_stdin_keep_open());
+    }
     
     if_trace!(info!("Spawned child process: {}", child.id()));
     /*Ok(child.wait()?
@@ -86,21 +461,240 @@ where I: IntoIterator<Item = OsString>,
     Ok((child, file))
 }
 
+/// The write ends of the `stdin` pipes of children spawned with `--exec-stdin-keep-open`, which are never closed before `collect` exits.
+static KEPT_OPEN: std::sync::Mutex<Vec<memfile::RawFile>> = std::sync::Mutex::new(Vec::new());
+
+/// Write all of `input` (from offset 0) into the `stdin` pipe of a child from a background thread (see `--exec-stdin-pipe`.)
+///
+/// The pipe is then closed so the child sees EOF; unless `keep_open` is set, in which case it is kept open so the child never sees EOF on it (see `--exec-stdin-keep-open`.)
+/// A child that exits (or closes its `stdin`) without reading all of it only stops the writing: `SIGPIPE` is ignored by Rust programs, so the write fails with `EPIPE` instead.
+///
+/// Without this, a child's `stdin` is the buffer itself, which ends at the buffer's length like any other file.
+fn feed_pipe(input: fs::File, mut pipe: memfile::RawFile, keep_open: bool)
+{
+    std::thread::spawn(move || {
+	use io::Write;
+	let mut buffer = vec![0u8; 64 * 1024];
+	let mut offset = 0;
+	let result = loop {
+	    match input.read_at(&mut buffer[..], offset) {
+		Ok(0) => break Ok(offset),
+		Ok(read) => if let Err(err) = pipe.write_all(&buffer[..read]) {
+		    break Err(err);
+		} else {
+		    offset += read as u64;
+		},
+		Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+		Err(err) => break Err(err),
+	    }
+	};
+	match result {
+	    Ok(_written) => {
+		if_trace!(debug!("wrote {_written} bytes to child's stdin pipe{}", if keep_open { ", keeping it open" } else { "" }));
+	    },
+	    Err(_err) if _err.kind() == io::ErrorKind::BrokenPipe => {
+		if_trace!(debug!("child closed its stdin pipe after {offset} bytes were written to it"));
+	    },
+	    Err(_err) => {
+		if_trace!(warn!("failed to write buffer to child's stdin pipe: {_err}"));
+	    },
+	}
+	if keep_open {
+	    KEPT_OPEN.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(pipe);
+	}
+    });
+}
+
 /// Run a single `-exec` / `-exec{}` and return the (possibly still running) child process if succeeded in spawning.
 ///
 /// The caller must wait for all child processes to exit before the parent does.
 #[inline]
-    #[cfg_attr(feature="logging", instrument(skip(file), err))]
-pub fn run_single<F: ?Sized + AsRawFd>(file: &F, opt: args::ExecMode) -> io::Result<(process::Child, Option<fs::File>)>
+pub fn run_single<F: ?Sized + AsRawFd>(file: &F, opt: args::ExecMode, global: &Options) -> io::Result<(process::Child, Option<fs::File>)>
 {
-    let input: std::mem::ManuallyDrop<memfile::RawFile> = std::mem::ManuallyDrop::new(dup_file(file)?);
+    run_single_with(file, opt, global, child_stdout()?)
+}
+
+/// Run a single `-exec` / `-exec{}` with its `stdout` set to `stdout`.
+    #[cfg_attr(feature="logging", instrument(skip(file, global, stdout), err))]
+fn run_single_with<F: ?Sized + AsRawFd>(file: &F, opt: args::ExecMode, global: &Options, stdout: process::Stdio) -> io::Result<(process::Child, Option<fs::File>)>
+{
+    let input = dup_file(file)?;
+    // `--exec-input-seal-level` was explicitly requested, so failing to apply it is an error.
+    // Seals belong to the memfd itself, not this descriptor: so they must be applied *before* `input` is dup'd into the child's `stdin` (or re-opened through its path), and both of those see them.
+    // (The `memfd` strategy has already applied them before writing the buffer to the output; but the input may have come from elsewhere.)
+    apply_seal_level(&input, global)?;
+    // Every `-exec` child reads its `stdin` from offset 0. A `dup()` would share its offset with the other children (and with anything they leave reading it in the background, or that runs at the same time with `--exec-parallel`), so each one is given its own open of the buffer instead.
+    // If that is not possible, it falls back to a `dup()` (which `run_stdin()` rewinds); unless `--exec-stdin-memfd-refresh` was passed, or `--exec-input-readonly-reopen` failed to re-open a regular file.
+    let readonly = global.exec_input_readonly_reopen();
+    let refresh = global.exec_stdin_memfd_refresh() && opt.is_stdin();
+    let input = if readonly || opt.is_stdin() {
+	match sys::classify_fd(&input) {
+	    Ok(sys::FdKind::Regular) => {
+		// Seals can only be added through a writable descriptor, so the size is sealed here instead of by `run_stdin()`.
+		if opt.is_stdin() && global.exec_input_seal_level().is_none() {
+		    let _ = try_seal_size(&input);
+		}
+		match reopen(&input, !readonly) {
+		    Ok(reopened) => reopened,
+		    Err(_err) if !(readonly || refresh) => {
+			if_trace!(debug!("{_err}, duplicating the input for the child instead"));
+			input
+		    },
+		    Err(err) => return Err(err),
+		}
+	    },
+	    kind if refresh => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("input is not a regular file ({kind:?}), so it cannot be re-opened for the child"))),
+	    _kind => {
+		if_trace!(if readonly {
+		    warn!("--exec-input-readonly-reopen: input is not a regular file ({_kind:?}), duplicating it instead");
+		} else {
+		    debug!("input is not a regular file ({_kind:?}), so it cannot be re-opened for the child: duplicating it instead");
+		});
+		input
+	    },
+	}
+    } else {
+	input
+    };
+    // The fd must stay open for `-exec{}` children to open its path.
+    let input: std::mem::ManuallyDrop<memfile::RawFile> = std::mem::ManuallyDrop::new(input);
+    let (cwd, path) = positional_path(&opt, global, proc_file(&*input, global.fd_path_style()))?;
+    // `--fd-style dev`: `/dev/fd/<fd>` is resolved in the child, so it must inherit the fd.
+    let inherit = (global.fd_path_style() == args::FdPathStyle::DevFd).then(|| input.as_raw_fd());
+    let (command, args, env) = prepare(opt, global, injected_env(file), path.as_os_str())?;
+    match args {
+	Ok(args) => run_stdin(Some(std::mem::ManuallyDrop::into_inner(input)), command, args, &env[..], None, None, stdout, global),
+	Err(args) => run_stdin(None::<fs::File>, command, args, &env[..], cwd.as_deref(), inherit, stdout, global),
+    }
+}
+
+/// The working directory of an `exec` child (if it should not inherit `collect`'s), and the path its `{}`s are replaced with, where `path` is the `/proc/<pid>/fd/<fd>` (or `/dev/fd/<fd>`) link to its input.
+///
+/// For `-exec{}` with `--exec-chdir-to-fd-dir`, this is the directory holding the link and the bare `<fd>`, which is relative to it. The directory is checked to exist first, since procfs may not be mounted.
+fn positional_path(exec: &args::ExecMode, global: &Options, path: PathBuf) -> io::Result<(Option<PathBuf>, OsString)>
+{
+    match exec {
+	args::ExecMode::Positional { .. } if global.exec_chdir_to_fd_dir() => {
+	    let (dir, fd) = path.parent().zip(path.file_name()).expect("`<dir>/fd/<fd>` has a parent and a file name");
+	    if !dir.is_dir() {
+		return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} does not exist (procfs is required for `--exec-chdir-to-fd-dir`)", dir.display())));
+	    }
+	    if_trace!(debug!("--exec-chdir-to-fd-dir: child starts in {dir:?}, `{{}}` is {fd:?}"));
+	    Ok((Some(dir.to_owned()), fd.to_owned()))
+	},
+	_ => Ok((None, path.into_os_string())),
+    }
+}
+
+/// The command, arguments, and environment of a child, as returned by `prepare()`.
+type Prepared = (OsString, Result<Vec<OsString>, Vec<OsString>>, Vec<(OsString, OsString)>);
+
+/// Prepare the command line and environment of a child for `exec`, where `path` is the path to its input.
+///
+/// # Returns
+/// The command, its arguments (`Ok` for `-exec`, which reads its input from `stdin`; `Err` for `-exec{}`, where every `{}` is replaced with `path`, and every `{size}` with the number of bytes collected), and the environment it should be given (`env`, along with `--exec-env`.)
+///
+/// The number of bytes collected is taken from `COLLECT_BYTES` in `env`: if it is not there (e.g. for `--exec-stdin-from=input`, where nothing is collected), `{size}` is left as it is.
+fn prepare(exec: args::ExecMode, global: &Options, mut env: Vec<(OsString, OsString)>, path: &OsStr) -> io::Result<Prepared>
+{
+    let size = env.iter()
+	.find_map(|(name, value)| (name == ENV_COLLECT_BYTES).then(|| value.clone()));
+    // `-exec{}`'s `{size}`s are replaced before `--exec-expand-env`, so the value of a variable is never substituted.
+    let sized = |arg: OsString| match &size {
+	Some(size) => substitute(&arg, args::SIZE_ARG_STRING, size),
+	None => arg,
+    };
+    // `--exec-env`: every `{}` in the value is the path to the input, like `-exec{}`'s positional arguments.
+    env.extend(global.exec_env().iter()
+	       .map(|(name, value)| (name.clone(), substitute_positional(value, path))));
+    let expand = |arg: OsString| -> io::Result<OsString> {
+	match global.exec_expand_env() {
+	    Some(mode) => expand_env(&arg, mode, |name| {
+		env.iter()
+		    .find_map(|(k, v)| (k.as_os_str() == name).then(|| v.clone()))
+		    .or_else(|| std::env::var_os(name))
+	    }),
+	    None => Ok(arg),
+	}
+    };
     
-    match opt {
+    let (command, args) = match exec {
 	args::ExecMode::Positional { command, args } => {
-	    run_stdin(None::<fs::File>, command, args.into_iter().map(|x| x.unwrap_or_else(|| proc_file(&*input).into())))
+	    let args = args.into_iter()
+		.map(|x| x.map(&sized).map(&expand).transpose().map(|x| x.unwrap_or_else(|| path.to_owned())))
+		.collect::<io::Result<Vec<_>>>()?;
+	    (command, Err(args))
 	},
 	args::ExecMode::Stdin { command, args } => {
-	    run_stdin(Some(std::mem::ManuallyDrop::into_inner(input)), command, args)
+	    let args = args.into_iter()
+		.map(&expand)
+		.collect::<io::Result<Vec<_>>>()?;
+	    (command, Ok(args))
+	}
+    };
+    // `--exec-prefix`: the command (after its `{}`s have been substituted) becomes an argument of the wrapper.
+    let (command, args) = match global.exec_prefix() {
+	[wrapper, wrapper_args @ ..] => {
+	    let prefixed = move |args: Vec<OsString>| wrapper_args.iter().cloned().chain(std::iter::once(command)).chain(args).collect();
+	    (wrapper.clone(), match args {
+		Ok(args) => Ok(prefixed(args)),
+		Err(args) => Err(prefixed(args)),
+	    })
+	},
+	[] => (command, args),
+    };
+    Ok((command, args, env))
+}
+
+/// Run a single `-exec` / `-exec{}` on `collect`'s own `stdin`, instead of the collected buffer (for `--exec-stdin-from=input`.)
+///
+/// An `-exec` child inherits `stdin` directly and reads the input itself; for `-exec{}`, `{}` is the path to `collect`'s `stdin`. Nothing is collected, so this can only be done for a single child.
+#[cfg_attr(feature="logging", instrument(skip(global), err))]
+pub fn run_on_input(exec: args::ExecMode, global: &Options) -> io::Result<process::Child>
+{
+    let (cwd, path) = positional_path(&exec, global, proc_file(&io::stdin(), global.fd_path_style()))?;
+    let env = WORKING_DIR_FD.get()
+	.map(|fd| (ENV_COLLECT_CWD_FD.into(), fd.to_string().into()))
+	.into_iter().collect();
+    // `--fd-style dev`: `/dev/fd/0` is resolved in the child, so an `-exec{}` child is given `stdin` too.
+    let positional_stdin = match global.fd_path_style() {
+	args::FdPathStyle::DevFd => process::Stdio::inherit(),
+	args::FdPathStyle::Proc => process::Stdio::null(),
+    };
+    let (command, args, env) = prepare(exec, global, env, path.as_os_str())?;
+    let child = match args {
+	Ok(args) => spawn_command(command, args, &env[..], None, None, process::Stdio::inherit(), child_stdout()?, global),
+	Err(args) => spawn_command(command, args, &env[..], cwd.as_deref(), None, positional_stdin, child_stdout()?, global),
+    }?;
+    PidFile::open(global)?.record(&child);
+    if_trace!(info!("Spawned child process on input: {}", child.id()));
+    Ok(child)
+}
+
+/// The file that `--exec-pid-file` writes the PIDs of spawned children to (if it was passed.)
+#[derive(Debug, Default)]
+struct PidFile(Option<fs::File>);
+
+impl PidFile
+{
+    /// Create (or truncate) the `--exec-pid-file`, if one was passed.
+    #[inline] 
+    fn open(opt: &Options) -> io::Result<Self>
+    {
+	opt.exec_pid_file().map(fs::File::create).transpose().map(Self)
+    }
+
+    /// Write the PID of `child` on its own line.
+    ///
+    /// `fs::File` is not buffered, so the line is visible to other processes as soon as this returns. The child has already been spawned, so a failure here is only a warning.
+    #[inline] 
+    fn record(&mut self, child: &process::Child)
+    {
+	use io::Write;
+	if let Some(file) = self.0.as_mut() {
+	    if let Err(_err) = file.write_all(format!("{}\n", child.id()).as_bytes()) {
+		if_trace!(warn!("Failed to write PID {} to `--exec-pid-file`: {_err}", child.id()));
+	    }
 	}
     }
 }
@@ -110,37 +704,865 @@ pub fn run_single<F: ?Sized + AsRawFd>(file: &F, opt: args::ExecMode) -> io::Res
 /// # Returns
 /// An iterator of each (possibly running) spawned child, or the error that occoured when trying to spawn that child from the `exec` option in `opt`.
     #[cfg_attr(feature="logging", instrument(skip(file)))]
-pub fn spawn_from<'a, F: ?Sized + AsRawFd>(file: &'a F, opt: Options) -> impl IntoIterator<Item = io::Result<(process::Child, Option<fs::File>)>> + 'a
+pub fn spawn_from<'a, F: ?Sized + AsRawFd>(file: &'a F, mut opt: Options) -> impl IntoIterator<Item = io::Result<(process::Child, Option<fs::File>)>> + 'a
 {
-    opt.into_opt_exec().map(|x| run_single(file, x))
+    let exec = opt.take_exec();
+    if opt.exec_stdin_tee() && !exec.iter().any(args::ExecMode::is_stdin) {
+	if_trace!(warn!("--exec-stdin-tee provided, but there are no `-exec` children to feed the buffer to"));
+    }
+    let mut pids = PidFile::open(&opt);
+    // After `SIGHUP`, nothing more is spawned (see `install_hangup_handler()`.)
+    exec.into_iter().take_while(|_| !hung_up()).map(move |x| {
+	let pids = pids.as_mut().map_err(|err| io::Error::new(err.kind(), format!("failed to create `--exec-pid-file`: {err}")))?;
+	let spawned = run_single(file, x, &opt)?;
+	pids.record(&spawned.0);
+	Ok(spawned)
+    })
 }
 
-/// Spawn all `-exec/{}` commands and wait for all children to complete.
+/// How a single `-exec/{}` child failed.
+#[derive(Debug)]
+pub enum ChildFailure
+{
+    /// The child could not be spawned.
+    Spawn(io::Error),
+    /// The child was spawned, but waiting on it failed.
+    Wait(io::Error),
+    /// The child exited with a non-zero status.
+    Exit(i32),
+    /// The child was terminated by a signal.
+    Signal(i32),
+    /// The child was still running after `--exec-timeout`, so it was killed.
+    TimedOut(Duration),
+}
+
+impl fmt::Display for ChildFailure
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match self {
+	    Self::Spawn(err) => write!(f, "failed to spawn: {err}"),
+	    Self::Wait(err) => write!(f, "failed to wait on child: {err}"),
+	    Self::Exit(code) => write!(f, "exited with status {code}"),
+	    Self::Signal(signal) => write!(f, "killed by signal {signal}"),
+	    Self::TimedOut(timeout) => write!(f, "timed out after {timeout:?} and was killed"),
+	}
+    }
+}
+
+/// A failed `-exec/{}` child, with its index and command.
+#[derive(Debug)]
+pub struct ChildError
+{
+    pub index: usize,
+    pub command: String,
+    pub failure: ChildFailure,
+}
+
+impl error::Error for ChildError
+{
+    #[inline] 
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	match &self.failure {
+	    ChildFailure::Spawn(err) | ChildFailure::Wait(err) => Some(err),
+	    _ => None,
+	}
+    }
+}
+
+impl fmt::Display for ChildError
+{
+    #[inline] 
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	write!(f, "child {} ({}) {}", self.index, self.command, self.failure)
+    }
+}
+
+/// Every `-exec/{}` child that failed (see `wait_all()`.)
+#[derive(Debug, Default)]
+pub struct AggregateError(Vec<ChildError>);
+
+impl AggregateError
+{
+    /// The exit code `collect` should exit with for these failures: that of the first child (by index) that exited with a non-zero status, or `128 + <signal>` if it was killed by a signal (as shells report it.)
+    ///
+    /// # Returns
+    /// `None` if none of the children that failed ran to completion (e.g. they could not be spawned), in which case there is no child code to pass on.
+    pub fn exit_code(&self) -> Option<i32>
+    {
+	self.0.iter().find_map(|error| match error.failure {
+	    ChildFailure::Exit(code) => Some(code),
+	    ChildFailure::Signal(signal) => Some(128 + signal),
+	    _ => None,
+	})
+    }
+}
+
+impl error::Error for AggregateError{}
+
+impl fmt::Display for AggregateError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match &self.0[..] {
+	    [single] => write!(f, "-exec/{{}} {single}"),
+	    failures => {
+		write!(f, "{} -exec/{{}} children failed:", failures.len())?;
+		for failure in failures {
+		    write!(f, "\n    {failure}")?;
+		}
+		Ok(())
+	    },
+	}
+    }
+}
+
+/// How often a child is polled while waiting on it with `--exec-timeout`.
+const TIMEOUT_POLL: Duration = Duration::from_millis(10);
+
+/// How long a child is given to exit after it is sent `SIGTERM` for `--exec-timeout`, before it is sent `SIGKILL`.
+const TIMEOUT_GRACE: Duration = Duration::from_secs(2);
+
+/// Poll `child` (with `waitpid(WNOHANG)`) until it exits, or `deadline` passes.
 ///
 /// # Returns
-/// An iterator of the result of spawning each child and its exit status (if one exists)
+/// The child's exit status, or `None` if it was still running at `deadline`.
+fn wait_until(child: &mut process::Child, deadline: Instant) -> io::Result<Option<process::ExitStatus>>
+{
+    loop {
+	if let Some(status) = child.try_wait()? {
+	    return Ok(Some(status));
+	}
+	let now = Instant::now();
+	if now >= deadline {
+	    return Ok(None);
+	}
+	std::thread::sleep(std::cmp::min(TIMEOUT_POLL, deadline - now));
+    }
+}
+
+/// Wait on `child`. If it is still running at `deadline` (see `--exec-timeout`), it is sent `SIGTERM`; and then `SIGKILL` if it has still not exited `TIMEOUT_GRACE` after that.
 ///
-/// If the child exited via a signal termination, or another method that does not return a status, the iterator's result will be `Ok(None)`
+/// # Returns
+/// The child's exit status, or `None` if it was killed for running past `deadline`.
+#[cfg_attr(feature="logging", instrument(level="debug", skip(child), fields(child = child.id()), err))]
+pub fn wait_timeout(child: &mut process::Child, deadline: Option<Instant>) -> io::Result<Option<process::ExitStatus>>
+{
+    let deadline = match deadline {
+	Some(deadline) => deadline,
+	None => return child.wait().map(Some),
+    };
+    if let Some(status) = wait_until(child, deadline)? {
+	return Ok(Some(status));
+    }
+    if_trace!(warn!("--exec-timeout: child {} is still running, sending it SIGTERM", child.id()));
+    if unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) } != 0 {
+	let err = io::Error::last_os_error();
+	// It exited (and was reaped by `try_wait()`) just now.
+	if err.raw_os_error() != Some(libc::ESRCH) {
+	    return Err(err);
+	}
+    }
+    if wait_until(child, Instant::now() + TIMEOUT_GRACE)?.is_none() {
+	if_trace!(warn!("--exec-timeout: child {} did not exit {TIMEOUT_GRACE:?} after SIGTERM, sending it SIGKILL", child.id()));
+	child.kill()?;
+	child.wait()?;
+    }
+    Ok(None)
+}
+
+/// Wait on the `index`th child (running `command`), which was spawned with the result `child`.
+///
+/// With `--exec-timeout`, the child is killed if it is still running `timeout` after `started` (see `wait_timeout()`.)
+///
+/// # Returns
+/// An error if the child could not be spawned or waited on, if it exited with a non-zero status or via a signal, or if it timed out.
+fn wait_child(index: usize, command: String, child: io::Result<(process::Child, Option<fs::File>)>, started: Instant, timeout: Option<Duration>) -> Result<(), ChildError>
+{
+    let failure = match child {
+	Ok(mut child) => match wait_timeout(&mut child.0, timeout.map(|timeout| started + timeout)) {
+	    Ok(None) => ChildFailure::TimedOut(timeout.unwrap_or_default()),
+	    Ok(Some(status)) => match (status.code(), status.signal()) {
+		(Some(0), _) => return Ok(()),
+		(Some(code), _) => ChildFailure::Exit(code),
+		(None, Some(signal)) => ChildFailure::Signal(signal),
+		// Neither an exit status nor a signal, there is nothing to report.
+		(None, None) => return Ok(()),
+	    },
+	    Err(err) => ChildFailure::Wait(err),
+	},
+	Err(err) => ChildFailure::Spawn(err),
+    };
+    if_trace!(error!("Child {index} failed: {failure}"));
+    Err(ChildError { index, command, failure })
+}
+
+/// Spawn all `-exec/{}` commands and wait for all children to complete.
+///
+/// # Returns
+/// An iterator of the result of spawning and waiting on each child. A child that exits with a non-zero status, via a signal, or that runs for longer than `--exec-timeout`, is an error.
 #[inline] 
     #[cfg_attr(feature="logging", instrument(skip(file)))]
-pub fn spawn_from_sync<'a, F: ?Sized + AsRawFd>(file: &'a F, opt: Options) -> impl IntoIterator<Item = eyre::Result<Option<i32>>> + 'a
+pub fn spawn_from_sync<'a, F: ?Sized + AsRawFd>(file: &'a F, opt: Options) -> impl IntoIterator<Item = Result<(), ChildError>> + 'a
 {
-    spawn_from(file, opt).into_iter().zip(0..).map(move |(child, idx)| -> eyre::Result<_> {
-	
-	let idx = move || idx.to_string().header("The child index");
-	match child {
-	    Ok(mut child) => {
-		Ok(child.0.wait()
-		   .wrap_err("Failed to wait on child")
-		   .with_note(|| "The child may have detached itself")
-		   .with_section(idx)?
-		   .code())
-	    },
-	    Err(err) => {
-		if_trace!(error!("Failed to spawn child: {err}"));
-		Err(err)
-		    .wrap_err("Failed to spawn child")
+    let commands: Vec<_> = opt.opt_exec().map(ToString::to_string).collect();
+    let timeout = opt.exec_timeout();
+    // Each child is waited on as soon as it has been spawned, so its time starts then.
+    spawn_from(file, opt).into_iter().zip(commands).enumerate().map(move |(index, (child, command))| wait_child(index, command, child, Instant::now(), timeout))
+}
+
+/// Spawn every `-exec/{}` command before waiting on any of them, so they all run at the same time (for `--exec-parallel`.)
+///
+/// Each `-exec` child is given its own open of the buffer (see `run_single_with()`), so children reading their `stdin` at the same time do not take data from each other.
+///
+/// # Returns
+/// Each (running) spawned child, or the error from spawning it, in the same order as the commands.
+    #[cfg_attr(feature="logging", instrument(skip(file)))]
+pub fn spawn_all_parallel<F: ?Sized + AsRawFd>(file: &F, opt: Options) -> Vec<io::Result<(process::Child, Option<fs::File>)>>
+{
+    spawn_from(file, opt).into_iter().collect()
+}
+
+/// Spawn all `-exec/{}` commands, and wait for them to complete.
+///
+/// Every child is ran, and all failures are returned together; unless `--exec-fail-fast` was passed, in which case no more children are ran after the first one fails. With `--exec-parallel`, every child has already been spawned by then, so the rest are killed instead.
+#[cfg_attr(feature="logging", instrument(skip(file)))]
+pub fn wait_all<F: ?Sized + AsRawFd>(file: &F, opt: Options) -> Result<(), AggregateError>
+{
+    let fail_fast = opt.exec_fail_fast();
+    let mut errors = AggregateError::default();
+    if opt.exec_parallel() {
+	let commands: Vec<_> = opt.opt_exec().map(ToString::to_string).collect();
+	let timeout = opt.exec_timeout();
+	// The children all run from (about) when they are spawned, not from when they are waited on: so they share one `--exec-timeout` deadline.
+	let started = Instant::now();
+	let mut children = spawn_all_parallel(file, opt).into_iter().zip(commands).enumerate();
+	// Waited on in order, so failures are still reported by child index.
+	while let Some((index, (child, command))) = children.next() {
+	    if let Err(error) = wait_child(index, command, child, started, timeout) {
+		errors.0.push(error);
+		if fail_fast {
+		    for (_index, (child, _)) in children.by_ref() {
+			if let Ok((mut child, _)) = child {
+			    if_trace!(debug!("--exec-fail-fast: killing child {_index} ({})", child.id()));
+			    let _ = child.kill();
+			    let _ = child.wait();
+			}
+		    }
+		}
 	    }
-	}.with_section(idx)
-    })
+	}
+    } else {
+	for result in spawn_from_sync(file, opt) {
+	    if let Err(error) = result {
+		errors.0.push(error);
+		if fail_fast {
+		    break;
+		}
+	    }
+	}
+    }
+    if errors.0.is_empty() {
+	Ok(())
+    } else {
+	Err(errors)
+    }
+}
+
+/// Run a single `-exec` / `-exec{}` to completion, capturing its `stdout` into a new in-memory file.
+///
+/// With `--exec-max-output`, only that much of its output is captured: if it writes any more, it is killed instead of being waited on to finish.
+///
+/// # Returns
+/// The exit status of the child (see `spawn_from_sync()`), the in-memory file holding its output, seeked to the start, and whether that output was truncated (in which case the child was killed.)
+    #[cfg_attr(feature="logging", instrument(skip(file, global, pids), err))]
+fn run_captured<F: ?Sized + AsRawFd>(file: &F, opt: args::ExecMode, global: &Options, pids: &mut PidFile) -> eyre::Result<(Option<i32>, fs::File, bool)>
+{
+    use std::io::Seek;
+    let (mut child, _input) = run_single_with(file, opt, global, process::Stdio::piped())
+	.wrap_err("Failed to spawn child")?;
+    pids.record(&child);
+    let mut stdout = child.stdout.take().expect("Child's stdout was not piped");
+    
+    let mut output = fs::File::from(memfile::RawFile::open_mem(Some("collect-exec-output"), 0)
+				    .wrap_err("Failed to create in-memory file for child's output")?);
+    let read = io::copy(&mut io::Read::take(&mut stdout, global.exec_max_output().unwrap_or(u64::MAX)), &mut output)
+	.wrap_err("Failed to copy child's output into in-memory file")
+	.with_section(|| format!("{:?}", output).header("Memory file"))?;
+    // The limit was reached: if there is anything left to read, the child wrote more than allowed.
+    let truncated = Some(read) == global.exec_max_output() && matches!(io::Read::read(&mut stdout, &mut [0u8]), Ok(1));
+    if truncated {
+	if_trace!(warn!("--exec-max-output: child {} wrote more than {read} bytes, killing it", child.id()));
+	child.kill()
+	    .wrap_err("Failed to kill child that exceeded `--exec-max-output`")?;
+    }
+    drop(stdout);
+    if_trace!(debug!("Captured {read} bytes from child {}", child.id()));
+    
+    let code = child.wait()
+	.wrap_err("Failed to wait on child")
+	.with_note(|| "The child may have detached itself")?
+	.code();
+    output.seek(io::SeekFrom::Start(0))
+	.wrap_err("Failed to seek in-memory file to start")?;
+    let _ = try_seal_size(&output);
+    Ok((code, output, truncated))
+}
+
+/// Run all `-exec/{}` commands as a chain, for `--exec-output-to-memfd`.
+///
+/// Unlike `spawn_from_sync()`, the children are run one after another: each child's `stdout` is captured into a new in-memory file, which becomes the input of the next child. The first child's input is `file`.
+///
+/// # Returns
+/// The exit status of each child (see `spawn_from_sync()`), and the in-memory file holding the output of the last child (`None` if there were no children.)
+    #[cfg_attr(feature="logging", instrument(skip(file)))]
+pub fn spawn_chain_sync<F: ?Sized + AsRawFd>(file: &F, mut opt: Options) -> eyre::Result<(Vec<Option<i32>>, Option<fs::File>)>
+{
+    let exec = opt.take_exec();
+    if opt.exec_parallel() {
+	if_trace!(warn!("--exec-parallel has no effect with --exec-output-to-memfd, each child's input is the output of the one before it"));
+    }
+    if opt.exec_timeout().is_some() {
+	if_trace!(warn!("--exec-timeout has no effect with --exec-output-to-memfd, each child's output is read until it closes it"));
+    }
+    if opt.exec_stdout().is_some() {
+	if_trace!(warn!("--exec-stdout has no effect with --exec-output-to-memfd, each child's output is captured for the next one"));
+    }
+    let mut codes = Vec::with_capacity(exec.len());
+    let mut output: Option<fs::File> = None;
+    let mut pids = PidFile::open(&opt)
+	.wrap_err("Failed to create `--exec-pid-file`")?;
+    for (idx, exec) in (0..).zip(exec.into_iter().take_while(|_| !hung_up())) {
+	let (code, stage, truncated) = match output.as_ref() {
+	    Some(previous) => run_captured(previous, exec, &opt, &mut pids),
+	    None => run_captured(file, exec, &opt, &mut pids),
+	}.with_section(move || idx.to_string().header("The child index"))?;
+	if_trace!(debug!("Stage {idx} exited with {code:?}"));
+	// The child was killed by us, so it did not fail: its (truncated) output is still passed on.
+	codes.push(if truncated { Some(0) } else { code });
+	output = Some(stage);
+    }
+    Ok((codes, output))
+}
+
+/// Find the executable that `command` would run: `command` itself if it contains a `/`, otherwise the first executable file named `command` in `PATH`.
+#[cfg_attr(feature="logging", instrument(level="debug", ret))]
+pub fn resolve_command(command: &OsStr) -> Option<PathBuf>
+{
+    fn is_executable(path: &Path) -> bool
+    {
+	use std::os::unix::fs::PermissionsExt;
+	fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    if command.is_empty() {
+	return None;
+    }
+    if command.as_bytes().contains(&b'/') {
+	let path = PathBuf::from(command);
+	return is_executable(&path).then_some(path);
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+	.map(|dir| dir.join(command))
+	.find(|path| is_executable(path))
+}
+
+/// Validate a single `-exec/{}` without running it, returning a description of each problem found with it.
+///
+/// This checks that its command can be found, and that `{}` is used where it is substituted (see `--check`.)
+pub fn check(exec: &args::ExecMode) -> Vec<String>
+{
+    use args::{
+	ExecMode,
+	POSITIONAL_ARG_STRING,
+	EXEC_MODE_STRING_TERMINATOR,
+    };
+    let mut problems = Vec::new();
+    let command = exec.command();
+    if command == EXEC_MODE_STRING_TERMINATOR {
+	problems.push(format!("the command is the terminator `{EXEC_MODE_STRING_TERMINATOR}`, is a command missing before it?"));
+    } else if command == POSITIONAL_ARG_STRING {
+	problems.push(format!("the command is `{POSITIONAL_ARG_STRING}`, which is not substituted"));
+    } else if resolve_command(command).is_none() {
+	problems.push(if command.as_bytes().contains(&b'/') {
+	    format!("{command:?} is not an executable file")
+	} else {
+	    format!("command {command:?} was not found in `PATH`")
+	});
+    }
+    match exec {
+	ExecMode::Stdin { args, .. } => {
+	    if args.iter().any(|arg| arg == POSITIONAL_ARG_STRING) {
+		problems.push(format!("`{POSITIONAL_ARG_STRING}` is passed literally by `-exec`, did you mean `-exec{{}}`?"));
+	    }
+	},
+	ExecMode::Positional { args, .. } => {
+	    if args.iter().all(Option::is_some) {
+		problems.push(format!("`-exec{{}}` has no `{POSITIONAL_ARG_STRING}` argument to replace with the buffer's path, did you mean `-exec`?"));
+	    }
+	},
+    }
+    if exec.arguments().flatten().any(|arg| arg == "-exec" || arg == "-exec{}") {
+	problems.push(format!("an argument is `-exec` or `-exec{{}}`, is a `{EXEC_MODE_STRING_TERMINATOR}` missing before it?"));
+    }
+    problems
+}
+
+/// The name of the environment variable containing the number of the signal that triggered `--exec-on-signal`.
+pub const ENV_COLLECT_SIGNAL: &str = "COLLECT_SIGNAL";
+
+/// The signals that trigger `--exec-on-signal`.
+pub const HOOK_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// Write end of the self-pipe that `on_hook_signal()` writes caught signals to (`-1` until `install_signal_hook()` is called.)
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// The last signal caught by `on_hook_signal()` (`0` if none has been.)
+static SIGNAL_CAUGHT: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn on_hook_signal(sig: libc::c_int)
+{
+    // Only async-signal-safe calls are allowed here, so the signal is only passed on to the watcher thread.
+    SIGNAL_CAUGHT.store(sig, Ordering::SeqCst);
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+	let sig = sig as u8;
+	unsafe {
+	    libc::write(fd, &sig as *const u8 as *const libc::c_void, 1);
+	}
+    }
+}
+
+/// Install handlers for `HOOK_SIGNALS` that run `command` (with `/bin/sh -c`) when one of them is caught.
+///
+/// The handler itself only writes the signal to a pipe; a watcher thread reads it, runs `command` with the signal number in `COLLECT_SIGNAL` and waits for it, then re-raises the signal with its default disposition, so `collect` still terminates the way it would have without the hook.
+#[cfg_attr(feature="logging", instrument(err))]
+pub fn install_signal_hook(command: OsString) -> io::Result<()>
+{
+    let (watch, notify) = sys::pipe_cloexec()?;
+    let mut watch = fs::File::from(watch);
+    // The write end is only ever used by the signal handler, so it is never closed.
+    SIGNAL_PIPE.store(notify.into_raw_fd(), Ordering::SeqCst);
+
+    std::thread::Builder::new().name("signal-hook".into()).spawn(move || {
+	use io::Read;
+	let mut sig = [0u8; 1];
+	if let Err(err) = watch.read_exact(&mut sig[..]) {
+	    if_trace!(error!("Failed to read from signal pipe, `--exec-on-signal` is disabled: {err}"));
+	    let _ = err;
+	    return;
+	}
+	let sig = sig[0] as libc::c_int;
+	if_trace!(info!("Caught signal {sig}, running `--exec-on-signal` command {command:?}"));
+	match process::Command::new("/bin/sh")
+	    .arg("-c")
+	    .arg(&command)
+	    .env(ENV_COLLECT_SIGNAL, sig.to_string())
+	    .stdin(process::Stdio::null())
+	    .status() {
+		Ok(_status) => {
+		    if_trace!(debug!("`--exec-on-signal` command exited: {_status}"));
+		},
+		Err(_err) => {
+		    if_trace!(error!("Failed to run `--exec-on-signal` command: {_err}"));
+		},
+	    }
+	unsafe {
+	    libc::signal(sig, libc::SIG_DFL);
+	    libc::raise(sig);
+	}
+    })?;
+
+    for sig in HOOK_SIGNALS {
+	let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+	action.sa_sigaction = on_hook_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+	action.sa_flags = libc::SA_RESTART;
+	if unsafe {
+	    libc::sigemptyset(&mut action.sa_mask);
+	    libc::sigaction(sig, &action, std::ptr::null_mut())
+	} < 0 {
+	    return Err(io::Error::last_os_error());
+	}
+    }
+    Ok(())
+}
+
+/// Write end of the self-pipe that `on_hangup()` wakes the hangup watcher thread through (`-1` until `install_hangup_handler()` is called.)
+static HANGUP_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Set by `on_hangup()` when `SIGHUP` is caught: no more children are spawned or waited on after it is.
+static HANGUP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_hangup(_: libc::c_int)
+{
+    HANGUP.store(true, Ordering::SeqCst);
+    let fd = HANGUP_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+	unsafe {
+	    libc::write(fd, &0u8 as *const u8 as *const libc::c_void, 1);
+	}
+    }
+}
+
+/// Has `SIGHUP` been caught by the handler installed by `install_hangup_handler()`?
+#[inline(always)] 
+pub fn hung_up() -> bool
+{
+    HANGUP.load(Ordering::SeqCst)
+}
+
+/// Install a handler for `SIGHUP` that makes `collect` stop waiting on its `-exec/{}` children and exit with `0`.
+///
+/// Children that are still running are not killed (whether they survive the hangup is up to them.) A child is waited on by blocking in `wait()`, so the exit happens from a watcher thread woken by the handler through a self-pipe (like `install_signal_hook()`); and no more children are spawned once `hung_up()` is set, in case the main thread gets to the next one first.
+#[cfg_attr(feature="logging", instrument(err))]
+pub fn install_hangup_handler() -> io::Result<()>
+{
+    let (watch, notify) = sys::pipe_cloexec()?;
+    let mut watch = fs::File::from(watch);
+    // The write end is only ever used by the signal handler, so it is never closed.
+    HANGUP_PIPE.store(notify.into_raw_fd(), Ordering::SeqCst);
+
+    std::thread::Builder::new().name("hangup".into()).spawn(move || {
+	use io::Read;
+	let mut byte = [0u8; 1];
+	if let Err(err) = watch.read_exact(&mut byte[..]) {
+	    if_trace!(error!("Failed to read from hangup pipe, `SIGHUP` will not exit: {err}"));
+	    let _ = err;
+	    return;
+	}
+	if_trace!(info!("Caught SIGHUP, no longer waiting on children"));
+	std::process::exit(0);
+    })?;
+
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = on_hangup as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESTART;
+    if unsafe {
+	libc::sigemptyset(&mut action.sa_mask);
+	libc::sigaction(libc::SIGHUP, &action, std::ptr::null_mut())
+    } < 0 {
+	return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// If a signal has been caught by the handlers installed by `install_signal_hook()`, wait for its watcher thread to run the command and terminate the process.
+///
+/// This is called before exiting, so that `collect` does not exit normally (and kill the hook) when it finishes its work while the hook is still running. If no signal has been caught, this returns immediately.
+#[inline] 
+pub fn wait_for_signal_hook()
+{
+    if SIGNAL_CAUGHT.load(Ordering::SeqCst) != 0 {
+	if_trace!(debug!("Signal caught, waiting for `--exec-on-signal` command to complete"));
+	loop {
+	    // The watcher thread re-raises the signal, terminating the whole process.
+	    std::thread::park();
+	}
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    fn lookup(name: &OsStr) -> Option<OsString>
+    {
+	(name == ENV_COLLECT_BYTES).then(|| OsString::from("1024"))
+    }
+    
+    #[test]
+    fn expand_env_collect_bytes() -> io::Result<()>
+    {
+	use args::EnvExpansion::*;
+	assert_eq!(expand_env(OsStr::new("--size=${COLLECT_BYTES}"), Strict, lookup)?, "--size=1024");
+	assert_eq!(expand_env(OsStr::new("$COLLECT_BYTES.bin"), Strict, lookup)?, "1024.bin");
+	Ok(())
+    }
+
+    #[test]
+    fn expand_env_literal() -> io::Result<()>
+    {
+	use args::EnvExpansion::*;
+	assert_eq!(expand_env(OsStr::new("cost: $5"), Strict, lookup)?, "cost: $5");
+	assert_eq!(expand_env(OsStr::new("trailing $"), Strict, lookup)?, "trailing $");
+	assert_eq!(expand_env(OsStr::new("\\$COLLECT_BYTES"), Strict, lookup)?, "$COLLECT_BYTES");
+	assert_eq!(expand_env(OsStr::new("${UNDEFINED} $UNDEFINED"), Lenient, lookup)?, "${UNDEFINED} $UNDEFINED");
+	assert!(expand_env(OsStr::new("$UNDEFINED"), Strict, lookup).is_err(), "Undefined variable accepted in strict mode");
+	Ok(())
+    }
+
+    #[test]
+    fn chain_through_memfd() -> eyre::Result<()>
+    {
+	use std::io::{Read, Write};
+	let opt = args::parse_from(["-exec", "tr", "a-z", "A-Z", ";", "-exec", "rev"])?;
+	let mut exec = opt.clone().take_exec().into_iter();
+	let input = {
+	    let mut file = fs::File::from(memfile::RawFile::open_mem(None, 0)?);
+	    file.write_all(b"hello world\n")?;
+	    file
+	};
+
+	// The intermediate result is held in a memfd.
+	let (code, stage, truncated) = run_captured(&input, exec.next().unwrap(), &opt, &mut PidFile::default())?;
+	assert_eq!((code, truncated), (Some(0), false));
+	let link = fs::read_link(proc_file(&stage, args::FdPathStyle::Proc))?;
+	assert!(link.as_os_str().as_bytes().starts_with(b"/memfd:"), "Intermediate output is not a memfd: {link:?}");
+
+	let (codes, output) = spawn_chain_sync(&input, opt)?;
+	assert_eq!(&codes[..], &[Some(0), Some(0)]);
+	let mut output_data = String::new();
+	output.expect("No output from the chain").read_to_string(&mut output_data)?;
+	assert_eq!(output_data, "DLROW OLLEH\n");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_max_output() -> eyre::Result<()>
+    {
+	use std::io::Read;
+	let input = memfile::RawFile::open_mem(None, 0)?;
+	let opt = args::parse_from(["--exec-max-output", "1K", "-exec", "yes"])?;
+	let exec = opt.clone().take_exec().pop().unwrap();
+	// `yes` never stops writing on its own: this only returns if it was killed (and reaped.)
+	let (code, mut stage, truncated) = run_captured(&input, exec, &opt, &mut PidFile::default())?;
+	assert!(truncated, "Output over the limit was not reported as truncated");
+	assert_eq!(code, None, "Child was not killed");
+	let mut output = Vec::new();
+	stage.read_to_end(&mut output)?;
+	assert_eq!(output.len(), 1024, "Output was not truncated to the limit");
+	assert!(output.chunks(2).all(|line| line == b"y\n"), "Captured output is not the child's");
+
+	// Output that fits exactly is not truncated.
+	let opt = args::parse_from(["--exec-max-output", "4", "-exec", "printf", "abcd"])?;
+	let exec = opt.clone().take_exec().pop().unwrap();
+	let (code, _, truncated) = run_captured(&input, exec, &opt, &mut PidFile::default())?;
+	assert_eq!((code, truncated), (Some(0), false));
+	Ok(())
+    }
+
+    #[test]
+    fn exec_input_seal_level() -> eyre::Result<()>
+    {
+	use args::SealLevel;
+	for (level, expected) in [
+	    ("write", SealLevel { write: true, ..SealLevel::NONE }),
+	    ("grow", SealLevel { grow: true, ..SealLevel::NONE }),
+	    ("shrink", SealLevel { shrink: true, ..SealLevel::NONE }),
+	    ("grow,shrink", SealLevel { grow: true, shrink: true, ..SealLevel::NONE }),
+	    ("all", SealLevel::ALL),
+	    ("none", SealLevel::NONE),
+	] {
+	    let mut opt = args::parse_from(["--exec-input-seal-level", level, "-exec", "true"])?;
+	    let input = memfile::RawFile::open_mem(None, 0)?;
+	    let exec = opt.take_exec().pop().unwrap();
+	    run_single(&input, exec, &opt)?.0.wait()?;
+	    
+	    let seals = input.get_seals()?;
+	    assert_eq!(SealLevel { shrink: seals.has_shrink(), grow: seals.has_grow(), write: seals.has_write() }, expected, "Wrong seals for level {level:?}");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn seal() -> eyre::Result<()>
+    {
+	use std::io::Write;
+	// The child tries to overwrite its `stdin`, which it must not be able to do.
+	let mut opt = args::parse_from(["--seal", "-exec", "sh", "-c", "printf 'XX' 2>/dev/null 1<>/dev/stdin && exit 1; exit 0"])?;
+	let mut input = memfile::RawFile::open_mem(None, 0)?;
+	input.write_all(b"hello")?;
+	let exec = opt.take_exec().pop().unwrap();
+	let status = run_single(&input, exec, &opt)?.0.wait()?;
+	assert!(status.success(), "Child could write to the sealed buffer");
+
+	let seals = input.get_seals()?;
+	assert!(seals.has_write() && seals.has_grow() && seals.has_shrink(), "Not all seals were applied: {seals:?}");
+	assert_eq!(fs::read(format!("/proc/self/fd/{}", input.as_raw_fd()))?, b"hello", "Sealed buffer was modified");
+	Ok(())
+    }
+
+    #[test]
+    fn size_placeholder() -> eyre::Result<()>
+    {
+	use std::io::Write;
+	let mut opt = args::parse_from(["-exec{}", "sh", "-c", r#"[ "$1" = 11 ] && [ "$2" = "--expect-bytes=11" ] && [ "$(cat "$3")" = "hello world" ]"#, "sh", "{size}", "--expect-bytes={size}", "{}"])?;
+	let mut input = memfile::RawFile::open_mem(None, 0)?;
+	input.write_all(b"hello world")?;
+	let exec = opt.take_exec().pop().unwrap();
+	assert!(run_single(&input, exec, &opt)?.0.wait()?.success(), "`{{size}}` was not replaced with the number of bytes collected");
+
+	// Without `COLLECT_BYTES`, it is left alone.
+	let opt = args::parse_from(["-exec{}", "true", "{size}", "{}"])?;
+	let exec = opt.clone().take_exec().pop().unwrap();
+	let (_, args, _) = prepare(exec, &opt, Vec::new(), OsStr::new("/path"))?;
+	assert_eq!(args, Err(vec!["{size}".into(), "/path".into()]));
+	Ok(())
+    }
+
+    #[test]
+    fn fd_path_style() -> eyre::Result<()>
+    {
+	use std::io::Write;
+	let mut input = memfile::RawFile::open_mem(None, 0)?;
+	input.write_all(b"hello world")?;
+	for (style, prefix) in [("proc", "/proc/"), ("dev", "/dev/fd/")] {
+	    if !Path::new(prefix).is_dir() {
+		eprintln!("{prefix} does not exist, skipping `--fd-style {style}`");
+		continue;
+	    }
+	    let mut opt = args::parse_from(["--fd-style", style, "-exec{}", "sh", "-c", r#"case "$1" in "$2"*) ;; *) exit 2;; esac; [ "$(cat "$1")" = "hello world" ]"#, "sh", "{}", prefix])?;
+	    let exec = opt.take_exec().pop().unwrap();
+	    let status = run_single(&input, exec, &opt)?.0.wait()?;
+	    assert!(status.success(), "`--fd-style {style}`: `{{}}` did not refer to the buffer ({status})");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn aggregate_exit_code() -> eyre::Result<()>
+    {
+	let mut opt = args::parse_from(["-exec", "/nonexistent/command", ";", "-exec", "sh", "-c", "kill -TERM $$", ";", "-exec", "sh", "-c", "exit 5"])?;
+	let input = memfile::RawFile::open_mem(None, 0)?;
+	let errors = wait_all(&input, opt.clone()).expect_err("Failing children were not reported");
+	assert_eq!(errors.0.len(), 3, "Not every failure was reported: {errors}");
+	assert_eq!(errors.exit_code(), Some(128 + libc::SIGTERM), "Exit code was not taken from the first child that ran");
+
+	opt = args::parse_from(["-exec", "/nonexistent/command"])?;
+	assert_eq!(wait_all(&input, opt).expect_err("Failing child was not reported").exit_code(), None, "Child that was never spawned has an exit code");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_nice() -> eyre::Result<()>
+    {
+	use std::io::Read;
+	// The 19th field of `/proc/self/stat` is the nice value (`sh`'s name has no spaces, so it can be split on them.)
+	let mut opt = args::parse_from(["--exec-nice", "19", "-exec", "sh", "-c", "cut -d ' ' -f 19 /proc/$$/stat"])?;
+	let input = memfile::RawFile::open_mem(None, 0)?;
+	let (mut child, _) = run_single_with(&input, opt.take_exec().pop().unwrap(), &opt, process::Stdio::piped())?;
+	let mut output = String::new();
+	child.stdout.take().unwrap().read_to_string(&mut output)?;
+	assert!(child.wait()?.success(), "Child failed");
+	assert_eq!(output.trim(), "19", "Child was not run with the nice value");
+	Ok(())
+    }
+
+    #[test]
+    fn working_dir_fd() -> eyre::Result<()>
+    {
+	use std::io::Read;
+	let fd = open_working_dir()?;
+	// The child changes directory first, and must still find `collect`'s working directory through the fd.
+	let mut opt = args::parse_from(["--exec-working-fd", "-exec", "sh", "-c", "cd / && readlink \"/proc/self/fd/$COLLECT_CWD_FD\""])?;
+	let input = memfile::RawFile::open_mem(None, 0)?;
+	let (mut child, _) = run_single_with(&input, opt.take_exec().pop().unwrap(), &opt, process::Stdio::piped())?;
+	let mut output = String::new();
+	child.stdout.take().unwrap().read_to_string(&mut output)?;
+	assert!(child.wait()?.success(), "Child failed");
+	assert_eq!(Path::new(output.trim()), std::env::current_dir()?, "Child did not resolve the working directory through fd {fd}");
+	Ok(())
+    }
+
+    #[test]
+    fn no_leaked_fds() -> eyre::Result<()>
+    {
+	use std::io::Read;
+	// A high fd without `O_CLOEXEC`, which would be inherited by default.
+	let leaked = match unsafe { libc::fcntl(0, libc::F_DUPFD, 100) } {
+	    -1 => return Err(io::Error::last_os_error().into()),
+	    fd => unsafe { fs::File::from_raw_fd(fd) },
+	};
+	let mut opt = args::parse_from(["-exec", "ls", "/proc/self/fd"])?;
+	let input = memfile::RawFile::open_mem(None, 0)?;
+	let (mut child, _) = run_single_with(&input, opt.take_exec().pop().unwrap(), &opt, process::Stdio::piped())?;
+	let mut output = String::new();
+	child.stdout.take().unwrap().read_to_string(&mut output)?;
+	assert!(child.wait()?.success(), "Child failed");
+	drop(leaked);
+
+	// The standard streams, and the lowest free fd (3) that `ls` opens `/proc/self/fd` with.
+	let fds: Vec<RawFd> = output.split_whitespace().map(|fd| fd.parse().expect("invalid fd")).collect();
+	assert!(fds.iter().all(|fd| (0..=3).contains(fd)), "Child inherited unexpected fds: {fds:?}");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_pid_file() -> eyre::Result<()>
+    {
+	let path = std::env::temp_dir().join(format!("collect-pid-file-test-{}", process::id()));
+	let opt = args::parse_from([OsStr::new("--exec-pid-file"), path.as_os_str(), OsStr::new("-exec"), OsStr::new("true"), OsStr::new(";"), OsStr::new("-exec"), OsStr::new("true")])?;
+	let input = memfile::RawFile::open_mem(None, 0)?;
+	let result = (|| -> eyre::Result<_> {
+	    let mut pids = Vec::new();
+	    for spawned in spawn_from(&input, opt) {
+		let (mut child, _) = spawned?;
+		pids.push(child.id().to_string());
+		child.wait()?;
+	    }
+	    Ok((pids, fs::read_to_string(&path)?))
+	})();
+	let _ = fs::remove_file(&path);
+	let (pids, written) = result?;
+	assert_eq!(pids.len(), 2);
+	assert_eq!(written.lines().collect::<Vec<_>>(), pids, "PID file does not contain exactly the PIDs of both children");
+	Ok(())
+    }
+
+    #[test]
+    fn command_from_fd_runs() -> eyre::Result<()>
+    {
+	use std::io::{Read, Write};
+	let pipe = || -> io::Result<(fs::File, RawFd)> {
+	    let mut fds = [0; 2];
+	    if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+		return Err(io::Error::last_os_error());
+	    }
+	    Ok((unsafe { fs::File::from_raw_fd(fds[1]) }, fds[0]))
+	};
+	
+	let (mut write, read) = pipe()?;
+	write.write_all(b"printf\0%s\0hello\0")?;
+	drop(write);
+	let exec = command_from_fd(read)?;
+	
+	let opt = args::Options::default();
+	let input = memfile::RawFile::open_mem(None, 0)?;
+	let (mut child, _) = run_single_with(&input, exec, &opt, process::Stdio::piped())?;
+	let mut output = String::new();
+	child.stdout.take().unwrap().read_to_string(&mut output)?;
+	assert!(child.wait()?.success(), "Command read from fd failed");
+	assert_eq!(output, "hello");
+
+	let (write, read) = pipe()?;
+	drop(write);
+	assert_eq!(command_from_fd(read).map_err(|e| e.kind()).unwrap_err(), io::ErrorKind::InvalidInput, "Empty command was accepted");
+	Ok(())
+    }
+
+    #[test]
+    fn exec_env_positional() -> eyre::Result<()>
+    {
+	use std::io::{Read, Write};
+	let run = |args: &[&str]| -> eyre::Result<String> {
+	    let mut opt = args::parse_from(args.iter().copied())?;
+	    let mut input = fs::File::from(memfile::RawFile::open_mem(None, 0)?);
+	    input.write_all(b"hello")?;
+	    // The buffer's path is only valid while its fd (returned here) is open.
+	    let (mut child, _file) = run_single_with(&input, opt.take_exec().pop().unwrap(), &opt, process::Stdio::piped())?;
+	    let mut output = String::new();
+	    child.stdout.take().unwrap().read_to_string(&mut output)?;
+	    assert!(child.wait()?.success(), "Child failed");
+	    Ok(output)
+	};
+	assert_eq!(run(&["--exec-env", "INPUT={}", "-exec{}", "sh", "-c", "cat \"$INPUT\""])?, "hello");
+	assert_eq!(run(&["--exec-env=INPUT={}", "-exec", "sh", "-c", "cat \"$INPUT\""])?, "hello");
+	
+	let both = run(&["--exec-env", "BOTH={}:{}", "--exec-env", "PLAIN=value", "-exec", "sh", "-c", "printf '%s %s' \"$BOTH\" \"$PLAIN\""])?;
+	let (both, plain) = both.split_once(' ').unwrap();
+	let (a, b) = both.split_once(':').unwrap();
+	assert!(a.starts_with("/proc/") && a == b, "Not every `{{}}` was substituted: {both:?}");
+	assert_eq!(plain, "value");
+	Ok(())
+    }
 }
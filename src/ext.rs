@@ -13,6 +13,7 @@ use std::{
 /// Essentially equivelant bound as `eyre::StdError` (private trait)
 ///
 /// Useful for when using traits that convert a generic type into an `eyre::Report`.
+#[allow(dead_code)]
 pub trait EyreError: std::error::Error + Send + Sync + 'static{}
 impl<T: ?Sized> EyreError for T
 where T: std::error::Error + Send + Sync + 'static{}
@@ -36,6 +37,7 @@ impl<I, F> Joiner<I, F>
 	}
     }
 }
+#[allow(dead_code)]
 type JoinerExt = Joiner<std::convert::Infallible, std::convert::Infallible>;
 
 impl<I, F> Iterator for Joiner<I, F>
@@ -95,7 +97,9 @@ where Joiner<I,F>: Iterator,
 
 pub trait IterJoinExt<T>: Sized
 {    
+    #[allow(dead_code)]
     fn join_by<F: FnMut() -> T>(self, joiner: F) -> Joiner<Self, F>;
+    #[allow(dead_code)]
     fn join_by_default(self) -> Joiner<Self, fn () -> T>
     where T: Default;
     fn join_by_clone(self, value: T) -> Joiner<Self, CloneJoiner<T>>
@@ -123,6 +127,7 @@ where I: Iterator<Item = T>
     }
 }
 
+#[allow(dead_code)]
 pub trait IntoEyre<T>
 {
     fn into_eyre(self) -> eyre::Result<T>;
@@ -139,12 +144,14 @@ impl<T, E: EyreError> IntoEyre<T> for Result<T, E>
     }
 }
 
+#[allow(dead_code)]
 pub trait FlattenReports<T>
 {
     /// Flatten a `eyre::Result<eyre::Result<T>>` into an `eyre::Result<T>`
     fn flatten(self) -> eyre::Result<T>;
 }
 
+#[allow(dead_code)]
 pub trait FlattenEyreResult<T, E>
 where E: EyreError
 {
@@ -152,6 +159,7 @@ where E: EyreError
     fn flatten(self) -> eyre::Result<T>;
 }
 
+#[allow(dead_code)]
 pub trait FlattenResults<T, E>
 {
     /// Flatten a `Result<Result<T, IE>, E>` into a `Result<T, E>`.
@@ -224,6 +232,7 @@ impl<T, E: EyreError> FlattenEyreResult<T, E> for Result<Option<T>, E>
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum RunOnceInternal<F>
 {
@@ -242,6 +251,7 @@ impl<F: Clone> Clone for RunOnceInternal<F>
     }
 }
 
+#[allow(dead_code)]
 impl<F> RunOnceInternal<F>
 {
     /// Take `F` now, unless it doesn't need to be dropped.
@@ -290,12 +300,14 @@ impl<F> ops::Drop for RunOnceInternal<F>
 }
 
 /// Holds a 0 argument closure that will only be ran *once*.
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct RunOnce<F, T>(PhantomData<fn () -> T>, RunOnceInternal<F>);
 
 unsafe impl<T, F> Send for RunOnce<F, T>
 where F: FnOnce() -> T + Send {}
 
+#[allow(dead_code)]
 impl<F, T> RunOnce<F, T>
 where F: FnOnce() -> T
 {
@@ -341,15 +353,12 @@ where F: FnOnce() -> T
     #[inline] 
     pub fn is_runnable(&self) -> bool
     {
-	if let RunOnceInternal::Dead = &self.1 {
-	    false
-	} else {
-	    true
-	}
+	!matches!(&self.1, RunOnceInternal::Dead)
     }
 }
 
 #[inline(always)] 
+#[cfg_attr(not(any(feature="memfile", feature="exec")), allow(dead_code))]
 pub(crate) fn map_bool<T>(ok: bool, value: T) -> T
 where T: Default
 {
@@ -359,15 +368,96 @@ where T: Default
 	T::default()
     }
 }
+/// The seals applied to a file, as returned by `SealExt::get_seals()`.
+#[cfg(any(feature="memfile", feature="exec"))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct SealFlags(libc::c_int);
+
+#[cfg(any(feature="memfile", feature="exec"))]
+impl std::fmt::Debug for SealFlags
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+	f.debug_struct("SealFlags")
+	    .field("raw", &format_args!("{:#x}", self.into_raw()))
+	    .field("write", &self.has_write())
+	    .field("grow", &self.has_grow())
+	    .field("shrink", &self.has_shrink())
+	    .field("seal", &self.has_seal())
+	    .finish()
+    }
+}
+
+#[cfg(any(feature="memfile", feature="exec"))]
+impl SealFlags
+{
+    #[inline(always)] 
+    pub const fn from_raw(raw: libc::c_int) -> Self
+    {
+	Self(raw)
+    }
+    #[inline(always)] 
+    pub const fn into_raw(self) -> libc::c_int
+    {
+	self.0
+    }
+
+    /// `F_SEAL_WRITE`
+    #[inline(always)] 
+    pub const fn has_write(&self) -> bool
+    {
+	self.0 & libc::F_SEAL_WRITE != 0
+    }
+    /// `F_SEAL_GROW`
+    #[inline(always)] 
+    pub const fn has_grow(&self) -> bool
+    {
+	self.0 & libc::F_SEAL_GROW != 0
+    }
+    /// `F_SEAL_SHRINK`
+    #[inline(always)] 
+    pub const fn has_shrink(&self) -> bool
+    {
+	self.0 & libc::F_SEAL_SHRINK != 0
+    }
+    /// `F_SEAL_SEAL`: no more seals can be added.
+    #[inline(always)] 
+    pub const fn has_seal(&self) -> bool
+    {
+	self.0 & libc::F_SEAL_SEAL != 0
+    }
+}
+
 pub trait SealExt
 {
     fn try_seal(&self, shrink: bool, grow: bool, write: bool) -> io::Result<()>;
 
+    /// Get the seals currently applied to this file.
+    #[cfg(any(feature="memfile", feature="exec"))]
+    fn get_seals(&self) -> io::Result<SealFlags>;
+
+    /// Apply seals with `try_seal()`, then read them back with `get_seals()` to check that all of the requested seals are present.
+    ///
+    /// # Errors
+    /// If applying or reading back the seals fails, that error is returned. If they were applied but any of the requested seals are not present afterwards, the error kind is `Unsupported`.
+    #[cfg(any(feature="memfile", feature="exec"))]
+    fn seal_verified(&self, shrink: bool, grow: bool, write: bool) -> io::Result<SealFlags>
+    {
+	self.try_seal(shrink, grow, write)?;
+	let seals = self.get_seals()?;
+	if (shrink && !seals.has_shrink()) || (grow && !seals.has_grow()) || (write && !seals.has_write()) {
+	    return Err(io::Error::new(io::ErrorKind::Unsupported, format!("requested seals (shrink: {shrink}, grow: {grow}, write: {write}) were not all applied, the file has {seals:?}")));
+	}
+	Ok(seals)
+    }
+
     #[inline] 
+    #[allow(dead_code)]
     fn sealed(self, shrink: bool, grow: bool, write: bool) -> Self
     where Self: Sized {
 	if let Err(e) = self.try_seal(shrink, grow, write) {
-	    panic!("Failed to apply seals: {}", io::Error::last_os_error())
+	    panic!("Failed to apply seals: {}", e)
 	}
 	self
     }
@@ -415,5 +505,46 @@ const _: () = {
 		Ok(())
 	    }
 	}
+
+	#[cfg_attr(feature="logging", instrument(skip(self), err))] 
+	fn get_seals(&self) -> io::Result<SealFlags> {
+	    match unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GET_SEALS) } {
+		-1 => Err(io::Error::last_os_error()),
+		seals => Ok(SealFlags::from_raw(seals)),
+	    }
+	}
     }
 };
+
+#[cfg(all(test, feature="memfile"))]
+mod tests
+{
+    use super::*;
+    #[test]
+    fn get_seals() -> eyre::Result<()>
+    {
+	let file = memfile::RawFile::open_mem(None, 0)?;
+	assert_eq!(file.get_seals()?, SealFlags::default(), "New memfile has seals");
+	
+	file.try_seal(false, false, true)?;
+	let seals = file.get_seals()?;
+	assert!(seals.has_write(), "Write seal was not applied");
+	assert!(!seals.has_grow() && !seals.has_shrink() && !seals.has_seal(), "Unrequested seals were applied: {seals:?}");
+	Ok(())
+    }
+
+    #[test]
+    fn seal_verified() -> eyre::Result<()>
+    {
+	let file = memfile::RawFile::open_mem(None, 0)?;
+	let seals = file.seal_verified(true, true, false)?;
+	assert!(seals.has_shrink() && seals.has_grow(), "Verified seals were not applied: {seals:?}");
+
+	// A regular file cannot be sealed at all, which must be an error and not a panic.
+	let path = std::env::temp_dir().join(format!("collect-seal-test-{}", std::process::id()));
+	let file = std::fs::File::options().read(true).write(true).create(true).truncate(true).open(&path)?;
+	let _ = std::fs::remove_file(&path);
+	assert!(file.seal_verified(false, false, true).is_err(), "Sealing a regular file was verified");
+	Ok(())
+    }
+}
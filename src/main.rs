@@ -1,5 +1,12 @@
 //#![feature(const_trait_impl)]
 
+// `memfd_create()`, `/proc/self/fd`, `fallocate()`, file sealing and `MAP_HUGE_*` are all Linux-specific.
+// Only the `buffered` strategy (without `-exec`) has a portable fallback, so only it is allowed on other Unix-like targets.
+#[cfg(not(unix))]
+compile_error!("collect requires a Unix-like target");
+#[cfg(all(unix, not(target_os="linux"), any(feature="memfile", feature="exec")))]
+compile_error!("collect requires Linux (memfd_create, /proc, splice) for the `memfile` and `exec` features: build with `--no-default-features --features mode-buffered` on other Unix-like targets");
+
 #[macro_use] extern crate cfg_if;
 #[cfg(feature="logging")] 
 #[macro_use] extern crate tracing;
@@ -68,6 +75,7 @@ use color_eyre::{
 };
 
 /// Get an `&'static str` of the current function name.
+#[cfg_attr(not(feature="memfile"), allow(unused_macros))]
 macro_rules! function {
     () => {{
         fn f() {}
@@ -82,31 +90,31 @@ macro_rules! function {
 mod ext; use ext::*;
 mod errors;
 mod sys;
-use sys::{
-    try_get_size,
-    tell_file,
-};
+use sys::try_get_size;
+#[cfg(feature="memfile")]
+use sys::tell_file;
 
-#[cfg(feature="exec")] 
+#[cfg(all(feature="exec", target_os="linux"))] 
 mod exec;
 
 mod buffers;
 use buffers::prelude::*;
 
-#[cfg(feature="memfile")] mod memfile;
+mod completions;
 
-#[cfg(feature="bytes")]
-use bytes::{
-    Buf,
-    BufMut,
-};
+mod progress;
+
+#[cfg(feature="digest")]
+mod digest;
+
+#[cfg(all(feature="memfile", target_os="linux"))] mod memfile;
 
 /* TODO: Allow `collect -exec <command>` /proc/self/fds/<memfd OR STDOUT_FILENO>, `collect -exec{} <command> {/proc/self/fds/<memfd OR STDOUT_FILENO>} <other args>`
 struct Options {
 /// If the arguments vector contains `None`, that `None` shall be replaced with the string referring to: If in memfd mode: The `memfd_create()` buffer fd, set to RW, truncated to size, seeked to 0. In this mode, the file will remain open when `ecec` is not `None`, and will instead be returned below, as the mode methods will all be modified to return `Option<Box<dyn ModeReturn + 'static>>::Some(<private struct: impl ModeReturn>)`, which will contain the memfd object so it is dropped *after* the child process has exited. If the mode is *not* memfd, then `STDOUT_FILENO` itself will be used; also set to RW, truncated correctly, and seeked to 0. The return code of this process shall be the return code of the child process once it has terminated.
 /// Execution of commands (if passed) **always** happens *after* the copy to `stdout`, but *before* the **close** of `stdout`. If the copy to `stdout` fails, the exec will not be executed regardless of if the mode required is actually using `stdout`.
 /// The process shall always wait for the child to terminate before exiting. If the child daemon forks, that fork is not followed, and the process exists anyway.
-/// Ideally, A `SIGHUP` handler should be registered, which tells the parent to stop waiting on the child and exit now. TODO: The behaviour of the child is unspecified if this happens. It may be killed, or re-attached to `init`. But the return code of the parent should always be `0` in this case. 
+/// A `SIGHUP` handler is registered (see `exec::install_hangup_handler()`), which tells the parent to stop waiting on the child and exit now. The behaviour of the child is unspecified if this happens. It may be killed, or re-attached to `init`. But the return code of the parent is always `0` in this case. 
 exec: Option<(OSString, Vec<Option<OSString>>)> 
 }
 trait ModeReturn: Send {
@@ -158,6 +166,23 @@ impl ModeReturn for std::fs::File {
     }
 }
 
+/// The filter of the tracing subscriber installed by `init()`, which can be replaced once the arguments are parsed (see `silence_tracing()`.)
+#[cfg(feature="logging")]
+static TRACING_FILTER: std::sync::OnceLock<tracing_subscriber::reload::Handle<tracing_subscriber::EnvFilter, tracing_subscriber::Registry>> = std::sync::OnceLock::new();
+
+/// Turn off all logging from now on, whatever `RUST_LOG` asked for (for `--quiet`.)
+///
+/// Tracing is installed (by `init()`) before the arguments are parsed, so its filter is swapped out here instead. Anything logged while parsing them is still filtered by `RUST_LOG`.
+#[cfg(feature="logging")]
+fn silence_tracing()
+{
+    if let Some(handle) = TRACING_FILTER.get() {
+	if let Err(_err) = handle.reload(tracing_subscriber::EnvFilter::new("off")) {
+	    if_trace!(error!("Failed to turn off logging for `--quiet`: {_err}"));
+	}
+    }
+}
+
 fn init() -> eyre::Result<()>
 {
     cfg_if!{ if #[cfg(feature="logging")] {
@@ -167,7 +192,7 @@ fn init() -> eyre::Result<()>
 	    
 	    use tracing_error::ErrorLayer;
 	    use tracing_subscriber::prelude::*;
-	    use tracing_subscriber::{fmt, EnvFilter};
+	    use tracing_subscriber::{fmt, reload, EnvFilter};
 
 	    let fmt_layer = fmt::layer()
 		.with_target(false)
@@ -180,10 +205,13 @@ fn init() -> eyre::Result<()>
 		    "info"
 		}))
 		.unwrap();
+	    // The filter can be replaced after the arguments are parsed (see `silence_tracing()`.)
+	    let (filter_layer, handle) = reload::Layer::new(filter_layer);
+	    let _ = TRACING_FILTER.set(handle);
 
 	    tracing_subscriber::registry()
-		.with(fmt_layer)
 		.with(filter_layer)
+		.with(fmt_layer)
 		.with(ErrorLayer::default())
 		.init();
 	}
@@ -202,7 +230,7 @@ fn init() -> eyre::Result<()>
 fn feature_check() -> eyre::Result<()>
 {
     if cfg!(feature="memfile") && cfg!(feature="mode-buffered") {
-	if_trace!(warn!("This is an incorrectly compiled binary! Compiled with `mode: buffered` and the `memfile` feature; `memfile` stragery will be used and the mode selection will be ignored."));
+	if_trace!(warn!("This is an incorrectly compiled binary! Compiled with `mode: buffered` and the `memfile` feature; the mode selection will be ignored, and the strategy will be chosen at runtime (see `--strategy`)."));
     }
 
     Ok(())
@@ -210,6 +238,7 @@ fn feature_check() -> eyre::Result<()>
 
 #[inline] 
     #[cfg_attr(feature="logging", instrument(skip_all, fields(fd = ?file.as_raw_fd())))]
+#[cfg(any(feature="memfile", feature="exec"))]
 fn try_seal_size<F: AsRawFd + ?Sized>(file: &F) -> eyre::Result<()>
 {
     //if cfg!(feature="exec") {
@@ -227,50 +256,1422 @@ fn try_seal_size<F: AsRawFd + ?Sized>(file: &F) -> eyre::Result<()>
     //}
 }
 
+/// Apply the seals chosen by `--exec-input-seal-level` (or `--seal`) to `file`, if any were.
+///
+/// Seals that were applied but are not present afterwards are an error, unless `--no-memfd-seal-check` was passed (then they are only a warning.) Seals belong to the file itself, so every descriptor of it (including ones `dup()`'d or re-opened later) sees them; and applying them again is harmless.
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, err, fields(fd = ?file.as_raw_fd())))]
+#[cfg(any(feature="memfile", feature="exec"))]
+fn apply_seal_level<F: AsRawFd + ?Sized>(file: &F, opt: &args::Options) -> io::Result<()>
+{
+    if let Some(level) = opt.exec_input_seal_level() {
+	match file.seal_verified(level.shrink, level.grow, level.write) {
+	    // The seals were applied, but some of them did not take effect.
+	    Err(err) if err.kind() == io::ErrorKind::Unsupported && !opt.memfd_seal_check() => {
+		if_trace!(warn!("--exec-input-seal-level {level:?}: {err}"));
+	    },
+	    result => { result?; },
+	}
+    }
+    Ok(())
+}
+
+/// Inputs of a known size up to this many bytes are collected into an allocated buffer by `--strategy=auto`.
+#[cfg(feature="memfile")]
+const AUTO_SMALL_INPUT: usize = 1024 * 1024;
+
+/// Choose a strategy for `--strategy=auto`.
+///
+/// * `size` - The size of the input, if it is known.
+/// * `kind` - The kind of file the input is.
+/// * `memory` - The amount of memory available to us, if it is known.
+/// * `exec` - Whether `-exec/{}` children will be given the collected buffer.
+///
+/// # Returns
+/// The strategy to use, and why it was chosen.
+#[cfg(feature="memfile")]
+fn choose_strategy(size: Option<NonZeroUsize>, kind: Option<sys::FdKind>, memory: Option<u64>, exec: bool) -> (args::Strategy, &'static str)
+{
+    use args::Strategy::*;
+    let size = size.filter(|_| matches!(kind, Some(sys::FdKind::Regular | sys::FdKind::Block)));
+    match size {
+	_ if exec => (Memfd, "`-exec/{}` children need the buffer as a file"),
+	None => (Memfd, "the input's size is unknown, an in-memory file can grow without reallocating"),
+	Some(size) if size.get() <= AUTO_SMALL_INPUT => (Buffered, "the input is small"),
+	Some(size) if memory.map(|memory| size.get() as u64 <= memory / 2).unwrap_or(true) => (Memfd, "the input is large, but fits comfortably in memory"),
+	Some(_) => (Buffered, "the input is large, and may not fit in memory (an in-memory file would be refused by `--mem-max`, an allocated buffer can be swapped out)"),
+    }
+}
+
+/// The strategy to collect the input with: `--strategy`, with `auto` resolved for the input (see `choose_strategy()`.)
+///
+/// The input is only `fstat()`ed to resolve it, nothing is read from it.
+fn resolve_strategy(opt: &args::Options) -> args::Strategy
+{
+    match opt.strategy() {
+	#[cfg(feature="memfile")]
+	args::Strategy::Auto if opt.hugetlb().is_some() => {
+	    if_trace!(info!("--strategy=auto: chose Memfd, because `--hugetlb` was passed"));
+	    args::Strategy::Memfd
+	},
+	#[cfg(feature="memfile")]
+	args::Strategy::Auto => {
+	    let stdin = io::stdin();
+	    // The input file is opened again by the strategy; if it cannot be opened, that is reported there.
+	    let file = match opt.input() {
+		args::InputSource::Path(path) => std::fs::File::open(path).ok(),
+		args::InputSource::Stdin => None,
+	    };
+	    let source: &dyn AsRawFd = match &file {
+		Some(file) => file,
+		None => &stdin,
+	    };
+	    let (strategy, _reason) = choose_strategy(work::source_size(opt, source), sys::classify_fd(source).ok(),
+						      sys::available_system_memory().or_else(sys::total_system_memory),
+						      opt.opt_exec().len() > 0);
+	    if_trace!(info!("--strategy=auto: chose {strategy:?}, because {_reason}"));
+	    strategy
+	},
+	strategy => strategy,
+    }
+}
+
+/// Write what `collect` would do with `opt` to `out`, for `--dry-run`: the input, the strategy it would be collected with, the output, and each `-exec/{}` command; one per line.
+///
+/// The strategy is resolved (see `resolve_strategy()`), which `fstat()`s the input, but nothing is read from it.
+fn write_dry_run<W: ?Sized + io::Write>(out: &mut W, opt: &args::Options) -> io::Result<()>
+{
+    match opt.input() {
+	args::InputSource::Stdin => writeln!(out, "input: stdin")?,
+	args::InputSource::Path(path) => writeln!(out, "input: {}", path.display())?,
+    }
+    // With `--exec-stdin-from=input`, nothing is collected or written.
+    let collected = opt.exec_stdin_from() != args::ExecStdinFrom::Input;
+    match opt.tail() {
+	_ if !collected => writeln!(out, "strategy: none (the input is given to the child directly)")?,
+	Some(tail) => writeln!(out, "strategy: tail (the last {tail} bytes)")?,
+	None => writeln!(out, "strategy: {}", resolve_strategy(opt))?,
+    }
+    if collected {
+	writeln!(out, "output: {}{}", opt.output(), if opt.append() { " (append)" } else { "" })?;
+    }
+    for exec in opt.opt_exec() {
+	writeln!(out, "exec: {} {exec}", if exec.is_positional() { "-exec{}" } else { "-exec" })?;
+    }
+    Ok(())
+}
+
 mod work {
     use super::*;
+    use std::{
+	fmt,
+	time::{
+	    Duration,
+	    Instant,
+	},
+    };
+
+    /// The size of the input `source` (or `--buffer-size`, if passed), if it can be determined, capped at `--head`/`--limit` (for `--strategy=auto`.)
+    #[inline] 
+    #[cfg(feature="memfile")]
+    pub(super) fn source_size<F: ?Sized + AsRawFd>(opt: &args::Options, source: &F) -> Option<NonZeroUsize>
+    {
+	let size = opt.buffer_size().or_else(|| try_get_size(source))?;
+	match opt.read_limit() {
+	    Some(head) => NonZeroUsize::new(std::cmp::min(size.get() as u64, head) as usize),
+	    None => Some(size),
+	}
+    }
+
+    /// An input that the strategies collect from.
+    ///
+    /// This is `stdin` (see `StdinInput`) or the input file when running (see `open_input()`), but anything readable can be collected from, e.g. an `io::Cursor` or a pipe in tests.
+    pub(super) trait Input: io::Read
+    {
+	/// The size of the input, if it can be determined.
+	#[inline] 
+	fn size(&self) -> Option<NonZeroUsize>
+	{
+	    None
+	}
+
+	/// The file descriptor the input is read from, if it has one (for `splice()`.)
+	#[cfg(feature="memfile")]
+	#[inline] 
+	fn raw_fd(&self) -> Option<RawFd>
+	{
+	    None
+	}
+
+	/// Called once the input has been read (up to `--head`), before what was collected is written.
+	#[inline] 
+	fn collected(&mut self, _opt: &args::Options) -> io::Result<()>
+	{
+	    Ok(())
+	}
+    }
+
+    impl<T: AsRef<[u8]>> Input for io::Cursor<T>
+    {
+	#[inline] 
+	fn size(&self) -> Option<NonZeroUsize>
+	{
+	    NonZeroUsize::new(self.get_ref().as_ref().len().saturating_sub(self.position() as usize))
+	}
+    }
+
+    impl Input for std::fs::File
+    {
+	#[inline] 
+	fn size(&self) -> Option<NonZeroUsize>
+	{
+	    try_get_size(self)
+	}
+
+	#[cfg(feature="memfile")]
+	#[inline] 
+	fn raw_fd(&self) -> Option<RawFd>
+	{
+	    Some(self.as_raw_fd())
+	}
+    }
+
+    /// Open the input to collect from: the file given as an argument, or `stdin`.
+    #[inline] 
+    pub(super) fn open_input(opt: &args::Options) -> eyre::Result<Box<dyn Input>>
+    {
+	match opt.input() {
+	    args::InputSource::Stdin => Ok(Box::new(StdinInput::new())),
+	    args::InputSource::Path(path) => std::fs::File::open(path)
+		.map(|file| Box::new(file) as Box<dyn Input>)
+		.wrap_err("Failed to open input file")
+		.with_section(|| path.display().to_string().header("Path was")),
+	}
+    }
+
+    /// `collect`'s own `stdin`.
+    pub(super) struct StdinInput
+    {
+	stdin: io::StdinLock<'static>,
+	/// Where to seek `stdout` back to after reading, if it is the same file as `stdin` (see `--on-same-file`.)
+	rewind: Option<libc::off_t>,
+    }
+
+    impl StdinInput
+    {
+	#[inline] 
+	pub(super) fn new() -> Self
+	{
+	    let rewind = stdin_is_stdout().then(|| unsafe { libc::lseek(libc::STDOUT_FILENO, 0, libc::SEEK_CUR) })
+		.filter(|&pos| pos >= 0);
+	    Self { stdin: io::stdin().lock(), rewind }
+	}
+    }
+
+    impl io::Read for StdinInput
+    {
+	#[inline] 
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+	{
+	    io::Read::read(&mut self.stdin, buf)
+	}
+    }
+
+    impl Input for StdinInput
+    {
+	#[inline] 
+	fn size(&self) -> Option<NonZeroUsize>
+	{
+	    try_get_size(&self.stdin)
+	}
+
+	#[cfg(feature="memfile")]
+	#[inline] 
+	fn raw_fd(&self) -> Option<RawFd>
+	{
+	    Some(self.stdin.as_raw_fd())
+	}
+
+	/// If `--head` was passed, `stdin` is closed afterwards.
+	///
+	/// If `stdin` and `stdout` are the same file, `stdout` is seeked back to where it was before the read, so the collected data is written over the input rather than after it (see `--on-same-file`.)
+	fn collected(&mut self, opt: &args::Options) -> io::Result<()>
+	{
+	    if opt.head().is_some() {
+		if_trace!(debug!("--head: closing stdin"));
+		sys::close_stdin()?;
+	    }
+	    if let Some(pos) = self.rewind {
+		if_trace!(debug!("stdin is stdout: seeking stdout back to {pos} for in-place rewrite"));
+		if unsafe { libc::lseek(libc::STDOUT_FILENO, pos, libc::SEEK_SET) } < 0 {
+		    return Err(io::Error::last_os_error());
+		}
+	    }
+	    Ok(())
+	}
+    }
+
+    /// An output that the strategies write to.
+    ///
+    /// This is `stdout` or the `--output` file (see `Output`) when running, but anything writable can be written to, e.g. a `Vec<u8>` in tests.
+    pub(super) trait Sink: io::Write
+    {
+	/// The file descriptor the output is written to, if it has one (for `splice()`.)
+	#[cfg(feature="memfile")]
+	#[inline] 
+	fn raw_fd(&self) -> Option<RawFd>
+	{
+	    None
+	}
+    }
+
+    impl Sink for Vec<u8> {}
+
+    impl Sink for std::fs::File
+    {
+	#[cfg(feature="memfile")]
+	#[inline] 
+	fn raw_fd(&self) -> Option<RawFd>
+	{
+	    Some(self.as_raw_fd())
+	}
+    }
+
+    impl Sink for io::StdoutLock<'_>
+    {
+	#[cfg(feature="memfile")]
+	#[inline] 
+	fn raw_fd(&self) -> Option<RawFd>
+	{
+	    Some(self.as_raw_fd())
+	}
+    }
+
+    /// Where the strategies write what they collected (see `--output`.)
+    ///
+    /// Every kind of output is written through the same path (`with_writer()`, or `io::Write` directly), so `stdout` is not special-cased by the strategies.
+    pub(super) enum Output
+    {
+	/// `collect`'s own `stdout`, which is closed at the end of `main()`.
+	Stdout(io::Stdout),
+	/// The file given to `--output`.
+	File(std::fs::File),
+	/// The file given to `--output` with `--append`, which is written to the end of and not truncated.
+	Append(std::fs::File),
+	/// An already open file descriptor (`--output-fd`, inherited from the parent), which is written to from its current position and not truncated.
+	Fd(std::fs::File),
+	/// Output is discarded (written to `/dev/null`, for `--null`.)
+	Null(std::fs::File),
+	/// A new in-memory file.
+	#[cfg_attr(not(all(test, feature="memfile")), allow(dead_code))]
+	Memfd(std::fs::File),
+    }
+
+    impl Output
+    {
+	/// Open the output target chosen by `--output`: a file is created if needed, and truncated (or appended to, with `--append`.)
+	///
+	/// A file that is the input itself is not truncated (see `open_over_input()`.)
+	#[inline] 
+	pub(super) fn open(opt: &args::Options) -> eyre::Result<Self>
+	{
+	    match opt.output() {
+		args::OutputTarget::File(path) if opt.append() => Self::open_append(path),
+		args::OutputTarget::File(path) => Self::open_over_input(opt, path),
+		target => Self::open_target(target),
+	    }
+	}
+
+	/// Open the `--output` file at `path`, which may be the input file itself (e.g. `collect -o f < f`, or `collect f -o f`.)
+	///
+	/// It is opened without `O_TRUNC`, and only truncated once it is known not to be the input. If it is the input, `--on-same-file` decides whether that is an error, or whether the file is left as it is until everything has been collected: it is then written over from the start, and cut to the length written by `set_written_len()`.
+	#[cfg_attr(feature="logging", instrument(level="debug", skip(opt), err))]
+	fn open_over_input(opt: &args::Options, path: &std::path::Path) -> eyre::Result<Self>
+	{
+	    if_trace!(info!("writing to {path:?}"));
+	    // Also opened for reading, like `open_target()`.
+	    let file = std::fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		// Not truncated here: it may be the input (see `output_is_input()`.)
+		.truncate(false)
+		.open(path)
+		.wrap_err("Failed to open output file")
+		.with_section(|| path.display().to_string().header("Path was"))?;
+	    if output_is_input(opt, &file) {
+		match opt.on_same_file() {
+		    args::SameFile::Error => return Err(eyre!("The output file is the same file as the input")
+							.with_section(|| path.display().to_string().header("Path was"))
+							.with_note(|| "Writing the output may destroy input that has not been read yet")
+							.with_suggestion(|| "Pass `--on-same-file buffer` to collect the whole input before rewriting the file in place")),
+		    args::SameFile::Buffer => {
+			if_trace!(info!("output file is the input: rewriting it in place after collecting"));
+			return Ok(Self::File(file));
+		    },
+		}
+	    }
+	    // As with `O_TRUNC`, a file that is not a regular file (e.g. a FIFO or `/dev/null`) is not truncated.
+	    if matches!(sys::classify_fd(&file), Ok(sys::FdKind::Regular)) {
+		file.set_len(0)
+		    .wrap_err("Failed to truncate output file")
+		    .with_section(|| path.display().to_string().header("Path was"))?;
+	    }
+	    Ok(Self::File(file))
+	}
+
+	/// Open the file at `path` to be appended to: it is created if needed, but not truncated.
+	#[cfg_attr(feature="logging", instrument(level="debug", err))]
+	pub(super) fn open_append(path: &std::path::Path) -> eyre::Result<Self>
+	{
+	    if_trace!(info!("appending to {path:?}"));
+	    // Also opened for reading, like `open_target()`.
+	    std::fs::OpenOptions::new()
+		.read(true)
+		.append(true)
+		.create(true)
+		.open(path)
+		.map(Self::Append)
+		.wrap_err("Failed to open output file for appending")
+		.with_section(|| path.display().to_string().header("Path was"))
+	}
+
+	/// Open `target`: a file is created if needed, and truncated.
+	#[cfg_attr(feature="logging", instrument(level="debug", err))]
+	pub(super) fn open_target(target: &args::OutputTarget) -> eyre::Result<Self>
+	{
+	    match target {
+		args::OutputTarget::Stdout => Ok(Self::Stdout(io::stdout())),
+		args::OutputTarget::File(path) => {
+		    if_trace!(info!("writing to {path:?}"));
+		    // Also opened for reading, since `-exec/{}` children may be given it (as with `stdout` in the `buffered` strategy.)
+		    std::fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(path)
+			.map(Self::File)
+			.wrap_err("Failed to open output file")
+			.with_section(|| path.display().to_string().header("Path was"))
+		},
+		&args::OutputTarget::Fd(fd) => {
+		    if_trace!(info!("writing to fd {fd}"));
+		    // Checked first, so a closed fd is reported here instead of as a failed write.
+		    if unsafe { libc::fcntl(fd, libc::F_GETFD) } < 0 {
+			return Err(io::Error::last_os_error())
+			    .wrap_err("Invalid output file descriptor")
+			    .with_section(move || fd.header("File descriptor was"));
+		    }
+		    Ok(Self::fd(unsafe { std::fs::File::from_raw_fd(fd) }))
+		},
+		args::OutputTarget::Null => Self::null(),
+	    }
+	}
+
+	/// Write to the already open `file`.
+	#[inline]
+	pub(super) fn fd(file: std::fs::File) -> Self
+	{
+	    Self::Fd(file)
+	}
+
+	/// Discard the output.
+	#[cfg_attr(feature="logging", instrument(level="debug", err))]
+	pub(super) fn null() -> eyre::Result<Self>
+	{
+	    std::fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.open("/dev/null")
+		.map(Self::Null)
+		.wrap_err("Failed to open /dev/null")
+	}
+
+	/// Write to a new in-memory file named `name` (not selectable from the command line: it would be gone once `collect` exits.)
+	#[cfg_attr(feature="logging", instrument(level="debug", err))]
+	#[cfg(feature="memfile")]
+	#[cfg_attr(not(test), allow(dead_code))]
+	pub(super) fn memfd(name: Option<&str>) -> eyre::Result<Self>
+	{
+	    memfile::RawFile::open_mem(name, 0)
+		.map(|file| Self::Memfd(file.into()))
+		.wrap_err("Failed to create in-memory output file")
+	}
+
+	/// Set the length of the output to exactly `len` bytes once everything has been written to it, if it is a file that `collect` truncated or created itself.
+	///
+	/// `stdout`, `Append` and `Fd` outputs are left alone: they may be pipes, or files that were meant to be written into from their current position (or end.) So is an `--output` path that is not a regular file (e.g. a FIFO or a device), which cannot be truncated.
+	#[cfg_attr(feature="logging", instrument(level="debug", skip(self), err))]
+	pub(super) fn set_written_len(&self, len: u64) -> eyre::Result<()>
+	{
+	    match self {
+		Self::File(file) | Self::Memfd(file) if matches!(sys::classify_fd(file), Ok(sys::FdKind::Regular)) => file.set_len(len)
+		    .wrap_err(eyre!("Failed to `ftruncate()` output file to {len} bytes")),
+		_ => Ok(()),
+	    }
+	}
+
+	/// Sync the output to its storage for `--fsync`, once everything has been written to it.
+	///
+	/// Only a file opened for `--output` is synced: `stdout` may be a pipe or a terminal, so it is left alone.
+	#[cfg_attr(feature="logging", instrument(level="debug", skip(self), err))]
+	pub(super) fn sync(&self, mode: args::Fsync) -> eyre::Result<()>
+	{
+	    match self {
+		Self::File(file) | Self::Append(file) => match mode {
+		    args::Fsync::All => file.sync_all(),
+		    args::Fsync::Data => file.sync_data(),
+		}.wrap_err("Failed to sync output file")
+		    .with_section(move || format!("{mode:?}").header("--fsync was")),
+		_ => {
+		    if_trace!(info!("--fsync: output is not a file opened by collect, so it is not synced"));
+		    Ok(())
+		},
+	    }
+	}
+
+	/// Write to the output through `f` (`stdout` is kept locked until it returns.)
+	#[inline]
+	pub(super) fn with_writer<F, T>(&mut self, f: F) -> T
+	where F: FnOnce(&mut dyn Sink) -> T
+	{
+	    match self {
+		Self::Stdout(stdout) => f(&mut stdout.lock()),
+		Self::File(file) | Self::Append(file) | Self::Fd(file) | Self::Null(file) | Self::Memfd(file) => f(file),
+	    }
+	}
+    }
+
+    impl io::Write for Output
+    {
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+	{
+	    self.with_writer(|to| to.write(buf))
+	}
+	#[inline]
+	fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize>
+	{
+	    self.with_writer(|to| to.write_vectored(bufs))
+	}
+	#[inline]
+	fn flush(&mut self) -> io::Result<()>
+	{
+	    self.with_writer(|to| to.flush())
+	}
+    }
+
+    impl Sink for Output
+    {
+	#[cfg(feature="memfile")]
+	#[inline]
+	fn raw_fd(&self) -> Option<RawFd>
+	{
+	    Some(self.as_raw_fd())
+	}
+    }
+
+    impl AsRawFd for Output
+    {
+	#[inline]
+	fn as_raw_fd(&self) -> RawFd
+	{
+	    match self {
+		Self::Stdout(stdout) => stdout.as_raw_fd(),
+		Self::File(file) | Self::Append(file) | Self::Fd(file) | Self::Null(file) | Self::Memfd(file) => file.as_raw_fd(),
+	    }
+	}
+    }
+
+    impl ModeReturn for Output
+    {
+	type ExecFile = Self;
+	#[inline(always)]
+	fn get_exec_file(self) -> Option<Self::ExecFile>
+	{
+	    Some(self)
+	}
+    }
+
+    /// The extra targets that the collected buffer is written to after the output (see `-t/--tee`.)
+    ///
+    /// A target that cannot be opened or written to is skipped (with a warning) and the others are still written to; unless `--tee-strict` was passed, in which case it is an error straight away.
+    pub(super) struct Tee
+    {
+	targets: Vec<(args::OutputTarget, Output)>,
+	strict: bool,
+    }
+
+    impl Tee
+    {
+	/// Open every `--tee` target.
+	pub(super) fn open(opt: &args::Options) -> eyre::Result<Self>
+	{
+	    let mut tee = Self { targets: Vec::with_capacity(opt.tee().len()), strict: opt.tee_strict() };
+	    for target in opt.tee() {
+		match Output::open_target(target) {
+		    Ok(output) => tee.targets.push((target.clone(), output)),
+		    Err(err) => tee.fail(target, err)?,
+		}
+	    }
+	    Ok(tee)
+	}
+
+	/// Are there no targets (left) to write to?
+	#[inline] 
+	#[cfg_attr(not(feature="memfile"), allow(dead_code))]
+	pub(super) fn is_empty(&self) -> bool
+	{
+	    self.targets.is_empty()
+	}
+
+	/// Handle `target` failing with `err`: it is an error with `--tee-strict`, otherwise only a warning.
+	fn fail(&self, target: &args::OutputTarget, err: eyre::Report) -> eyre::Result<()>
+	{
+	    let err = err.with_section(|| target.to_string().header("Tee target was"));
+	    if self.strict {
+		return Err(err.with_note(|| "`--tee-strict` was passed"));
+	    }
+	    if_trace!(warn!("--tee: skipping {target}: {err:?}"));
+	    let _ = err;
+	    Ok(())
+	}
+
+	/// Write all of `bufs` to every target, dropping (see `fail()`) those that fail.
+	pub(super) fn write_all(&mut self, bufs: &[io::IoSlice<'_>]) -> eyre::Result<()>
+	{
+	    let mut index = 0;
+	    while index < self.targets.len() {
+		let output = &mut self.targets[index].1;
+		let mut bufs = bufs.to_vec();
+		match sys::write_all_vectored(output, &mut bufs[..]) {
+		    Ok(()) => index += 1,
+		    Err(err) => self.remove(index, eyre::Report::new(err).wrap_err("Failed to write to tee target"))?,
+		}
+	    }
+	    Ok(())
+	}
+
+	/// Drop the target at `index`, which failed with `err` (see `fail()`.)
+	fn remove(&mut self, index: usize, err: eyre::Report) -> eyre::Result<()>
+	{
+	    let (target, _) = self.targets.remove(index);
+	    self.fail(&target, err)
+	}
+
+	/// Are all of the targets pipes? If so, the buffer can be written to them with `splice_from_file()`.
+	#[cfg(all(feature="memfile", target_os="linux"))]
+	fn all_pipes(&self) -> bool
+	{
+	    self.targets.iter().all(|(_, output)| matches!(sys::classify_fd(output), Ok(sys::FdKind::Pipe)))
+	}
+
+	/// Write `len` bytes of `file` from `offset` to every target, which must all be pipes (see `all_pipes()`), without copying them through userspace.
+	///
+	/// Each chunk is `splice()`d from `file` into a pipe of its own, duplicated from there into every target but the last with `tee()` (see `sys::tee_all()`), and then moved into the last one. A target that only part of the chunk fit into is given the rest of it by copying from `file`. Targets that fail are dropped (see `fail()`.)
+	///
+	/// `file`'s position is moved past what was written, as writing it to the output does.
+	///
+	/// # Returns
+	/// The number of bytes written; or `None` if `file` cannot be spliced from, in which case nothing was written and the caller should copy instead.
+	#[cfg(all(feature="memfile", target_os="linux"))]
+	fn splice_from_file(&mut self, file: &std::fs::File, offset: u64, len: u64) -> eyre::Result<Option<u64>>
+	{
+	    let (mut read, write) = sys::pipe_cloexec().wrap_err("Failed to create a pipe for `--tee`")?;
+	    // Nothing reads from the pipe until a whole chunk has been spliced into it, so a chunk must fit in it.
+	    let chunk = sys::pipe_size(write.as_raw_fd()).wrap_err("Failed to get the capacity of the pipe for `--tee`")? as u64;
+	    io::Seek::seek(&mut &*file, io::SeekFrom::Start(offset))
+		.wrap_err("Failed to seek the memory buffer for `--tee`")?;
+	    let mut done = 0u64;
+	    while done < len && !self.is_empty() {
+		let moved = match sys::splice_all(file.as_raw_fd(), write.as_raw_fd(), Some(std::cmp::min(len - done, chunk) as usize)) {
+		    Ok(0) => break,
+		    Ok(moved) => moved,
+		    Err(err) if done == 0 && err.kind() == io::ErrorKind::InvalidInput => return Ok(None),
+		    Err(err) => return Err(err).wrap_err("Failed to splice the memory buffer for `--tee`"),
+		};
+		let at = offset + done;
+		let mut index = 0;
+		while index + 1 < self.targets.len() {
+		    let output = &mut self.targets[index].1;
+		    // A short `tee()` cannot be continued (see `sys::tee_all()`), so the rest is copied instead.
+		    let result = sys::tee_all(read.as_raw_fd(), output.as_raw_fd(), moved)
+			.and_then(|teed| copy_from_file(file, at + teed, moved - teed as usize, output));
+		    match result {
+			Ok(()) => index += 1,
+			Err(err) => self.remove(index, eyre::Report::new(err).wrap_err("Failed to write to tee target"))?,
+		    }
+		}
+		// Whatever the last target did not take out of the pipe is discarded, so it is empty for the next chunk.
+		let mut queued = moved;
+		if let Some(index) = self.targets.len().checked_sub(1) {
+		    let output = &mut self.targets[index].1;
+		    let result = sys::splice_all(read.as_raw_fd(), output.as_raw_fd(), Some(moved))
+			.and_then(|spliced| {
+			    queued -= spliced;
+			    copy_from_file(file, at + spliced as u64, moved - spliced, output)
+			});
+		    if let Err(err) = result {
+			self.remove(index, eyre::Report::new(err).wrap_err("Failed to write to tee target"))?;
+		    }
+		}
+		if queued > 0 {
+		    io::copy(&mut io::Read::take(&mut read, queued as u64), &mut io::sink())
+			.wrap_err("Failed to empty the pipe for `--tee`")?;
+		}
+		done += moved as u64;
+	    }
+	    if_trace!(debug!("--tee: spliced {done} bytes into the targets"));
+	    Ok(Some(done))
+	}
+
+	/// Flush every target, and set those that were created to exactly the `len` bytes written to them.
+	pub(super) fn finish(mut self, len: u64) -> eyre::Result<()>
+	{
+	    for (target, mut output) in std::mem::take(&mut self.targets) {
+		if let Err(err) = io::Write::flush(&mut output).wrap_err("Failed to flush tee target")
+		    .and_then(|_| output.set_written_len(len)) {
+		    self.fail(&target, err)?;
+		}
+	    }
+	    Ok(())
+	}
+    }
+
+    /// What a strategy collected from its input, and wrote to its output; and how long that took (see `--stats`.)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub(super) struct TransferReport
+    {
+	/// The number of bytes read from the input (including any that were not kept, e.g. with `--tail`.)
+	pub collected: u64,
+	/// The number of bytes written to the output.
+	pub written: u64,
+	/// How long collecting the input took.
+	pub read_duration: Duration,
+	/// How long writing the output took.
+	pub write_duration: Duration,
+	/// How long spawning (and waiting on) `-exec/{}` children took. This is set by the caller, since the strategies do not run them.
+	pub exec_duration: Duration,
+    }
+
+    impl fmt::Display for TransferReport
+    {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+	    writeln!(f, "read: {} bytes in {:?}", self.collected, self.read_duration)?;
+	    writeln!(f, "written: {} bytes in {:?}", self.written, self.write_duration)?;
+	    write!(f, "exec: {:?}", self.exec_duration)
+	}
+    }
+
+    /// The size to preallocate the buffer for `input` to: `--buffer-size` if passed, otherwise its size if it can be determined; capped at `--head`/`--limit`.
+    #[inline] 
+    fn input_size<I: ?Sized + Input>(opt: &args::Options, input: &I) -> Option<NonZeroUsize>
+    {
+	let size = opt.buffer_size().or_else(|| input.size())?;
+	match opt.read_limit() {
+	    Some(head) => NonZeroUsize::new(std::cmp::min(size.get() as u64, head) as usize),
+	    None => Some(size),
+	}
+    }
+
+    /// Copy all of `input` into `to`.
+    ///
+    /// If `--best-effort` was passed, a read error does not fail the copy: the bytes collected before it are kept, and the error is returned alongside their count so it can be handled by `raise_deferred()` once they have been written.
+    ///
+    /// If `--head` or `--limit` was passed, only that many bytes are copied.
+    ///
+    /// `already` is the number of bytes that were moved from `input` before this by other means (see `splice_input()`): they count towards the limit, and are included in the returned count.
+    ///
+    /// If `--progress` was passed (and is enabled, see `progress::enabled()`), the number of bytes read is shown on `stderr` as they are copied.
+    #[inline] 
+    fn collect_input<I: ?Sized + Input, W: ?Sized + io::Write>(opt: &args::Options, input: &mut I, to: &mut W, already: u64) -> io::Result<(u64, Option<io::Error>)>
+    {
+	let total = input.size().map(|size| size.get() as u64)
+	    .map(|size| opt.read_limit().map_or(size, |limit| std::cmp::min(size, limit)));
+	let result = {
+	    let mut limited = io::Read::take(&mut *input, opt.read_limit().unwrap_or(u64::MAX).saturating_sub(already));
+	    let mut progress;
+	    let reader: &mut dyn io::Read = if progress::shown(opt) {
+		progress = progress::ProgressReader::new(&mut limited, total, already);
+		&mut progress
+	    } else {
+		&mut limited
+	    };
+	    if opt.best_effort().is_some() {
+		let (read, err) = sys::copy_until_error(reader, to)?;
+		if_trace!(if let Some(err) = err.as_ref() {
+		    warn!("--best-effort: reading from input failed after {read} bytes, writing what was collected: {err}");
+		});
+		(read, err)
+	    } else {
+		(io::copy(reader, to)?, None)
+	    }
+	};
+	let result = (already + result.0, result.1);
+	if_trace!(if let Some(limit) = opt.read_limit() {
+	    debug!("--head/--limit: read {} of {limit} bytes", result.0);
+	});
+	input.collected(opt)?;
+	Ok(result)
+    }
+
+    /// Checks the size of the in-memory buffer against `--mem-warn` and `--mem-max` as it grows, so that a huge input fails to be collected instead of running the whole system out of memory.
+    ///
+    /// Both are percentages of the memory available to the buffer: `MemAvailable` (see `sys::available_memory()`), plus what the buffer already holds (which is no longer available once it holds it.) This is re-read every `INTERVAL` bytes, not on every write.
+    #[cfg(feature="memfile")]
+    struct MemoryGuard
+    {
+	warn: u8,
+	max: u8,
+	available: fn() -> io::Result<u64>,
+	/// The size at which to check next.
+	next: u64,
+	/// How much was allocated for the buffer up front, which it holds no matter how much has been written to it.
+	allocated: u64,
+	warned: bool,
+    }
+
+    #[cfg(feature="memfile")]
+    impl MemoryGuard
+    {
+	/// How much the buffer grows between checks.
+	const INTERVAL: u64 = 16 * 1024 * 1024;
+
+	#[inline]
+	fn new(opt: &args::Options) -> Self
+	{
+	    Self::with_available(opt, sys::available_memory)
+	}
+
+	fn with_available(opt: &args::Options, available: fn() -> io::Result<u64>) -> Self
+	{
+	    Self {
+		warn: opt.mem_warn(),
+		max: opt.mem_max(),
+		available,
+		next: 0,
+		allocated: 0,
+		warned: false,
+	    }
+	}
+
+	/// Check `size` bytes about to be allocated up front for the buffer, which are not yet taken from the available memory.
+	fn allocate(&mut self, size: u64) -> io::Result<()>
+	{
+	    let available = match (self.available)() {
+		Ok(available) => available,
+		Err(_err) => {
+		    if_trace!(warn!("--mem-max: could not find the available memory, not checking the buffer's size: {_err}"));
+		    self.next = u64::MAX;
+		    return Ok(());
+		},
+	    };
+	    self.judge(size, available)?;
+	    self.allocated = size;
+	    Ok(())
+	}
+
+	/// Check the buffer now that `size` bytes have been written to it.
+	fn check(&mut self, size: u64) -> io::Result<()>
+	{
+	    if size < self.next {
+		return Ok(());
+	    }
+	    self.next = size.saturating_add(Self::INTERVAL);
+	    let held = std::cmp::max(size, self.allocated);
+	    match (self.available)() {
+		Ok(available) => self.judge(held, available.saturating_add(held)),
+		Err(_err) => {
+		    if_trace!(warn!("--mem-max: could not find the available memory, not checking the buffer's size: {_err}"));
+		    self.next = u64::MAX;
+		    Ok(())
+		},
+	    }
+	}
+
+	/// Is `held` bytes of `total` over `--mem-max` (an error) or `--mem-warn`?
+	fn judge(&mut self, held: u64, total: u64) -> io::Result<()>
+	{
+	    let over = |percent: u8| held as u128 * 100 > total as u128 * percent as u128;
+	    if over(self.max) {
+		return Err(io::Error::new(io::ErrorKind::OutOfMemory, format!("the buffer would hold {held} of the {total} bytes of memory available to it, more than the `--mem-max` of {}%", self.max)));
+	    }
+	    if !self.warned && over(self.warn) {
+		if_trace!(warn!("--mem-warn: the buffer holds {held} of the {total} bytes of memory available to it, more than {}%", self.warn));
+		self.warned = true;
+	    }
+	    Ok(())
+	}
+    }
+
+    /// A writer into the in-memory buffer that checks it with a `MemoryGuard` as it is written to.
+    #[cfg(feature="memfile")]
+    struct Guarded<'a, W: ?Sized>
+    {
+	inner: &'a mut W,
+	guard: &'a mut MemoryGuard,
+	size: u64,
+    }
+
+    #[cfg(feature="memfile")]
+    impl<W: ?Sized + io::Write> io::Write for Guarded<'_, W>
+    {
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+	{
+	    let written = self.inner.write(buf)?;
+	    self.size += written as u64;
+	    self.guard.check(self.size)?;
+	    Ok(written)
+	}
+	#[inline(always)]
+	fn flush(&mut self) -> io::Result<()>
+	{
+	    self.inner.flush()
+	}
+    }
+
+    /// The size of the huge pages to back the in-memory buffer with, and the flags to pass to `memfd_create()` for them, if `--hugetlb` was passed.
+    #[cfg(feature="memfile")]
+    fn huge_pages(opt: &args::Options) -> eyre::Result<Option<(u64, libc::c_uint)>>
+    {
+	cfg_if! {
+	    if #[cfg(feature="hugetlb")] {
+		match opt.hugetlb() {
+		    Some(size) => {
+			let mask = memfile::hp::find_mask(size.bytes())
+			    .wrap_err("Failed to find the huge page size for `--hugetlb`")?;
+			if_trace!(info!("--hugetlb: backing the buffer with huge pages of {} bytes (mask {mask:x})", mask.size()));
+			Ok(Some((mask.size(), mask.mask())))
+		    },
+		    None => Ok(None),
+		}
+	    } else {
+		// Rejected when the arguments are parsed.
+		let _ = opt;
+		Ok(None)
+	    }
+	}
+    }
+
+    /// A writer into an in-memory file that can only be written to through a mapping (one backed by huge pages, see `--hugetlb`.)
+    ///
+    /// The file is grown (and re-mapped) a whole number of `page`s at a time, so it is always larger than what has been written to it.
+    #[cfg(feature="memfile")]
+    struct MappedWriter
+    {
+	file: memfile::RawFile,
+	page: u64,
+	map: memfile::map::MmapRegion,
+	/// The number of bytes written.
+	len: usize,
+    }
+
+    #[cfg(feature="memfile")]
+    impl MappedWriter
+    {
+	const PROTECTION: memfile::map::MapProtection = memfile::map::MapProtection::READ.union(memfile::map::MapProtection::WRITE);
+
+	fn new(file: &std::fs::File, page: u64) -> io::Result<Self>
+	{
+	    Ok(Self {
+		file: memfile::RawFile::from(file.try_clone()?),
+		page,
+		map: memfile::map::MmapRegion::empty(Self::PROTECTION),
+		len: 0,
+	    })
+	}
+
+	/// Grow the file so at least `additional` more bytes can be written to it.
+	#[cfg_attr(feature="logging", instrument(level="debug", skip(self), fields(len = self.len, mapped = self.map.len())))]
+	fn reserve(&mut self, additional: usize) -> io::Result<()>
+	{
+	    let want = self.len + additional;
+	    if want <= self.map.len() {
+		return Ok(());
+	    }
+	    // At least doubled, so the file is not re-mapped for every page.
+	    let size = std::cmp::max(want, self.map.len() * 2) as u64;
+	    let size = size.div_ceil(self.page) * self.page;
+	    let size = usize::try_from(size).map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "buffer larger than the address space"))?;
+	    if_trace!(debug!("growing mapped buffer from {} to {size} bytes", self.map.len()));
+	    self.file.truncate_size(size as u64)?;
+	    // Running out of huge pages is not the buffer being too large for `--mem-max`, so it is not reported as `OutOfMemory`.
+	    self.map = self.file.map(Self::PROTECTION, true, Some(size))
+		.map_err(|err| if err.is_out_of_memory() {
+		    io::Error::other(format!("not enough free huge pages of {} bytes for a {size} byte buffer (see `nr_hugepages` in `/sys/kernel/mm/hugepages/hugepages-{}kB`): {err}", self.page, self.page / 1024))
+		} else {
+		    err.into()
+		})?;
+	    Ok(())
+	}
+    }
+
+    #[cfg(feature="memfile")]
+    impl io::Write for MappedWriter
+    {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+	{
+	    self.reserve(buf.len())?;
+	    self.map[self.len..(self.len + buf.len())].copy_from_slice(buf);
+	    self.len += buf.len();
+	    Ok(buf.len())
+	}
+	#[inline(always)]
+	fn flush(&mut self) -> io::Result<()>
+	{
+	    Ok(())
+	}
+    }
+
+    /// Copy all of `input` into the file `to` through a `MappedWriter` growing it by `page` bytes at a time, like `collect_input()`. Only the data is counted by `guard`.
+    #[cfg(feature="memfile")]
+    fn map_input<I: ?Sized + Input>(opt: &args::Options, input: &mut I, to: &std::fs::File, page: u64, guard: &mut MemoryGuard) -> io::Result<(u64, Option<io::Error>)>
+    {
+	let mut writer = MappedWriter::new(to, page)?;
+	collect_input(opt, input, &mut Guarded { inner: &mut writer, guard, size: 0 }, 0)
+    }
+
+    /// Copy all of `input` into the file `to`, like `collect_input()`; but if `input` is a pipe, its data is moved into `to` with `splice()` instead of being copied through userspace.
+    ///
+    /// Whatever could not be spliced (all of it, if `splice()` is not supported between the two, or progress is shown) is copied by `collect_input()`. Either way, `to` is checked by `guard` as it grows.
+    #[cfg(feature="memfile")]
+    fn splice_input<I: ?Sized + Input>(opt: &args::Options, input: &mut I, to: &mut std::fs::File, guard: &mut MemoryGuard) -> io::Result<(u64, Option<io::Error>)>
+    {
+	let spliced = match input.raw_fd() {
+	    // `--best-effort` must see the read error itself, to keep what was read before it; and `--progress` must see the data to count it.
+	    Some(fd) if opt.best_effort().is_none() && !progress::shown(opt) && sys::is_pipe(fd) => {
+		let head = opt.read_limit().unwrap_or(u64::MAX);
+		let mut spliced = 0u64;
+		// Spliced `MemoryGuard::INTERVAL` bytes at a time, so the guard can check the buffer in between.
+		loop {
+		    let want = std::cmp::min(head - spliced, MemoryGuard::INTERVAL);
+		    if want == 0 {
+			break;
+		    }
+		    match sys::splice_all(fd, to.as_raw_fd(), Some(want as usize)) {
+			Ok(moved) => {
+			    spliced += moved as u64;
+			    guard.check(spliced)?;
+			    if (moved as u64) < want {
+				break;
+			    }
+			},
+			Err(err) if spliced == 0 && err.kind() == io::ErrorKind::InvalidInput => {
+			    if_trace!(debug!("input cannot be spliced, copying instead: {err}"));
+			    break;
+			},
+			Err(err) => return Err(err),
+		    }
+		}
+		if_trace!(debug!("spliced {spliced} bytes from input"));
+		spliced
+	    },
+	    _ => 0,
+	};
+	collect_input(opt, input, &mut Guarded { inner: to, guard, size: spliced }, spliced)
+    }
+
+    /// Write `len` bytes of the buffer `file` from `offset` to every `--tee` target (see `Tee`.)
+    ///
+    /// The buffer is read once (with `pread()`, so its position is not moved), a chunk at a time: each chunk is written to all of the targets before the next one is read.
+    #[cfg(feature="memfile")]
+    fn tee_from_file(opt: &args::Options, file: &std::fs::File, offset: u64, len: u64) -> eyre::Result<()>
+    {
+	use std::os::unix::fs::FileExt;
+	let mut tee = Tee::open(opt)?;
+	if tee.is_empty() {
+	    return Ok(());
+	}
+	// Pipes may keep referring to the pages of the buffer until they are read from, so (as in `send_output()`) they are only spliced into if there are no `-exec/{}` children to write to it in place.
+	#[cfg(target_os="linux")]
+	if opt.opt_exec().len() == 0 && tee.all_pipes() {
+	    if let Some(done) = tee.splice_from_file(file, offset, len)? {
+		return tee.finish(done);
+	    }
+	    if_trace!(debug!("--tee: the memory buffer cannot be spliced, copying instead"));
+	}
+	let mut buffer = vec![0u8; 64 * 1024];
+	let mut done = 0u64;
+	while done < len && !tee.is_empty() {
+	    let want = std::cmp::min(len - done, buffer.len() as u64) as usize;
+	    let read = match file.read_at(&mut buffer[..want], offset + done) {
+		Ok(0) => break,
+		Ok(read) => read,
+		Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+		Err(err) => return Err(err).wrap_err("Failed to read the memory buffer for `--tee`"),
+	    };
+	    tee.write_all(&[io::IoSlice::new(&buffer[..read])])?;
+	    done += read as u64;
+	}
+	tee.finish(done)
+    }
+
+    /// Write `len` bytes of `file` from `offset` to `to`, reading them with `pread()` (so the position of `file` is not moved.)
+    #[cfg(all(feature="memfile", target_os="linux"))]
+    fn copy_from_file<W: ?Sized + io::Write>(file: &std::fs::File, offset: u64, len: usize, to: &mut W) -> io::Result<()>
+    {
+	use std::os::unix::fs::FileExt;
+	if len == 0 {
+	    return Ok(());
+	}
+	let mut buffer = vec![0u8; len];
+	file.read_exact_at(&mut buffer[..], offset)?;
+	to.write_all(&buffer[..])
+    }
+
+    /// Move up to `len` bytes from the current position of `from` into `output` in the kernel: with `splice()` if `output` is a pipe, or `sendfile()` if it is a regular file or a socket.
+    ///
+    /// A pipe (or socket) may keep referring to the pages of `from` until they are read from it, so those are not used if there are `-exec/{}` children: they may write to the buffer in place before then.
+    ///
+    /// # Returns
+    /// The number of bytes moved: the caller must copy the rest (which is all of it, if neither can be used for `output`.)
+    #[cfg(feature="memfile")]
+    fn send_output<W: ?Sized + Sink>(opt: &args::Options, from: &std::fs::File, output: &mut W, len: usize) -> io::Result<u64>
+    {
+	let fd = match output.raw_fd() {
+	    Some(fd) => fd,
+	    None => return Ok(0),
+	};
+	let shared = opt.opt_exec().len() == 0;
+	let result = match sys::fd_kind(fd) {
+	    Ok(sys::FdKind::Pipe) if shared => sys::splice_all(from.as_raw_fd(), fd, Some(len)),
+	    Ok(sys::FdKind::Socket) if shared => sys::sendfile_all(fd, from.as_raw_fd(), len),
+	    Ok(sys::FdKind::Regular) => sys::sendfile_all(fd, from.as_raw_fd(), len),
+	    _ => return Ok(0),
+	};
+	match result {
+	    Ok(sent) => {
+		if_trace!(debug!("moved {sent} bytes to output in the kernel"));
+		Ok(sent as u64)
+	    },
+	    Err(err) if matches!(err.kind(), io::ErrorKind::InvalidInput | io::ErrorKind::Unsupported) => {
+		if_trace!(debug!("output cannot be spliced or sent to, copying instead: {err}"));
+		Ok(0)
+	    },
+	    Err(err) => Err(err),
+	}
+    }
+
+    /// The part of the `read` collected bytes that should be written: all of them, unless `--output-range` was passed.
+    fn output_range(opt: &args::Options, read: u64) -> eyre::Result<std::ops::Range<u64>>
+    {
+	match opt.output_range() {
+	    None => Ok(0..read),
+	    Some(range) if opt.output_range_clamp() => {
+		let clamped = range.clamped(read);
+		if_trace!(if clamped.end - clamped.start < range.end.unwrap_or(read).saturating_sub(range.start) {
+		    warn!("--output-range {range} is past the end of the {read} bytes collected, writing only {clamped:?}");
+		});
+		Ok(clamped)
+	    },
+	    Some(range) => range.within(read)
+		.ok_or_else(|| eyre!("--output-range {range} is out of bounds"))
+		.with_section(move || read.header("Bytes collected"))
+		.with_suggestion(|| "Pass `--output-range-clamp` to write only the part of the range that was collected"),
+	}
+    }
+
+    /// Is `output` the same regular file as the input (`stdin`, or the `FILE` argument)?
+    ///
+    /// If the input file cannot be opened, it is not the same file: that is reported when it is opened to be collected.
+    pub(super) fn output_is_input<F: ?Sized + AsRawFd>(opt: &args::Options, output: &F) -> bool
+    {
+	if !matches!(sys::classify_fd(output), Ok(sys::FdKind::Regular)) {
+	    return false;
+	}
+	match opt.input() {
+	    args::InputSource::Stdin => sys::same_file(&io::stdin(), output).unwrap_or(false),
+	    args::InputSource::Path(path) => std::fs::File::open(path)
+		.and_then(|input| sys::same_file(&input, output))
+		.unwrap_or(false),
+	}
+    }
+
+    /// Are `stdin` and `stdout` the same regular file?
+    #[inline] 
+    pub(super) fn stdin_is_stdout() -> bool
+    {
+	let (stdin, stdout) = (io::stdin(), io::stdout());
+	matches!(sys::classify_fd(&stdin), Ok(sys::FdKind::Regular))
+	    && sys::same_file(&stdin, &stdout).unwrap_or(false)
+    }
+
+    /// Handle a read error deferred by `collect_input()`, after the `read` bytes collected before it have been written.
+    #[inline] 
+    fn raise_deferred(opt: &args::Options, deferred: Option<io::Error>, read: usize) -> eyre::Result<()>
+    {
+	match (deferred, opt.best_effort().unwrap_or_default()) {
+	    (None, _) => Ok(()),
+	    (Some(err), args::BestEffort::Warn) => {
+		if_trace!(warn!("--best-effort=warn: ignoring read error after writing {read} bytes: {err}"));
+		let _ = err;
+		Ok(())
+	    },
+	    (Some(err), args::BestEffort::Error) => Err(err)
+		.wrap_err("Failed to read from input")
+		.with_note(|| format!("`--best-effort`: the {read} bytes collected before the error were written")),
+	}
+    }
     #[cfg_attr(feature="logging", instrument(err))]
     #[inline] 
-    pub(super) fn buffered() -> eyre::Result<impl ModeReturn>
+    pub(super) fn buffered(opt: &args::Options) -> eyre::Result<(impl ModeReturn, TransferReport)>
+    {
+	let mut output = Output::open(opt)?;
+	let report = output.with_writer(|to| buffered_from(opt, &mut *open_input(opt)?, to))?;
+	// The output file may be the input, which was not truncated when it was opened (see `Output::open_over_input()`.)
+	output.set_written_len(report.written)?;
+	if let Some(mode) = opt.fsync() {
+	    output.sync(mode)?;
+	}
+	Ok((output, report))
+    }
+
+    /// Collect all of `input` into an allocated buffer, then write it to `output`.
+    ///
+    /// # Returns
+    /// The number of bytes collected and written.
+    pub(super) fn buffered_from<I, W>(opt: &args::Options, input: &mut I, output: &mut W) -> eyre::Result<TransferReport>
+    where I: ?Sized + Input,
+	  W: ?Sized + io::Write
     {
 	if_trace!(info!("strategy: allocated buffer"));
 	
-	let (bytes, read) = {
-	    let stdin = io::stdin();
-	    let mut bytes: buffers::DefaultMut = try_get_size(&stdin).create_buffer();
+	let read_start = Instant::now();
+	let (bytes, read, deferred) = {
+	    let mut bytes = opt.buffer_backend().create_buffer(input_size(opt, input));
+	    if_trace!(debug!("buffer backend: {:?}", bytes.backend()));
 	    
-	    let read = io::copy(&mut stdin.lock(), &mut (&mut bytes).writer())
-		.with_section(|| bytes.len().header("Buffer size is"))
-		.with_section(|| bytes.capacity().header("Buffer cap is"))
-		.with_section(|| format!("{:?}", bytes).header("Buffer is"))
-		.wrap_err("Failed to read into buffer")?;
-	    (bytes.freeze(), read as usize)
+	    let (read, deferred) = fill_buffer(opt, input, &mut bytes)
+		.with_section(|| bytes.capacity().header("Buffer cap is"))?;
+	    (bytes.freeze(), read, deferred)
+	};
+	write_buffer(opt, output, &bytes, read, deferred, read_start.elapsed())
+    }
+
+    /// Collect all of `input` into a buffer taken from `pool`, then write it to `output`. The buffer is put back into `pool` afterwards (even if this fails), so the next call reuses its capacity.
+    ///
+    /// This is `buffered_from()` for collecting many inputs one after another. `--buffer-backend` is not used: the pool's buffers are always `DefaultMut`. `collect` itself only collects one input per run, so only the tests call this (as `buffers::BufferPool` is, it is only built for them.)
+    ///
+    /// # Returns
+    /// The number of bytes collected and written.
+    #[cfg(test)]
+    pub(super) fn buffered_into<I, W>(opt: &args::Options, input: &mut I, output: &mut W, pool: &mut buffers::BufferPool) -> eyre::Result<TransferReport>
+    where I: ?Sized + Input,
+	  W: ?Sized + io::Write
+    {
+	let read_start = Instant::now();
+	let mut bytes = pool.acquire(input_size(opt, input));
+	let result = fill_buffer(opt, input, &mut bytes)
+	    .and_then(|(read, deferred)| write_buffer(opt, output, &bytes, read, deferred, read_start.elapsed()));
+	pool.release(bytes);
+	result
+    }
+
+    /// Collect all of `input` into the (empty) buffer `bytes`.
+    ///
+    /// # Returns
+    /// The number of bytes collected, and the read error deferred by `--best-effort` (if any.)
+    fn fill_buffer<I, B>(opt: &args::Options, input: &mut I, bytes: &mut B) -> eyre::Result<(usize, Option<io::Error>)>
+    where I: ?Sized + Input,
+	  B: ?Sized + MutBuffer + fmt::Debug
+    {
+	let (read, deferred) = collect_input(opt, input, &mut buffers::MutBufferExt::writer(&mut *bytes), 0)
+	    .with_section(|| bytes.as_mut().len().header("Buffer size is"))
+	    .with_section(|| format!("{:?}", bytes).header("Buffer is"))
+	    .wrap_err("Failed to read into buffer")?;
+	Ok((read as usize, deferred))
+    }
+
+    /// Write the `read` bytes collected into `bytes` (or the `--head`/`--tail` range of them) to `output`, and to the `--tee` targets.
+    fn write_buffer<W>(opt: &args::Options, output: &mut W, bytes: &[u8], read: usize, deferred: Option<io::Error>, read_duration: Duration) -> eyre::Result<TransferReport>
+    where W: ?Sized + io::Write
+    {
+	if_trace!(info!("collected {read} from input. starting write."));
+	let range = output_range(opt, read as u64)?;
+	let range = (range.start as usize)..(range.end as usize);
+
+	let write_start = Instant::now();
+	let written = {
+	    let written = io::copy(&mut buffers::BufferExt::reader(&mut &bytes[range.clone()]) , output)
+		.with_section(|| read.header("Bytes read"))
+		.with_section(|| bytes.len().header("Buffer length"))
+		.with_section(|| format!("{:?}", &bytes[..read]).header("Read Buffer"))
+		.wrap_err("Failed to write from buffer")?;
+	    // `stdout` is line-buffered, and will be closed by its raw fd later; so it must be flushed now.
+	    output.flush().wrap_err("Failed to flush output")?;
+	    written
+	};
+	let mut tee = Tee::open(opt)?;
+	tee.write_all(&[io::IoSlice::new(&bytes[range.clone()])])?;
+	tee.finish(range.len() as u64)?;
+	let write_duration = write_start.elapsed();
+	if_trace!(info!("written {written} to output."));
+
+	if range.len() != written as usize {
+	    return Err(io::Error::new(io::ErrorKind::BrokenPipe, format!("read {read} bytes, but only wrote {written} of the {} to write", range.len())))
+		.wrap_err("Writing failed: size mismatch");
+	}
+	#[cfg(feature="digest")]
+	if let Some(kind) = opt.digest() {
+	    digest::print(kind, &digest::digest_slices(kind, &[&bytes[range.clone()]]))?;
+	}
+	raise_deferred(opt, deferred, read)?;
+	
+	Ok(TransferReport { collected: read as u64, written: range.len() as u64, read_duration, write_duration, ..Default::default() })
+    }
+
+    /// Collect only the last `tail` bytes of `stdin` into a ring buffer, and write them to `stdout`.
+    #[cfg_attr(feature="logging", instrument(err))]
+    #[inline] 
+    pub(super) fn tail(opt: &args::Options, tail: usize) -> eyre::Result<(impl ModeReturn, TransferReport)>
+    {
+	let mut output = Output::open(opt)?;
+	let report = output.with_writer(|to| tail_from(opt, tail, &mut *open_input(opt)?, to))?;
+	// The output file may be the input, which was not truncated when it was opened (see `Output::open_over_input()`.)
+	output.set_written_len(report.written)?;
+	if let Some(mode) = opt.fsync() {
+	    output.sync(mode)?;
+	}
+	Ok((output, report))
+    }
+
+    /// Collect only the last `tail` bytes of `input` into a ring buffer, and write them to `output`.
+    ///
+    /// # Returns
+    /// The number of bytes collected (all of the input), and written (only the last `tail` of them.)
+    pub(super) fn tail_from<I, W>(opt: &args::Options, tail: usize, input: &mut I, output: &mut W) -> eyre::Result<TransferReport>
+    where I: ?Sized + Input,
+	  W: ?Sized + io::Write
+    {
+	if_trace!(info!("strategy: ring buffer of the last {tail} bytes"));
+
+	let mut ring = buffers::RingBuffer::new(tail);
+	let read_start = Instant::now();
+	let (read, deferred) = collect_input(opt, input, &mut ring, 0)
+	    .with_section(|| ring.len().header("Bytes kept"))
+	    .with_section(|| ring.capacity().header("Tail size"))
+	    .wrap_err("Failed to read into ring buffer")?;
+	let read_duration = read_start.elapsed();
+	if_trace!(info!("collected {read} from input, keeping the last {}. starting write.", ring.len()));
+
+	let write_start = Instant::now();
+	{
+	    // The ring's content may wrap around, so it is written as (up to) two pieces in one `writev()`.
+	    let (first, second) = ring.as_slices();
+	    sys::write_all_vectored(output, &mut [io::IoSlice::new(first), io::IoSlice::new(second)])
+		.with_section(|| read.header("Bytes read"))
+		.with_section(|| ring.len().header("Bytes kept"))
+		.wrap_err("Failed to write from ring buffer")?;
+	    // `stdout` is line-buffered, and will be closed by its raw fd later; so it must be flushed now.
+	    output.flush().wrap_err("Failed to flush output")?;
+	    let mut tee = Tee::open(opt)?;
+	    tee.write_all(&[io::IoSlice::new(first), io::IoSlice::new(second)])?;
+	    tee.finish(ring.len() as u64)?;
+	}
+	let write_duration = write_start.elapsed();
+	if_trace!(info!("written {} to output.", ring.len()));
+	#[cfg(feature="digest")]
+	if let Some(kind) = opt.digest() {
+	    let (first, second) = ring.as_slices();
+	    digest::print(kind, &digest::digest_slices(kind, &[first, second]))?;
+	}
+
+	raise_deferred(opt, deferred, ring.len())?;
+	
+	Ok(TransferReport { collected: read, written: ring.len() as u64, read_duration, write_duration, ..Default::default() })
+    }
+
+    #[cfg_attr(feature="logging", instrument(err))]
+    #[inline]
+    #[cfg(feature="memfile")]
+    pub(super) fn memfd(opt: &args::Options) -> eyre::Result<(impl ModeReturn, TransferReport)>
+    {
+	let mut output = Output::open(opt)?;
+	let (file, report) = output.with_writer(|to| memfd_from(opt, &mut *open_input(opt)?, to))?;
+	// The whole length is known now, so the output file is set to exactly what was written to it (which is less than the buffer with `--output-range`.)
+	output.set_written_len(report.written)?;
+	if let Some(mode) = opt.fsync() {
+	    output.sync(mode)?;
+	}
+	Ok((file, report))
+    }
+
+    /// Check the number of bytes `read` into the memory buffer `file` against its stream position and length, and truncate it to `read` if its length does not match (e.g. it was preallocated to more than was collected.)
+    ///
+    /// # Returns
+    /// The number of bytes in the buffer, and the stream position and length reported for it before any correction.
+    #[cfg(feature="memfile")]
+    fn check_memfile_len(file: &mut std::fs::File, read: u64) -> eyre::Result<(u64, io::Result<u64>, io::Result<u64>)>
+    {
+	use io::Seek;
+	use std::borrow::Cow;
+
+	let sp = file.stream_position(); //TODO: XXX: Is this really needed?
+	let sl = memfile::stream_len(&*file);
+
+	if_trace!(trace!("Stream position after read: {:?}", sp));
+	if_trace!(trace!("Stream length after read: {:?}", sl));
+
+	let read = match sp.as_ref() {
+	    Ok(&v) if v != read  => {
+		if_trace!(warn!("Reported read value not equal to memfile stream position: expected from `io::copy()`: {v}, got {read}"));
+		v
+	    },
+	    Ok(&x) => {
+		if_trace!(trace!("Reported memfile stream position and copy result equal: {x} == {}", read));
+		x
+	    },
+	    Err(e) => {
+		if_trace!(error!("Could not report memfile stream position, ignoring check on {read}: {e}"));
+		read
+	    },
+	};
+
+	let truncate_stream = |bad: u64, good: u64| {
+	    use std::num::NonZeroU64;
+	    file.set_len(good)
+		.map(|_| good)
+		.with_section(|| match NonZeroU64::new(bad) {Some (b) => Cow::Owned(b.get().to_string()), None => Cow::Borrowed("<unknown>") }.header("Original (bad) length"))
+		.with_section(|| good.header("New (correct) length"))
+		.wrap_err(eyre!("Failed to truncate stream to correct length")
+			  .with_section(|| format!("{:?}", file).header("Memory buffer file")))
+	};
+
+	let read = match sl.as_ref() {
+	    Ok(&v) if v != read  => {
+		if_trace!(warn!("Reported read value not equal to memfile stream length: expected from `io::copy()`: {read}, got {v}"));
+		if_trace!(debug!("Attempting to correct memfile stream length from {v} to {read}"));
+
+		truncate_stream(v, read)?
+	    },
+	    Ok(&v) => {
+		if_trace!(trace!("Reported memfile stream length and copy result equal: {v} == {}", read));
+		v
+	    },
+	    Err(e) => {
+		if_trace!(error!("Could not report memfile stream length, ignoring check on {read}: {e}"));
+		if_trace!(warn!("Attempting to correct memfile stream length anyway"));
+		if let Err(e) = truncate_stream(0, read) {
+		    if_trace!(error!("Truncate failed: {e}"));
+		}
+
+		read
+	    }
 	};
-	if_trace!(info!("collected {read} from stdin. starting write."));
-
-	let stdout = io::stdout();
-	let written = 
-	    io::copy(&mut (&bytes[..read]).reader() , &mut stdout.lock())
-	    .with_section(|| read.header("Bytes read"))
-	    .with_section(|| bytes.len().header("Buffer length (frozen)"))
-	    .with_section(|| format!("{:?}", &bytes[..read]).header("Read Buffer"))
-	    .with_section(|| format!("{:?}", bytes).header("Full Buffer"))
-	    .wrap_err("Failed to write from buffer")?;
-	if_trace!(info!("written {written} to stdout."));
-
-	if read != written as usize {
-	    return Err(io::Error::new(io::ErrorKind::BrokenPipe, format!("read {read} bytes, but only wrote {written}")))
-		.wrap_err("Writing failed: size mismatch");
-	}
-	
-	Ok(stdout)
+
+	Ok((read, sp, sl))
     }
 
-    #[cfg_attr(feature="logging", instrument(err))]
-    #[inline]
+    /// Collect all of `input` into an in-memory file, then write it to `output`.
+    ///
+    /// # Returns
+    /// The in-memory file, which is given to `-exec/{}` children; and the number of bytes collected and written.
     #[cfg(feature="memfile")]
-    //TODO: We should establish a max memory threshold for this to prevent full system OOM: Output a warning message if it exceeeds, say, 70-80% of free memory (not including used by this program (TODO: How do we calculate this efficiently?)), and fail with an error if it exceeds 90% of memory... Or, instead of using free memory as basis of the requirement levels on the max size of the memory file, use max memory? Or just total free memory at the start of program? Or check free memory each time (slow!! probably not this one...). Umm... I think basing it off total memory would be best; perhaps make the percentage levels user-configurable at compile time (and allow the user to set the memory value as opposed to using the total system memory at runtime.) or runtime (compile-time preffered; use that crate that lets us use TOML config files at comptime (find it pretty easy by looking through ~/work's rust projects, I've used it before.))
-    pub(super) fn memfd() -> eyre::Result<impl ModeReturn>
+    pub(super) fn memfd_from<I, W>(opt: &args::Options, input: &mut I, output: &mut W) -> eyre::Result<(std::fs::File, TransferReport)>
+    where I: ?Sized + Input,
+	  W: ?Sized + Sink
     {
 	const DEFAULT_BUFFER_SIZE: fn () -> Option<std::num::NonZeroUsize> = || {
 	    cfg_if!{ 
@@ -298,9 +1699,19 @@ mod work {
 		.unwrap_or_else(|e| format!("<unknown: {e}>"))
 	}
 
+	/// `unwrap_int_string()` of a value that was only reported in some builds (`<unknown>` if it was not.)
+	#[inline]
+	fn maybe_int_string<T, E>(i: Option<Result<T, E>>) -> std::borrow::Cow<'static, str>
+	where T: std::fmt::Display,
+	      E: std::fmt::Display
+	{
+	    i.map_or(std::borrow::Cow::Borrowed("<unknown>"), |i| unwrap_int_string(i).into())
+	}
+
 	
 	#[cfg_attr(feature="logging", instrument(skip_all, err, fields(i = ?i.as_raw_fd())))]
 	    #[inline]
+	#[allow(dead_code)]
 	fn truncate_file<S>(i: impl AsRawFd, to: S) -> eyre::Result<()>
 	where S: TryInto<u64>,
 	<S as TryInto<u64>>::Error: EyreError
@@ -309,6 +1720,7 @@ mod work {
 	    Ok(())
 	}
 	
+	#[allow(dead_code)]
 	fn truncate_file_raw(i: impl AsRawFd, to: impl Into<u64>) -> io::Result<()>
 	{
 	    use libc::ftruncate;
@@ -406,12 +1818,13 @@ mod work {
 	    }
 	};
 
-	let (mut file, read) = {
-	    let stdin = io::stdin();
-
-	    let buffsz = try_get_size(&stdin);
+	let huge = huge_pages(opt)?;
+	let read_start = Instant::now();
+	let (mut file, deferred, read) = {
+	    let buffsz = input_size(opt, input);
 	    if_trace!(debug!("Attempted determining input size: {:?}", buffsz));
-	    let buffsz = if cfg!(feature="memfile-size-output") {
+	    // `--buffer-size` is only a guess at the size, which `stdout` must not be set to.
+	    let buffsz = if cfg!(feature="memfile-size-output") && opt.buffer_size().is_none() {
 		//TODO: XXX: Even if this actually works, is it safe to do this? Won't the consumer try to read `value` bytes before we've written them? Perhaps remove pre-setting entirely...
 		match buffsz {
 		    y @ Some(ref value) => {
@@ -433,71 +1846,50 @@ mod work {
 		trace!("Failed to determine input size: alllocating on-the-fly (no preallocation)");
 	    });
 	    
-	    let mut file = memfile::create_memfile(Some("collect-buffer"), 
-						   buffsz.map(|x| x.get()).unwrap_or(0))	    
+	    let name = match opt.memfile_name() {
+		Some(name) if opt.memfile_name_truncate() && name.len() > memfile::MEMFD_NAME_MAX => {
+		    let short = memfile::truncate_name(name);
+		    if_trace!(warn!("--name is {} bytes long, truncating to {} bytes (the maximum is {})", name.len(), short.len(), memfile::MEMFD_NAME_MAX));
+		    short
+		},
+		Some(name) => name,
+		None => "collect-buffer",
+	    };
+	    let too_large = |err: io::Error| eyre::Report::new(err)
+		.wrap_err("Input is too large to collect in memory")
+		.with_suggestion(|| "Pass a higher `--mem-max` (a percentage of the available memory) to allow a larger buffer, or `--head`/`--tail` to keep less of the input");
+	    let mut guard = MemoryGuard::new(opt);
+	    if let Some(size) = buffsz {
+		guard.allocate(size.get() as u64).map_err(too_large)?;
+	    }
+	    // `--hugetlb`: the file is sized by `map_input()` instead, since its size must be a multiple of the page size.
+	    let mut file = memfile::create_memfile(Some(name), 
+						   buffsz.filter(|_| huge.is_none()).map(|x| x.get()).unwrap_or(0),
+						   huge.map(|(_, flags)| flags).unwrap_or(0))
 		.with_section(|| format!("{:?}", buffsz).header("Deduced input buffer size"))
 		.wrap_err(eyre!("Failed to create in-memory buffer"))?;
 
-	    let read = io::copy(&mut stdin.lock(), &mut file)
-		.with_section(|| format!("{:?}", file).header("Memory buffer file"))?;
+	    let collected = match huge {
+		Some((page, _)) => map_input(opt, input, &file, page, &mut guard),
+		None => splice_input(opt, input, &mut file, &mut guard),
+	    };
+	    let (read, deferred) = match collected {
+		Err(err) if err.kind() == io::ErrorKind::OutOfMemory => return Err(too_large(err)),
+		result => result.with_section(|| format!("{:?}", file).header("Memory buffer file"))?,
+	    };
+	    if opt.buffer_size().is_some() && huge.is_none() {
+		// The buffer was preallocated to `--buffer-size`, which may be more than was collected.
+		file.set_len(read)
+		    .wrap_err(eyre!("Failed to truncate the memory buffer to the {read} bytes collected"))
+		    .with_section(|| format!("{:?}", opt.buffer_size()).header("--buffer-size was"))?;
+	    }
 	    
 	    let read =  {
 		use io::*;
-		use std::borrow::Cow;
 
-		let (read, sp, sl) = if cfg!(any(feature="memfile-preallocate", debug_assertions)) {
-		    let sp = file.stream_position(); //TODO: XXX: Is this really needed?
-		    let sl = memfile::stream_len(&file);
-		    
-		    if_trace!(trace!("Stream position after read: {:?}", sp));
-		    if_trace!(trace!("Stream length after read: {:?}", sp));
-		    
-		    let read = match sp.as_ref() {
-			Ok(&v) if v != read  => {
-			    if_trace!(warn!("Reported read value not equal to memfile stream position: expected from `io::copy()`: {v}, got {read}"));
-			    v
-			},
-			Ok(&x) => {
-			    if_trace!(trace!("Reported memfile stream position and copy result equal: {x} == {}", read));
-			    x
-			},
-			Err(e) => {
-			    if_trace!(error!("Could not report memfile stream position, ignoring check on {read}: {e}"));
-			    read
-			},
-		    };
-
-		    let truncate_stream = |bad: u64, good: u64| {
-			use std::num::NonZeroU64;
-			file.set_len(good)
-			    .map(|_| good)
-			    .with_section(|| match NonZeroU64::new(bad) {Some (b) => Cow::Owned(b.get().to_string()), None => Cow::Borrowed("<unknown>") }.header("Original (bad) length"))
-			    .with_section(|| good.header("New (correct) length"))
-			    .wrap_err(eyre!("Failed to truncate stream to correct length")
-				      .with_section(|| format!("{:?}", file).header("Memory buffer file")))
-		    };
-		    
-		    let read = match sl.as_ref() {
-			Ok(&v) if v != read  => {
-			    if_trace!(warn!("Reported read value not equal to memfile stream length: expected from `io::copy()`: {read}, got {v}"));
-			    if_trace!(debug!("Attempting to correct memfile stream length from {v} to {read}"));
-			    
-			    truncate_stream(v, read)?
-			},
-			Ok(&v) => {
-			    if_trace!(trace!("Reported memfile stream length and copy result equal: {v} == {}", read));
-			    v
-			},
-			Err(e) => {
-			    if_trace!(error!("Could not report memfile stream length, ignoring check on {read}: {e}"));
-			    if_trace!(warn!("Attempting to correct memfile stream length anyway"));
-			    if let Err(e) = truncate_stream(0, read) {
-				if_trace!(error!("Truncate failed: {e}"));
-			    }
-			    
-			    read
-			}
-		    };
+		// A `--hugetlb` buffer is written through a mapping and cannot be truncated to the size collected, so neither its position nor its length match it.
+		let (read, sp, sl) = if cfg!(any(feature="memfile-preallocate", debug_assertions)) && huge.is_none() {
+		    let (read, sp, sl) = check_memfile_len(&mut file, read)?;
 		    (read, Some(sp), Some(sl))
 		} else {
 		    (read, None, None)
@@ -506,27 +1898,33 @@ mod work {
 		file.seek(SeekFrom::Start(0))
 		    .with_section(|| read.header("Actual read bytes"))
 		    .wrap_err(eyre!("Failed to seek back to start of memory buffer file for output")
-			      .with_section(move || if let Some(sp) = sp { Cow::Owned(unwrap_int_string(sp)) }
-					    else { Cow::Borrowed("<unknown>")  }.header("Memfile position"))
-			      .with_section(move || if let Some(sp) = sl { Cow::Owned(unwrap_int_string(sp)) }
-					    else { Cow::Borrowed("<unknown>")  }.header("Memfile full length"))
+			      .with_section(move || maybe_int_string(sp).header("Memfile position"))
+			      .with_section(move || maybe_int_string(sl).header("Memfile full length"))
 			      /*.with_section(|| file.stream_len().map(|x| x.to_string())
 			      .unwrap_or_else(|e| format!("<unknown: {e}>")).header("Memfile full length"))*/)?;
 		
 		read
 	    };
 	    
-	    (file, usize::try_from(read)
+	    (file, deferred, usize::try_from(read)
 	     .wrap_err(eyre!("Failed to convert read bytes to `usize`")
 		       .with_section(|| read.header("Number of bytes was"))
 		       .with_section(|| u128::abs_diff(read.into(), usize::MAX as u128).header("Difference between `read` and `usize::MAX` is"))
 		       .with_suggestion(|| "It is likely you are running on a 32-bit ptr width machine and this input exceeds that of the maximum 32-bit unsigned integer value")
 		       .with_note(|| usize::MAX.header("Maximum value of `usize`")))?)
 	};
-	if_trace!(info!("collected {} from stdin. starting write.", read));
+	let read_duration = read_start.elapsed();
+	if_trace!(info!("collected {} from input. starting write.", read));
 
-	// Seal memfile
-	let _ = try_seal_size(&file);
+	// Nothing writes to the buffer after this point: it is only read from (into `output`, and then by `-exec/{}` children), so it is sealed now.
+	// With `--exec-input-seal-level`, that includes `F_SEAL_WRITE` if requested; otherwise only the size is sealed.
+	if opt.exec_input_seal_level().is_some() {
+	    apply_seal_level(&file, opt)
+		.wrap_err("Failed to seal the memory buffer")
+		.with_section(|| format!("{:?}", opt.exec_input_seal_level()).header("Requested seals were"))?;
+	} else {
+	    let _ = try_seal_size(&file);
+	}
 
 	
 	
@@ -537,19 +1935,466 @@ mod work {
 	    .wrap_err(eyre!("Failed to `ftruncate()` stdout after collection of {read} bytes"))
 	    .with_note(|| "Was not pre-set")?;	
 
-	let written =
-	    io::copy(&mut file, &mut io::stdout().lock())
-	    .with_section(|| read.header("Bytes read from stdin"))
-	    .with_section(|| unwrap_int_string(tell_file(&mut file)).header("Current buffer position"))
-	    .wrap_err("Failed to write buffer to stdout")?;
-	if_trace!(info!("written {written} to stdout."));
+	let range = output_range(opt, read as u64)?;
+	let len = (range.end - range.start) as usize;
+	if range.start > 0 {
+	    io::Seek::seek(&mut file, io::SeekFrom::Start(range.start))
+		.wrap_err(eyre!("Failed to seek to the start of `--output-range` in the memory buffer"))?;
+	}
+	let write_start = Instant::now();
+	let written = {
+	    // The buffer is moved into `output` in the kernel when possible, and anything left over copied.
+	    let written = send_output(opt, &file, output, len)
+		.and_then(|spliced| Ok(spliced + io::copy(&mut io::Read::take(&mut file, len as u64 - spliced), output)?))
+		.with_section(|| read.header("Bytes read from input"))
+		.with_section(|| unwrap_int_string(tell_file(&mut file)).header("Current buffer position"))
+		.wrap_err("Failed to write buffer to output")?;
+	    // `stdout` is line-buffered, and will be closed by its raw fd later; so it must be flushed now.
+	    output.flush().wrap_err("Failed to flush output")?;
+	    written
+	};
+	tee_from_file(opt, &file, range.start, len as u64)?;
+	let write_duration = write_start.elapsed();
+	if_trace!(info!("written {written} to output."));
 
-	if read != written as usize {
-	    return Err(io::Error::new(io::ErrorKind::BrokenPipe, format!("read {read} bytes, but only wrote {written}")))
+	if len != written as usize {
+	    return Err(io::Error::new(io::ErrorKind::BrokenPipe, format!("read {read} bytes, but only wrote {written} of the {len} to write")))
 		.wrap_err("Writing failed: size mismatch");
 	}
+	#[cfg(feature="digest")]
+	if let Some(kind) = opt.digest() {
+	    // The buffer's position is wherever writing the output left it, and it is given to `-exec/{}` children after this; so it is read with `pread()`.
+	    let hex = digest::digest_file(kind, &file, range.start, len as u64)
+		.wrap_err("Failed to read the memory buffer for the digest")?;
+	    digest::print(kind, &hex)?;
+	}
+	raise_deferred(opt, deferred, read)?;
 	
-	Ok(file)
+	Ok((file, TransferReport { collected: read as u64, written: len as u64, read_duration, write_duration, ..Default::default() }))
+    }
+
+    #[cfg(test)]
+    mod tests
+    {
+	use super::*;
+	use std::{
+	    fs,
+	    thread,
+	};
+
+	/// Both ends of a real `pipe()`, for testing the paths that need a file descriptor (e.g. `fstat()`.)
+	struct PipePair
+	{
+	    read: fs::File,
+	    write: fs::File,
+	}
+
+	impl PipePair
+	{
+	    fn new() -> io::Result<Self>
+	    {
+		let mut fds = [0; 2];
+		if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+		    return Err(io::Error::last_os_error());
+		}
+		Ok(unsafe { Self { read: fs::File::from_raw_fd(fds[0]), write: fs::File::from_raw_fd(fds[1]) } })
+	    }
+
+	    /// Write all of `data` into the pipe from another thread, closing the write end afterwards.
+	    fn feed(self, data: Vec<u8>) -> (fs::File, thread::JoinHandle<io::Result<()>>)
+	    {
+		let Self { read, mut write } = self;
+		(read, thread::spawn(move || io::Write::write_all(&mut write, &data[..])))
+	    }
+	}
+
+	fn gen_input(len: usize) -> Vec<u8>
+	{
+	    (0..len).map(|i| (i.wrapping_mul(31) ^ (i >> 8)) as u8).collect()
+	}
+
+	#[test]
+	fn buffered_from_cursor() -> eyre::Result<()>
+	{
+	    let input = gen_input(100 * 1024 + 3);
+	    let mut output = Vec::new();
+	    let report = buffered_from(&args::Options::default(), &mut io::Cursor::new(&input[..]), &mut output)?;
+	    assert_eq!((report.collected, report.written), (input.len() as u64, input.len() as u64), "Wrong number of bytes collected or written");
+	    assert!(output == input, "Output data mismatch");
+	    Ok(())
+	}
+
+	#[test]
+	fn output_variants() -> eyre::Result<()>
+	{
+	    use io::Read;
+	    /// Read back everything written to `output`, through a new open of it.
+	    fn written(output: &Output) -> io::Result<Vec<u8>>
+	    {
+		let mut data = Vec::new();
+		fs::File::open(format!("/proc/self/fd/{}", output.as_raw_fd()))?.read_to_end(&mut data)?;
+		Ok(data)
+	    }
+	    let input = gen_input(64 * 1024 + 7);
+	    let write = |output: &mut Output| -> eyre::Result<()> {
+		let report = output.with_writer(|to| buffered_from(&args::Options::default(), &mut io::Cursor::new(&input[..]), to))?;
+		assert_eq!(report.written, input.len() as u64, "Wrong number of bytes written");
+		output.set_written_len(report.written)
+	    };
+
+	    // `--output <PATH>`
+	    let path = std::env::temp_dir().join(format!("collect-output-test-{}", std::process::id()));
+	    fs::write(&path, &gen_input(128 * 1024)[..])?;
+	    let mut output = Output::open(&args::parse_from([std::ffi::OsString::from("--output"), path.clone().into()])?)?;
+	    let result = write(&mut output).and_then(|_| Ok(written(&output)?));
+	    let _ = fs::remove_file(&path);
+	    assert!(result? == input, "File output mismatch");
+
+	    // `--output <PATH> --append`: written after what was already there, and not cut to the length written.
+	    let prefix = gen_input(4096 + 3);
+	    fs::write(&path, &prefix[..])?;
+	    let mut output = Output::open(&args::parse_from([std::ffi::OsString::from("--output"), path.clone().into(), "--append".into()])?)?;
+	    let result = write(&mut output).and_then(|_| Ok(written(&output)?));
+	    let _ = fs::remove_file(&path);
+	    assert!(result? == [&prefix[..], &input[..]].concat(), "Append output mismatch");
+
+	    // `--output-fd <FD>`: an inherited pipe, which is not truncated.
+	    let PipePair { read: mut output_read, write: output_write } = PipePair::new()?;
+	    let drain = thread::spawn(move || {
+		let mut output = Vec::new();
+		output_read.read_to_end(&mut output).map(move |_| output)
+	    });
+	    let fd = output_write.into_raw_fd().to_string();
+	    let mut output = Output::open(&args::parse_from(["--output-fd", &fd[..]])?)?;
+	    write(&mut output)?;
+	    drop(output);
+	    assert!(drain.join().expect("Output reader panicked")? == input, "Fd output mismatch");
+
+	    // `--null`
+	    let mut output = Output::open(&args::parse_from(["--null"])?)?;
+	    write(&mut output)?;
+	    assert!(written(&output)?.is_empty(), "Null output kept data");
+
+	    #[cfg(feature="memfile")]
+	    {
+		let mut output = Output::memfd(Some("collect-output-test"))?;
+		write(&mut output)?;
+		assert!(written(&output)? == input, "Memfd output mismatch");
+	    }
+	    Ok(())
+	}
+
+	#[test]
+	fn buffered_from_pipe_to_pipe() -> eyre::Result<()>
+	{
+	    let input = gen_input(256 * 1024);
+	    let (mut read, feeder) = PipePair::new()?.feed(input.clone());
+	    let PipePair { read: mut output_read, write: mut output } = PipePair::new()?;
+	    let drain = thread::spawn(move || {
+		let mut output = Vec::new();
+		io::Read::read_to_end(&mut output_read, &mut output).map(move |_| output)
+	    });
+
+	    buffered_from(&args::Options::default(), &mut read, &mut output)?;
+	    drop(output);
+	    feeder.join().expect("Pipe feeder panicked")?;
+	    assert!(drain.join().expect("Pipe drain panicked")? == input, "Output data mismatch");
+	    Ok(())
+	}
+
+	#[test]
+	fn empty_input() -> eyre::Result<()>
+	{
+	    let opt = args::Options::default();
+	    // An empty pipe (the write end is closed straight away), like an empty `stdin`.
+	    let empty = || PipePair::new().map(|pipe| pipe.feed(Vec::new()));
+	    let (mut read, feeder) = empty()?;
+	    let mut output = Vec::new();
+	    let report = buffered_from(&opt, &mut read, &mut output)?;
+	    feeder.join().expect("Pipe feeder panicked")?;
+	    assert_eq!((report.collected, report.written), (0, 0), "buffered: wrong number of bytes collected or written");
+	    assert!(output.is_empty(), "buffered: output was written with nothing collected");
+
+	    let (mut read, feeder) = empty()?;
+	    let report = tail_from(&opt, 1000, &mut read, &mut output)?;
+	    feeder.join().expect("Pipe feeder panicked")?;
+	    assert_eq!((report.collected, report.written), (0, 0), "tail: wrong number of bytes collected or written");
+	    assert!(output.is_empty(), "tail: output was written with nothing collected");
+
+	    #[cfg(feature="memfile")]
+	    {
+		// The buffer is still created (and preallocated, with `memfile-preallocate`), then cut down to nothing: `-exec/{}` children are given it empty.
+		let (mut read, feeder) = empty()?;
+		let (file, report) = memfd_from(&opt, &mut read, &mut output)?;
+		feeder.join().expect("Pipe feeder panicked")?;
+		assert_eq!((report.collected, report.written), (0, 0), "memfd: wrong number of bytes collected or written");
+		assert!(output.is_empty(), "memfd: output was written with nothing collected");
+		assert_eq!(memfile::stream_len(&file)?, 0, "memfd: buffer is not empty");
+	    }
+	    Ok(())
+	}
+
+	#[test]
+	fn tail_from_cursor() -> eyre::Result<()>
+	{
+	    let input = gen_input(10 * 1024);
+	    let mut output = Vec::new();
+	    let report = tail_from(&args::Options::default(), 1000, &mut io::Cursor::new(&input[..]), &mut output)?;
+	    assert!(output == input[input.len() - 1000..], "Output is not the last 1000 bytes");
+	    assert_eq!((report.collected, report.written), (input.len() as u64, 1000), "Wrong number of bytes collected or written");
+	    Ok(())
+	}
+
+	#[test]
+	fn buffered_into_pool() -> eyre::Result<()>
+	{
+	    let opt = args::Options::default();
+	    let mut pool = buffers::BufferPool::new();
+	    for len in [10 * 1024, 1024, 0, 10 * 1024] {
+		let input = gen_input(len);
+		let mut output = Vec::new();
+		let report = buffered_into(&opt, &mut io::Cursor::new(&input[..]), &mut output, &mut pool)?;
+		assert!(output == input, "Output of length {len} does not match input");
+		assert_eq!((report.collected, report.written), (len as u64, len as u64), "Wrong number of bytes collected or written");
+	    }
+	    assert_eq!((pool.allocated(), pool.free()), (1, 1), "Buffer was not reused between collections");
+	    Ok(())
+	}
+
+	#[test]
+	fn head_from_cursor() -> eyre::Result<()>
+	{
+	    let input = gen_input(10 * 1024);
+	    let opt = args::parse_from(["--head", "100"])?;
+	    let mut output = Vec::new();
+	    buffered_from(&opt, &mut io::Cursor::new(&input[..]), &mut output)?;
+	    assert!(output == input[..100], "Output is not the first 100 bytes");
+	    Ok(())
+	}
+
+	#[test]
+	#[cfg(feature="memfile")]
+	fn mapped_writer() -> eyre::Result<()>
+	{
+	    const PAGE: u64 = 4096;
+	    let input = gen_input(3 * PAGE as usize + 5);
+	    let file = memfile::create_memfile(None, 0, 0)?;
+	    let mut guard = MemoryGuard::new(&args::Options::default());
+	    let (read, deferred) = map_input(&args::Options::default(), &mut io::Cursor::new(&input[..]), &file, PAGE, &mut guard)?;
+	    assert!(deferred.is_none(), "Read error was deferred");
+	    assert_eq!(read, input.len() as u64, "Invalid read size");
+
+	    // The file is grown by whole pages, and its position is not moved.
+	    let len = memfile::stream_len(&file)?;
+	    assert!(len >= read && len % PAGE == 0, "Buffer of {len} bytes is not a whole number of pages holding {read} bytes");
+	    let mut buffer = Vec::new();
+	    io::Read::read_to_end(&mut io::Read::take(&file, read), &mut buffer)?;
+	    assert!(buffer == input, "Buffer data mismatch");
+	    Ok(())
+	}
+
+	#[test]
+	#[cfg(feature="memfile")]
+	fn memfd_from_pipe() -> eyre::Result<()>
+	{
+	    let input = gen_input(100 * 1024 + 3);
+	    let (mut read, feeder) = PipePair::new()?.feed(input.clone());
+	    let mut output = Vec::new();
+	    let (mut file, _) = memfd_from(&args::Options::default(), &mut read, &mut output)?;
+	    feeder.join().expect("Pipe feeder panicked")?;
+	    assert!(output == input, "Output data mismatch");
+
+	    // The buffer itself (given to `-exec/{}`) holds the whole input too.
+	    let mut buffer = Vec::new();
+	    io::Seek::seek(&mut file, io::SeekFrom::Start(0))?;
+	    io::Read::read_to_end(&mut file, &mut buffer)?;
+	    assert!(buffer == input, "Buffer data mismatch");
+	    Ok(())
+	}
+
+	#[test]
+	#[cfg(feature="memfile")]
+	fn memfd_from_sealed() -> eyre::Result<()>
+	{
+	    let input = gen_input(100 * 1024 + 3);
+	    let mut output = Vec::new();
+	    // The buffer is write-sealed before it is written to `output`, which only reads it.
+	    let (file, report) = memfd_from(&args::parse_from(["--seal"])?, &mut io::Cursor::new(&input[..]), &mut output)?;
+	    assert_eq!(report.written, input.len() as u64, "Wrong number of bytes written");
+	    assert!(output == input, "Output data mismatch");
+
+	    let seals = file.get_seals()?;
+	    assert!(seals.has_write() && seals.has_grow() && seals.has_shrink(), "Buffer was not sealed: {seals:?}");
+	    let err = io::Write::write_all(&mut &file, b"x").expect_err("Sealed buffer could be written to");
+	    assert_eq!(err.raw_os_error(), Some(libc::EPERM), "Unexpected error: {err}");
+	    Ok(())
+	}
+
+	/// Everything written to a test subscriber (see `memfile_len_corrected()`.)
+	#[cfg(feature="logging")]
+	#[derive(Debug, Clone)]
+	struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+	#[cfg(feature="logging")]
+	impl CapturedLogs
+	{
+	    fn new() -> Self
+	    {
+		Self(std::sync::Arc::new(std::sync::Mutex::new(Vec::new())))
+	    }
+
+	    fn contents(&self) -> String
+	    {
+		String::from_utf8_lossy(&self.0.lock().unwrap()[..]).into_owned()
+	    }
+	}
+
+	#[cfg(feature="logging")]
+	impl io::Write for CapturedLogs
+	{
+	    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+	    {
+		self.0.lock().unwrap().extend_from_slice(buf);
+		Ok(buf.len())
+	    }
+
+	    fn flush(&mut self) -> io::Result<()>
+	    {
+		Ok(())
+	    }
+	}
+
+	#[test]
+	#[cfg(all(feature="memfile", feature="logging"))]
+	fn memfile_len_corrected() -> eyre::Result<()>
+	{
+	    let input = gen_input(1000);
+	    let mut file = fs::File::from(memfile::RawFile::open_mem(None, 0)?);
+	    io::Write::write_all(&mut file, &input[..])?;
+	    // Preallocated to more than was collected, as `memfile-preallocate` does when the input size is unknown.
+	    file.set_len(4096)?;
+
+	    let logs = CapturedLogs::new();
+	    let subscriber = tracing_subscriber::fmt()
+		.with_max_level(tracing::Level::TRACE)
+		.with_ansi(false)
+		.with_writer({
+		    let logs = logs.clone();
+		    move || logs.clone()
+		})
+		.finish();
+	    let (read, sp, sl) = tracing::subscriber::with_default(subscriber, || check_memfile_len(&mut file, input.len() as u64))?;
+	    assert_eq!(read, input.len() as u64, "Wrong number of bytes read");
+	    assert_eq!(sp?, 1000, "Wrong stream position reported");
+	    assert_eq!(sl?, 4096, "Reported stream length is not the one from before the correction");
+	    assert_eq!(memfile::stream_len(&file)?, 1000, "Buffer was not truncated to the bytes collected");
+
+	    let logs = logs.contents();
+	    assert!(logs.contains("Stream position after read: Ok(1000)"), "Stream position was not logged:\n{logs}");
+	    assert!(logs.contains("Stream length after read: Ok(4096)"), "Stream length was not logged:\n{logs}");
+	    assert!(logs.contains("Attempting to correct memfile stream length from 4096 to 1000"), "Correction was not logged:\n{logs}");
+	    Ok(())
+	}
+
+	#[test]
+	fn tee() -> eyre::Result<()>
+	{
+	    let input = gen_input(100 * 1024 + 3);
+	    let dir = std::env::temp_dir();
+	    let path = dir.join(format!("collect-tee-test-{}", std::process::id()));
+	    let missing = dir.join(format!("collect-tee-test-{}-missing", std::process::id())).join("output");
+	    let opt = |strict: bool| args::parse_from([std::ffi::OsString::from("-t"), missing.clone().into(), "--tee".into(), path.clone().into()]
+						     .into_iter().chain(strict.then(|| "--tee-strict".into())));
+
+	    #[allow(unused_mut)]
+	    let mut results = vec![
+		("buffered", buffered_from(&opt(false)?, &mut io::Cursor::new(&input[..]), &mut Vec::new()).map(|_| fs::read(&path))),
+		("tail", tail_from(&opt(false)?, 1000, &mut io::Cursor::new(&input[..]), &mut Vec::new()).map(|_| fs::read(&path))),
+	    ];
+	    #[cfg(feature="memfile")]
+	    results.push(("memfd", memfd_from(&opt(false)?, &mut io::Cursor::new(&input[..]), &mut Vec::new()).map(|_| fs::read(&path))));
+	    // The target that cannot be opened is only skipped, unless `--tee-strict` is passed.
+	    let strict = buffered_from(&opt(true)?, &mut io::Cursor::new(&input[..]), &mut Vec::new());
+	    let _ = fs::remove_file(&path);
+	    for (strategy, result) in results {
+		let expected = if strategy == "tail" { &input[input.len() - 1000..] } else { &input[..] };
+		assert!(result?? == expected, "{strategy}: tee target does not hold the output");
+	    }
+	    assert!(strict.is_err(), "A tee target that cannot be opened was skipped with `--tee-strict`");
+	    Ok(())
+	}
+
+	#[test]
+	#[cfg(all(feature="memfile", target_os="linux"))]
+	fn tee_to_pipes() -> eyre::Result<()>
+	{
+	    use io::Read;
+	    let input = gen_input(300 * 1024 + 3);
+	    let (first, second) = (PipePair::new()?, PipePair::new()?);
+	    let opt = args::parse_from([&first, &second].into_iter()
+				       .flat_map(|pipe| [std::ffi::OsString::from("--tee"), format!("/proc/self/fd/{}", pipe.write.as_raw_fd()).into()]))?;
+	    assert!(Tee::open(&opt)?.all_pipes(), "Pipe targets were not seen as pipes");
+	    // The targets are opened through the write ends, so those are kept open until the strategy has.
+	    let drain = |PipePair { mut read, write }| (write, thread::spawn(move || {
+		let mut output = Vec::new();
+		read.read_to_end(&mut output).map(move |_| output)
+	    }));
+	    let (first, second) = (drain(first), drain(second));
+	    memfd_from(&opt, &mut io::Cursor::new(&input[..]), &mut Vec::new())?;
+	    for (name, (write, reader)) in [("first", first), ("second", second)] {
+		drop(write);
+		assert!(reader.join().expect("Tee target reader panicked")? == input, "{name} tee target does not hold the output");
+	    }
+	    Ok(())
+	}
+
+	#[test]
+	fn transfer_durations() -> eyre::Result<()>
+	{
+	    let input = gen_input(100 * 1024 + 3);
+	    let opt = args::Options::default();
+	    #[allow(unused_mut)]
+	    let mut reports = vec![
+		("buffered", buffered_from(&opt, &mut io::Cursor::new(&input[..]), &mut Vec::new())?),
+		("tail", tail_from(&opt, 1000, &mut io::Cursor::new(&input[..]), &mut Vec::new())?),
+	    ];
+	    #[cfg(feature="memfile")]
+	    reports.push(("memfd", memfd_from(&opt, &mut io::Cursor::new(&input[..]), &mut Vec::new())?.1));
+	    for (strategy, report) in reports {
+		assert!(report.read_duration > Duration::ZERO, "{strategy}: reading was not timed: {report:?}");
+		assert!(report.write_duration > Duration::ZERO, "{strategy}: writing was not timed: {report:?}");
+		// Children are not ran by the strategies.
+		assert_eq!(report.exec_duration, Duration::ZERO, "{strategy}: exec was timed: {report:?}");
+	    }
+	    Ok(())
+	}
+
+	#[test]
+	#[cfg(feature="memfile")]
+	fn memory_guard_thresholds()
+	{
+	    const I: u64 = MemoryGuard::INTERVAL;
+	    let opt = args::Options::default();
+	    let mut guard = MemoryGuard::with_available(&opt, || Ok(10 * I));
+
+	    guard.check(0).expect("Empty buffer is over the limit");
+	    guard.check(30 * I).expect("Buffer under `--mem-max` is over it");
+	    assert!(guard.warned, "Buffer over `--mem-warn` did not warn");
+	    // Not checked again until it has grown by `INTERVAL`.
+	    guard.check(30 * I + 1).expect("Buffer was checked before growing by `INTERVAL`");
+	    let err = guard.check(100 * I).expect_err("Buffer over `--mem-max` was not an error");
+	    assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+
+	    // An up-front allocation is not yet taken from the available memory.
+	    let mut guard = MemoryGuard::with_available(&opt, || Ok(10 * I));
+	    guard.allocate(10 * I).expect_err("Allocating all of the available memory was not an error");
+	    guard.allocate(8 * I).expect("Allocation under `--mem-max` is over it");
+	    guard.check(I).expect("Buffer within its allocation is over the limit");
+
+	    // If the available memory cannot be found, nothing is checked.
+	    let mut guard = MemoryGuard::with_available(&opt, || Err(io::Error::from(io::ErrorKind::NotFound)));
+	    guard.check(u64::MAX / 2).expect("Buffer was checked without knowing the available memory");
+	}
     }
 }
 
@@ -569,7 +2414,7 @@ fn close_fileno<T: IntoRawFd>(fd: T) -> eyre::Result<()>
 {
     let fd = fd.into_raw_fd();
     if fd < 0 {
-	return Err(eyre!("Invalid fd").with_note(|| format!("fds begin at 0 and end at {}", RawFd::MAX)));
+	Err(eyre!("Invalid fd").with_note(|| format!("fds begin at 0 and end at {}", RawFd::MAX)))
     } else {
 	if_trace!(debug!("closing consumed fd {fd}"));
 	unsafe {
@@ -582,7 +2427,12 @@ fn close_fileno<T: IntoRawFd>(fd: T) -> eyre::Result<()>
 
 fn parse_args() -> eyre::Result<args::Options>
 {
-    args::parse_args()
+    let parsed = args::parse_args();
+    // Only the outermost message of the error is printed when not verbose (see `errors::Dispersed`), so it is the argument error itself instead of a wrapper around it with the argv dump.
+    if !(errors::DEFAULT_USE_ENV && errors::dispersed_env_verbosity().is_verbose()) {
+	return parsed.map_err(eyre::Report::new);
+    }
+    parsed
 	.wrap_err("Parsing arguments failed")
 	.with_section(|| std::env::args_os().skip(1)
 		      .map(|x| std::borrow::Cow::Owned(format!("{x:?}")))
@@ -600,62 +2450,338 @@ fn main() -> errors::DispersedResult<()> {
     feature_check()?;
     if_trace!(debug!("initialised"));
 
-    //TODO: How to cleanly feature-gate `args`?
-    
-    let opt = { cfg_if!{
-	if #[cfg(feature="exec")] {
-	    #[cfg(feature="logging")]
-	    let _span = debug_span!("args");
-	    #[cfg(feature="logging")]
-	    let _in_span = _span.enter();
-	    let parsed = parse_args()?;
-	    if_trace!(debug!("Parsed arguments: {parsed:?}"));
-	    parsed
-	} else {
-	    ()
+    let opt = {
+	#[cfg(feature="logging")]
+	let _span = debug_span!("args");
+	#[cfg(feature="logging")]
+	let _in_span = _span.enter();
+	#[allow(unused_mut)]
+	let mut parsed = parse_args()?;
+	#[cfg(feature="logging")]
+	if parsed.quiet() {
+	    silence_tracing();
 	}
-    } };
+	if_trace!(debug!("Parsed arguments: {parsed:?}"));
+	if !cfg!(feature="exec") && parsed.uses_exec() {
+	    return Err(eyre!("-exec/{{}} is not supported by this build")
+		       .with_suggestion(|| "Build with the `exec` feature enabled (on Linux)")
+		       .into());
+	}
+	if !cfg!(feature="digest") && parsed.digest().is_some() {
+	    return Err(eyre!("`--sha256`/`--crc32` are not supported by this build")
+		       .with_suggestion(|| "Build with the `digest` feature enabled")
+		       .into());
+	}
+	if !cfg!(feature="hugetlb") && parsed.hugetlb().is_some() {
+	    return Err(eyre!("`--hugetlb` is not supported by this build")
+		       .with_suggestion(|| "Build with the `hugetlb` feature enabled (on Linux)")
+		       .into());
+	}
+	#[cfg(feature="exec")]
+	if let Some(fd) = parsed.exec_command_from_fd() {
+	    let mode = exec::command_from_fd(fd)
+		.wrap_err("Failed to read command for `--exec-command-from-fd`")
+		.with_section(move || fd.header("File descriptor was"))?;
+	    parsed.push_exec(mode);
+	}
+	#[cfg(feature="exec")]
+	if parsed.exec_working_fd() {
+	    let _fd = exec::open_working_dir()
+		.wrap_err("Failed to open the working directory for `--exec-working-fd`")?;
+	    if_trace!(debug!("Opened working directory as fd {_fd} for children"));
+	}
+	#[cfg(feature="exec")]
+	if let Some(command) = parsed.exec_on_signal() {
+	    exec::install_signal_hook(command.to_owned())
+		.wrap_err("Failed to install signal handlers for `--exec-on-signal`")
+		.with_section(|| command.to_string_lossy().into_owned().header("Command was"))?;
+	}
+	parsed
+    };
+
+    if let Some(shell) = opt.completions() {
+	use io::Write;
+	let mut stdout = io::stdout().lock();
+	stdout.write_all(completions::generate(shell, "collect").as_bytes())
+	    .and_then(|_| stdout.flush())
+	    .wrap_err("Failed to write completion script to stdout")?;
+	return Ok(());
+    }
+
+    if opt.check() {
+	use io::Write;
+	let mut stdout = io::stdout().lock();
+	#[allow(unused_mut)]
+	let mut problems = 0usize;
+	#[cfg(feature="exec")]
+	for exec in opt.opt_exec() {
+	    let found = exec::check(exec);
+	    if found.is_empty() {
+		writeln!(stdout, "ok: {exec}")
+	    } else {
+		problems += found.len();
+		found.iter().try_for_each(|problem| writeln!(stdout, "error: {exec}: {problem}"))
+	    }.wrap_err("Failed to write check report to stdout")?;
+	}
+	stdout.flush().wrap_err("Failed to write check report to stdout")?;
+	if problems > 0 {
+	    return Err(eyre!("`--check` found {problems} problem(s) with the -exec/{{}} commands")
+		       .with_note(|| "The problems are listed on stdout")
+		       .into());
+	}
+	return Ok(());
+    }
+
+    // With `--output`, `stdout` is not written to at all.
+    let to_stdout = *opt.output() == args::OutputTarget::Stdout;
+    if to_stdout && work::output_is_input(&opt, &io::stdout()) {
+	match opt.on_same_file() {
+	    args::SameFile::Error => return Err(eyre!("The input and stdout are the same file")
+						.with_note(|| "Writing the output may destroy input that has not been read yet")
+						.with_suggestion(|| "Pass `--on-same-file buffer` to collect the whole input before rewriting the file in place")
+						.into()),
+	    args::SameFile::Buffer => {
+		if_trace!(info!("the input and stdout are the same file: rewriting in place after collecting"));
+	    },
+	}
+    }
+
+    if opt.append() && !matches!(opt.output(), args::OutputTarget::File(_)) {
+	return Err(eyre!("`--append` can only be used with `-o/--output <PATH>`")
+		   .with_note(|| "`stdout` and `--output-fd` are not opened by `collect`, so they cannot be opened for appending")
+		   .with_suggestion(|| "Pass `-o <PATH>` to append to a file, or use the shell's `>>` redirection for `stdout`")
+		   .into());
+    }
+
+    if let (Some(tail), Some(range)) = (opt.tail(), opt.output_range()) {
+	return Err(eyre!("`--tail` and `--output-range` cannot be used together")
+		   .with_section(move || tail.header("--tail was"))
+		   .with_section(move || range.header("--output-range was"))
+		   .with_suggestion(|| "`--output-range` counts from the start of the input, drop `--tail` and give the range directly")
+		   .into());
+    }
+
+    if opt.probe() {
+	// Report on the input without reading from it, then exit.
+	use io::Write;
+	let probe = match opt.input() {
+	    args::InputSource::Stdin => sys::Probe::new(&io::stdin()),
+	    args::InputSource::Path(path) => sys::Probe::new(&std::fs::File::open(path)
+							     .wrap_err("Failed to open input file")
+							     .with_section(|| path.display().to_string().header("Path was"))?),
+	};
+	if_trace!(debug!("Probed input: {probe:?}"));
+	let mut stdout = io::stdout().lock();
+	write!(stdout, "{probe}")
+	    .and_then(|_| stdout.flush())
+	    .wrap_err("Failed to write probe report to stdout")?;
+	return Ok(());
+    }
+
+    if opt.dry_run() {
+	// Report what would be done, without reading the input or creating the buffer, then exit.
+	use io::Write;
+	let mut stdout = io::stdout().lock();
+	write_dry_run(&mut stdout, &opt)
+	    .and_then(|_| stdout.flush())
+	    .wrap_err("Failed to write dry-run report to stdout")?;
+	return Ok(());
+    }
+
+    // Opened only once it is known that children will be run (not for `--check` or `--dry-run`, which must not create or truncate it), but while `stdout` is still open: `--exec-stdout /dev/stdout` refers to it.
+    #[cfg(feature="exec")]
+    if let Some(target) = opt.exec_stdout() {
+	exec::open_exec_stdout(target)
+	    .wrap_err("Failed to open `--exec-stdout` for children")
+	    .with_section(|| target.to_string().header("Path was"))?;
+    }
+
+    if let Some(size) = opt.pipe_size() {
+	cfg_if! {
+	    if #[cfg(target_os="linux")] {
+		let stdin = io::stdin();
+		match sys::classify_fd(&stdin) {
+		    Ok(sys::FdKind::Pipe) => {
+			let _set = sys::set_pipe_size(stdin.as_raw_fd(), size)
+			    .wrap_err("Failed to set the capacity of the stdin pipe")
+			    .with_section(move || size.header("Requested capacity was"))
+			    .with_suggestion(|| "Try a smaller `--pipe-size`, the total size of a user's pipes may be limited by `/proc/sys/fs/pipe-user-pages-soft`")?;
+			if_trace!(info!("--pipe-size: stdin pipe capacity set to {_set} bytes (requested {size})"));
+		    },
+		    _kind => {
+			if_trace!(warn!("--pipe-size: stdin is not a pipe ({_kind:?}), ignoring"));
+		    },
+		}
+	    } else {
+		// `F_SETPIPE_SZ` is Linux-only.
+		if_trace!(info!("--pipe-size: the capacity of a pipe cannot be changed on this platform, ignoring {size}"));
+		let _ = size;
+	    }
+	}
+    }
+
+    #[cfg(feature="exec")]
+    if opt.exec_stdin_from() == args::ExecStdinFrom::Input {
+	// The child reads the input itself: nothing is collected or written by us.
+	if let args::InputSource::Path(path) = opt.input() {
+	    return Err(eyre!("`--exec-stdin-from=input` cannot be used with an input file")
+		       .with_section(|| path.display().to_string().header("Input file was"))
+		       .with_suggestion(|| "Give the file to the child directly, or redirect it into `collect`'s stdin")
+		       .into());
+	}
+	let count = opt.opt_exec().len();
+	if count != 1 {
+	    return Err(eyre!("`--exec-stdin-from=input` requires exactly one -exec/{{}}, but {count} were given")
+		       .with_note(|| "The input is not collected, so it can only be read once")
+		       .with_suggestion(|| "Use `--exec-stdin-from=buffer` (the default) to give the collected buffer to every child")
+		       .into());
+	}
+	let exec = opt.clone().take_exec().pop().expect("Checked above");
+	let status = exec::run_on_input(exec, &opt)
+	    .wrap_err("Failed to spawn child on input")?
+	    .wait()
+	    .wrap_err("Failed to wait on child")?;
+	if_trace!(info!("Child on input exited: {status}"));
+	exec::wait_for_signal_hook();
+	if let Some(rc) = status.code().filter(|&rc| rc != 0) {
+	    std::process::exit(rc);
+	}
+	return Ok(());
+    }
 
     //TODO: maybe look into fd SEALing? Maybe we can prevent a consumer process from reading from stdout until we've finished the transfer. The name SEAL sounds like it might have something to do with that?
-    let execfile;
-    cfg_if!{ 
-	if #[cfg(feature="memfile")] {
-	    execfile = work::memfd()
-		.wrap_err("Operation failed").with_note(|| "Stragery was `memfd`")?;
-	} else {
-	    execfile = work::buffered()
-		.wrap_err("Operation failed").with_note(|| "Strategy was `buffered`")?;
+    let strategy = resolve_strategy(&opt);
+    #[cfg(feature="hugetlb")]
+    if opt.hugetlb().is_some() {
+	if strategy != args::Strategy::Memfd || opt.tail().is_some() {
+	    return Err(eyre!("`--hugetlb` can only be used with the `memfd` strategy")
+		       .with_section(move || format!("{strategy:?}").header("Strategy was"))
+		       .with_suggestion(|| "Remove `--strategy buffered` (and `--tail`) to collect into an in-memory file")
+		       .into());
+	}
+	if opt.opt_exec().len() > 0 {
+	    return Err(eyre!("`--hugetlb` cannot be used with -exec/{{}}")
+		       .with_note(|| "The size of a buffer backed by huge pages is always a multiple of the page size, so children would see it padded with zeros")
+		       .into());
 	}
     }
+    // `report.exec_duration` is set once `-exec/{}` children have ran.
+    #[cfg_attr(not(feature="exec"), allow(unused_mut, unused_variables))]
+    let (execfile, mut report): (Option<Box<dyn AsRawFd>>, work::TransferReport) = if let Some(tail) = opt.tail() {
+	let (mode, report) = work::tail(&opt, tail)
+	    .wrap_err("Operation failed").with_note(|| format!("`--tail {tail}` was passed"))?;
+	(mode.get_exec_file().map(|file| Box::new(file) as Box<dyn AsRawFd>), report)
+    } else { match strategy {
+	#[cfg(feature="memfile")]
+	args::Strategy::Memfd => {
+	    let (mode, report) = work::memfd(&opt)
+		.wrap_err("Operation failed").with_note(|| "Stragery was `memfd`")?;
+	    (mode.get_exec_file().map(|file| Box::new(file) as Box<dyn AsRawFd>), report)
+	},
+	#[cfg(feature="memfile")]
+	args::Strategy::Auto => unreachable!("`auto` strategy was not resolved"),
+	args::Strategy::Buffered => {
+	    let (mode, report) = work::buffered(&opt)
+		.wrap_err("Operation failed").with_note(|| "Strategy was `buffered`")?;
+	    (mode.get_exec_file().map(|file| Box::new(file) as Box<dyn AsRawFd>), report)
+	},
+    } };
+    if_trace!(debug!("{report:?}"));
+    let fail_on_empty = opt.fail_on_empty();
+    let stats = opt.stats();
+    let close_stdout = opt.close_stdout();
     // Transfer complete, run exec if enabled
     
     let rc = { cfg_if! {
 	if #[cfg(feature="exec")] {
-	    let rc = if let Some(file) = execfile.get_exec_file() {
-		exec::spawn_from_sync(&file, opt).into_iter().try_fold(0i32, |opt, res| res.map(|x| opt | x.unwrap_or(0)))
+	    // The buffer is still being read by a background thread with `--exec-stdin-keep-open`.
+	    let release = !opt.exec_stdin_keep_open();
+	    // `SIGHUP` stops waiting on the children, and exits with `0`. (`--exec-on-signal` handles it itself instead.)
+	    if execfile.is_some() && opt.opt_exec().len() > 0 && opt.exec_on_signal().is_none() {
+		exec::install_hangup_handler()
+		    .wrap_err("Failed to install `SIGHUP` handler")?;
+	    }
+	    // The exit code of the first child that failed, which `collect` exits with (see `AggregateError::exit_code()`.)
+	    let mut child_code = None;
+	    let exec_start = std::time::Instant::now();
+	    let rc = if let Some(file) = execfile.as_deref() {
+		if opt.exec_output_to_memfd() {
+		    exec::spawn_chain_sync(file, opt).and_then(|(codes, output)| {
+			if let Some(mut output) = output {
+			    // Only the last child's output is written to `stdout`, the rest are consumed by the next child.
+			    let mut stdout = io::stdout().lock();
+			    io::copy(&mut output, &mut stdout)
+				.and_then(|_| io::Write::flush(&mut stdout))
+				.wrap_err("Failed to write output of the last child to stdout")?;
+			}
+			Ok(codes.into_iter().fold(0i32, |opt, x| opt | x.unwrap_or(0)))
+		    })
+		} else {
+		    // All failed children are reported together (or just the first, with `--exec-fail-fast`.)
+		    exec::wait_all(file, opt).map(|()| 0i32).map_err(|errors| {
+			child_code = errors.exit_code();
+			eyre::Report::from(errors)
+		    })
+		}
 	    } else {
 		if_trace!(debug!("there is no file to apply potential -exec/{{}} to"));
 		Ok(0i32)
-	    }.wrap_err("-exec/{} operations failed")?;
+	    }.wrap_err("-exec/{} operations failed");
+	    if execfile.is_some() {
+		report.exec_duration = exec_start.elapsed();
+	    }
+	    // `--stats` are printed before any failed children are reported (which exits.)
+	    if stats {
+		eprintln!("{report}");
+	    }
+	    let rc = match (rc, child_code) {
+		(Ok(rc), _) => rc,
+		// The failures are reported as usual, but with the child's own exit code instead of `1`.
+		(Err(err), Some(code)) => {
+		    let err: errors::Dispersed = err.into();
+		    err.exit_with(code)
+		},
+		(Err(err), None) => return Err(err.into()),
+	    };
+	    if let Some(file) = execfile.filter(|_| release) {
+		// All children have exited: the buffer is not needed any more, so release its memory instead of holding on to it until exit.
+		match sys::release_pages(&*file) {
+		    Ok(_released) => {
+			if_trace!(debug!("released {_released} bytes of buffer memory"));
+		    },
+		    Err(_err) => {
+			if_trace!(debug!("failed to release buffer memory: {_err}"));
+		    },
+		}
+	    }
 	    if_trace!(match rc {
 		0 => trace!("-exec/{{}} operation(s all) returned 0 exit status"),
 		n => error!("-exec/{{}} operation(s) returned non-zero exit code (total: {}) or were killed by signal", n),
 	    });
 	    rc
 	} else {
+	    if stats {
+		eprintln!("{report}");
+	    }
 	    0i32
 	}
     } };
 
+    #[cfg(feature="exec")]
+    exec::wait_for_signal_hook();
+
     // Now that transfer is complete from buffer to `stdout`, close `stdout` pipe before exiting process.
-    if_trace!(info!("Transfer complete, closing `stdout` pipe"));
-    {
+    // (With `--output`, the file was already closed when `execfile` was dropped, and `stdout` was never written to.)
+    // With `--no-close-stdout`, it is left to be closed when the process exits instead.
+    if to_stdout && close_stdout {
+	if_trace!(info!("Transfer complete, closing `stdout` pipe"));
 	let stdout_fd = libc::STDOUT_FILENO; // (io::Stdout does not impl `IntoRawFd`, just use the raw fd directly; using the constant from libc may help in weird cases where STDOUT_FILENO is not 1...)
 	debug_assert_eq!(stdout_fd, std::io::stdout().as_raw_fd(), "STDOUT_FILENO and io::stdout().as_raw_fd() are not returning the same value.");
 	close_fileno(/*std::io::stdout().as_raw_fd()*/ stdout_fd) // SAFETY: We just assume fd 1 is still open. If it's not (i.e. already been closed), this will return error. 
             .with_section(move || stdout_fd.header("Attempted to close this fd (STDOUT_FILENO)"))
             .with_warning(|| format!("It is possible fd {} (STDOUT_FILENO) has already been closed; if so, look for where that happens and prevent it. `stdout` should be closed here.", stdout_fd).header("Possible bug"))
-    }.wrap_err(eyre!("Failed to close stdout"))?;
+	    .wrap_err(eyre!("Failed to close stdout"))?;
+    }
 
     if rc != 0 {
 	if cfg!(feature="exec") {
@@ -663,6 +2789,67 @@ fn main() -> errors::DispersedResult<()> {
 	}
 	std::process::exit(rc);
     }
+
+    if let Some(code) = fail_on_empty.filter(|_| report.collected == 0) {
+	if_trace!(info!("--fail-on-empty: no input was collected, exiting with {code}"));
+	std::process::exit(code);
+    }
     
     Ok(())
 }
+
+#[cfg(all(test, feature="memfile"))]
+mod tests
+{
+    use super::*;
+    use args::Strategy;
+    const GIB: u64 = 1024 * 1024 * 1024;
+    
+    #[test]
+    fn auto_strategy_small_file()
+    {
+	let (strategy, _) = choose_strategy(NonZeroUsize::new(4096), Some(sys::FdKind::Regular), Some(GIB), false);
+	assert_eq!(strategy, Strategy::Buffered);
+    }
+
+    #[test]
+    fn auto_strategy_large_file()
+    {
+	let (strategy, _) = choose_strategy(NonZeroUsize::new(64 * 1024 * 1024), Some(sys::FdKind::Regular), Some(GIB), false);
+	assert_eq!(strategy, Strategy::Memfd);
+	let (strategy, _) = choose_strategy(NonZeroUsize::new(64 * 1024 * 1024), Some(sys::FdKind::Regular), None, false);
+	assert_eq!(strategy, Strategy::Memfd, "Unknown memory is assumed to fit");
+    }
+
+    #[test]
+    fn auto_strategy_memory()
+    {
+	let size = NonZeroUsize::new(64 * 1024 * 1024);
+	let (high, _) = choose_strategy(size, Some(sys::FdKind::Regular), Some(GIB), false);
+	let (low, _) = choose_strategy(size, Some(sys::FdKind::Regular), Some(96 * 1024 * 1024), false);
+	assert_eq!((high, low), (Strategy::Memfd, Strategy::Buffered), "The same large file should not fit in less memory");
+	// Small inputs are always buffered.
+	let (strategy, _) = choose_strategy(NonZeroUsize::new(4096), Some(sys::FdKind::Regular), Some(4096), false);
+	assert_eq!(strategy, Strategy::Buffered);
+    }
+
+    #[test]
+    fn auto_strategy_pipe()
+    {
+	let (strategy, _) = choose_strategy(None, Some(sys::FdKind::Pipe), Some(1024 * 1024), false);
+	assert_eq!(strategy, Strategy::Memfd);
+	// A pipe's `st_size` is not its size.
+	let (strategy, _) = choose_strategy(NonZeroUsize::new(10), Some(sys::FdKind::Pipe), Some(GIB), false);
+	assert_eq!(strategy, Strategy::Memfd);
+    }
+
+    #[test]
+    fn auto_strategy_exec()
+    {
+	let (strategy, _) = choose_strategy(NonZeroUsize::new(4096), Some(sys::FdKind::Regular), Some(GIB), true);
+	assert_eq!(strategy, Strategy::Memfd);
+	// The children still need a file, however little memory there is.
+	let (strategy, _) = choose_strategy(NonZeroUsize::new(64 * 1024 * 1024), Some(sys::FdKind::Regular), Some(1024 * 1024), true);
+	assert_eq!(strategy, Strategy::Memfd);
+    }
+}
@@ -1,27 +1,44 @@
 //! Memory file handling
 use super::*;
-use std::os::unix::io::*;
 use std::{
     mem,
     ops,
     fs,
     io,
     path::Path,
-    borrow::{
-	Borrow,
-	BorrowMut,
-    },
+    borrow::Borrow,
 };
 
 pub mod fd;
 pub mod error;
-mod map;
+pub mod map;
 #[cfg(feature="hugetlb")] 
-mod hp;
+pub mod hp;
 
 
 /// Flags passed to `memfd_create()` when used in this module
-const MEMFD_CREATE_FLAGS: libc::c_uint = libc::MFD_CLOEXEC;
+///
+/// `MFD_ALLOW_SEALING` is needed for the buffer's size (or more, see `--exec-input-seal-level`) to be sealed before it is given to `-exec/{}` children.
+const MEMFD_CREATE_FLAGS: libc::c_uint = libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING;
+
+/// The maximum length (in bytes, not including the nul-terminator) of a name passed to `memfd_create()`.
+///
+/// The kernel prefixes the name with `memfd:`, and the result must fit in `NAME_MAX` (255) bytes. Longer names fail with `EINVAL`.
+pub const MEMFD_NAME_MAX: usize = 249;
+
+/// Truncate `name` to at most `MEMFD_NAME_MAX` bytes, on a `char` boundary.
+#[inline] 
+pub fn truncate_name(name: &str) -> &str
+{
+    if name.len() <= MEMFD_NAME_MAX {
+	return name;
+    }
+    let mut len = MEMFD_NAME_MAX;
+    while !name.is_char_boundary(len) {
+	len -= 1;
+    }
+    &name[..len]
+}
 
 #[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(transparent)]
@@ -43,12 +60,12 @@ pub fn stream_len(from: &(impl AsRawFd + ?Sized)) -> io::Result<u64>
     }
 }
 
-/// Create an in-memory `File`, with an optional name
+/// Create an in-memory `File`, with an optional name, passing `flags` to `memfd_create()` along with the default ones (e.g. a `hp::Mask` for huge pages, or `0`.)
 #[cfg_attr(feature="logging", instrument(level="info", err))]
-pub fn create_memfile(name: Option<&str>, size: usize) -> eyre::Result<fs::File>
+pub fn create_memfile(name: Option<&str>, size: usize, flags: libc::c_uint) -> eyre::Result<fs::File>
 {
     if_trace!(debug!("Attempting to allocate {size} bytes of contiguous physical memory for memory file named {:?}", name.unwrap_or("<unbound>")));
-    RawFile::open_mem(name, size).map(Into::into)
+    RawFile::open_mem_with_flags(name, size, flags).map(Into::into)
 	.wrap_err(eyre!("Failed to open in-memory file")
 		  .with_section(move || format!("{:?}", name).header("Proposed name"))
 		  .with_section(|| size.header("Requested physical memory buffer size")))
@@ -146,9 +163,9 @@ impl RawFile
     ///
     /// # Note
     /// If `T` is a buffered container (e.g. `std::io::BufWriter<T: AsRawFd>`), make sure the buffer is flushed *before* calling this method on it, or the buffered data will be lost.
-    #[cfg_attr(feature="logging", instrument(err, skip(other), fields(other = ?other.as_raw_fd())))]
-    pub fn try_link_to<'o, T: ?Sized>(&self, other: &'o mut T) -> Result<&'o mut T, error::DuplicateError>
-    where T: AsRawFd
+    #[cfg_attr(feature="logging", instrument(skip(other), fields(other = ?other.as_raw_fd())))]
+    pub fn try_link_to<'o, T>(&self, other: &'o mut T) -> Result<&'o mut T, error::DuplicateError>
+    where T: ?Sized + AsRawFd
     {
 	if unsafe {
 	    libc::dup2(self.0.get(), other.as_raw_fd())
@@ -166,8 +183,8 @@ impl RawFile
     /// # Note
     /// After this call succeeds, writing to `self` will have the same effect of writing directly to `other`'s contained file descriptor. If `other` is a buffered stream, you must ensure that `other` has been flushed *before* writing anything to `self`.
     #[cfg_attr(feature="logging", instrument(err, skip(other), fields(other = ?other.as_raw_fd())))]
-    pub fn try_link_from<'i, T: ?Sized>(&mut self, other: &'i T) -> Result<&'i T, error::DuplicateError>
-    where T: AsRawFd
+    pub fn try_link_from<'i, T>(&mut self, other: &'i T) -> Result<&'i T, error::DuplicateError>
+    where T: ?Sized + AsRawFd
     {
 	if unsafe {
 	    libc::dup2(other.as_raw_fd(), self.0.get())
@@ -187,8 +204,8 @@ impl RawFile
     /// This is a panicking version of `try_link_from()`. See that function for more information on how to safely use `self` after this call.
     #[inline]
 	#[cfg_attr(feature="logging", instrument(skip_all))]
-    pub fn link_from<'i, T: ?Sized>(&mut self, other: &'i T) -> &'i T
-    where T: AsRawFd
+    pub fn link_from<'i, T>(&mut self, other: &'i T) -> &'i T
+    where T: ?Sized + AsRawFd
     {
 	self.try_link_from(other).expect("failed to duplicate file descriptor from another container")
     }
@@ -202,21 +219,20 @@ impl RawFile
     /// This is a panicking version of `try_link_to()`. See that function for more information on how to safely use `self` after this call.
     #[inline]
 	#[cfg_attr(feature="logging", instrument(skip_all))]
-    pub fn link_to<'o, T: ?Sized>(&self, other: &'o mut T) -> &'o mut T
-    where T: AsRawFd
+    pub fn link_to<'o, T>(&self, other: &'o mut T) -> &'o mut T
+    where T: ?Sized + AsRawFd
     {
 	self.try_link_to(other).expect("failed to duplicate file descriptor into another container")
     }
     
     /// Attempt to duplicate this raw file
+    ///
+    /// The duplicate has `O_CLOEXEC` set (see `fd::RawFileDescriptor::dup_cloexec()`.)
     #[cfg_attr(feature="logging", instrument(err))]
     pub fn try_clone(&self) -> Result<Self, error::DuplicateError>
     {
-	match unsafe { libc::dup(self.0.get()) }
-	{
-	    -1 => Err(error::DuplicateError::new_dup(self)),
-	    fd => Ok(Self::take_ownership_of_unchecked(fd))
-	}
+	self.0.dup_cloexec()
+	    .map_err(|err| error::DuplicateError::new(self, error::DuplicateKind::Duplicate, err))
     }
 
     /// Consume a managed file into a raw file, attempting to synchronise it first.
@@ -272,6 +288,23 @@ impl RawFile
 	}
     }
 
+    /// Create a new pipe (`pipe2()` with `O_CLOEXEC`.)
+    ///
+    /// # Returns
+    /// The read end and the write end of the pipe, in that order.
+    #[cfg_attr(feature="logging", instrument(level="debug", err))]
+    pub fn pipe() -> io::Result<(Self, Self)>
+    {
+	let mut fds: [RawFd; 2] = [-1; 2];
+	match unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } {
+	    -1 => Err(io::Error::last_os_error()),
+	    _ => {
+		if_trace!(trace!("created pipe (read: {}, write: {})", fds[0], fds[1]));
+		Ok((Self::take_ownership_of_unchecked(fds[0]), Self::take_ownership_of_unchecked(fds[1])))
+	    },
+	}
+    }
+
     /// Attempt to open a new raw file with these options
     #[inline] 
     pub fn open(path: impl AsRef<Path>, opt: impl Borrow<fs::OpenOptions>) -> io::Result<Self>
@@ -316,12 +349,87 @@ impl RawFile
 	}
     }
 
+    /// Read from the file at `offset` into `buf` (`pread()`), without changing the file's offset.
+    ///
+    /// Returns the number of bytes read, which is `0` at or past the end of the file.
+    #[cfg_attr(feature="logging", instrument(level="trace", skip(buf), fields(buf = ?buf.len()), err))]
+    #[inline] 
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>
+    {
+	let offset: libc::off_t = offset.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Offset larger than max offset size"))?;
+	retry_interrupted(|| unsafe {
+	    libc::pread(self.0.get(), buf.as_mut_ptr() as *mut _, buf.len(), offset)
+	})
+    }
+
+    /// Write all of `buf` to the file at `offset` (`pwrite()`), without changing the file's offset.
+    ///
+    /// The file is extended if `offset + buf.len()` is past its end.
+    #[cfg_attr(feature="logging", instrument(level="trace", skip(buf), fields(buf = ?buf.len()), err))]
+    pub fn write_all_at(&self, mut buf: &[u8], mut offset: u64) -> io::Result<()>
+    {
+	while !buf.is_empty() {
+	    let at: libc::off_t = offset.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Offset larger than max offset size"))?;
+	    match unsafe {
+		libc::pwrite(self.0.get(), buf.as_ptr() as *const _, buf.len(), at)
+	    } {
+		-1 => match io::Error::last_os_error() {
+		    err if err.kind() == io::ErrorKind::Interrupted => continue,
+		    err => return Err(err),
+		},
+		0 => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")),
+		wr => {
+		    buf = &buf[(wr as usize)..];
+		    offset += wr as u64;
+		},
+	    }
+	}
+	Ok(())
+    }
+
+    /// Allocate `len` bytes for a newly created memory file using `allocate` (which is `allocate_size()` for `open_mem()`.)
+    ///
+    /// # Errors
+    /// * If `allocate` is not supported for this file (`EOPNOTSUPP`, e.g. on `hugetlbfs`), this falls back to `truncate_size()`.
+    /// * If there is not enough memory for the allocation (`ENOSPC`), the returned error's `is_out_of_memory()` will be `true`.
+    #[cfg_attr(feature="logging", instrument(level="debug", skip(allocate), err))]
+    fn allocate_mem_with<A>(&mut self, len: usize, allocate: A) -> Result<(), error::MemfileError>
+    where A: FnOnce(&mut Self, u64) -> io::Result<()>
+    {
+	use error::{
+	    MemfileError,
+	    MemfileCreationStep::*,
+	};
+	match allocate(self, len as u64) {
+	    Ok(()) => Ok(()),
+	    Err(err) if err.raw_os_error() == Some(libc::EOPNOTSUPP) => {
+		if_trace!(warn!("fallocate() is not supported for memory file {}, falling back to ftruncate()", self.0));
+		self.truncate_size(len as u64)
+		    .map_err(|err| MemfileError::new(Truncate(self.fileno().clone(), len), err))
+	    },
+	    Err(err) => {
+		if_trace!(warn!("systemcall failed: {err}"));
+		Err(MemfileError::new(Allocate(Some(self.fileno().clone()), len), err))
+	    },
+	}
+    }
+
     /// Open a new in-memory (W+R) file with an optional name and a fixed size.
-    #[cfg_attr(feature="logging", instrument(level="debug", skip_all, err))]
+    #[inline] 
     pub fn open_mem(name: Option<&str>, len: usize) -> Result<Self, error::MemfileError>
+    {
+	Self::open_mem_with_flags(name, len, 0)
+    }
+
+    /// Open a new in-memory (W+R) file with an optional name and a fixed size, passing `flags` to `memfd_create()` along with `MEMFD_CREATE_FLAGS`.
+    ///
+    /// # Huge pages
+    /// If `flags` is a huge page mask (`hp::Mask::mask()`), the file cannot be written to with `write()` (only through a mapping), and its size must be a multiple of the page size.
+    #[cfg_attr(feature="logging", instrument(level="debug", skip_all, err))]
+    pub fn open_mem_with_flags(name: Option<&str>, len: usize, flags: libc::c_uint) -> Result<Self, error::MemfileError>
     {
 	use std::{
-	    ffi::CString,
+	    ffi::{CString, CStr},
 	    borrow::Cow,
 	};
 	lazy_static! {
@@ -330,16 +438,22 @@ impl RawFile
 
 	use libc::{
 	    memfd_create,
-	    fallocate,
 	};
 	use error::MemfileCreationStep::*;
+	let flags = MEMFD_CREATE_FLAGS | flags;
+
+	if let Some(name) = name.filter(|name| name.len() > MEMFD_NAME_MAX) {
+	    if_trace!(error!("name is {} bytes long, the maximum is {MEMFD_NAME_MAX}", name.len()));
+	    return Err(error::MemfileError::new(Create(Some(name.to_owned()), flags), io::Error::from_raw_os_error(libc::EINVAL)));
+	}
 
-	let bname: Cow<CString> = match name {
-	    Some(s) => Cow::Owned(CString::new(Vec::from(s)).expect("Invalid name")),
-	    None => Cow::Borrowed(&DEFAULT_NAME),
+	let bname: Cow<CStr> = match name {
+	    Some(s) => Cow::Owned(CString::new(Vec::from(s))
+				   .map_err(|_| error::MemfileError::new(Create(Some(s.to_owned()), flags), io::Error::from_raw_os_error(libc::EINVAL)))?),
+	    None => Cow::Borrowed(DEFAULT_NAME.as_c_str()),
 	};
 
-	let bname = bname.as_bytes_with_nul();
+	let bname = bname.to_bytes_with_nul();
 	if_trace!(trace!("created nul-terminated buffer for name `{:?}': ({})", std::str::from_utf8(bname), bname.len()));
 	
 	macro_rules! attempt_call
@@ -358,7 +472,7 @@ impl RawFile
 	    }
 	}
 	
-	let fd = attempt_call!(-1, memfd_create(bname.as_ptr() as *const _, MEMFD_CREATE_FLAGS), Create(name.map(str::to_owned), MEMFD_CREATE_FLAGS))
+	let mut fd = attempt_call!(-1, memfd_create(bname.as_ptr() as *const _, flags), Create(name.map(str::to_owned), flags))
 	    .map(Self::take_ownership_of_unchecked)?; // Ensures `fd` is dropped if any subsequent calls fail
 
 	#[cfg(feature="logging")] 
@@ -368,10 +482,9 @@ impl RawFile
 	    let _span = using_memfile.enter();
 	    
 	    if len > 0 {
-		attempt_call!(-1
-			      , fallocate(fd.0.get(), 0, 0, len.try_into()
-					  .map_err(|_| Allocate(None, len))?)
-			      , Allocate(Some(fd.fileno().clone()), len))?;
+		let _: libc::off_t = len.try_into()
+		    .map_err(|_| Allocate(None, len))?;
+		fd.allocate_mem_with(len, |file, size| file.allocate_size(size))?;
 		if cfg!(debug_assertions) {
 		    if_trace!(trace!("Allocated {len} bytes to memory buffer"));
 		    let seeked;
@@ -393,16 +506,33 @@ impl RawFile
 
 
 
+/// Make a `read()`/`write()`-like syscall with `call`, again for as long as it fails with `EINTR` (e.g. because a signal handler ran while it was blocked.)
+///
+/// # Returns
+/// The non-negative result of the call, or the error it failed with otherwise.
+#[inline] 
+fn retry_interrupted(mut call: impl FnMut() -> isize) -> io::Result<usize>
+{
+    loop {
+	match call() {
+	    -1 => match io::Error::last_os_error() {
+		err if err.kind() == io::ErrorKind::Interrupted => {
+		    if_trace!(trace!("syscall was interrupted, retrying"));
+		},
+		err => return Err(err),
+	    },
+	    wr => return Ok(wr as usize),
+	}
+    }
+}
+
 impl io::Write for RawFile
 {
     #[inline] 
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-	match unsafe {
+	retry_interrupted(|| unsafe {
 	    libc::write(self.0.get(), buf.as_ptr() as *const _, buf.len())
-	}  {
-	    -1 =>  Err(io::Error::last_os_error()),
-	    wr => Ok(wr as usize)
-	}
+	})
     }
     #[inline] 
     fn flush(&mut self) -> io::Result<()> {
@@ -413,12 +543,9 @@ impl io::Write for RawFile
     #[inline] 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
 	// SAFETY: IoSlice is guaranteed to be ABI-compatible with `struct iovec`
-	match unsafe {
+	retry_interrupted(|| unsafe {
 	    libc::writev(self.0.get(), bufs.as_ptr() as *const _, bufs.len() as i32)
-	} {
-	    -1 =>  Err(io::Error::last_os_error()),
-	    wr => Ok(wr as usize)
-	}
+	})
     }
 }
 
@@ -426,23 +553,17 @@ impl io::Read for RawFile
 {
     #[inline] 
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-	match unsafe {
+	retry_interrupted(|| unsafe {
 	    libc::read(self.0.get(), buf.as_mut_ptr() as *mut _, buf.len())
-	} {
-	    -1 =>  Err(io::Error::last_os_error()),
-	    wr => Ok(wr as usize)
-	}
+	})
     }
     
     #[inline] 
     fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
 	// SAFETY: IoSlice is guaranteed to be ABI-compatible with `struct iovec`
-	match unsafe {
+	retry_interrupted(|| unsafe {
 	    libc::readv(self.0.get(), bufs.as_mut_ptr() as *mut _, bufs.len() as i32)
-	} {
-	    -1 =>  Err(io::Error::last_os_error()),
-	    wr => Ok(wr as usize)
-	}
+	})
     }
 }
 
@@ -500,6 +621,35 @@ impl IntoRawFd for RawFile
     }
 }
 
+impl AsFd for RawFile
+{
+    #[inline] 
+    fn as_fd(&self) -> BorrowedFd<'_> {
+	// SAFETY: The fd is owned by `self`, so it stays open for as long as it is borrowed.
+	unsafe {
+	    BorrowedFd::borrow_raw(self.0.get())
+	}
+    }
+}
+
+impl From<OwnedFd> for RawFile
+{
+    #[inline] 
+    fn from(from: OwnedFd) -> Self
+    {
+	Self::from_file(from)
+    }
+}
+
+impl From<RawFile> for OwnedFd
+{
+    #[inline] 
+    fn from(from: RawFile) -> Self
+    {
+	from.into_file()
+    }
+}
+
 #[cfg(test)]
 mod tests
 {
@@ -523,7 +673,268 @@ mod tests
 	};
 
 	assert_eq!(v.len(), STRING.len(), "Invalid read size.");
-	assert_eq!(&v[..], &STRING[..], "Invalid read data.");
+	assert_eq!(&v[..], STRING, "Invalid read data.");
+	Ok(())
+    }
+
+    #[test]
+    fn positional_io() -> eyre::Result<()>
+    {
+	use std::io::*;
+	const STRING: &[u8] = b"Hello world!";
+	let mut file = RawFile::open_mem(None, 0)?;
+	file.write_all(b"head")?;
+	let offset = |file: &RawFile| unsafe { libc::lseek(file.as_raw_fd(), 0, libc::SEEK_CUR) };
+
+	file.write_all_at(STRING, 4096)?;
+	assert_eq!(stream_len(&file)?, 4096 + STRING.len() as u64, "File was not extended by the positional write");
+	assert_eq!(offset(&file), 4, "Positional write changed the file offset");
+
+	let mut buf = vec![0; STRING.len()];
+	assert_eq!(file.read_at(&mut buf[..], 4096)?, STRING.len(), "Invalid read size");
+	assert_eq!(&buf[..], STRING, "Invalid read data");
+	assert_eq!(file.read_at(&mut buf[..], 4096 + STRING.len() as u64)?, 0, "Read past the end of the file");
+	assert_eq!(offset(&file), 4, "Positional read changed the file offset");
+	Ok(())
+    }
+
+    #[test]
+    fn pipe() -> eyre::Result<()>
+    {
+	use std::io::*;
+	const STRING: &[u8] = b"Hello world!";
+	let (mut read, mut write) = RawFile::pipe()?;
+	for end in [&read, &write] {
+	    let flags = unsafe { libc::fcntl(end.as_raw_fd(), libc::F_GETFD) };
+	    assert!(flags != -1 && flags & libc::FD_CLOEXEC != 0, "Pipe end {} is not close-on-exec", end.as_raw_fd());
+	}
+	write.write_all(STRING)?;
+	drop(write);
+
+	let mut buf = Vec::new();
+	read.read_to_end(&mut buf)?;
+	assert_eq!(&buf[..], STRING, "Invalid data read from the pipe");
+	Ok(())
+    }
+
+    #[test]
+    fn allocate_unsupported_falls_back_to_truncate() -> eyre::Result<()>
+    {
+	const SIZE: usize = 4096 * 3;
+	let mut file = RawFile::open_mem(None, 0)?;
+	file.allocate_mem_with(SIZE, |_, _| Err(io::Error::from_raw_os_error(libc::EOPNOTSUPP)))?;
+	assert_eq!(stream_len(&file)?, SIZE as u64, "Fallback did not set the correct size.");
+	Ok(())
+    }
+
+    #[test]
+    fn allocate_no_space_is_out_of_memory()
+    {
+	let mut file = RawFile::open_mem(None, 0).expect("Failed to open memfile");
+	let err = file.allocate_mem_with(4096, |_, _| Err(io::Error::from_raw_os_error(libc::ENOSPC)))
+	    .expect_err("Allocation failure was ignored");
+	assert!(err.is_out_of_memory(), "ENOSPC was not reported as out of memory");
+	assert!(err.to_string().contains("out of memory"), "Error message does not mention memory: {err}");
+    }
+
+    #[test]
+    fn name_too_long()
+    {
+	let name = "x".repeat(MEMFD_NAME_MAX + 1);
+	let err = RawFile::open_mem(Some(&name[..]), 0).expect_err("Over-long name was accepted");
+	assert!(err.is_name_too_long() && err.is_invalid_name(), "Over-long name was not reported as such: {err}");
+	assert!(err.to_string().contains(&format!("maximum is {MEMFD_NAME_MAX} bytes")), "Error message does not mention the limit: {err}");
+
+	RawFile::open_mem(Some(truncate_name(&name[..])), 0).expect("Truncated name was not accepted");
+	RawFile::open_mem(Some(&name[..MEMFD_NAME_MAX]), 0).expect("Name of exactly `MEMFD_NAME_MAX` bytes was not accepted");
+    }
+
+    #[test]
+    fn name_of_300_bytes()
+    {
+	let name = "n".repeat(300);
+	let err = RawFile::open_mem(Some(&name[..]), 0).expect_err("300 byte name was accepted");
+	assert!(err.is_name_too_long(), "300 byte name was not reported as too long: {err}");
+	assert!(err.to_string().contains("name is 300 bytes long, but the maximum is 249 bytes"), "Error message does not give the length and the limit: {err}");
+	assert_eq!(truncate_name(&name[..]).len(), MEMFD_NAME_MAX, "300 byte name was not truncated to the limit");
+	RawFile::open_mem(Some(truncate_name(&name[..])), 0).expect("Truncated 300 byte name was not accepted");
+    }
+
+    #[test]
+    fn name_with_nul()
+    {
+	let err = RawFile::open_mem(Some("bad\0name"), 0).expect_err("Name with a nul byte was accepted");
+	assert!(matches!(err.step(), error::MemfileCreationStep::Create(Some(name), _) if name == "bad\0name"), "Name with a nul byte failed at the wrong step: {err}");
+	assert!(err.is_name_with_nul() && err.is_invalid_name(), "Name with a nul byte was not reported as such: {err}");
+	assert!(!err.is_name_too_long(), "Name with a nul byte was reported as too long: {err}");
+	assert!(err.to_string().contains("contains a nul byte"), "Error message does not mention the nul byte: {err}");
+    }
+
+    #[test]
+    fn map_shared_and_private() -> eyre::Result<()>
+    {
+	use std::io::*;
+	use map::MapProtection;
+	const STRING: &[u8] = b"Hello world!";
+	let mut file = RawFile::open_mem(None, 0)?;
+	file.write_all(STRING)?;
+
+	let region = file.map(MapProtection::READ, false, None)?;
+	assert_eq!(&region[..], STRING, "Mapping of the whole file has invalid data");
+
+	// Writes through a shared mapping are written to the file, but not those through a private one.
+	let mut shared = file.map(MapProtection::READ | MapProtection::WRITE, true, Some(5))?;
+	shared.copy_from_slice(b"HELLO");
+	let mut private = file.map(MapProtection::READ | MapProtection::WRITE, false, Some(STRING.len()))?;
+	private[6..].copy_from_slice(b"WORLD!");
+	drop((shared, private));
+
+	let mut buf = vec![0; STRING.len()];
+	assert_eq!(file.read_at(&mut buf[..], 0)?, STRING.len(), "Invalid read size");
+	assert_eq!(&buf[..], b"HELLO world!", "Writes through the mappings were not (or wrongly) written to the file");
+	Ok(())
+    }
+
+    #[test]
+    fn map_at_offset_and_sync() -> eyre::Result<()>
+    {
+	use map::MapProtection;
+	let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+	let file = RawFile::open_mem(None, page * 2)?;
+	file.write_all_at(b"second page", page as u64)?;
+
+	let mut region = map::map_fd(&file, MapProtection::READ | MapProtection::WRITE, true, page as u64, None)?;
+	assert_eq!(region.len(), page, "Mapping from an offset should reach the end of the file");
+	assert_eq!(&region[..11], b"second page", "Mapping from an offset has invalid data");
+	region[..6].copy_from_slice(b"SECOND");
+	region.sync()?;
+	drop(region);
+
+	let mut buf = [0u8; 11];
+	file.read_at(&mut buf[..], page as u64)?;
+	assert_eq!(&buf[..], b"SECOND page", "Writes through the mapping were not written to the file");
+	Ok(())
+    }
+
+    #[test]
+    fn map_empty_and_invalid()
+    {
+	use map::MapProtection;
+	let file = RawFile::open_mem(None, 0).expect("Failed to open memfile");
+	let region = file.map(MapProtection::READ, false, None).expect("Failed to map empty file");
+	assert!(region.is_empty(), "Mapping of an empty file is not empty");
+
+	// Not a file that can be mapped.
+	let (sock, _peer) = std::os::unix::net::UnixStream::pair().expect("Failed to create socket pair");
+	let sock = RawFile::from_file(sock);
+	let err = sock.map(MapProtection::READ, false, Some(4096)).expect_err("Mapped a socket");
+	assert!(matches!(err.step(), error::MemfileCreationStep::Map { size: 4096, .. }), "Wrong step in error: {err}");
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_not_writable()
+    {
+	use map::MapProtection;
+	let file = RawFile::open_mem(None, 4096).expect("Failed to open memfile");
+	let mut region = file.map(MapProtection::READ, true, None).expect("Failed to map file");
+	region[0] = 1;
+    }
+
+    #[test]
+    fn owned_fd_round_trip() -> eyre::Result<()>
+    {
+	use std::io::{Read, Write};
+	let (read, mut write) = RawFile::pipe()?;
+	let fd = read.as_raw_fd();
+	let owned = OwnedFd::from(read);
+	assert_eq!(owned.as_raw_fd(), fd, "Converting into `OwnedFd` changed the fd");
+	let mut read = RawFile::from(owned);
+	assert_eq!(read.as_fd().as_raw_fd(), fd, "Converting back from `OwnedFd` changed the fd");
+
+	// A duplicate made through the borrowed fd refers to the same pipe.
+	let mut dup = RawFile::from(write.as_fd().try_clone_to_owned()?);
+	assert_ne!(dup.as_raw_fd(), write.as_raw_fd());
+	write.write_all(b"hello ")?;
+	dup.write_all(b"world")?;
+	drop((write, dup));
+	let mut buffer = String::new();
+	read.read_to_string(&mut buffer)?;
+	assert_eq!(buffer, "hello world");
+	Ok(())
+    }
+
+    #[test]
+    fn interrupted_write_retried() -> eyre::Result<()>
+    {
+	use std::{
+	    io::{Read, Write},
+	    thread,
+	    time::Duration,
+	};
+	extern "C" fn ignore(_: libc::c_int) {}
+	// Without `SA_RESTART`, a blocked `write()` fails with `EINTR` once the handler has run.
+	unsafe {
+	    let mut action: libc::sigaction = mem::zeroed();
+	    action.sa_sigaction = ignore as *const () as usize;
+	    libc::sigemptyset(&mut action.sa_mask);
+	    if libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()) != 0 {
+		return Err(io::Error::last_os_error().into());
+	    }
+	}
+	let input: Vec<u8> = (0..1024 * 1024u32).map(|i| (i % 253) as u8).collect();
+	let (mut read, mut write) = RawFile::pipe()?;
+	let writer = unsafe { libc::pthread_self() } as usize;
+	// The pipe is drained slowly, so the writer is (almost) always blocked on it when it is signalled.
+	let reader = thread::spawn(move || -> io::Result<Vec<u8>> {
+	    let mut output = Vec::new();
+	    let mut buffer = [0u8; 4096];
+	    loop {
+		unsafe { libc::pthread_kill(writer as libc::pthread_t, libc::SIGUSR1) };
+		thread::sleep(Duration::from_millis(1));
+		match read.read(&mut buffer[..])? {
+		    0 => break Ok(output),
+		    n => output.extend_from_slice(&buffer[..n]),
+		}
+	    }
+	});
+	// `write_all()` retries `EINTR` itself, so `write()` is called directly.
+	let mut written = 0;
+	while written < input.len() {
+	    written += write.write(&input[written..]).wrap_err("Interrupted `write()` was not retried")?;
+	}
+	drop(write);
+	let output = reader.join().expect("Pipe reader panicked")?;
+	assert!(output == input, "Output data mismatch");
 	Ok(())
     }
+
+    #[test]
+    fn duplicates_are_cloexec() -> eyre::Result<()>
+    {
+	let file = RawFile::open_mem(None, 0)?;
+	let dups = [file.fileno().dup_cloexec()?, file.try_clone()?];
+	for dup in &dups {
+	    let flags = unsafe { libc::fcntl(dup.as_raw_fd(), libc::F_GETFD) };
+	    assert!(flags >= 0 && flags & libc::FD_CLOEXEC != 0, "Duplicate fd {} is not `O_CLOEXEC`", dup.as_raw_fd());
+	}
+	// `std::process::Command` does not close any fds itself, so only `O_CLOEXEC` keeps them from the child.
+	let output = std::process::Command::new("ls").arg("/proc/self/fd").output()?;
+	assert!(output.status.success(), "Child failed");
+	let inherited: Vec<RawFd> = String::from_utf8_lossy(&output.stdout[..]).split_whitespace().map(|fd| fd.parse().expect("invalid fd")).collect();
+	for dup in &dups {
+	    assert!(!inherited.contains(&dup.as_raw_fd()), "Duplicate fd {} was inherited by the child: {inherited:?}", dup.as_raw_fd());
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn truncate_name_on_char_boundary()
+    {
+	let name = "\u{e9}".repeat(MEMFD_NAME_MAX); // 2 bytes each
+	let short = truncate_name(&name[..]);
+	assert!(short.len() <= MEMFD_NAME_MAX, "Name was not truncated");
+	assert_eq!(short.len(), MEMFD_NAME_MAX - 1, "Name was truncated more than needed");
+	assert_eq!(truncate_name("collect-buffer"), "collect-buffer");
+    }
 }
@@ -123,6 +123,8 @@ pub enum MemfileCreationStep
     Create(Option<String>, libc::c_uint),
     /// `fallocate()` call
     Allocate(Option<fd::RawFileDescriptor>, usize),
+    /// `ftruncate()` call (fallback for when `fallocate()` is not supported)
+    Truncate(fd::RawFileDescriptor, usize),
     /// `mmap()` call
     Map {
 	addr: usize,
@@ -151,6 +153,7 @@ impl fmt::Display for MemfileCreationStep
 	    Self::Create(Some(name), flag) => write!(f, "memfd_create({name}, {flag})"),
 	    Self::Allocate(None, size) => write!(f, "checked_cast<off_t>({size})"),
 	    Self::Allocate(Some(fd), size) => write!(f, "fallocate({fd}, 0, 0, {size})"),
+	    Self::Truncate(fd, size) => write!(f, "ftruncate({fd}, {size})"),
 	    Self::Map{ addr: 0, size, prot, flags, fd: Some(fd), offset } => write!(f, "mmap(NULL, {size}, {prot:?}, {flags}, {fd}, {offset})"),
 	    Self::Map{ addr: 0, size, prot, flags, fd: None, offset } => write!(f, "mmap(NULL, {size}, {prot:?}, {flags}, -1, {offset})"),
 	    Self::Map{ addr, size, prot, flags, fd: Some(fd), offset } => write!(f, "mmap(0x{addr:x}, {size}, {prot:?}, {flags}, {fd}, {offset})"),
@@ -171,12 +174,61 @@ impl fmt::Display for MemfileError
     #[inline] 
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
     {
-	write!(f, "failed to create in-memory file: `{}` failed", self.step)
+	match &self.step {
+	    MemfileCreationStep::Create(Some(name), _) if self.is_name_too_long() => write!(f, "failed to create in-memory file: name is {} bytes long, but the maximum is {MEMFD_NAME_MAX} bytes", name.len()),
+	    MemfileCreationStep::Create(Some(name), _) if self.is_name_with_nul() => write!(f, "failed to create in-memory file: name {name:?} contains a nul byte"),
+	    MemfileCreationStep::Allocate(_, size) | MemfileCreationStep::Truncate(_, size) if self.is_out_of_memory() => write!(f, "failed to create in-memory file: out of memory while allocating {size} bytes (`{}` failed)", self.step),
+	    step => write!(f, "failed to create in-memory file: `{}` failed", step),
+	}
     }
 }
 
 impl MemfileError
 {
+    /// Create a new error for `step` with a specific reason.
+    #[inline] 
+    pub fn new(step: MemfileCreationStep, inner: io::Error) -> Self
+    {
+	Self { step, inner }
+    }
+
+    /// The step of creation that failed.
+    #[inline] 
+    pub fn step(&self) -> &MemfileCreationStep
+    {
+	&self.step
+    }
+
+    /// Did this error occur because there was not enough memory to allocate the file (`ENOSPC`/`ENOMEM`)?
+    #[inline] 
+    pub fn is_out_of_memory(&self) -> bool
+    {
+	matches!(self.inner.raw_os_error(), Some(libc::ENOSPC | libc::ENOMEM))
+    }
+
+    /// Did this error occur because the name given to `memfd_create()` was longer than `MEMFD_NAME_MAX`?
+    #[inline] 
+    pub fn is_name_too_long(&self) -> bool
+    {
+	matches!(&self.step, MemfileCreationStep::Create(Some(name), _) if name.len() > MEMFD_NAME_MAX)
+	    && self.inner.raw_os_error() == Some(libc::EINVAL)
+    }
+
+    /// Did this error occur because the name given to `memfd_create()` contained a nul byte?
+    #[inline] 
+    pub fn is_name_with_nul(&self) -> bool
+    {
+	matches!(&self.step, MemfileCreationStep::Create(Some(name), _) if name.contains('\0'))
+	    && self.inner.raw_os_error() == Some(libc::EINVAL)
+    }
+
+    /// Was the name given to `memfd_create()` invalid (see `is_name_too_long()` and `is_name_with_nul()`)?
+    #[inline] 
+    pub fn is_invalid_name(&self) -> bool
+    {
+	self.is_name_too_long() || self.is_name_with_nul()
+    }
+
     #[inline] 
     pub fn from_step(step: MemfileCreationStep) -> Self
     {
@@ -187,6 +239,15 @@ impl MemfileError
     }
 }
 
+impl From<MemfileError> for io::Error
+{
+    #[inline] 
+    fn from(from: MemfileError) -> Self
+    {
+	Self::new(from.inner.kind(), from)
+    }
+}
+
 impl From<MemfileCreationStep> for MemfileError
 {
     #[inline] 
@@ -152,7 +152,20 @@ impl RawFileDescriptor
 	self.0.get()
     }
 
+    /// Duplicate this fd into a new `RawFile` with `O_CLOEXEC` set (`fcntl(F_DUPFD_CLOEXEC)`), so the duplicate is not inherited by processes spawned afterwards.
+    ///
+    /// Unlike a plain `dup()`, the duplicate cannot leak into the children of a child that was only meant to be given the original (or into any other process spawned concurrently.)
+    #[cfg_attr(feature="logging", instrument(level="trace", err))]
+    pub fn dup_cloexec(&self) -> io::Result<RawFile>
+    {
+	match unsafe { libc::fcntl(self.get(), libc::F_DUPFD_CLOEXEC, 0) } {
+	    -1 => Err(io::Error::last_os_error()),
+	    fd => Ok(RawFile::take_ownership_of_unchecked(fd)),
+	}
+    }
+
     #[inline(always)] 
+    #[allow(dead_code)]
     pub(super) const fn clone_const(&self) -> Self
     {
 	//! **Internal**: `clone()` but useable in `memfile`-local `const fn`s
@@ -26,12 +26,12 @@ use libc::{
 ///
 /// This is a directory, and its subdirectories' *names* will contain the size in this format: `hugepages-(\d+)kB` (where the first capture-group is the size in kB.)
 /// The contents of those subdirectories themselves are irrelevent for our purpose.
-pub const HUGEPAGE_SIZES_LOCATION: &'static str = "/sys/kernel/mm/hugepages";
+pub const HUGEPAGE_SIZES_LOCATION: &str = "/sys/kernel/mm/hugepages";
 
 /// Should creation of `Mask`s from extracted kernel information be subject to integer conversion checks?
 ///
 /// This is `true` on debug builds or if the feature `hugepage-checked-masks` is enabled.
-const CHECKED_MASK_CREATION: bool = if cfg!(feature="hugepage-checked-masks") || cfg!(debug_assertions) { true } else { false };
+const CHECKED_MASK_CREATION: bool = cfg!(feature="hugepage-checked-masks") || cfg!(debug_assertions);
 
 
 /// Find all `Mask`s defined within this specific directory.
@@ -62,7 +62,7 @@ where P: AsRef<Path>
        .map(|x| x.map(|n| n.file_name()))
        .map(|name| name.map(|name| (find_size_bytes(&name), name)))
        .map(move |result| match result {
-	   Ok((Some(ok), path)) => {
+	   Ok((Ok(ok), path)) => {
 	       if CHECKED_MASK_CREATION {
 		   Mask::new_checked(ok)
 		       .wrap_err(eyre!("Failed to create mask from extracted bytes")
@@ -76,7 +76,8 @@ where P: AsRef<Path>
 		   Ok(SizedMask{ mask: Mask::new(ok), size: ok as u64 })
 	       }
 	   },
-	   Ok((None, path)) => Err(eyre!("Failed to extract bytes from path"))
+	   Ok((Err(err), path)) => Err(eyre!("Failed to extract bytes from path"))
+	       .with_section(move || err.to_string().header("Reason was"))
 	       .with_section(move || format!("{path:?}").header("Checked path was"))
 	       .with_section(root_path_section.clone()),
 	   Err(e) => Err(e).wrap_err(eyre!("Failed to read path from which to extract bytes")
@@ -93,6 +94,37 @@ pub fn get_masks() -> eyre::Result<impl Iterator<Item=eyre::Result<SizedMask>> +
 }
 
 
+/// Find the `Mask` for huge pages of `size` bytes on this system, or for the largest huge pages it supports if `size` is `None`.
+///
+/// # Errors
+/// If the system does not support huge pages (of `size`, if it is given.)
+#[cfg_attr(feature="logging", instrument(level="debug", err))]
+pub fn find_mask(size: Option<u64>) -> eyre::Result<SizedMask>
+{
+    let masks = get_masks()
+	.and_then(|masks| masks.collect::<eyre::Result<Vec<_>>>())
+	.wrap_err("Failed to find the huge page sizes supported by this system")
+	.with_suggestion(|| "Huge pages may not be supported by this kernel")?;
+    if_trace!(debug!("found {} huge page sizes: {:?}", masks.len(), masks.iter().map(SizedMask::size).collect::<Vec<_>>()));
+    let found = match size {
+	Some(size) => masks.iter().find(|mask| mask.size() == size),
+	None => masks.iter().max_by_key(|mask| mask.size()),
+    };
+    match found {
+	Some(&mask) => Ok(mask),
+	None => Err(match size {
+	    Some(size) => eyre!("Huge pages of {size} bytes are not supported by this system"),
+	    None => eyre!("This system does not support any huge page sizes"),
+	})
+	    .with_section(move || {
+		let mut sizes = masks.iter().map(SizedMask::size).collect::<Vec<_>>();
+		sizes.sort();
+		if sizes.is_empty() { "<none>".to_owned() } else { sizes.iter().map(u64::to_string).collect::<Vec<_>>().join(", ") }.header("Supported sizes (bytes) are")
+	    })
+	    .with_note(|| format!("Supported sizes are listed in `{HUGEPAGE_SIZES_LOCATION}`")),
+    }
+}
+
 /// A huge-page mask that can be bitwise OR'd with `HUGETLB_MASK`, but retains the size of that huge-page.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Copy)]
 pub struct SizedMask
@@ -109,6 +141,7 @@ impl SizedMask
 	self.size
     }
     #[inline] 
+    #[allow(dead_code)]
     pub const fn as_mask(&self) -> &Mask
     {
 	&self.mask
@@ -274,6 +307,7 @@ impl Mask {
     /// # Safety
     /// The caller **must** guarantee that `mask` is a valid `MAP_HUGE_` mask.
     #[inline] 
+    #[allow(dead_code)]
     pub const unsafe fn from_raw(mask: c_uint) -> Self
     {
 	Self(mask)
@@ -297,6 +331,7 @@ impl Mask {
     ///
     /// The `flags` argument is erased. To pass arbitrary flags to `memfd_create()`, use `memfd_create_raw_wrapper_flags()`
     #[inline(always)] 
+    #[allow(dead_code)]
     pub const fn memfd_create_raw_wrapper(self) -> impl Fn (*const libc::c_char) -> c_int
     {
 	use libc::memfd_create;
@@ -309,6 +344,7 @@ impl Mask {
 
     /// Create a function that acts as `memfd_create()` with this mask applied to it.
     #[inline(always)] 
+    #[cfg_attr(not(test), allow(dead_code))]
     pub const fn memfd_create_raw_wrapper_flags(self) -> impl Fn (*const libc::c_char, c_uint) -> c_int
     {
 	use libc::memfd_create;
@@ -325,6 +361,7 @@ impl Mask {
     /// # Returns
     /// A RAII-guarded wrapper over the memory-file, or the `errno` in an `Err(io::Error)` if the operation failed.
     #[inline] 
+    #[allow(dead_code)]
     pub const fn memfd_create_wrapper(self) -> impl Fn(*const libc::c_char) -> io::Result<super::RawFile>
     {
 	let memfd_create = self.memfd_create_raw_wrapper();
@@ -340,6 +377,7 @@ impl Mask {
     /// # Returns
     /// A RAII-guarded wrapper over the memory-file, or the `errno` in an `Err(io::Error)` if the operation failed.
     #[inline] 
+    #[cfg_attr(not(test), allow(dead_code))]
     pub const fn memfd_create_wrapper_flags(self) -> impl Fn(*const libc::c_char, c_uint) -> io::Result<super::RawFile>
     {
 	let memfd_create = self.memfd_create_raw_wrapper_flags();
@@ -366,14 +404,61 @@ impl TryFrom<usize> for Mask
 
 //TODO: add test `.memfd_create_wrapper{,_flags}()` usage, too with some `MAP_HUGE_` constants as sizes
 
+/// The part of the lookup in `find_size_bytes()` that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HugePageParseError
+{
+    /// There is no `-` seperating the size from the `hugepages` prefix.
+    NoDashSeparator,
+    /// The size has no IEC suffix (e.g. the `k` in `kB`.)
+    NoSuffix,
+    /// There is no number between the `-` and the suffix.
+    EmptyNumber,
+    /// The number is not valid UTF-8.
+    BadUtf8(std::str::Utf8Error),
+    /// The number is not a valid base-10 `usize`.
+    BadNumber(std::num::ParseIntError),
+}
+
+impl fmt::Display for HugePageParseError
+{
+    #[inline] 
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match self {
+	    Self::NoDashSeparator => f.write_str("no `-` seperator before the size"),
+	    Self::NoSuffix => write!(f, "no size suffix (one of `{}`)", unsafe { std::str::from_utf8_unchecked(KMAP_TAGS) }),
+	    Self::EmptyNumber => f.write_str("no number before the size suffix"),
+	    Self::BadUtf8(err) => write!(f, "size is not valid UTF-8: {err}"),
+	    Self::BadNumber(err) => write!(f, "size is not a valid number: {err}"),
+	}
+    }
+}
+
+impl std::error::Error for HugePageParseError
+{
+    #[inline] 
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+	match self {
+	    Self::BadUtf8(err) => Some(err),
+	    Self::BadNumber(err) => Some(err),
+	    _ => None,
+	}
+    }
+}
+
+/// The IEC suffixes `find_size_bytes()` looks for after the number.
+const KMAP_TAGS: &[u8] = b"kmgbB"; //"bB";
+
 /// Take a directory path and try to parse the hugepage size from it.
 ///
 /// All subdirectories from `HUGEPAGE_SIZES_LOCATION` should be passed to this, and the correct system-valid hugepage size will be returned for that specific hugepage.
-//TODO: Maybe make this `Result` instead? So we can know what part of the lookup is failing?
-#[cfg_attr(feature="logging", instrument(ret, skip_all, fields(path = ?path.as_ref())))]
-fn find_size_bytes(path: impl AsRef<Path>) -> Option<usize>
+///
+/// # Errors
+/// Which part of the lookup failed.
+#[cfg_attr(feature="logging", instrument(ret, err, skip_all, fields(path = ?path.as_ref())))]
+fn find_size_bytes(path: impl AsRef<Path>) -> Result<usize, HugePageParseError>
 {
-    const KMAP_TAGS: &[u8] = b"kmgbB"; //"bB";
     const KMAP_SIZES: &[usize] = &[1024, 1024*1024, 1024*1024*1024, 0, 0]; // Having `b` and `B` (i.e. single bytes) be 0 means `sz` will be passed unmodified: the default multiplier is 1 and never 0. Making these two values 0 instead of 1 saves a single useless `* 1` call, but still allows for them to be looked up in `dir_bytes` when finding `k_loc` below.
 
     /// Lookup the correct multiplier for `sz` to get the number of individual bytes from the IEC bytes-suffix `chr`.
@@ -411,12 +496,12 @@ fn find_size_bytes(path: impl AsRef<Path>) -> Option<usize>
     return None;
 } */
 
-    let dir_name = path.file_name()?;
+    let dir_name = path.file_name().unwrap_or(path.as_os_str());
     let dir_bytes = dir_name.as_bytes();
     if_trace!(trace!("dir_name: {dir_name:?}"));
     
     // location of the b'-' in the dir name
-    let split_loc = memchr::memchr(b'-', dir_bytes)?;
+    let split_loc = memchr::memchr(b'-', dir_bytes).ok_or(HugePageParseError::NoDashSeparator)?;
     
     
     // The rest of the string including the b'-' seperator. (i.e. '-(\d+)kB$')
@@ -424,19 +509,17 @@ fn find_size_bytes(path: impl AsRef<Path>) -> Option<usize>
     if_trace!(debug!("split_bytes (from `-'): {:?}", std::ffi::OsStr::from_bytes(split_bytes)));
 
     // location of the IEC tag (in `KMAP_TAGS`, expected to be b'k') after the number of kilobytes
-    let (k_loc, k_chr) = 'lookup: loop  {
-	for &tag in KMAP_TAGS {
-	    if_trace!(trace!("attempting check for `{}' ({tag}) in {split_bytes:?}", tag as char));
-	    if let Some(k_loc) = memchr::memchr(tag, split_bytes) {
-		break 'lookup (k_loc, tag);
-	    } else {
-		if_trace!(warn!("lookup failed"));
-		continue 'lookup;
-	    }
+    let Some((k_loc, k_chr)) = KMAP_TAGS.iter().find_map(|&tag| {
+	if_trace!(trace!("attempting check for `{}' ({tag}) in {split_bytes:?}", tag as char));
+	let found = memchr::memchr(tag, split_bytes).map(|k_loc| (k_loc, tag));
+	if found.is_none() {
+	    if_trace!(trace!("lookup failed"));
 	}
+	found
+    }) else {
 	// No suffixes in `KMAP_TAGS` found.
 	if_trace!(error!("No appropriate suffix ({}) found in {:?}", unsafe { std::str::from_utf8_unchecked(KMAP_TAGS) }, split_bytes));
-	return None;
+	return Err(HugePageParseError::NoSuffix);
     };
     
 
@@ -448,21 +531,20 @@ fn find_size_bytes(path: impl AsRef<Path>) -> Option<usize>
 	if kb_str.len() <= 1 {
 	    // There is no number between the digits and the `kB` (unlikely)
 	    if_trace!(error!("Invalid format of hugepage kB size in pathname `{:?}': Extracted string was `{}'", dir_name, String::from_utf8_lossy(kb_str)));
-	    return None;
+	    return Err(HugePageParseError::EmptyNumber);
 	}
 	match std::str::from_utf8(&kb_str[1..]) {
 	    Ok(v) => v,
 	    Err(e) => {
 		if_trace!(error!("Kilobyte string number (base-10) in pathname `{:?}' is not valid utf8: {e}", kb_str));
-		drop(e);
-		return None;
+		return Err(HugePageParseError::BadUtf8(e));
 	    }
 	}
     };
     
     if_trace!(debug!("kb_str (extracted): {kb_str}"));
 
-    kb_str.parse::<usize>().ok().map(move |sz| {
+    kb_str.parse::<usize>().map_err(HugePageParseError::BadNumber).map(move |sz| {
 	if_trace!(debug!("found raw size {sz}, looking up in table for byte result of suffix `{}'", k_chr as char));
 	kmap_lookup(sz, k_chr)
     })
@@ -474,34 +556,45 @@ mod tests
     use super::*;
 
     #[inline] 
-    fn get_bytes<'a, P: 'a>(from: P) -> eyre::Result<impl Iterator<Item=eyre::Result<usize>> +'a>
-    where P: AsRef<Path>
+    fn get_bytes<'a, P>(from: P) -> eyre::Result<impl Iterator<Item=eyre::Result<usize>> +'a>
+    where P: 'a + AsRef<Path>
     {
 	let dir = from.as_ref().read_dir()?;
 	Ok(dir
 	   .map(|x| x.map(|n| n.file_name()))
-	   .map(|name| name.map(|name| super::find_size_bytes(name)))
+	   .map(|name| name.map(super::find_size_bytes))
 	   .map(|result| result.flatten()))
     }
     
     #[test]
     fn find_size_bytes() -> eyre::Result<()>
     {
-	//crate::init()?; XXX: Make `find_size_bytes` return eyre::Result<usize> instead of Option<usize>
+	//crate::init()?;
 	let dir = Path::new(super::HUGEPAGE_SIZES_LOCATION).read_dir()?;
 	for result in dir
 	    .map(|x| x.map(|n| n.file_name()))
-	    .map(|name| name.map(|name| super::find_size_bytes(name)))
+	    .map(|name| name.map(super::find_size_bytes))
 	{
 	    println!("size: {}", result
 		     .wrap_err(eyre!("Failed to extract name"))?
-		     .ok_or(eyre!("Failed to find size"))?);
+		     .wrap_err(eyre!("Failed to find size"))?);
 	}
 	
 	
 	Ok(())
     }
 
+    #[test]
+    fn find_size_bytes_errors()
+    {
+	assert_eq!(super::find_size_bytes("hugepages-2048kB"), Ok(2048 * 1024));
+	assert_eq!(super::find_size_bytes("hugepages"), Err(HugePageParseError::NoDashSeparator));
+	assert_eq!(super::find_size_bytes("hugepages-2048"), Err(HugePageParseError::NoSuffix));
+	assert_eq!(super::find_size_bytes("hugepages-kB"), Err(HugePageParseError::EmptyNumber));
+	assert!(matches!(super::find_size_bytes(std::ffi::OsStr::from_bytes(b"hugepages-20\xff8kB")), Err(HugePageParseError::BadUtf8(_))));
+	assert!(matches!(super::find_size_bytes("hugepages-2x48kB"), Err(HugePageParseError::BadNumber(_))));
+    }
+
     mod map_huge {
 	use super::*;
 	/// Some `MAP_HUGE_` constants provided by libc.
@@ -588,7 +681,7 @@ mod tests
 						   }).filter_map(Result::ok))
 		.count();
 	    
-	    (masks > 0).then(|| drop(println!("Found {masks} masks matching pre-set constants"))).ok_or(eyre!("Found no masks matching constants"))
+	    (masks > 0).then(|| println!("Found {masks} masks matching pre-set constants")).ok_or(eyre!("Found no masks matching constants"))
 	}
 	
 	#[test]
@@ -601,7 +694,21 @@ mod tests
 		})
 		.count();
 	    
-	    (masks > 0).then(|| drop(println!("Found {masks} masks on system"))).ok_or(eyre!("Found no masks"))
+	    (masks > 0).then(|| println!("Found {masks} masks on system")).ok_or(eyre!("Found no masks"))
+	}
+
+	#[test]
+	fn find_mask() -> eyre::Result<()>
+	{
+	    let sizes = super::get_masks()?.map(|mask| mask.map(|mask| mask.size())).collect::<eyre::Result<Vec<_>>>()?;
+	    let largest = super::find_mask(None)?;
+	    assert_eq!(Some(largest.size()), sizes.iter().copied().max(), "Not the largest huge page size");
+	    for size in sizes {
+		assert_eq!(super::find_mask(Some(size))?.size(), size);
+	    }
+	    // Smaller than any huge page.
+	    assert!(super::find_mask(Some(4096)).is_err(), "Unsupported huge page size was found");
+	    Ok(())
 	}
 
 	#[test]
@@ -611,7 +718,7 @@ mod tests
 	    type MappedFile = fs::File;
 	    
 	    use std::ffi::CString;
-	    let name = CString::new(Vec::from_iter(b"memfd_create_wrapper() test".into_iter().copied())).unwrap();
+	    let name = CString::new(Vec::from_iter(b"memfd_create_wrapper() test".iter().copied())).unwrap();
 	    let mask = super::get_masks()?.next().ok_or(eyre!("No masks found"))?.wrap_err("Failed to extract mask")?;
 	    eprintln!("Using mask: {mask:x} ({mask:b})");
 	    let create = mask.memfd_create_wrapper_flags();
@@ -639,7 +746,7 @@ mod tests
 		//crate::init()?;
 		
 		use std::ffi::CString;
-		let name = CString::new(Vec::from_iter(b"memfd_create_wrapper() test".into_iter().copied())).unwrap();
+		let name = CString::new(Vec::from_iter(b"memfd_create_wrapper() test".iter().copied())).unwrap();
 		let mask = super::get_masks()?.next().ok_or(eyre!("No masks found"))?.wrap_err("Failed to extract mask")?;
 		eprintln!("Using mask: {mask:x} ({mask:b})");
 		let create = mask.memfd_create_wrapper_flags();
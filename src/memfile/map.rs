@@ -1,23 +1,25 @@
 //! Memory mapping
 use super::*;
+use std::ptr::NonNull;
 use libc::{
     c_int,
-    
+
     PROT_NONE,
     PROT_READ,
     PROT_WRITE,
     PROT_EXEC,
 };
 
-//TODO: Make this a `bitflags` struct.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Copy)]
-#[repr(i32)]
-pub enum MapProtection
-{
-    None = PROT_NONE,
-    Read = PROT_READ,
-    Write = PROT_WRITE,
-    Execute = PROT_EXEC,
+bitflags::bitflags! {
+    /// Protection of a memory mapping (the `prot` argument to `mmap()`.)
+    #[repr(transparent)]
+    pub struct MapProtection: c_int
+    {
+	const NONE = PROT_NONE;
+	const READ = PROT_READ;
+	const WRITE = PROT_WRITE;
+	const EXECUTE = PROT_EXEC;
+    }
 }
 
 impl Default for MapProtection
@@ -25,6 +27,206 @@ impl Default for MapProtection
     #[inline(always)]
     fn default() -> Self
     {
-	Self::None
+	Self::NONE
+    }
+}
+
+/// A region of memory mapped by `mmap()`, which is unmapped when dropped.
+///
+/// # Access
+/// The region can only be read through `Deref` if it was mapped with `MapProtection::READ`, and only written through `DerefMut` if it was mapped with `MapProtection::WRITE`; otherwise these panic instead of faulting.
+///
+/// # Shared mappings
+/// The contents of a shared mapping change if the file is written to through another mapping or descriptor (including by another process), and accessing a page past the end of the file if it is shrunk raises `SIGBUS`.
+/// Seal the file (at least `F_SEAL_SHRINK`, and `F_SEAL_WRITE` for it not to change) before mapping it if it is shared with anything that may do so.
+#[derive(Debug)]
+pub struct MmapRegion
+{
+    ptr: NonNull<u8>,
+    len: usize,
+    prot: MapProtection,
+}
+
+// The region is owned memory, like a `Box<[u8]>`.
+unsafe impl Send for MmapRegion{}
+unsafe impl Sync for MmapRegion{}
+
+impl MmapRegion
+{
+    /// Take ownership of a region of `len` bytes at `ptr` previously mapped with `prot`.
+    ///
+    /// # Safety
+    /// `ptr` must be the address returned by a successful `mmap()` call of `len` bytes with protection `prot` which is not unmapped elsewhere. If `len` is 0, `ptr` is not unmapped when dropped.
+    #[inline]
+    pub unsafe fn from_raw_parts(ptr: NonNull<u8>, len: usize, prot: MapProtection) -> Self
+    {
+	Self { ptr, len, prot }
+    }
+
+    /// An empty region, which does not map anything.
+    ///
+    /// `mmap()` does not accept a length of `0`, so this is used for empty files instead.
+    #[inline]
+    pub fn empty(prot: MapProtection) -> Self
+    {
+	Self { ptr: NonNull::dangling(), len: 0, prot }
+    }
+
+    /// The size of the region in bytes.
+    #[inline(always)]
+    pub fn len(&self) -> usize
+    {
+	self.len
+    }
+
+    /// Is the region empty?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool
+    {
+	self.len == 0
+    }
+
+    /// The protection the region was mapped with.
+    #[inline(always)]
+    pub fn protection(&self) -> MapProtection
+    {
+	self.prot
+    }
+
+    /// The start address of the region.
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *const u8
+    {
+	self.ptr.as_ptr() as *const _
+    }
+
+    /// The mutable start address of the region.
+    #[inline(always)]
+    pub fn as_mut_ptr(&mut self) -> *mut u8
+    {
+	self.ptr.as_ptr()
     }
-} 
+
+    /// Write the changes made through a shared mapping back to the file, and wait for them to be written (`msync(MS_SYNC)`.)
+    pub fn sync(&self) -> io::Result<()>
+    {
+	if self.len == 0 {
+	    return Ok(());
+	}
+	match unsafe {
+	    libc::msync(self.ptr.as_ptr() as *mut _, self.len, libc::MS_SYNC)
+	} {
+	    0 => Ok(()),
+	    _ => Err(io::Error::last_os_error()),
+	}
+    }
+}
+
+impl ops::Deref for MmapRegion
+{
+    type Target = [u8];
+    #[inline]
+    fn deref(&self) -> &Self::Target
+    {
+	assert!(self.is_empty() || self.prot.contains(MapProtection::READ), "Region mapped with {:?} is not readable", self.prot);
+	unsafe {
+	    std::slice::from_raw_parts(self.ptr.as_ptr() as *const _, self.len)
+	}
+    }
+}
+
+impl ops::DerefMut for MmapRegion
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target
+    {
+	assert!(self.is_empty() || self.prot.contains(MapProtection::READ | MapProtection::WRITE), "Region mapped with {:?} is not writable", self.prot);
+	unsafe {
+	    std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len)
+	}
+    }
+}
+
+impl AsRef<[u8]> for MmapRegion
+{
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8]
+    {
+	&self[..]
+    }
+}
+
+impl ops::Drop for MmapRegion
+{
+    fn drop(&mut self)
+    {
+	if self.len == 0 {
+	    return;
+	}
+	if unsafe {
+	    libc::munmap(self.ptr.as_ptr() as *mut _, self.len)
+	} != 0 {
+	    if_trace!(error!("munmap({:?}, {}) failed: {}", self.ptr, self.len, io::Error::last_os_error()));
+	}
+    }
+}
+
+/// Map `len` bytes of `file` (or all of it from `offset`, if `None`) from `offset` into memory with `prot`.
+///
+/// `offset` must be a multiple of the page size. See `RawFile::map()` for `shared` and empty regions.
+#[cfg_attr(feature="logging", instrument(level="debug", skip(file), fields(fd = ?file.as_raw_fd()), err))]
+pub fn map_fd<F: ?Sized + AsRawFd>(file: &F, prot: MapProtection, shared: bool, offset: u64, len: Option<usize>) -> Result<MmapRegion, error::MemfileError>
+{
+    use error::{
+	MemfileError,
+	MemfileCreationStep::Map,
+    };
+    let flags = if shared { libc::MAP_SHARED } else { libc::MAP_PRIVATE };
+    let fd = file.as_raw_fd();
+    let step = |size, offset| Map {
+	addr: 0,
+	size,
+	prot,
+	flags,
+	fd: fd::RawFileDescriptor::try_new(fd).ok(),
+	offset,
+    };
+    let off: libc::off_t = offset.try_into()
+	.map_err(|_| MemfileError::new(step(0, libc::off_t::MAX), io::Error::new(io::ErrorKind::InvalidInput, "Offset larger than `off_t`")))?;
+    let len = match len {
+	Some(len) => len,
+	None => stream_len(file)
+	    .and_then(|len| len.saturating_sub(offset).try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "File larger than the address space")))
+	    .map_err(|err| MemfileError::new(step(0, off), err))?,
+    };
+    if len == 0 {
+	if_trace!(trace!("Nothing to map for fd {fd}, returning an empty region"));
+	return Ok(MmapRegion::empty(prot));
+    }
+
+    match unsafe {
+	libc::mmap(std::ptr::null_mut(), len, prot.bits(), flags, fd, off)
+    } {
+	libc::MAP_FAILED => {
+	    if_trace!(warn!("systemcall failed: {}", error::raw_errno()));
+	    Err(MemfileError::from_step(step(len, off)))
+	},
+	ptr => Ok(unsafe {
+	    MmapRegion::from_raw_parts(NonNull::new_unchecked(ptr as *mut u8), len, prot)
+	}),
+    }
+}
+
+impl RawFile
+{
+    /// Map `len` bytes of the file (or all of it, if `None`) from its start into memory with `prot`.
+    ///
+    /// If `shared` is `true`, the mapping is `MAP_SHARED`: writes to it are written to the file, and changes to the file are visible through it. Otherwise it is a private copy-on-write mapping (`MAP_PRIVATE`.)
+    ///
+    /// An empty file (or a `len` of `Some(0)`) gives an empty region without calling `mmap()`.
+    #[inline]
+    pub fn map(&self, prot: MapProtection, shared: bool, len: Option<usize>) -> Result<MmapRegion, error::MemfileError>
+    {
+	map_fd(self, prot, shared, 0, len)
+    }
+}
@@ -0,0 +1,164 @@
+//! Showing how much of the input has been read (`--progress`)
+use super::*;
+use std::{
+    fmt,
+    sync::atomic::{
+	AtomicU64,
+	Ordering,
+    },
+    time::{
+	Duration,
+	Instant,
+    },
+};
+
+/// The least time between two redraws of the progress line.
+pub const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Should progress be shown for `mode`?
+///
+/// Unless it is forced (`--progress=force`), it is only shown if `stderr` is a terminal.
+#[inline]
+pub fn enabled(mode: args::Progress) -> bool
+{
+    match mode {
+	args::Progress::Force => true,
+	args::Progress::Auto => sys::isatty(&io::stderr()),
+    }
+}
+
+/// Is progress shown for these options (was `--progress` passed, and is it `enabled()`)?
+#[inline]
+pub fn shown(opt: &args::Options) -> bool
+{
+    opt.progress().is_some_and(enabled)
+}
+
+/// A number of bytes, displayed in the largest binary unit there is at least one of (e.g. `1.5 MiB`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bytes(u64);
+
+impl fmt::Display for Bytes
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+	if self.0 < 1024 {
+	    return write!(f, "{} B", self.0);
+	}
+	let mut value = self.0 as f64 / 1024.0;
+	let mut unit = 0;
+	while value >= 1024.0 && unit < UNITS.len() - 1 {
+	    value /= 1024.0;
+	    unit += 1;
+	}
+	write!(f, "{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// A reader that counts the bytes read through it, and shows that count on `stderr` at most every `INTERVAL`.
+///
+/// The line is redrawn in place (with `\r`), with the percentage of `total` read if it is known; and it is ended (with the final count) when the reader is dropped.
+#[derive(Debug)]
+pub struct ProgressReader<R>
+{
+    inner: R,
+    read: AtomicU64,
+    total: Option<u64>,
+    /// When the line was last drawn.
+    last: Option<Instant>,
+}
+
+impl<R> ProgressReader<R>
+{
+    /// Count the bytes read from `inner`, out of `total` if it is known.
+    ///
+    /// `already` is the number of bytes that were read before by other means (e.g. `splice()`), which the count starts from.
+    #[inline]
+    pub fn new(inner: R, total: Option<u64>, already: u64) -> Self
+    {
+	Self {
+	    inner,
+	    read: AtomicU64::new(already),
+	    total,
+	    last: None,
+	}
+    }
+
+    /// The number of bytes read so far (including `already`.)
+    #[inline]
+    pub fn read_bytes(&self) -> u64
+    {
+	self.read.load(Ordering::Relaxed)
+    }
+
+    /// The progress line for `read` bytes.
+    fn line(&self, read: u64) -> String
+    {
+	match self.total {
+	    Some(total) if total > 0 => format!("{}: read {} of {} ({}%)", args::program_name(), Bytes(read), Bytes(total), std::cmp::min(read.saturating_mul(100) / total, 100)),
+	    _ => format!("{}: read {}", args::program_name(), Bytes(read)),
+	}
+    }
+
+    /// Draw the progress line over the last one, and end it if this is the `last` time.
+    fn draw(&self, read: u64, last: bool)
+    {
+	use io::Write;
+	// Padded so that a shorter line covers the one before it.
+	let line = format!("\r{:<64}{}", self.line(read), if last { "\n" } else { "" });
+	// Progress is only informational, so failing to show it is not an error.
+	let _ = io::stderr().lock().write_all(line.as_bytes());
+    }
+}
+
+impl<R: io::Read> io::Read for ProgressReader<R>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+    {
+	let n = self.inner.read(buf)?;
+	let read = self.read.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
+	let now = Instant::now();
+	if self.last.is_none_or(|last| now.duration_since(last) >= INTERVAL) {
+	    self.last = Some(now);
+	    self.draw(read, false);
+	}
+	Ok(n)
+    }
+}
+
+impl<R> Drop for ProgressReader<R>
+{
+    fn drop(&mut self)
+    {
+	self.draw(self.read_bytes(), true);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn bytes_display()
+    {
+	assert_eq!(Bytes(0).to_string(), "0 B");
+	assert_eq!(Bytes(1023).to_string(), "1023 B");
+	assert_eq!(Bytes(1536).to_string(), "1.5 KiB");
+	assert_eq!(Bytes(3 << 30).to_string(), "3.0 GiB");
+    }
+
+    #[test]
+    fn counts_reads() -> io::Result<()>
+    {
+	let input = vec![7u8; 10000];
+	let mut reader = ProgressReader::new(&input[..], Some(20000), 5000);
+	let mut output = Vec::new();
+	io::copy(&mut reader, &mut output)?;
+	assert_eq!(output, input, "Data was changed by reading through the progress reader");
+	assert_eq!(reader.read_bytes(), 15000, "Bytes read were not counted on top of `already`");
+	assert!(reader.line(reader.read_bytes()).ends_with("read 14.6 KiB of 19.5 KiB (75%)"), "Wrong progress line: {}", reader.line(reader.read_bytes()));
+	Ok(())
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Basic system interactions.
 use super::*;
+use std::fmt;
 
 /// Attempt to get the size of any stream that is backed by a file-descriptor.
 ///
@@ -9,32 +10,993 @@ use super::*;
 #[cfg_attr(feature="logging", instrument(level="info", skip(reader), ret, fields(reader = std::any::type_name::<R>())))]
 #[inline]
 //TODO: XXX: What if the size of `reader` really *is* 0. We shouldn't use `NonZeroUsize` here, we should just use `usize`. I think `st_size` can be `-1` if `fstat64()` fails to find a size...
-pub fn try_get_size<R: ?Sized>(reader: &R) -> Option<NonZeroUsize>
-where R: AsRawFd
+pub fn try_get_size<R>(reader: &R) -> Option<NonZeroUsize>
+where R: ?Sized + AsRawFd
 {
-    let fd = reader.as_raw_fd();
-    use libc::{
-	fstat64,
-	stat64,
-    };
-    if fd < 0 {
+    if reader.as_raw_fd() < 0 {
 	return None;
     }
+    kind_and_size(reader).ok()
+	.and_then(|(_, size)| size)
+}
+
+cfg_if! {
+    if #[cfg(target_os="linux")] {
+	use libc::stat64;
+    } else {
+	// Non-Linux Unix-likes have a 64-bit `off_t` in `struct stat` anyway.
+	use libc::stat as stat64;
+    }
+}
+
+/// `fstat()` the file descriptor of `file`.
+#[inline] 
+fn fstat<F: ?Sized + AsRawFd>(file: &F) -> io::Result<stat64>
+{
+    cfg_if! {
+	if #[cfg(target_os="linux")] {
+	    use libc::fstat64;
+	} else {
+	    use libc::fstat as fstat64;
+	}
+    }
     let mut st: MaybeUninit<stat64> = MaybeUninit::uninit();
     unsafe {
-	match fstat64(fd, st.as_mut_ptr()) {
-	    0 => {
-		NonZeroUsize::new(st.assume_init().st_size as usize)
+	match fstat64(file.as_raw_fd(), st.as_mut_ptr()) {
+	    0 => Ok(st.assume_init()),
+	    _ => Err(io::Error::last_os_error()),
+	}
+    }
+}
+
+/// The total amount of physical memory in the system, in bytes.
+#[inline] 
+#[cfg(feature="memfile")]
+pub fn total_system_memory() -> Option<u64>
+{
+    let (pages, page_size) = unsafe { (libc::sysconf(libc::_SC_PHYS_PAGES), libc::sysconf(libc::_SC_PAGESIZE)) };
+    (pages > 0 && page_size > 0).then(|| pages as u64 * page_size as u64)
+}
+
+/// The amount of physical memory currently available in the system, in bytes.
+///
+/// # Returns
+/// `None` if this could not be found, or on non-Linux targets.
+#[inline] 
+#[cfg(feature="memfile")]
+pub fn available_system_memory() -> Option<u64>
+{
+    cfg_if! {
+	if #[cfg(target_os="linux")] {
+	    let (pages, page_size) = unsafe { (libc::sysconf(libc::_SC_AVPHYS_PAGES), libc::sysconf(libc::_SC_PAGESIZE)) };
+	    (pages > 0 && page_size > 0).then(|| pages as u64 * page_size as u64)
+	} else {
+	    None
+	}
+    }
+}
+
+/// The amount of memory available for new allocations without swapping (`MemAvailable` in `/proc/meminfo`), in bytes.
+///
+/// Unlike `available_system_memory()`, which is only the free memory, this includes the memory (e.g. the page cache) the kernel can reclaim for it.
+#[cfg_attr(feature="logging", instrument(level="debug", ret, err))]
+#[cfg(feature="memfile")]
+pub fn available_memory() -> io::Result<u64>
+{
+    let meminfo = std::fs::read_to_string("/proc/meminfo")?;
+    meminfo_field(&meminfo, "MemAvailable")
+	.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no valid `MemAvailable` field in /proc/meminfo"))
+}
+
+/// Find the field `name` (in `kB`) in the contents of `/proc/meminfo`, in bytes.
+#[cfg(feature="memfile")]
+fn meminfo_field(meminfo: &str, name: &str) -> Option<u64>
+{
+    meminfo.lines()
+	.filter_map(|line| line.split_once(':'))
+	.find(|&(field, _)| field == name)
+	.and_then(|(_, value)| value.trim().strip_suffix("kB")?.trim_end().parse::<u64>().ok())
+	.and_then(|kb| kb.checked_mul(1024))
+}
+
+/// Do the file descriptors of `a` and `b` refer to the same file (i.e. are their `st_dev` and `st_ino` equal)?
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, ret, err, fields(a = ?a.as_raw_fd(), b = ?b.as_raw_fd())))]
+pub fn same_file<A: ?Sized + AsRawFd, B: ?Sized + AsRawFd>(a: &A, b: &B) -> io::Result<bool>
+{
+    let (a, b) = (fstat(a)?, fstat(b)?);
+    Ok(a.st_dev == b.st_dev && a.st_ino == b.st_ino)
+}
+
+/// Close the input on `stdin` early, so the producer writing into it is not waited on (it receives `SIGPIPE`/`EPIPE` if `stdin` was the only read end of a pipe.)
+///
+/// `/dev/null` is put in its place instead of leaving fd 0 closed, so it cannot be reused by (or inherited as `stdin` by the children of) anything opened afterwards.
+#[cfg_attr(feature="logging", instrument(level="debug", err))]
+pub fn close_stdin() -> io::Result<()>
+{
+    let null = std::fs::File::open("/dev/null")?;
+    if unsafe { libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO) } < 0 {
+	return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Release the memory backing an in-memory file (memfd) by punching a hole over its whole pages, keeping its size.
+///
+/// The content reads back as zeroes afterwards, so this must only be done once nothing will read it again. Files that do not support sealing (i.e. that are not memfds) are never touched.
+///
+/// A trailing partial page is kept: punching it would zero it in place, and its page may still be referenced by a pipe the file was `splice()`d into (see `splice_all()`.) Whole pages are only dropped from the file, so such a pipe keeps their content.
+///
+/// # Returns
+/// The number of bytes released, `0` if `file` is not a memfd (always, on platforms other than Linux.)
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, ret, err, fields(fd = ?file.as_raw_fd())))]
+#[cfg_attr(not(feature="exec"), allow(dead_code))]
+pub fn release_pages<F: ?Sized + AsRawFd>(file: &F) -> io::Result<u64>
+{
+    cfg_if! {
+	if #[cfg(target_os="linux")] {
+	    let fd = file.as_raw_fd();
+	    if unsafe { libc::fcntl(fd, libc::F_GET_SEALS) } < 0 {
+		return Ok(0);
+	    }
+	    let page = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+		page if page > 0 => page as libc::off_t,
+		_ => return Err(io::Error::last_os_error()),
+	    };
+	    let size = fstat(file)?.st_size / page * page;
+	    if size > 0 && unsafe { libc::fallocate(fd, libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE, 0, size) } < 0 {
+		return Err(io::Error::last_os_error());
+	    }
+	    Ok(size as u64)
+	} else {
+	    // There are no memfds (or `FALLOC_FL_PUNCH_HOLE`) outside of Linux, so there is never anything to release.
+	    let _ = file;
+	    Ok(0)
+	}
+    }
+}
+
+/// The largest capacity an unprivileged process can set on a pipe, from `/proc/sys/fs/pipe-max-size`.
+#[cfg_attr(feature="logging", instrument(level="debug", ret))]
+#[cfg(target_os="linux")]
+pub fn pipe_max_size() -> Option<usize>
+{
+    std::fs::read_to_string("/proc/sys/fs/pipe-max-size").ok()?
+	.trim().parse().ok()
+}
+
+/// Get the capacity of the pipe `fd` with `F_GETPIPE_SZ`.
+#[inline] 
+#[cfg(target_os="linux")]
+#[cfg_attr(not(feature="memfile"), allow(dead_code))]
+pub fn pipe_size(fd: RawFd) -> io::Result<usize>
+{
+    match unsafe { libc::fcntl(fd, libc::F_GETPIPE_SZ) } {
+	-1 => Err(io::Error::last_os_error()),
+	size => Ok(size as usize),
+    }
+}
+
+/// Set the capacity of the pipe `fd` to at least `size` bytes with `F_SETPIPE_SZ`.
+///
+/// `size` is clamped to `pipe_max_size()` (so an unprivileged process does not fail with `EPERM`), and the kernel rounds it up to a power-of-two number of pages.
+///
+/// # Returns
+/// The capacity the pipe actually has afterwards.
+#[cfg_attr(feature="logging", instrument(level="debug", ret, err))]
+#[cfg(target_os="linux")]
+pub fn set_pipe_size(fd: RawFd, size: usize) -> io::Result<usize>
+{
+    let size = std::cmp::min(size, pipe_max_size().unwrap_or(usize::MAX));
+    let size = libc::c_int::try_from(size).unwrap_or(libc::c_int::MAX);
+    match unsafe { libc::fcntl(fd, libc::F_SETPIPE_SZ, size) } {
+	-1 => Err(io::Error::last_os_error()),
+	size => Ok(size as usize),
+    }
+}
+
+/// Create a pipe with `pipe2(O_CLOEXEC)`, so neither end leaks into children that are not meant to have it.
+///
+/// A caller that wants a child to inherit one of the ends should clear `FD_CLOEXEC` on only that end (`process::Command` already does this for the ends it is given as a child's `stdin`/`stdout`/`stderr`.)
+///
+/// This is `memfile::RawFile::pipe()`.
+///
+/// # Returns
+/// The `(read, write)` ends of the pipe.
+#[cfg(feature="memfile")]
+#[inline(always)]
+pub fn pipe_cloexec() -> io::Result<(memfile::RawFile, memfile::RawFile)>
+{
+    memfile::RawFile::pipe()
+}
+
+/// The most `splice_all()` asks `splice()` to move at once.
+#[cfg(feature="memfile")]
+const SPLICE_CHUNK: usize = 1 << 30;
+
+/// Move up to `len` bytes (or everything, if `None`) from `from` to `to` with `splice()`, stopping early if `from` reaches EOF.
+///
+/// One of the two must be a pipe. The data is moved by the kernel without being copied through userspace. Short splices are continued, `EINTR` is retried, and a non-blocking side that is not ready (`EAGAIN`) is waited on with `poll()`.
+///
+/// If `splice()` fails before anything was moved, the error is returned: notably `EINVAL` (`io::ErrorKind::InvalidInput`) if `from` or `to` cannot be spliced, in which case the caller should copy instead. If it fails after some data was moved, the splice stops there and the number of bytes moved so far is returned, so the caller can copy the rest (and see the error again, if it persists.)
+///
+/// # Returns
+/// The number of bytes moved.
+#[cfg_attr(feature="logging", instrument(level="debug", ret, err))]
+#[cfg(feature="memfile")]
+pub fn splice_all(from: RawFd, to: RawFd, len: Option<usize>) -> io::Result<usize>
+{
+    let mut moved = 0usize;
+    loop {
+	let want = match len {
+	    Some(len) if moved >= len => break,
+	    Some(len) => std::cmp::min(len - moved, SPLICE_CHUNK),
+	    None => SPLICE_CHUNK,
+	};
+	match unsafe { libc::splice(from, std::ptr::null_mut(), to, std::ptr::null_mut(), want, libc::SPLICE_F_MOVE) } {
+	    0 => break,
+	    n if n > 0 => moved += n as usize,
+	    _ => {
+		let err = io::Error::last_os_error();
+		match err.raw_os_error() {
+		    Some(libc::EINTR) => continue,
+		    Some(libc::EAGAIN) => {
+			poll_ready(from, libc::POLLIN)?;
+			poll_ready(to, libc::POLLOUT)?;
+		    },
+		    _ if moved == 0 => return Err(err),
+		    _ => {
+			if_trace!(warn!("splice() failed after moving {moved} bytes, stopping there: {err}"));
+			break;
+		    },
+		}
 	    },
-	    _ => None,
+	}
+    }
+    Ok(moved)
+}
+
+/// Duplicate up to `len` bytes at the front of the pipe `from` into the pipe `to` with `tee()`, without consuming them from `from`.
+///
+/// Both must be pipes. `EINTR` is retried, and a non-blocking side that is not ready (`EAGAIN`) is waited on with `poll()`. A short `tee()` is *not* continued: calling it again would duplicate the same bytes again, so the caller must first consume what was duplicated from `from` (e.g. by `splice_all()`ing it into another output) before calling this again for the rest.
+///
+/// `EINVAL` (`io::ErrorKind::InvalidInput`) is returned if either side is not a pipe, in which case the caller should copy instead.
+///
+/// # Returns
+/// The number of bytes duplicated, which is `0` once `from` is empty and has no writers left.
+#[cfg_attr(feature="logging", instrument(level="debug", ret, err))]
+#[cfg(feature="memfile")]
+pub fn tee_all(from: RawFd, to: RawFd, len: usize) -> io::Result<u64>
+{
+    loop {
+	match unsafe { libc::tee(from, to, std::cmp::min(len, SPLICE_CHUNK), 0) } {
+	    n if n >= 0 => return Ok(n as u64),
+	    _ => {
+		let err = io::Error::last_os_error();
+		match err.raw_os_error() {
+		    Some(libc::EINTR) => continue,
+		    Some(libc::EAGAIN) => {
+			poll_ready(from, libc::POLLIN)?;
+			poll_ready(to, libc::POLLOUT)?;
+		    },
+		    _ => return Err(err),
+		}
+	    },
+	}
+    }
+}
+
+/// Send `count` bytes of `input`, from its current position, to `out` with `sendfile()`; stopping early if `input` ends first.
+///
+/// `input` must be a file that can be `mmap()`ed (e.g. a memfd.) The data is copied by the kernel, without going through userspace. Short writes are continued, `EINTR` is retried, and a non-blocking `out` that is full (`EAGAIN`) is waited on with `poll()`. The position of `input` is advanced past what was sent, so the caller can continue from there.
+///
+/// If `sendfile()` fails before anything was sent, the error is returned: `EINVAL` (`io::ErrorKind::InvalidInput`) or `ENOSYS` (`io::ErrorKind::Unsupported`) mean it cannot be used between these files, and the caller should copy instead. If it fails after something was sent, it stops there and returns the number of bytes sent so far (see `splice_all()`.)
+///
+/// # Returns
+/// The number of bytes sent.
+#[cfg_attr(feature="logging", instrument(level="debug", ret, err))]
+#[cfg(feature="memfile")]
+pub fn sendfile_all(out: RawFd, input: RawFd, count: usize) -> io::Result<usize>
+{
+    let mut offset = match unsafe { libc::lseek(input, 0, libc::SEEK_CUR) } {
+	-1 => return Err(io::Error::last_os_error()),
+	offset => offset,
+    };
+    let mut sent = 0usize;
+    while sent < count {
+	match unsafe { libc::sendfile(out, input, &mut offset, std::cmp::min(count - sent, SPLICE_CHUNK)) } {
+	    0 => break,
+	    n if n > 0 => sent += n as usize,
+	    _ => {
+		let err = io::Error::last_os_error();
+		match err.raw_os_error() {
+		    Some(libc::EINTR) => continue,
+		    Some(libc::EAGAIN) => poll_ready(out, libc::POLLOUT)?,
+		    _ if sent == 0 => return Err(err),
+		    _ => {
+			if_trace!(warn!("sendfile() failed after sending {sent} bytes, stopping there: {err}"));
+			break;
+		    },
+		}
+	    },
+	}
+    }
+    // `sendfile()` only updates `offset`, not the position of `input` itself.
+    if unsafe { libc::lseek(input, offset, libc::SEEK_SET) } < 0 {
+	return Err(io::Error::last_os_error());
+    }
+    Ok(sent)
+}
+
+/// Wait until `events` are ready on `fd` (e.g. `POLLIN` for a non-blocking pipe to be readable.)
+#[cfg(feature="memfile")]
+fn poll_ready(fd: RawFd, events: libc::c_short) -> io::Result<()>
+{
+    let mut pollfd = libc::pollfd { fd, events, revents: 0 };
+    loop {
+	match unsafe { libc::poll(&mut pollfd, 1, -1) } {
+	    -1 => match io::Error::last_os_error() {
+		err if err.kind() == io::ErrorKind::Interrupted => continue,
+		err => return Err(err),
+	    },
+	    _ => return Ok(()),
+	}
+    }
+}
+
+/// The kind of file a file descriptor refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FdKind
+{
+    Regular,
+    Pipe,
+    Socket,
+    /// A character device that is not a terminal.
+    Char,
+    Block,
+    /// A character device that is a terminal.
+    Tty,
+    Directory,
+    /// Anything else (e.g. a symlink opened with `O_PATH`.)
+    Other,
+}
+
+impl fmt::Display for FdKind
+{
+    #[inline] 
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	f.write_str(match self {
+	    Self::Regular => "regular",
+	    Self::Pipe => "pipe",
+	    Self::Socket => "socket",
+	    Self::Char => "char",
+	    Self::Block => "block",
+	    Self::Tty => "tty",
+	    Self::Directory => "directory",
+	    Self::Other => "other",
+	})
+    }
+}
+
+impl FdKind
+{
+    /// The kind of file `st` (from `fstat()`ing `file`) describes.
+    ///
+    /// `file` is only used to tell terminals apart from other character devices.
+    fn from_stat<F: ?Sized + AsRawFd>(file: &F, st: &stat64) -> Self
+    {
+	use libc::{
+	    S_IFMT,
+	    S_IFREG, S_IFIFO, S_IFSOCK, S_IFCHR, S_IFBLK, S_IFDIR,
+	};
+	match st.st_mode & S_IFMT {
+	    S_IFREG => Self::Regular,
+	    S_IFIFO => Self::Pipe,
+	    S_IFSOCK => Self::Socket,
+	    S_IFCHR if isatty(file) => Self::Tty,
+	    S_IFCHR => Self::Char,
+	    S_IFBLK => Self::Block,
+	    S_IFDIR => Self::Directory,
+	    _ => Self::Other,
+	}
+    }
+}
+
+/// Find out what kind of file `file`'s file descriptor refers to.
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, ret, err, fields(fd = ?file.as_raw_fd())))]
+pub fn classify_fd<F: ?Sized + AsRawFd>(file: &F) -> io::Result<FdKind>
+{
+    fstat(file).map(|st| FdKind::from_stat(file, &st))
+}
+
+/// Find out what kind of file the raw file descriptor `fd` refers to (see `classify_fd()`.)
+///
+/// This is for choosing how to move data to or from `fd` (`splice()`, `sendfile()`, or copying) up front, instead of trying each one until it does not fail with `EINVAL`.
+#[inline] 
+#[cfg(feature="memfile")]
+pub fn fd_kind(fd: RawFd) -> io::Result<FdKind>
+{
+    classify_fd(&fd)
+}
+
+/// Is the raw file descriptor `fd` a pipe?
+///
+/// If it cannot be `fstat()`ed, it is not.
+#[inline] 
+#[cfg(feature="memfile")]
+pub fn is_pipe(fd: RawFd) -> bool
+{
+    matches!(fd_kind(fd), Ok(FdKind::Pipe))
+}
+
+/// Find out what kind of file `file`'s file descriptor refers to, and its size (if it has one), with a single `fstat()`.
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, ret, err, fields(fd = ?file.as_raw_fd())))]
+pub fn kind_and_size<F: ?Sized + AsRawFd>(file: &F) -> io::Result<(FdKind, Option<NonZeroUsize>)>
+{
+    fstat(file).map(|st| (FdKind::from_stat(file, &st), NonZeroUsize::new(st.st_size as usize)))
+}
+
+/// Is `file`'s file descriptor a terminal?
+#[inline] 
+pub fn isatty<F: ?Sized + AsRawFd>(file: &F) -> bool
+{
+    unsafe { libc::isatty(file.as_raw_fd()) == 1 }
+}
+
+/// Can `file`'s file descriptor be seeked?
+///
+/// This does not change the stream position.
+#[inline] 
+pub fn is_seekable<F: ?Sized + AsRawFd>(file: &F) -> bool
+{
+    unsafe { libc::lseek(file.as_raw_fd(), 0, libc::SEEK_CUR) >= 0 }
+}
+
+/// Is `O_NONBLOCK` set on `file`'s open file description?
+#[inline] 
+pub fn is_nonblocking<F: ?Sized + AsRawFd>(file: &F) -> io::Result<bool>
+{
+    match unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFL) } {
+	-1 => Err(io::Error::last_os_error()),
+	flags => Ok(flags & libc::O_NONBLOCK != 0),
+    }
+}
+
+/// Get the magic number of the filesystem `file` is on, and its name if it is one we know of.
+///
+/// # Returns
+/// `Ok(None)` on non-Linux targets, where filesystem magic numbers are not available.
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, ret, err, fields(fd = ?file.as_raw_fd())))]
+pub fn fs_type<F: ?Sized + AsRawFd>(file: &F) -> io::Result<Option<(u64, Option<&'static str>)>>
+{
+    cfg_if! {
+	if #[cfg(target_os="linux")] {
+	    let mut st = MaybeUninit::uninit();
+	    let magic = unsafe {
+		match libc::fstatfs(file.as_raw_fd(), st.as_mut_ptr()) {
+		    0 => st.assume_init().f_type as u64,
+		    _ => return Err(io::Error::last_os_error()),
+		}
+	    };
+	    Ok(Some((magic, match magic {
+		0x50495045 => Some("pipefs"),
+		0x534f434b => Some("sockfs"),
+		0x01021994 => Some("tmpfs"),
+		0x62656572 => Some("sysfs"),
+		0x00009fa0 => Some("proc"),
+		0x00001cd1 => Some("devpts"),
+		0x958458f6 => Some("hugetlbfs"),
+		0x0000ef53 => Some("ext2/3/4"),
+		0x9123683e => Some("btrfs"),
+		0x58465342 => Some("xfs"),
+		0x2fc12fc1 => Some("zfs"),
+		0x794c7630 => Some("overlayfs"),
+		0x00006969 => Some("nfs"),
+		0x65735546 => Some("fuse"),
+		0x00004d44 => Some("vfat"),
+		_ => None,
+	    })))
+	} else {
+	    let _ = file;
+	    Ok(None)
+	}
+    }
+}
+
+/// A report on a file descriptor, for `--probe`.
+#[derive(Debug)]
+pub struct Probe
+{
+    fd: RawFd,
+    kind: io::Result<FdKind>,
+    size: io::Result<u64>,
+    seekable: bool,
+    nonblocking: io::Result<bool>,
+    fs_type: io::Result<Option<(u64, Option<&'static str>)>>,
+}
+
+impl Probe
+{
+    /// Inspect `file`'s file descriptor without reading from it.
+    #[cfg_attr(feature="logging", instrument(level="info", skip_all, fields(fd = ?file.as_raw_fd())))]
+    pub fn new<F: ?Sized + AsRawFd>(file: &F) -> Self
+    {
+	Self {
+	    fd: file.as_raw_fd(),
+	    kind: classify_fd(file),
+	    size: fstat(file).map(|st| st.st_size as u64),
+	    seekable: is_seekable(file),
+	    nonblocking: is_nonblocking(file),
+	    fs_type: fs_type(file),
 	}
     }
 }
 
+impl fmt::Display for Probe
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	#[inline(always)] 
+	fn yes_no(b: bool) -> &'static str
+	{
+	    if b { "yes" } else { "no" }
+	}
+	writeln!(f, "fd: {}", self.fd)?;
+	match &self.kind {
+	    Ok(kind) => writeln!(f, "kind: {kind}")?,
+	    Err(err) => writeln!(f, "kind: <unknown: {err}>")?,
+	}
+	match &self.size {
+	    Ok(size) => writeln!(f, "size: {size}")?,
+	    Err(err) => writeln!(f, "size: <unknown: {err}>")?,
+	}
+	writeln!(f, "seekable: {}", yes_no(self.seekable))?;
+	match &self.nonblocking {
+	    Ok(nb) => writeln!(f, "nonblocking: {}", yes_no(*nb))?,
+	    Err(err) => writeln!(f, "nonblocking: <unknown: {err}>")?,
+	}
+	match &self.fs_type {
+	    Ok(Some((magic, Some(name)))) => writeln!(f, "filesystem: {name} (0x{magic:x})"),
+	    Ok(Some((magic, None))) => writeln!(f, "filesystem: 0x{magic:x}"),
+	    Ok(None) => writeln!(f, "filesystem: <unsupported>"),
+	    Err(err) => writeln!(f, "filesystem: <unknown: {err}>"),
+	}
+    }
+}
+
+/// The maximum number of buffers a single `writev()` accepts (`IOV_MAX` on Linux.)
+pub const IOV_MAX: usize = 1024;
+
+/// Write all of `bufs` to `writer`, gathering them into as few `write_vectored()` calls (i.e. `writev()` syscalls) as possible.
+///
+/// At most `IOV_MAX` buffers are passed to each call, so more than that are written in chunks. Partial writes are continued from where they stopped.
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, err, fields(bufs = ?bufs.len())))]
+pub fn write_all_vectored<W: ?Sized + io::Write>(writer: &mut W, mut bufs: &mut [io::IoSlice<'_>]) -> io::Result<()>
+{
+    // Skip any leading empty buffers.
+    io::IoSlice::advance_slices(&mut bufs, 0);
+    while !bufs.is_empty() {
+	let len = std::cmp::min(bufs.len(), IOV_MAX);
+	match writer.write_vectored(&bufs[..len]) {
+	    Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")),
+	    Ok(written) => io::IoSlice::advance_slices(&mut bufs, written),
+	    Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+	    Err(err) => return Err(err),
+	}
+    }
+    Ok(())
+}
+
+/// Copy all of `reader` into `writer`, like `io::copy()`, but stop at the first read error instead of failing.
+///
+/// # Returns
+/// The number of bytes copied, and the read error that stopped the copy if there was one. Errors writing to `writer` are returned as `Err`.
+#[cfg_attr(feature="logging", instrument(level="debug", skip_all, err))]
+pub fn copy_until_error<R, W>(reader: &mut R, writer: &mut W) -> io::Result<(u64, Option<io::Error>)>
+where R: ?Sized + io::Read,
+      W: ?Sized + io::Write
+{
+    let mut buffer = [0u8; 8192];
+    let mut copied = 0u64;
+    loop {
+	let read = match reader.read(&mut buffer[..]) {
+	    Ok(0) => return Ok((copied, None)),
+	    Ok(read) => read,
+	    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+	    Err(err) => {
+		if_trace!(warn!("read failed after {copied} bytes: {err}"));
+		return Ok((copied, Some(err)));
+	    },
+	};
+	writer.write_all(&buffer[..read])?;
+	copied += read as u64;
+    }
+}
+
 /// Get the current stream position of any seekable stream.
 #[inline(always)] 
+#[cfg_attr(not(feature="memfile"), allow(dead_code))]
 pub fn tell_file<T>(file: &mut T) -> io::Result<u64>
 where T: io::Seek + ?Sized
 {
     file.stream_position()
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    /// A reader that produces `.0` bytes and then fails.
+    struct FailAfter(usize);
+    impl io::Read for FailAfter
+    {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+	{
+	    match std::cmp::min(self.0, std::cmp::min(buf.len(), 100)) {
+		0 => Err(io::Error::new(io::ErrorKind::ConnectionReset, "source died")),
+		n => {
+		    buf[..n].fill(b'x');
+		    self.0 -= n;
+		    Ok(n)
+		},
+	    }
+	}
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn meminfo_available() -> io::Result<()>
+    {
+	const MEMINFO: &str = "MemTotal:       16303428 kB\nMemFree:          583016 kB\nMemAvailable:    9211984 kB\nHugePages_Total:       0\n";
+	assert_eq!(meminfo_field(MEMINFO, "MemAvailable"), Some(9211984 * 1024));
+	assert_eq!(meminfo_field(MEMINFO, "MemFree"), Some(583016 * 1024));
+	assert_eq!(meminfo_field(MEMINFO, "HugePages_Total"), None, "Field without a unit was parsed as `kB`");
+	assert_eq!(meminfo_field(MEMINFO, "Mem"), None, "Prefix of a field name was matched");
+
+	if std::path::Path::new("/proc/meminfo").exists() {
+	    assert!(available_memory()? > 0, "No memory is available");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn copy_until_error_keeps_partial() -> io::Result<()>
+    {
+	const N: usize = 1234;
+	let mut output = Vec::new();
+	let (copied, err) = copy_until_error(&mut FailAfter(N), &mut output)?;
+	assert_eq!(copied, N as u64, "Wrong number of bytes reported as copied");
+	assert_eq!(output.len(), N, "Bytes before the error did not reach the output");
+	assert_eq!(err.map(|e| e.kind()), Some(io::ErrorKind::ConnectionReset), "Read error was not returned");
+	Ok(())
+    }
+
+    /// A writer that accepts at most `.1` bytes per call, counting its `write_vectored()` calls in `.2`.
+    struct Gather(Vec<u8>, usize, usize);
+    impl io::Write for Gather
+    {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+	{
+	    self.write_vectored(&[io::IoSlice::new(buf)])
+	}
+	fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize>
+	{
+	    assert!(bufs.len() <= IOV_MAX, "More than `IOV_MAX` buffers passed to one call");
+	    self.2 += 1;
+	    let mut written = 0;
+	    for buf in bufs {
+		let len = std::cmp::min(buf.len(), self.1 - written);
+		self.0.extend_from_slice(&buf[..len]);
+		written += len;
+	    }
+	    Ok(written)
+	}
+	fn flush(&mut self) -> io::Result<()>
+	{
+	    Ok(())
+	}
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn release_pages_keeps_size() -> io::Result<()>
+    {
+	let file = memfile::RawFile::open_mem(None, 0).map_err(|err| io::Error::other(err.to_string()))?;
+	file.write_all_at(&[0xaa; 64 * 1024][..], 0)?;
+	assert!(fstat(&file)?.st_blocks > 0, "Memfd has no pages after writing to it");
+
+	assert_eq!(release_pages(&file)?, 64 * 1024, "Wrong number of bytes released");
+	let st = fstat(&file)?;
+	assert_eq!(st.st_size, 64 * 1024, "Size was changed by releasing pages");
+	assert_eq!(st.st_blocks, 0, "Pages were not released");
+	let mut buf = [0xffu8; 16];
+	file.read_at(&mut buf[..], 1024)?;
+	assert_eq!(buf, [0u8; 16], "Released pages do not read as zeroes");
+
+	// A regular file is left alone.
+	let path = std::env::temp_dir().join(format!("collect-release-test-{}", std::process::id()));
+	std::fs::write(&path, b"keep me")?;
+	let regular = std::fs::File::open(&path)?;
+	let released = release_pages(&regular);
+	let content = std::fs::read(&path);
+	let _ = std::fs::remove_file(&path);
+	assert_eq!(released?, 0, "Pages of a regular file were released");
+	assert_eq!(&content?[..], b"keep me", "Regular file was modified");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn pipe_cloexec_both_ends() -> io::Result<()>
+    {
+	use io::{Read, Write};
+	let (mut read, mut write) = pipe_cloexec()?;
+	for fd in [read.as_raw_fd(), write.as_raw_fd()] {
+	    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+	    assert!(flags >= 0, "F_GETFD failed: {}", io::Error::last_os_error());
+	    assert!(flags & libc::FD_CLOEXEC != 0, "Pipe fd {fd} does not have FD_CLOEXEC set");
+	}
+	write.write_all(b"through the pipe")?;
+	drop(write);
+	let mut buf = Vec::new();
+	read.read_to_end(&mut buf)?;
+	assert_eq!(&buf[..], b"through the pipe", "Pipe data mismatch");
+	Ok(())
+    }
+
+    /// A `pipe()` without `O_CLOEXEC`, as `(read, write)`.
+    #[cfg(feature="memfile")]
+    fn pipe_files() -> io::Result<(std::fs::File, std::fs::File)>
+    {
+	let mut fds = [0; 2];
+	if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+	    return Err(io::Error::last_os_error());
+	}
+	Ok(unsafe { (std::fs::File::from_raw_fd(fds[0]), std::fs::File::from_raw_fd(fds[1])) })
+    }
+
+    #[cfg(feature="memfile")]
+    fn memfd() -> io::Result<std::fs::File>
+    {
+	memfile::RawFile::open_mem(None, 0)
+	    .map(Into::into)
+	    .map_err(|err| io::Error::other(err.to_string()))
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn splice_all_partial() -> io::Result<()>
+    {
+	use io::{Read, Write};
+	let data = (0..10_000u32).map(|i| i as u8).collect::<Vec<_>>();
+	let (mut read, mut write) = pipe_files()?;
+	write.write_all(&data[..])?;
+
+	// Only `len` bytes are moved, the rest stays in the pipe.
+	let mut file = memfd()?;
+	assert_eq!(splice_all(read.as_raw_fd(), file.as_raw_fd(), Some(4000))?, 4000, "Wrong number of bytes spliced");
+	drop(write);
+	let mut rest = Vec::new();
+	read.read_to_end(&mut rest)?;
+	assert!(rest == data[4000..], "Pipe does not hold the data that was not spliced");
+	let mut spliced = Vec::new();
+	io::Seek::rewind(&mut file)?;
+	file.read_to_end(&mut spliced)?;
+	assert!(spliced == data[..4000], "Spliced data mismatch");
+
+	// Input that arrives in pieces is spliced in several short splices, until EOF.
+	let (read, mut write) = pipe_files()?;
+	let writer = std::thread::spawn(move || -> io::Result<()> {
+	    for chunk in [&b"first "[..], b"second ", b"third"] {
+		write.write_all(chunk)?;
+		std::thread::sleep(std::time::Duration::from_millis(20));
+	    }
+	    Ok(())
+	});
+	let mut file = memfd()?;
+	assert_eq!(splice_all(read.as_raw_fd(), file.as_raw_fd(), None)?, 18, "Did not splice until EOF");
+	writer.join().expect("Pipe writer panicked")?;
+	let mut spliced = Vec::new();
+	io::Seek::rewind(&mut file)?;
+	file.read_to_end(&mut spliced)?;
+	assert_eq!(&spliced[..], b"first second third", "Spliced data mismatch");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn splice_all_retries() -> io::Result<()>
+    {
+	use io::Write;
+	extern "C" fn ignore(_: libc::c_int) {}
+	// Installed without `SA_RESTART`, so a blocked `splice()` fails with `EINTR` when the signal arrives.
+	unsafe {
+	    let mut action: libc::sigaction = std::mem::zeroed();
+	    action.sa_sigaction = ignore as extern "C" fn(libc::c_int) as libc::sighandler_t;
+	    if libc::sigaction(libc::SIGUSR2, &action, std::ptr::null_mut()) < 0 {
+		return Err(io::Error::last_os_error());
+	    }
+	}
+	let (read, mut write) = pipe_files()?;
+	let out = memfd()?;
+	let (tx, rx) = std::sync::mpsc::channel();
+	let splicer = std::thread::spawn(move || {
+	    let _ = tx.send(unsafe { libc::pthread_self() });
+	    splice_all(read.as_raw_fd(), out.as_raw_fd(), None)
+	});
+	let thread = rx.recv().expect("Splicing thread did not start");
+	std::thread::sleep(std::time::Duration::from_millis(50));
+	unsafe { libc::pthread_kill(thread, libc::SIGUSR2); }
+	write.write_all(b"after the signal")?;
+	drop(write);
+	assert_eq!(splicer.join().expect("Splicing thread panicked")?, 16, "Splice did not continue after EINTR");
+
+	// A non-blocking input that is not ready yet (`EAGAIN`) is waited on.
+	let (read, mut write) = pipe_files()?;
+	unsafe { libc::fcntl(read.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) };
+	let out = memfd()?;
+	let splicer = std::thread::spawn(move || splice_all(read.as_raw_fd(), out.as_raw_fd(), None));
+	std::thread::sleep(std::time::Duration::from_millis(50));
+	write.write_all(b"late")?;
+	drop(write);
+	assert_eq!(splicer.join().expect("Splicing thread panicked")?, 4, "Splice did not wait for non-blocking input");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn splice_all_unsupported() -> io::Result<()>
+    {
+	// Neither side is a pipe.
+	let (from, to) = (memfd()?, memfd()?);
+	io::Write::write_all(&mut &from, b"data")?;
+	io::Seek::rewind(&mut &from)?;
+	let err = splice_all(from.as_raw_fd(), to.as_raw_fd(), None).expect_err("Splice between two memfds succeeded");
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "Unexpected error: {err}");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn tee_all_to_two_pipes() -> io::Result<()>
+    {
+	use io::{Read, Write};
+	let data = (0..20_000u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+	let (input, mut write) = pipe_files()?;
+	let (mut first, first_write) = pipe_files()?;
+	let (mut second, second_write) = pipe_files()?;
+	let writer = std::thread::spawn(move || write.write_all(&data[..]).map(|_| data));
+
+	// Duplicate into the first output, then move the same bytes into the second.
+	let mut total = 0u64;
+	loop {
+	    let teed = tee_all(input.as_raw_fd(), first_write.as_raw_fd(), usize::MAX)?;
+	    if teed == 0 {
+		break;
+	    }
+	    assert_eq!(splice_all(input.as_raw_fd(), second_write.as_raw_fd(), Some(teed as usize))? as u64, teed, "Did not consume what was teed");
+	    total += teed;
+	}
+	let data = writer.join().expect("Pipe writer panicked")?;
+	drop((first_write, second_write));
+	assert_eq!(total, data.len() as u64, "Wrong number of bytes teed");
+
+	let (mut a, mut b) = (Vec::new(), Vec::new());
+	first.read_to_end(&mut a)?;
+	second.read_to_end(&mut b)?;
+	assert!(a == data, "First output mismatch");
+	assert!(b == data, "Second output mismatch");
+
+	// An output that is not a pipe.
+	let (input, mut write) = pipe_files()?;
+	write.write_all(b"data")?;
+	let err = tee_all(input.as_raw_fd(), memfd()?.as_raw_fd(), 4).expect_err("tee() into a memfd succeeded");
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "Unexpected error: {err}");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn sendfile_all_to_file_and_socket() -> io::Result<()>
+    {
+	use io::{Read, Seek, Write};
+	let data = (0..300_000u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+	let mut input = memfd()?;
+	input.write_all(&data[..])?;
+
+	// From the current position of `input`, which is advanced past what was sent.
+	input.seek(io::SeekFrom::Start(1000))?;
+	let path = std::env::temp_dir().join(format!("collect-sendfile-test-{}", std::process::id()));
+	let out = std::fs::File::create(&path)?;
+	let sent = sendfile_all(out.as_raw_fd(), input.as_raw_fd(), 5000);
+	let written = std::fs::read(&path);
+	let _ = std::fs::remove_file(&path);
+	assert_eq!(sent?, 5000, "Wrong number of bytes sent");
+	assert!(written? == data[1000..6000], "Sent data mismatch");
+	assert_eq!(input.stream_position()?, 6000, "Input position was not advanced");
+
+	// A socket that fills up takes several (short) sends, until the end of `input`.
+	let (reader, writer) = std::os::unix::net::UnixStream::pair()?;
+	let drain = std::thread::spawn(move || {
+	    let mut received = Vec::new();
+	    (&reader).read_to_end(&mut received).map(move |_| received)
+	});
+	input.rewind()?;
+	assert_eq!(sendfile_all(writer.as_raw_fd(), input.as_raw_fd(), usize::MAX)?, data.len(), "Did not send until the end of input");
+	drop(writer);
+	assert!(drain.join().expect("Socket drain panicked")? == data, "Sent data mismatch");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn sendfile_all_unsupported() -> io::Result<()>
+    {
+	let input = memfd()?;
+	io::Write::write_all(&mut &input, b"data")?;
+	io::Seek::rewind(&mut &input)?;
+	// `sendfile()` cannot write to a file opened with `O_APPEND`.
+	let path = std::env::temp_dir().join(format!("collect-sendfile-append-test-{}", std::process::id()));
+	let out = std::fs::OpenOptions::new().create(true).append(true).open(&path);
+	let _ = std::fs::remove_file(&path);
+	let err = sendfile_all(out?.as_raw_fd(), input.as_raw_fd(), 4).expect_err("sendfile() to an `O_APPEND` file succeeded");
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "Unexpected error: {err}");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(feature="memfile")]
+    fn fd_kinds() -> io::Result<()>
+    {
+	let (read, write) = pipe_files()?;
+	assert_eq!(fd_kind(read.as_raw_fd())?, FdKind::Pipe);
+	assert!(is_pipe(write.as_raw_fd()), "Write end of a pipe is not a pipe");
+
+	let file = memfd()?;
+	io::Write::write_all(&mut &file, b"data")?;
+	assert!(!is_pipe(file.as_raw_fd()), "In-memory file is a pipe");
+	assert_eq!(kind_and_size(&file)?, (FdKind::Regular, NonZeroUsize::new(4)));
+	assert_eq!(try_get_size(&file), NonZeroUsize::new(4));
+	assert!(!is_pipe(-1), "Invalid fd is a pipe");
+	Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn set_pipe_size_enlarges() -> io::Result<()>
+    {
+	let mut fds = [0; 2];
+	if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+	    return Err(io::Error::last_os_error());
+	}
+	let (read, _write) = unsafe { (std::fs::File::from_raw_fd(fds[0]), std::fs::File::from_raw_fd(fds[1])) };
+	let wanted = std::cmp::min(256 * 1024, pipe_max_size().unwrap_or(256 * 1024));
+	let set = set_pipe_size(read.as_raw_fd(), wanted)?;
+	assert!(set >= wanted, "Pipe capacity {set} is less than the requested {wanted}");
+	assert_eq!(pipe_size(read.as_raw_fd())?, set, "F_GETPIPE_SZ does not reflect the new capacity");
+
+	// Larger than the maximum is clamped to it, instead of failing.
+	if let Some(max) = pipe_max_size() {
+	    assert!(set_pipe_size(read.as_raw_fd(), usize::MAX)? >= max, "Pipe capacity was not clamped to `pipe-max-size`");
+	}
+	Ok(())
+    }
+
+    #[test]
+    fn write_all_vectored_gathers() -> io::Result<()>
+    {
+	let pieces: Vec<Vec<u8>> = (0..3000u32).map(|i| i.to_string().into_bytes()).collect();
+	let expected = pieces.concat();
+
+	let mut output = Gather(Vec::new(), usize::MAX, 0);
+	write_all_vectored(&mut output, &mut pieces.iter().map(|piece| io::IoSlice::new(piece)).collect::<Vec<_>>()[..])?;
+	assert!(output.0 == expected, "Output data mismatch");
+	assert_eq!(output.2, pieces.len().div_ceil(IOV_MAX), "Pieces were not gathered into one call per `IOV_MAX` buffers");
+
+	// Partial writes are continued.
+	let mut output = Gather(Vec::new(), 7, 0);
+	write_all_vectored(&mut output, &mut pieces.iter().map(|piece| io::IoSlice::new(piece)).collect::<Vec<_>>()[..])?;
+	assert!(output.0 == expected, "Output data mismatch with partial writes");
+	Ok(())
+    }
+}
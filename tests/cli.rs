@@ -0,0 +1,1362 @@
+//! End-to-end tests running the built `collect` binary.
+//!
+//! These test whichever strategy the binary was compiled with (`memfile` or `buffered`), so they should be ran under both:
+//! ``` shell
+//! $ cargo test
+//! $ cargo test --no-default-features --features mode-buffered
+//! ```
+use std::{
+    io::{
+	self,
+	Read,
+	Write,
+    },
+    process::{
+	Command,
+	Stdio,
+	Output,
+    },
+    thread,
+};
+
+/// Path to the compiled binary under test.
+const COLLECT: &str = env!("CARGO_BIN_EXE_collect");
+
+/// Create some non-repeating-ish input of `len` bytes.
+fn gen_input(len: usize) -> Vec<u8>
+{
+    (0..len).map(|i| (i.wrapping_mul(31) ^ (i >> 8)) as u8).collect()
+}
+
+/// Run `collect` with `args`, piping `input` into its `stdin` from another thread.
+///
+/// `stdin` is written to from a seperate thread so that inputs larger than the pipe capacity do not dead-lock.
+fn run_with(args: &[&str], input: &[u8]) -> io::Result<Output>
+{
+    let mut child = Command::new(COLLECT)
+	.args(args)
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::piped())
+	.stdout(Stdio::piped())
+	.stderr(Stdio::piped())
+	.spawn()?;
+    let mut stdin = child.stdin.take().expect("no stdin pipe");
+    let input = input.to_owned();
+    let writer = thread::spawn(move || stdin.write_all(&input[..]));
+    let output = child.wait_with_output()?;
+    writer.join().expect("stdin writer panicked")?;
+    Ok(output)
+}
+
+#[inline]
+fn run(input: &[u8]) -> io::Result<Output>
+{
+    run_with(&[], input)
+}
+
+fn assert_passthrough(len: usize) -> io::Result<()>
+{
+    let input = gen_input(len);
+    let output = run(&input[..])?;
+    assert!(output.status.success(), "collect failed on {len} bytes: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(output.stdout.len(), input.len(), "Output size mismatch.");
+    assert!(output.stdout == input, "Output data mismatch.");
+    Ok(())
+}
+
+#[test]
+fn passthrough_empty() -> io::Result<()>
+{
+    assert_passthrough(0)
+}
+
+#[test]
+fn passthrough_small() -> io::Result<()>
+{
+    assert_passthrough(13)
+}
+
+#[test]
+fn passthrough_large() -> io::Result<()>
+{
+    assert_passthrough(8 * 1024 * 1024 + 7)
+}
+
+#[test]
+fn stdout_reaches_eof() -> io::Result<()>
+{
+    const INPUT: &[u8] = b"Hello world!";
+    let mut child = Command::new(COLLECT)
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::piped())
+	.stdout(Stdio::piped())
+	.stderr(Stdio::null())
+	.spawn()?;
+    child.stdin.take().expect("no stdin pipe").write_all(INPUT)?; // `stdin` is dropped (closed) here.
+
+    // `read_to_end()` only returns once the downstream reader observes EOF.
+    let mut output = Vec::new();
+    child.stdout.take().expect("no stdout pipe").read_to_end(&mut output)?;
+    assert_eq!(&output[..], INPUT, "Invalid output data.");
+    assert!(child.wait()?.success(), "collect exited with a failure status");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_stdin_tee() -> io::Result<()>
+{
+    let input = gen_input(64 * 1024);
+    let output = run_with(&["--exec-stdin-tee", "-exec", "cat"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    // `collect`'s own output, followed by the child's view of its `stdin` (written to the inherited `stdout`.)
+    let (ours, childs) = output.stdout.split_at(std::cmp::min(input.len(), output.stdout.len()));
+    assert!(ours == &input[..], "collect's output does not match the input");
+    assert!(childs == &input[..], "the child's view of the buffer does not match the input");
+    Ok(())
+}
+
+#[test]
+fn passthrough_buffer_backends() -> io::Result<()>
+{
+    let backends = [
+	"vec",
+	#[cfg(feature="bytes")]
+	"bytes",
+    ];
+    let input = gen_input(1024 * 1024 + 3);
+    for backend in backends {
+	let output = run_with(&["--buffer-backend", backend], &input[..])?;
+	assert!(output.status.success(), "collect failed with backend {backend}: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout == input, "Output data mismatch with backend {backend}");
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_input_nonblock() -> io::Result<()>
+{
+    /// Run `collect`, with `-exec` reading the status flags of its own `stdin` from `/proc`.
+    fn child_stdin_flags(args: &[&str]) -> io::Result<u32>
+    {
+	let args = [args, &["-exec", "grep", "^flags:", "/proc/self/fdinfo/0"]].concat();
+	let output = run_with(&args[..], b"")?;
+	assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	let output = String::from_utf8_lossy(&output.stdout[..]);
+	let flags = output.trim().strip_prefix("flags:").expect("unexpected fdinfo output").trim();
+	Ok(u32::from_str_radix(flags, 8).expect("invalid flags"))
+    }
+    const O_NONBLOCK: u32 = 0o4000;
+
+    assert_ne!(child_stdin_flags(&["--exec-input-nonblock"])? & O_NONBLOCK, 0, "Child's stdin is not O_NONBLOCK");
+    assert_eq!(child_stdin_flags(&[])? & O_NONBLOCK, 0, "Child's stdin is O_NONBLOCK without `--exec-input-nonblock`");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="memfile")]
+fn memfile_name_too_long() -> io::Result<()>
+{
+    // `MEMFD_NAME_MAX` + 1, and well past it.
+    for len in [250, 300] {
+	memfile_name_of_len(len)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature="memfile")]
+fn memfile_name_of_len(len: usize) -> io::Result<()>
+{
+    let name = "x".repeat(len);
+    let input = gen_input(1024);
+
+    // The full error chain is only printed with `RUST_VERBOSE`.
+    let output = Command::new(COLLECT)
+	.args(["--name", &name[..]])
+	.env("RUST_LOG", "off")
+	.env("RUST_VERBOSE", "1")
+	.stdin(Stdio::null())
+	.output()?;
+    assert!(!output.status.success(), "collect accepted an over-long `--name`");
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(stderr.contains(&format!("name is {len} bytes long, but the maximum is 249 bytes")), "Error does not mention the name limit: {stderr}");
+
+    let output = run_with(&["--name", &name[..], "--name-truncate"], &input[..])?;
+    assert!(output.status.success(), "collect failed with `--name-truncate`: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == input, "Output data mismatch.");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_output_to_memfd() -> io::Result<()>
+{
+    const INPUT: &[u8] = b"c\nb\na\nb\n";
+    let output = run_with(&["--exec-output-to-memfd", "-exec", "sort", ";", "-exec", "uniq"], INPUT)?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    // `collect`'s own output, followed by the output of the last stage only.
+    assert_eq!(&output.stdout[..], &b"c\nb\na\nb\na\nb\nc\n"[..]);
+    Ok(())
+}
+
+#[test]
+fn probe_pipe() -> io::Result<()>
+{
+    let output = run_with(&["--probe"], b"")?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    let report = String::from_utf8_lossy(&output.stdout[..]);
+    assert!(report.contains("kind: pipe\n"), "Pipe was not classified as a pipe: {report}");
+    assert!(report.contains("seekable: no\n"), "Pipe was reported as seekable: {report}");
+    Ok(())
+}
+
+#[test]
+fn probe_regular_file() -> io::Result<()>
+{
+    let path = std::env::temp_dir().join(format!("collect-probe-test-{}", std::process::id()));
+    std::fs::write(&path, gen_input(4096))?;
+    let file = std::fs::File::open(&path);
+    let _ = std::fs::remove_file(&path);
+    
+    let output = Command::new(COLLECT)
+	.arg("--probe")
+	.env("RUST_LOG", "off")
+	.stdin(file?)
+	.output()?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    let report = String::from_utf8_lossy(&output.stdout[..]);
+    assert!(report.contains("kind: regular\n"), "File was not classified as regular: {report}");
+    assert!(report.contains("size: 4096\n"), "File size was not reported: {report}");
+    assert!(report.contains("seekable: yes\n"), "File was not reported as seekable: {report}");
+    Ok(())
+}
+
+#[test]
+fn best_effort_read_error() -> io::Result<()>
+{
+    // Reading from a directory fails immediately (`EISDIR`.)
+    let run_on_dir = |args: &[&str]| -> io::Result<Output> {
+	Command::new(COLLECT)
+	    .args(args)
+	    .env("RUST_LOG", "off")
+	    .stdin(std::fs::File::open("/")?)
+	    .output()
+    };
+    assert!(!run_on_dir(&[])?.status.success(), "collect succeeded on a failing read");
+    assert!(!run_on_dir(&["--best-effort"])?.status.success(), "`--best-effort=error` did not report the read error");
+    
+    let output = run_on_dir(&["--best-effort=warn"])?;
+    assert!(output.status.success(), "`--best-effort=warn` failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout.is_empty(), "Output was written with nothing collected");
+    Ok(())
+}
+
+#[test]
+fn same_file_in_place() -> io::Result<()>
+{
+    let path = std::env::temp_dir().join(format!("collect-same-file-test-{}", std::process::id()));
+    let input = gen_input(64 * 1024 + 5);
+    std::fs::write(&path, &input[..])?;
+    // `stdin` and `stdout` share the same open file description (and therefore offset), like `collect 0<>file 1>&0`.
+    let run_in_place = |args: &[&str]| -> io::Result<Output> {
+	let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+	Command::new(COLLECT)
+	    .args(args)
+	    .env("RUST_LOG", "off")
+	    .stdin(file.try_clone()?)
+	    .stdout(file)
+	    .stderr(Stdio::piped())
+	    .output()
+    };
+    let result = (|| {
+	let output = run_in_place(&["--on-same-file", "error"])?;
+	assert!(!output.status.success(), "`--on-same-file error` did not fail");
+	assert!(std::fs::read(&path)? == input, "File was modified with `--on-same-file error`");
+	
+	let output = run_in_place(&[])?;
+	assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(std::fs::read(&path)? == input, "File was not rewritten in place");
+	Ok(())
+    })();
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+#[test]
+fn same_file_output() -> io::Result<()>
+{
+    let path = std::env::temp_dir().join(format!("collect-same-file-output-test-{}", std::process::id()));
+    let path_str = path.to_str().expect("temp path is not utf-8");
+    let input = gen_input(64 * 1024 + 5);
+    // Like `collect -o file < file`: the output file is opened by `collect` itself, after the shell has opened `stdin`.
+    let run_over_input = |args: &[&str]| -> io::Result<Output> {
+	std::fs::write(&path, &input[..])?;
+	Command::new(COLLECT)
+	    .args(args)
+	    .args(["-o", path_str])
+	    .env("RUST_LOG", "off")
+	    .stdin(std::fs::File::open(&path)?)
+	    .stdout(Stdio::piped())
+	    .stderr(Stdio::piped())
+	    .output()
+    };
+    let result = (|| {
+	let output = run_over_input(&["--on-same-file", "error"])?;
+	assert!(!output.status.success(), "`--on-same-file error` did not fail");
+	assert!(std::fs::read(&path)? == input, "File was modified with `--on-same-file error`");
+
+	let strategies = [
+	    "buffered",
+	    #[cfg(feature="memfile")]
+	    "memfd",
+	    #[cfg(feature="memfile")]
+	    "auto",
+	];
+	for strategy in strategies {
+	    let output = run_over_input(&["--strategy", strategy])?;
+	    assert!(output.status.success(), "collect failed with strategy {strategy}: {}", String::from_utf8_lossy(&output.stderr[..]));
+	    assert!(std::fs::read(&path)? == input, "File was not rewritten in place with strategy {strategy}");
+	}
+
+	// Less is written than was read, so the file is cut down to what was written.
+	let output = run_over_input(&["--tail", "1000"])?;
+	assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(std::fs::read(&path)? == input[input.len() - 1000..], "File is not the last 1000 bytes of itself");
+	Ok(())
+    })();
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+#[test]
+fn output_not_regular_file() -> io::Result<()>
+{
+    // `/dev/null` cannot be truncated, so it is written to as it is (as with `O_TRUNC`.)
+    let input = gen_input(64 * 1024 + 5);
+    for strategy in ["buffered", #[cfg(feature="memfile")] "memfd"] {
+	for args in [&["-o", "/dev/null"][..], &["-o", "/dev/null", "--tail", "1000"][..]] {
+	    let output = run_with(&[&["--strategy", strategy][..], args].concat(), &input[..])?;
+	    assert!(output.status.success(), "collect {args:?} failed with strategy {strategy}: {}", String::from_utf8_lossy(&output.stderr[..]));
+	}
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature="memfile"), allow(clippy::single_element_loop))]
+fn same_file_input_path() -> io::Result<()>
+{
+    let path = std::env::temp_dir().join(format!("collect-same-file-path-test-{}", std::process::id()));
+    let path_str = path.to_str().expect("temp path is not utf-8");
+    let input = gen_input(64 * 1024 + 5);
+    let result = (|| {
+	// `collect FILE -o FILE`
+	for strategy in ["buffered", #[cfg(feature="memfile")] "memfd"] {
+	    std::fs::write(&path, &input[..])?;
+	    let output = run_with(&["--on-same-file", "error", "--strategy", strategy, path_str, "-o", path_str], b"")?;
+	    assert!(!output.status.success(), "`--on-same-file error` did not fail with strategy {strategy}");
+	    assert!(std::fs::read(&path)? == input, "File was modified with `--on-same-file error` (strategy {strategy})");
+
+	    let output = run_with(&["--strategy", strategy, path_str, "-o", path_str], b"")?;
+	    assert!(output.status.success(), "collect failed with strategy {strategy}: {}", String::from_utf8_lossy(&output.stderr[..]));
+	    assert!(std::fs::read(&path)? == input, "File was not rewritten in place with strategy {strategy}");
+	}
+
+	// `collect FILE 1<>FILE`: `stdout` is the input file, opened without truncating it.
+	std::fs::write(&path, &input[..])?;
+	let output = Command::new(COLLECT)
+	    .args(["--on-same-file", "error", path_str])
+	    .env("RUST_LOG", "off")
+	    .stdin(Stdio::null())
+	    .stdout(std::fs::OpenOptions::new().read(true).write(true).open(&path)?)
+	    .stderr(Stdio::piped())
+	    .output()?;
+	assert!(!output.status.success(), "`--on-same-file error` did not fail with the input file as stdout");
+	assert!(std::fs::read(&path)? == input, "File was modified with `--on-same-file error`");
+	Ok(())
+    })();
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+#[test]
+fn passthrough_strategies() -> io::Result<()>
+{
+    let strategies = [
+	"buffered",
+	#[cfg(feature="memfile")]
+	"memfd",
+	#[cfg(feature="memfile")]
+	"auto",
+    ];
+    let input = gen_input(1024 * 1024 + 3);
+    for strategy in strategies {
+	let output = run_with(&["--strategy", strategy], &input[..])?;
+	assert!(output.status.success(), "collect failed with strategy {strategy}: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout == input, "Output data mismatch with strategy {strategy}");
+    }
+    Ok(())
+}
+
+#[test]
+fn tail_window() -> io::Result<()>
+{
+    let input = gen_input(1024 * 1024 + 3);
+    for tail in [0, 1, 4096, input.len(), input.len() + 100] {
+	let output = run_with(&["--tail", &tail.to_string()], &input[..])?;
+	assert!(output.status.success(), "collect failed with `--tail {tail}`: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout == input[input.len().saturating_sub(tail)..], "Output is not the last {tail} bytes of the input");
+    }
+    Ok(())
+}
+
+#[test]
+fn head_terminates_early() -> io::Result<()>
+{
+    use std::os::unix::process::ExitStatusExt;
+    // `yes` never stops writing on its own, so it only exits if `collect` closes its end of the pipe.
+    let mut producer = Command::new("yes")
+	.stdout(Stdio::piped())
+	.spawn()?;
+    let output = Command::new(COLLECT)
+	.args(["--head", "4097"])
+	.env("RUST_LOG", "off")
+	.stdin(producer.stdout.take().expect("no stdout pipe"))
+	.output();
+    let status = producer.wait()?;
+    let output = output?;
+    
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(output.stdout.len(), 4097, "Output was not exactly the first N bytes");
+    assert!(output.stdout.iter().copied().eq(b"y\n".iter().copied().cycle().take(4097)), "Output data mismatch");
+    assert_eq!(status.signal(), Some(libc::SIGPIPE), "Producer was not stopped by `SIGPIPE`: {status:?}");
+    Ok(())
+}
+
+#[test]
+fn limit_leaves_input_open() -> io::Result<()>
+{
+    let input = gen_input(32 * 1024);
+    let mut child = Command::new(COLLECT)
+	.args(["--limit", "10K"])
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::piped())
+	.stdout(Stdio::piped())
+	.stderr(Stdio::piped())
+	.spawn()?;
+    // The write end is held open until `collect` has exited, so it never sees EOF on its input.
+    let mut stdin = child.stdin.take().expect("no stdin pipe");
+    stdin.write_all(&input[..])?;
+    let mut output = Vec::new();
+    child.stdout.take().expect("no stdout pipe").read_to_end(&mut output)?;
+    let status = child.wait()?;
+    drop(stdin);
+
+    assert!(status.success(), "collect failed: {status}");
+    assert_eq!(output.len(), 10 * 1024, "Output was not exactly the first N bytes");
+    assert!(output == input[..10 * 1024], "Output data mismatch");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_on_signal() -> io::Result<()>
+{
+    use std::os::unix::process::ExitStatusExt;
+    let path = std::env::temp_dir().join(format!("collect-signal-test-{}", std::process::id()));
+    let command = format!("echo \"$COLLECT_SIGNAL\" > '{}'", path.display());
+    let mut child = Command::new(COLLECT)
+	.args(["--exec-on-signal", &command[..]])
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::piped()) // Kept open, so `collect` is still reading when the signal arrives.
+	.stdout(Stdio::null())
+	.stderr(Stdio::null())
+	.spawn()?;
+
+    // Wait until the handler for `SIGTERM` is installed (`SigCgt` in `/proc/<pid>/status`.)
+    let status_path = format!("/proc/{}/status", child.id());
+    let caught = |status: &str| status.lines()
+	.find_map(|line| line.strip_prefix("SigCgt:"))
+	.and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+	.is_some_and(|mask| mask & (1 << (libc::SIGTERM - 1)) != 0);
+    while !caught(&std::fs::read_to_string(&status_path)?) {
+	thread::sleep(std::time::Duration::from_millis(5));
+    }
+    
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+    let status = child.wait()?;
+    let hook_output = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(status.signal(), Some(libc::SIGTERM), "collect was not terminated by the signal: {status:?}");
+    assert_eq!(hook_output?.trim(), libc::SIGTERM.to_string(), "Hook was not given the signal number");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn check_commands() -> io::Result<()>
+{
+    // Nothing is read from `stdin` (which is a tty-less null device here) or spawned.
+    let check = |args: &[&str]| Command::new(COLLECT)
+	.arg("--check")
+	.args(args)
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::null())
+	.output();
+    
+    let output = check(&["-exec", "cat", ";", "-exec{}", "wc", "-c", "{}"])?;
+    assert!(output.status.success(), "Valid commands failed the check: {}", String::from_utf8_lossy(&output.stdout[..]));
+    let report = String::from_utf8_lossy(&output.stdout[..]);
+    assert_eq!(report.lines().filter(|line| line.starts_with("ok: ")).count(), 2, "Not every command was reported as ok: {report}");
+
+    let output = check(&["-exec", "collect-test-no-such-command", ";", "-exec{}", "cat"])?;
+    assert!(!output.status.success(), "Invalid commands passed the check");
+    let report = String::from_utf8_lossy(&output.stdout[..]);
+    assert!(report.contains("collect-test-no-such-command\" was not found in `PATH`"), "Missing command was not reported: {report}");
+    assert!(report.contains("has no `{}` argument"), "Missing placeholder was not reported: {report}");
+    Ok(())
+}
+
+#[test]
+fn dry_run() -> io::Result<()>
+{
+    let mut child = Command::new(COLLECT)
+	.args(["--dry-run", "--strategy", "buffered", "-o", "out.bin", "--append"])
+	.args(if cfg!(feature="exec") { &["-exec", "sort", "-r", ";", "-exec{}", "wc", "-c", "{}"][..] } else { &[][..] })
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::piped())
+	.stdout(Stdio::piped())
+	.spawn()?;
+    // `stdin` is left open with nothing written to it: if it were read from, `collect` would never exit.
+    let _stdin = child.stdin.take();
+    let output = child.wait_with_output()?;
+    assert!(output.status.success(), "collect --dry-run failed");
+    let report = String::from_utf8_lossy(&output.stdout[..]);
+    let mut expected = String::from("input: stdin\nstrategy: buffered\noutput: out.bin (append)\n");
+    if cfg!(feature="exec") {
+	expected.push_str("exec: -exec sort -r\nexec: -exec{} wc -c {}\n");
+    }
+    assert_eq!(report, expected, "Wrong dry-run report");
+    assert!(!std::path::Path::new("out.bin").exists(), "Output file was created by a dry run");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_stdin_from() -> io::Result<()>
+{
+    let input = gen_input(64 * 1024 + 1);
+
+    // `buffer` (the default): the buffer is written, then the child is given it.
+    let output = run_with(&["--exec-stdin-from=buffer", "-exec", "cat"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == [&input[..], &input[..]].concat(), "Output is not the buffer followed by the child's view of it");
+
+    // `input`: the child reads the input itself, and nothing else is written.
+    let output = run_with(&["--exec-stdin-from", "input", "-exec", "cat"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == input, "Output is not only the child's view of the input");
+
+    let output = Command::new(COLLECT)
+	.args(["--exec-stdin-from=input", "-exec", "cat", ";", "-exec", "cat"])
+	.env("RUST_LOG", "off")
+	.env("RUST_VERBOSE", "1")
+	.stdin(Stdio::null())
+	.output()?;
+    assert!(!output.status.success(), "`--exec-stdin-from=input` accepted two children");
+    assert!(String::from_utf8_lossy(&output.stderr[..]).contains("exactly one"), "Error does not explain the problem: {}", String::from_utf8_lossy(&output.stderr[..]));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_failures_aggregated() -> io::Result<()>
+{
+    let run = |fail_fast: bool| {
+	let mut command = Command::new(COLLECT);
+	if fail_fast {
+	    command.arg("--exec-fail-fast");
+	}
+	command
+	    .args(["-exec", "sh", "-c", "exit 3", ";", "-exec", "true", ";", "-exec", "sh", "-c", "exit 5"])
+	    .env("RUST_LOG", "off")
+	    .env("RUST_VERBOSE", "1")
+	    .stdin(Stdio::null())
+	    .output()
+    };
+
+    // By default, every child is ran and both failures are reported.
+    let output = run(false)?;
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(!output.status.success(), "collect succeeded with failing children");
+    assert!(stderr.contains("child 0") && stderr.contains("status 3"), "First failure is not reported: {stderr}");
+    assert!(stderr.contains("child 2") && stderr.contains("status 5"), "Second failure is not reported: {stderr}");
+    assert!(!stderr.contains("child 1"), "Successful child was reported as a failure: {stderr}");
+    assert_eq!(output.status.code(), Some(3), "Did not exit with the code of the first child that failed");
+
+    // With `--exec-fail-fast`, only the first is.
+    let output = run(true)?;
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(!output.status.success(), "collect succeeded with failing children");
+    assert!(stderr.contains("child 0"), "First failure is not reported: {stderr}");
+    assert!(!stderr.contains("child 2"), "Children were ran after the first failure: {stderr}");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_positional_substitution() -> io::Result<()>
+{
+    // `{}` is replaced with a `/proc/<pid>/fd/<fd>` path to the buffer.
+    if !std::path::Path::new("/proc/self/fd").is_dir() {
+	eprintln!("skipping: `/proc/self/fd` is not available, so `-exec{{}}` paths cannot be opened");
+	return Ok(());
+    }
+    let input = gen_input(64 * 1024 + 1);
+
+    let output = run_with(&["-exec{}", "cat", "{}"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == [&input[..], &input[..]].concat(), "Child did not reproduce the buffer from its path");
+
+    // Every `{}` is replaced.
+    let output = run_with(&["-exec{}", "cat", "{}", "{}"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == [&input[..], &input[..], &input[..]].concat(), "Not every placeholder was replaced with the buffer's path");
+
+    // Without any `{}`, the child is still ran (with nothing substituted), but a warning is issued.
+    let mut child = Command::new(COLLECT)
+	.args(["-exec{}", "echo", "no placeholder"])
+	.env("RUST_LOG", "warn")
+	.stdin(Stdio::piped())
+	.stdout(Stdio::piped())
+	.stderr(Stdio::piped())
+	.spawn()?;
+    child.stdin.take().expect("no stdin pipe").write_all(b"input\n")?;
+    let output = child.wait_with_output()?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(&output.stdout[..], b"input\nno placeholder\n", "Child without a placeholder was not ran as given");
+    #[cfg(feature="logging")]
+    assert!(String::from_utf8_lossy(&output.stderr[..]).contains("no positional arguments"), "No warning for `-exec{{}}` without a placeholder: {}", String::from_utf8_lossy(&output.stderr[..]));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_chdir_to_fd_dir() -> io::Result<()>
+{
+    if !std::path::Path::new("/proc/self/fd").is_dir() {
+	eprintln!("skipping: `/proc/self/fd` is not available");
+	return Ok(());
+    }
+    let input = gen_input(8 * 1024);
+
+    // `{}` is just the fd number, which the child opens relative to its working directory.
+    let output = run_with(&["--exec-chdir-to-fd-dir", "-exec{}", "sh", "-c", "case \"$1\" in *[!0-9]*) echo \"not a bare fd: $1\" >&2; exit 1;; esac; pwd; cat \"./$1\"", "sh", "{}"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    let child = &output.stdout[input.len()..];
+    let newline = child.iter().position(|&b| b == b'\n').expect("Child did not print its working directory");
+    let cwd = String::from_utf8_lossy(&child[..newline]);
+    assert!(cwd.starts_with("/proc/") && cwd.ends_with("/fd"), "Child was not started in a `/proc/<pid>/fd` directory: {cwd}");
+    assert!(child[(newline + 1)..] == input, "Child could not read the buffer through its fd number");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_input_readonly_reopen() -> io::Result<()>
+{
+    if !std::path::Path::new("/proc/self/fd").is_dir() {
+	eprintln!("skipping: `/proc/self/fd` is not available");
+	return Ok(());
+    }
+    let input = gen_input(64 * 1024);
+    let fifos = ["go", "done"].map(|name| std::env::temp_dir().join(format!("collect-test-reopen-{name}-{}", std::process::id())));
+    let [go, done] = fifos.each_ref().map(|fifo| fifo.to_str().expect("temp path is not utf-8"));
+    // Children are ran one after another, so the first leaves a reader in the background that reads 1000 bytes of its input only once the second has started, and before it reads its own.
+    // (`sh` gives background jobs `/dev/null` as their `stdin`, so the input is kept on fd 3 for it.)
+    const FIRST: &str = "exec 3<&0; (read _ <\"$1\"; dd bs=1000 count=1 of=/dev/null <&3 2>/dev/null; echo >\"$2\") >/dev/null &";
+    const SECOND: &str = "echo >\"$1\"; read _ <\"$2\"; cat | wc -c";
+
+    let run = |reopen: bool| -> io::Result<String> {
+	for fifo in &fifos {
+	    assert!(Command::new("mkfifo").arg(fifo).status()?.success(), "mkfifo failed");
+	}
+	let mut args = vec!["-exec", "sh", "-c", FIRST, "sh", go, done, ";", "-exec", "sh", "-c", SECOND, "sh", go, done];
+	if reopen {
+	    args.insert(0, "--exec-input-readonly-reopen");
+	}
+	let output = run_with(&args[..], &input[..]);
+	for fifo in &fifos {
+	    std::fs::remove_file(fifo)?;
+	}
+	let output = output?;
+	assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	Ok(String::from_utf8_lossy(&output.stdout[input.len()..]).trim().to_owned())
+    };
+    // Each child has its own open of the buffer (read-write by default), so the second child sees all of it, even though the first one read from its own.
+    assert_eq!(run(false)?, input.len().to_string(), "Children shared the buffer's offset");
+    assert_eq!(run(true)?, input.len().to_string(), "Re-opened children did not read the buffer independently from offset 0");
+
+    // The re-opened input cannot be written to.
+    let output = run_with(&["--exec-input-readonly-reopen", "-exec", "sh", "-c", "if printf x >&0 2>/dev/null; then echo writable; else echo readonly; fi"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(String::from_utf8_lossy(&output.stdout[input.len()..]).trim(), "readonly", "Re-opened input is writable");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_stdin_eof() -> io::Result<()>
+{
+    let input = gen_input(4096);
+
+    // By default the child sees EOF after the buffer, so `wc -c` can finish.
+    let output = run_with(&["-exec", "wc", "-c"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(String::from_utf8_lossy(&output.stdout[input.len()..]).trim(), input.len().to_string(), "Child did not read the whole buffer up to EOF");
+
+    // `--exec-stdin-keep-open`: the whole buffer is still there, but reading past it blocks instead of reaching EOF.
+    let output = run_with(&["--exec-stdin-keep-open", "-exec", "sh", "-c", "head -c 4096 | wc -c; timeout 0.2 cat; echo $?"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    let child = String::from_utf8_lossy(&output.stdout[input.len()..]).split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+    assert_eq!(child, ["4096", "124"], "Child's stdin was not kept open after the buffer");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_stdin_pipe() -> io::Result<()>
+{
+    // More than a pipe holds, so the writer has to wait on the children.
+    let input = gen_input(256 * 1024);
+
+    // Every child reads the whole buffer from its own pipe, up to EOF.
+    let output = run_with(&["--exec-stdin-pipe", "-exec", "sh", "-c", "test -p /dev/stdin && wc -c", ";", "-exec", "wc", "-c"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    let children = String::from_utf8_lossy(&output.stdout[input.len()..]).split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+    assert_eq!(children, [input.len().to_string(), input.len().to_string()], "Children did not each read the whole buffer through a pipe");
+
+    // A child that never reads its `stdin` does not stop `collect` (or the children after it.)
+    let output = run_with(&["--exec-stdin-pipe", "-exec", "true", ";", "-exec", "head", "-c", "16"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(&output.stdout[input.len()..], &input[..16], "Child after one that did not read its stdin was not fed");
+    Ok(())
+}
+
+#[test]
+fn output_to_file() -> io::Result<()>
+{
+    let input = gen_input(256 * 1024 + 5);
+    let path = std::env::temp_dir().join(format!("collect-test-output-{}", std::process::id()));
+    // An existing file is truncated.
+    std::fs::write(&path, gen_input(512 * 1024))?;
+    let path_str = path.to_str().expect("temp path is not utf-8");
+
+    for args in [&["-o", path_str][..], &["--output", path_str], &["--strategy=buffered", "--output", path_str]] {
+	let output = run_with(args, &input[..])?;
+	assert!(output.status.success(), "collect {args:?} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout.is_empty(), "collect {args:?} wrote to stdout");
+	assert!(std::fs::read(&path)? == input, "collect {args:?}: output file data mismatch");
+    }
+
+    // `-exec` children given the output itself can read it back from the file.
+    #[cfg(feature="exec")]
+    {
+	let output = run_with(&["--strategy=buffered", "-o", path_str, "-exec", "wc", "-c"], &input[..])?;
+	assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert_eq!(String::from_utf8_lossy(&output.stdout[..]).trim(), input.len().to_string(), "Child could not read the output file");
+    }
+    std::fs::remove_file(&path)?;
+
+    // `-` is `stdout`.
+    let output = run_with(&["-o", "-"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == input, "`-o -` did not write to stdout");
+
+    // An output that cannot be opened is an error.
+    let output = Command::new(COLLECT)
+	.args(["-o", "/nonexistent-collect-dir/output"])
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::null())
+	.output()?;
+    assert!(!output.status.success(), "collect succeeded with an output that cannot be opened");
+    Ok(())
+}
+
+#[test]
+fn append_to_file() -> io::Result<()>
+{
+    let existing = gen_input(4096);
+    let input = gen_input(256 * 1024 + 5);
+    let path = std::env::temp_dir().join(format!("collect-test-append-{}", std::process::id()));
+    let path_str = path.to_str().expect("temp path is not utf-8");
+
+    let strategies = [
+	"--strategy=buffered",
+	#[cfg(feature="memfile")]
+	"--strategy=memfd",
+    ];
+    for strategy in strategies {
+	std::fs::write(&path, &existing[..])?;
+	let output = run_with(&[strategy, "-o", path_str, "--append"], &input[..])?;
+	assert!(output.status.success(), "collect {strategy} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(std::fs::read(&path)? == [&existing[..], &input[..]].concat(), "collect {strategy}: input was not appended to the file");
+    }
+    std::fs::remove_file(&path)?;
+
+    // A file that does not exist yet is created.
+    let output = run_with(&["-o", path_str, "--append", "--output-range", "0:10"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(std::fs::read(&path)? == input[..10], "Range was not appended to a new file");
+    std::fs::remove_file(&path)?;
+
+    // There is nothing to append to without a file.
+    for args in [&["--append"][..], &["-o", "-", "--append"]] {
+	let output = Command::new(COLLECT)
+	    .args(args)
+	    .env("RUST_LOG", "off")
+	    .stdin(Stdio::null())
+	    .output()?;
+	assert!(!output.status.success(), "collect {args:?} succeeded without an output file");
+    }
+    Ok(())
+}
+
+#[test]
+fn null_output() -> io::Result<()>
+{
+    let input = gen_input(256 * 1024 + 5);
+    let strategies = [
+	"--strategy=buffered",
+	#[cfg(feature="memfile")]
+	"--strategy=memfd",
+    ];
+    for strategy in strategies {
+	let output = run_with(&[strategy, "--null"], &input[..])?;
+	assert!(output.status.success(), "collect {strategy} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout.is_empty(), "collect {strategy}: output was written to stdout");
+    }
+    // `/dev/null` cannot be appended to.
+    let output = Command::new(COLLECT)
+	.args(["--null", "--append"])
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::null())
+	.output()?;
+    assert!(!output.status.success(), "collect --null --append succeeded");
+    Ok(())
+}
+
+#[test]
+fn fsync_output() -> io::Result<()>
+{
+    let input = gen_input(64 * 1024 + 3);
+    let path = std::env::temp_dir().join(format!("collect-test-fsync-{}", std::process::id()));
+    let path_str = path.to_str().expect("temp path is not utf-8");
+
+    let strategies = [
+	"--strategy=buffered",
+	#[cfg(feature="memfile")]
+	"--strategy=memfd",
+    ];
+    for strategy in strategies {
+	for fsync in ["--fsync", "--fsync=data", "--fsync=all"] {
+	    let output = run_with(&[strategy, fsync, "-o", path_str], &input[..])?;
+	    assert!(output.status.success(), "collect {strategy} {fsync} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	    assert!(std::fs::read(&path)? == input, "collect {strategy} {fsync}: output file is wrong");
+	}
+	// `stdout` is not synced, but that is not an error.
+	let output = run_with(&[strategy, "--fsync"], &input[..])?;
+	assert!(output.status.success(), "collect {strategy} --fsync to stdout failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout == input, "collect {strategy} --fsync: stdout is wrong");
+    }
+    std::fs::remove_file(&path)
+}
+
+#[test]
+fn no_close_stdout() -> io::Result<()>
+{
+    let input = gen_input(64 * 1024 + 3);
+    let output = run_with(&["--no-close-stdout"], &input[..])?;
+    assert!(output.status.success(), "collect --no-close-stdout failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == input, "Output was changed by `--no-close-stdout`");
+    Ok(())
+}
+
+#[test]
+fn fail_on_empty() -> io::Result<()>
+{
+    let strategies = [
+	"--strategy=buffered",
+	#[cfg(feature="memfile")]
+	"--strategy=memfd",
+    ];
+    for strategy in strategies {
+	let output = run_with(&[strategy, "--fail-on-empty=3"], &[])?;
+	assert_eq!(output.status.code(), Some(3), "collect {strategy} did not exit with the chosen code on empty input: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout.is_empty(), "collect {strategy} wrote output for empty input");
+
+	let output = run_with(&[strategy, "--fail-on-empty"], &[])?;
+	assert_eq!(output.status.code(), Some(1), "collect {strategy} did not exit with the default code on empty input");
+
+	let output = run_with(&[strategy, "--fail-on-empty=3"], b"x")?;
+	assert!(output.status.success(), "collect {strategy} failed on non-empty input: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert_eq!(&output.stdout[..], b"x");
+    }
+
+    // Input that was collected but not all kept still counts.
+    let output = run_with(&["--fail-on-empty=3", "--tail", "0"], b"xyz")?;
+    assert!(output.status.success(), "collect failed on non-empty input with nothing kept: {}", String::from_utf8_lossy(&output.stderr[..]));
+    Ok(())
+}
+
+#[test]
+fn buffer_size() -> io::Result<()>
+{
+    let input = gen_input(64 * 1024 + 7);
+    let strategies = [
+	"--strategy=buffered",
+	#[cfg(feature="memfile")]
+	"--strategy=memfd",
+    ];
+    // Smaller and larger than the input: either way, exactly the input is written.
+    for strategy in strategies {
+	for [flag, size] in [["--buffer-size", "1K"], ["--buffer-size", "1M"], ["-b", "65543"]] {
+	    let args = [strategy, flag, size];
+	    let output = run_with(&args[..], &input[..])?;
+	    assert!(output.status.success(), "collect {args:?} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	    assert!(output.stdout == input, "collect {args:?}: output data mismatch");
+	}
+    }
+
+    // The in-memory buffer given to `-exec` children is no larger than what was collected.
+    #[cfg(feature="exec")]
+    {
+	let output = run_with(&["--strategy=memfd", "--buffer-size", "1M", "-exec", "wc", "-c"], &input[..])?;
+	assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert_eq!(String::from_utf8_lossy(&output.stdout[input.len()..]).trim(), input.len().to_string(), "Buffer was not truncated to the input");
+    }
+    Ok(())
+}
+
+#[test]
+fn output_range() -> io::Result<()>
+{
+    let input = gen_input(256 * 1024 + 5);
+    let strategies = [
+	"--strategy=buffered",
+	#[cfg(feature="memfile")]
+	"--strategy=memfd",
+    ];
+    for strategy in strategies {
+	let output = run_with(&[strategy, "--output-range", "1000:5000"], &input[..])?;
+	assert!(output.status.success(), "collect {strategy} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout == input[1000..5000], "collect {strategy}: middle of the range mismatch");
+
+	let output = run_with(&[strategy, "--output-range", "1024:"], &input[..])?;
+	assert!(output.status.success(), "collect {strategy} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout == input[1024..], "collect {strategy}: open-ended range mismatch");
+
+	// Past the end of the input is an error, unless clamped.
+	let past = format!("{}:{}", input.len() - 10, input.len() + 10);
+	let output = run_with(&[strategy, "--output-range", &past], &input[..])?;
+	assert!(!output.status.success(), "collect {strategy} succeeded with an out-of-bounds range");
+	let output = run_with(&[strategy, "--output-range", &past, "--output-range-clamp"], &input[..])?;
+	assert!(output.status.success(), "collect {strategy} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(output.stdout == input[input.len() - 10..], "collect {strategy}: clamped range mismatch");
+    }
+
+    let output = Command::new(COLLECT)
+	.args(["--tail", "10", "--output-range", "0:5"])
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::null())
+	.output()?;
+    assert!(!output.status.success(), "collect accepted both --tail and --output-range");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_input_write_sealed() -> io::Result<()>
+{
+    let input = gen_input(4096);
+    // Try to write to the input, through `stdin` for `-exec` and through the path for `-exec{}`.
+    const WRITE_STDIN: &str = "if printf x >&0 2>/dev/null; then echo writable; else echo sealed; fi";
+    const WRITE_PATH: &str = "if printf x >>\"$1\" 2>/dev/null; then echo writable; else echo sealed; fi";
+
+    // Only the size is sealed by default, so a child may still overwrite its input in-place.
+    let output = run_with(&["-exec", "sh", "-c", WRITE_STDIN], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(&output.stdout[input.len()..], b"writable\n", "Input was not writable without a write-seal");
+
+    // With the write-seal, both the dup'd `stdin` and a re-opened path to it refuse writes (`EPERM`.)
+    let output = run_with(&["--exec-input-seal-level", "write", "-exec", "sh", "-c", WRITE_STDIN, ";", "-exec{}", "sh", "-c", WRITE_PATH, "sh", "{}"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(&output.stdout[..input.len()], &input[..], "Sealed buffer was modified");
+    assert_eq!(&output.stdout[input.len()..], b"sealed\nsealed\n", "Child could write to its write-sealed input");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_prefix() -> io::Result<()>
+{
+    let input = gen_input(1024);
+
+    // The wrapper runs the real command, which still gets the buffer on `stdin`.
+    let output = run_with(&["--exec-prefix", "/usr/bin/env COLLECT_WRAPPED=yes", "-exec", "sh", "-c", "echo \"$COLLECT_WRAPPED\"; wc -c"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    let child = String::from_utf8_lossy(&output.stdout[input.len()..]).split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+    assert_eq!(child, ["yes", &input.len().to_string()[..]], "Command was not ran through the wrapper");
+
+    // `{}` is substituted in the real command's arguments, and not in the wrapper's.
+    let output = run_with(&["--exec-prefix", "/usr/bin/env ARG={}", "-exec{}", "sh", "-c", "echo \"$ARG\"; cat \"$1\"", "sh", "{}"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    let (arg, content) = output.stdout[input.len()..].split_at(b"{}\n".len());
+    assert_eq!(arg, b"{}\n", "`{{}}` in the wrapper was substituted");
+    assert!(content == input, "`{{}}` in the command was not substituted with the buffer");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_parallel() -> io::Result<()>
+{
+    use std::time::{Duration, Instant};
+    const INPUT: &[u8] = b"hello parallel";
+    // Each child reads all of its `stdin` while the others are too.
+    let check = r#"sleep 0.5; [ "$(cat)" = "hello parallel" ] || exit 7"#;
+    let started = Instant::now();
+    let output = run_with(&["--exec-parallel", "-exec", "sh", "-c", check, ";", "-exec", "sh", "-c", check, ";", "-exec", "sh", "-c", check], INPUT)?;
+    let elapsed = started.elapsed();
+    assert!(output.status.success(), "A child did not see the whole buffer: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(&output.stdout[..], INPUT, "Output mismatch");
+    assert!(elapsed < Duration::from_millis(1400), "Children were not ran concurrently (took {elapsed:?})");
+
+    // Failures are still reported in the order of the children, not the order they exited in.
+    let output = Command::new(COLLECT)
+	.args(["--exec-parallel", "-exec", "sh", "-c", "sleep 0.3; exit 3", ";", "-exec", "sh", "-c", "exit 5"])
+	.env("RUST_LOG", "off")
+	.env("RUST_VERBOSE", "1")
+	.stdin(Stdio::null())
+	.output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(!output.status.success(), "collect succeeded with failing children");
+    match (stderr.find("child 0"), stderr.find("child 1")) {
+	(Some(first), Some(second)) => assert!(first < second, "Failures were not reported in order: {stderr}"),
+	_ => panic!("Not every failure was reported: {stderr}"),
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn hangup_stops_waiting() -> io::Result<()>
+{
+    use std::time::{Duration, Instant};
+    let marker = std::env::temp_dir().join(format!("collect-hangup-test-{}", std::process::id()));
+    let touch = format!("touch '{}'", marker.display());
+    let mut child = Command::new(COLLECT)
+	.args(["-exec", "sleep", "5", ";", "-exec", "sh", "-c", &touch[..]])
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::null())
+	.stdout(Stdio::null())
+	.stderr(Stdio::null())
+	.spawn()?;
+
+    // Wait until the handler for `SIGHUP` is installed (`SigCgt` in `/proc/<pid>/status`.)
+    let status_path = format!("/proc/{}/status", child.id());
+    let caught = |status: &str| status.lines()
+	.find_map(|line| line.strip_prefix("SigCgt:"))
+	.and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+	.is_some_and(|mask| mask & (1 << (libc::SIGHUP - 1)) != 0);
+    while !caught(&std::fs::read_to_string(&status_path)?) {
+	thread::sleep(Duration::from_millis(5));
+    }
+
+    let started = Instant::now();
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGHUP) };
+    let status = child.wait()?;
+    let elapsed = started.elapsed();
+    let spawned_next = marker.exists();
+    let _ = std::fs::remove_file(&marker);
+
+    assert_eq!(status.code(), Some(0), "collect did not exit with 0 on SIGHUP: {status:?}");
+    assert!(elapsed < Duration::from_secs(3), "collect kept waiting on the child after SIGHUP (took {elapsed:?})");
+    assert!(!spawned_next, "A child was spawned after SIGHUP");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_stdin_independent_offsets() -> io::Result<()>
+{
+    use std::time::{Duration, Instant};
+    let input = gen_input(100 * 1024 + 5);
+    for args in [&[][..], &["--exec-stdin-memfd-refresh"][..]] {
+	let counts = std::env::temp_dir().join(format!("collect-test-offsets-{}", std::process::id()));
+	// Each child leaves `cat` reading its `stdin` in the background, so all three read at the same time after `collect` has spawned them all.
+	// (`sh` gives background jobs `/dev/null` as their `stdin`, so the input is kept on fd 3 for it.)
+	let child = format!("exec 3<&0; (sleep 0.2; cat <&3 | wc -c >>'{}') &", counts.display());
+	let mut argv = args.to_vec();
+	for i in 0..3 {
+	    if i > 0 {
+		argv.push(";");
+	    }
+	    argv.extend(["-exec", "sh", "-c", &child[..]]);
+	}
+	let output = run_with(&argv[..], &input[..])?;
+	assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+
+	let started = Instant::now();
+	let lines = loop {
+	    let lines: Vec<String> = std::fs::read_to_string(&counts).unwrap_or_default().lines().map(|line| line.trim().to_owned()).collect();
+	    if lines.len() >= 3 || started.elapsed() > Duration::from_secs(5) {
+		break lines;
+	    }
+	    thread::sleep(Duration::from_millis(10));
+	};
+	let _ = std::fs::remove_file(&counts);
+	assert_eq!(lines, vec![input.len().to_string(); 3], "Children did not each read the whole buffer (with {args:?})");
+    }
+    Ok(())
+}
+
+#[test]
+fn stats() -> io::Result<()>
+{
+    let input = gen_input(4096);
+    let output = run_with(&["--stats"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(output.stdout, input);
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(stderr.contains("read: 4096 bytes in ") && stderr.contains("written: 4096 bytes in ") && stderr.contains("exec: "), "Stats were not printed: {stderr}");
+
+    let output = run_with(&[], &input[..])?;
+    assert!(!String::from_utf8_lossy(&output.stderr[..]).contains("read: 4096 bytes"), "Stats were printed without `--stats`");
+    Ok(())
+}
+
+#[test]
+fn progress() -> io::Result<()>
+{
+    let input = gen_input(256 * 1024 + 5);
+    // `stderr` is not a terminal here, so it is only shown when forced.
+    let output = run_with(&["--progress=force"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == input, "Output data mismatch with --progress");
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(stderr.contains("read 256.0 KiB") && stderr.ends_with('\n'), "Final progress was not shown: {stderr:?}");
+
+    let output = run_with(&["--progress"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stderr.is_empty(), "Progress was shown on a non-terminal: {}", String::from_utf8_lossy(&output.stderr[..]));
+    Ok(())
+}
+
+#[test]
+fn argument_error_verbosity() -> io::Result<()>
+{
+    let run = |verbosity: &str| Command::new(COLLECT)
+	.args(["--strategy", "bogus"])
+	.env("RUST_LOG", "off")
+	.env("RUST_VERBOSE", verbosity)
+	.stdin(Stdio::null())
+	.output();
+
+    // Only the argument error itself.
+    let output = run("simple")?;
+    assert!(!output.status.success(), "collect accepted an invalid `--strategy`");
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(stderr.contains("--strategy"), "Error does not mention the argument: {stderr}");
+    assert!(!stderr.contains("Program arguments"), "Simple error has the argv dump: {stderr}");
+
+    // The whole report, with the arguments.
+    let output = run("verbose")?;
+    assert!(!output.status.success(), "collect accepted an invalid `--strategy`");
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(stderr.contains("--strategy") && stderr.contains("Program arguments"), "Verbose error is missing the argv dump: {stderr}");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_env() -> io::Result<()>
+{
+    let input = gen_input(1024);
+
+    // The variables are global: every child is given all of them, whatever its kind.
+    let output = run_with(&["--exec-env", "FOO=bar", "--exec-env=EMPTY=", "-exec", "sh", "-c", "echo \"$FOO:$EMPTY:${EMPTY+set}\"", ";", "-exec{}", "sh", "-c", "echo \"$FOO\"", "{}"], &input[..])?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(String::from_utf8_lossy(&output.stdout[input.len()..]), "bar::set\nbar\n");
+
+    // An entry without a `=` is rejected before anything is read or ran.
+    let output = Command::new(COLLECT)
+	.args(["--exec-env", "FOO", "-exec", "true"])
+	.env("RUST_LOG", "off")
+	.stdin(Stdio::null())
+	.output()?;
+    assert!(!output.status.success(), "collect accepted `--exec-env` without a value");
+    let stderr = String::from_utf8_lossy(&output.stderr[..]);
+    assert!(stderr.contains("--exec-env") && stderr.contains("NAME=VALUE"), "Error does not explain the expected form: {stderr}");
+    Ok(())
+}
+
+#[test]
+fn quiet_overrides_rust_log() -> io::Result<()>
+{
+    let input = gen_input(4096);
+    for quiet in ["-q", "--quiet"] {
+	let mut child = Command::new(COLLECT)
+	    .arg(quiet)
+	    .env("RUST_LOG", "info")
+	    .stdin(Stdio::piped())
+	    .stdout(Stdio::piped())
+	    .stderr(Stdio::piped())
+	    .spawn()?;
+	child.stdin.take().expect("no stdin pipe").write_all(&input[..])?;
+	let output = child.wait_with_output()?;
+	assert!(output.status.success(), "collect {quiet} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert_eq!(output.stdout, input);
+	assert!(output.stderr.is_empty(), "collect {quiet} logged with `RUST_LOG=info`: {}", String::from_utf8_lossy(&output.stderr[..]));
+    }
+    Ok(())
+}
+
+#[test]
+fn input_file() -> io::Result<()>
+{
+    let path = std::env::temp_dir().join(format!("collect-input-test-{}", std::process::id()));
+    let input = gen_input(100 * 1024 + 3);
+    std::fs::write(&path, &input[..])?;
+    let path_arg = path.to_str().expect("temporary path is not UTF-8");
+
+    // `stdin` is ignored when a file is given.
+    let output = run_with(&[path_arg], b"not the input");
+    let probe = run_with(&["--probe", path_arg], b"");
+    let _ = std::fs::remove_file(&path);
+    let (output, probe) = (output?, probe?);
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == input, "Output was not the input file");
+    let report = String::from_utf8_lossy(&probe.stdout[..]);
+    assert!(report.contains("kind: regular\n") && report.contains(&format!("size: {}\n", input.len())), "Input file was not probed: {report}");
+
+    let output = run_with(&["/nonexistent/input"], b"")?;
+    assert!(!output.status.success(), "A missing input file was accepted");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="digest")]
+fn digest_to_stderr() -> io::Result<()>
+{
+    // With `--tail`, only the last 4 bytes (`6789`) are written, so only they are digested.
+    let strategies = [
+	(&["--strategy=buffered"][..], "cbf43926  -"),
+	#[cfg(feature="memfile")]
+	(&["--strategy=memfd"][..], "cbf43926  -"),
+	(&["--tail", "4"][..], "9dbabf87  -"),
+    ];
+    for (strategy, expected) in strategies {
+	let output = run_with(&[strategy, &["-q", "--crc32"]].concat(), b"123456789")?;
+	assert!(output.status.success(), "collect {strategy:?} --crc32 failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert!(String::from_utf8_lossy(&output.stderr[..]).lines().any(|line| line == expected), "collect {strategy:?} --crc32 did not print {expected:?}: {}", String::from_utf8_lossy(&output.stderr[..]));
+    }
+    let output = run_with(&["-q", "--sha256"], b"abc")?;
+    assert!(output.status.success(), "collect --sha256 failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert!(output.stdout == b"abc", "collect --sha256: stdout is wrong");
+    assert!(String::from_utf8_lossy(&output.stderr[..]).lines().any(|line| line == "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  -"), "collect --sha256 did not print the digest: {}", String::from_utf8_lossy(&output.stderr[..]));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn empty_input_runs_children() -> io::Result<()>
+{
+    let path = std::env::temp_dir().join(format!("collect-test-empty-{}", std::process::id()));
+    let path_str = path.to_str().expect("temp path is not utf-8");
+    // With `buffered`, the children are given the output, so it is a file they can read back.
+    let strategies = [
+	&["--strategy=buffered", "-o", path_str][..],
+	#[cfg(feature="memfile")]
+	&["--strategy=memfd"][..],
+    ];
+    for strategy in strategies {
+	let output = run_with(&[strategy, &["-exec", "wc", "-c", ";", "-exec{}", "wc", "-c", "{}"]].concat(), &[])?;
+	assert!(output.status.success(), "collect {strategy:?} failed on empty input: {}", String::from_utf8_lossy(&output.stderr[..]));
+	let counts: Vec<_> = String::from_utf8_lossy(&output.stdout[..]).lines()
+	    .filter_map(|line| line.split_whitespace().next().map(str::to_owned))
+	    .collect();
+	assert_eq!(counts, ["0", "0"], "collect {strategy:?}: both children did not run and see an empty buffer");
+    }
+    std::fs::remove_file(&path)
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_timeout() -> io::Result<()>
+{
+    use std::time::{Duration, Instant};
+    // Finishes in time.
+    let output = run_with(&["--exec-timeout", "10s", "-exec", "true"], b"x")?;
+    assert!(output.status.success(), "collect failed with a child that finished in time: {}", String::from_utf8_lossy(&output.stderr[..]));
+
+    // Stopped by `SIGTERM`. (The full error chain is only printed with `RUST_VERBOSE`.)
+    let start = Instant::now();
+    let output = Command::new(COLLECT)
+	.args(["--exec-timeout", "200ms", "-exec", "sleep", "30"])
+	.env("RUST_LOG", "off")
+	.env("RUST_VERBOSE", "1")
+	.stdin(Stdio::null())
+	.output()?;
+    assert!(!output.status.success(), "collect succeeded with a child that timed out");
+    assert!(start.elapsed() < Duration::from_secs(10), "Child was not killed at the timeout");
+    assert!(String::from_utf8_lossy(&output.stderr[..]).contains("timed out"), "Timeout was not reported: {}", String::from_utf8_lossy(&output.stderr[..]));
+
+    // Ignores `SIGTERM` (which is kept across `exec`), so it needs `SIGKILL`.
+    let start = Instant::now();
+    let output = run_with(&["--exec-timeout=200ms", "-exec", "sh", "-c", "trap '' TERM; exec sleep 30"], b"x")?;
+    assert!(!output.status.success(), "collect succeeded with a child that timed out");
+    assert!(start.elapsed() < Duration::from_secs(10), "Child that ignores SIGTERM was not killed");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature="exec")]
+fn exec_stdout() -> io::Result<()>
+{
+    const INPUT: &[u8] = b"hello\n";
+    let path = std::env::temp_dir().join(format!("collect-test-exec-stdout-{}", std::process::id()));
+    // An existing file is truncated.
+    std::fs::write(&path, gen_input(4096))?;
+    let path_str = path.to_str().expect("temp path is not utf-8");
+
+    // Both children write into the one file, one after the other; `collect` only writes the input to its own `stdout`.
+    let output = run_with(&["--exec-stdout", path_str, "-exec", "cat", ";", "-exec", "echo", "done"], INPUT)?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(&output.stdout[..], INPUT, "Children's output was written to stdout");
+    assert_eq!(std::fs::read(&path)?, b"hello\ndone\n", "Children's output was not written to --exec-stdout");
+    std::fs::remove_file(&path)?;
+
+    let output = run_with(&["--exec-stdout", "/dev/null", "-exec", "echo", "discarded"], INPUT)?;
+    assert!(output.status.success(), "collect failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+    assert_eq!(&output.stdout[..], INPUT, "Child's output was not discarded");
+
+    // `/dev/stdout` is opened before `collect` closes its `stdout`, and the children run after the input is written to it.
+    for target in ["/dev/stdout", "-"] {
+	let output = run_with(&["--exec-stdout", target, "-exec", "echo", "child"], INPUT)?;
+	assert!(output.status.success(), "collect --exec-stdout {target} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert_eq!(&output.stdout[..], b"hello\nchild\n", "--exec-stdout {target}: wrong output");
+    }
+
+    // Nothing is run with `--dry-run` or `--check`, so the file is not touched.
+    std::fs::write(&path, b"keep me")?;
+    for mode in ["--dry-run", "--check"] {
+	let output = run_with(&[mode, "--exec-stdout", path_str, "-exec", "true"], b"")?;
+	assert!(output.status.success(), "collect {mode} failed: {}", String::from_utf8_lossy(&output.stderr[..]));
+	assert_eq!(std::fs::read(&path)?, b"keep me", "--exec-stdout file was truncated with {mode}");
+    }
+    std::fs::remove_file(&path)?;
+    Ok(())
+}